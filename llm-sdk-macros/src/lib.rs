@@ -0,0 +1,251 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Error, Expr, ExprLit, FnArg, GenericArgument, Ident,
+    ItemFn, Lit, Meta, Pat, PathArguments, Type,
+};
+
+/// Turn a function into a tool the model can call.
+///
+/// The function's doc comment becomes the tool description and its parameters the JSON Schema,
+/// with per-parameter descriptions taken from `* `name` - description` lines. A unit struct
+/// named after the function (`get_weather` -> `GetWeatherTool`) implements `llm_sdk::ToolFunction`
+/// and can be registered in a `llm_sdk::ToolRegistry`.
+///
+/// The function may be async, must return `Result<T, E>` with `T: Serialize`, and its
+/// parameters must be strings, numbers, booleans, `serde_json::Value`, or `Vec`/`Option` of those.
+#[proc_macro_attribute]
+pub fn llm_tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return Error::new(Span::call_site(), "#[llm_tool] takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let func = parse_macro_input!(item as ItemFn);
+    expand(func)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(func: ItemFn) -> syn::Result<TokenStream2> {
+    let sig = &func.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new(
+            sig.generics.span(),
+            "#[llm_tool] functions cannot be generic",
+        ));
+    }
+
+    let docs = doc_lines(&func);
+    let (description, param_docs) = split_docs(&docs);
+
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    let mut properties = Vec::new();
+    let mut required = Vec::new();
+    for arg in &sig.inputs {
+        let FnArg::Typed(arg) = arg else {
+            return Err(Error::new(
+                arg.span(),
+                "#[llm_tool] functions cannot take self",
+            ));
+        };
+        let Pat::Ident(pat) = &*arg.pat else {
+            return Err(Error::new(
+                arg.pat.span(),
+                "#[llm_tool] parameters must be plain identifiers",
+            ));
+        };
+        let name = pat.ident.clone();
+        let key = name.to_string();
+        let (schema, optional) = match option_inner(&arg.ty) {
+            Some(inner) => (schema(inner)?, true),
+            None => (schema(&arg.ty)?, false),
+        };
+        properties.push(match param_docs.iter().find(|(n, _)| *n == key) {
+            Some((_, desc)) => quote! {
+                let mut schema = ::llm_sdk::__private::serde_json::json!(#schema);
+                schema["description"] = #desc.into();
+                properties.insert(#key.to_string(), schema);
+            },
+            None => quote! {
+                properties.insert(#key.to_string(), ::llm_sdk::__private::serde_json::json!(#schema));
+            },
+        });
+        if !optional {
+            required.push(key);
+        }
+        names.push(name);
+        types.push(arg.ty.clone());
+    }
+
+    let fn_name = &sig.ident;
+    let tool_name = fn_name.to_string();
+    let struct_name = format_ident!("{}Tool", camel_case(&tool_name));
+    let vis = &func.vis;
+    let description = match description {
+        Some(d) => quote!(Some(#d.to_string())),
+        None => quote!(None),
+    };
+    let call = match sig.asyncness {
+        Some(_) => quote!(#fn_name(#(#names),*).await),
+        None => quote!(#fn_name(#(#names),*)),
+    };
+    let doc = format!("The `{}` tool, generated by `#[llm_tool]`.", tool_name);
+
+    Ok(quote! {
+        #func
+
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, Default)]
+        #vis struct #struct_name;
+
+        #[cfg_attr(
+            not(target_arch = "wasm32"),
+            ::llm_sdk::__private::async_trait::async_trait
+        )]
+        #[cfg_attr(
+            target_arch = "wasm32",
+            ::llm_sdk::__private::async_trait::async_trait(?Send)
+        )]
+        impl ::llm_sdk::ToolFunction for #struct_name {
+            fn tool(&self) -> ::llm_sdk::Tool {
+                #[allow(unused_mut)]
+                let mut properties = ::llm_sdk::__private::serde_json::Map::new();
+                #(#properties)*
+                ::llm_sdk::Tool::new_function(
+                    #tool_name,
+                    #description,
+                    ::llm_sdk::__private::serde_json::json!({
+                        "type": "object",
+                        "properties": properties,
+                        "required": [#(#required),*],
+                    }),
+                )
+            }
+
+            async fn call(
+                &self,
+                arguments: &str,
+            ) -> ::llm_sdk::__private::anyhow::Result<::llm_sdk::__private::serde_json::Value> {
+                #[derive(::llm_sdk::__private::serde::Deserialize)]
+                #[serde(crate = "::llm_sdk::__private::serde")]
+                struct Arguments {
+                    #(#names: #types,)*
+                }
+                let Arguments { #(#names),* } =
+                    ::llm_sdk::__private::serde_json::from_str(arguments)?;
+                let output = #call?;
+                Ok(::llm_sdk::__private::serde_json::to_value(output)?)
+            }
+        }
+    })
+}
+
+fn doc_lines(func: &ItemFn) -> Vec<String> {
+    func.attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) if nv.path.is_ident("doc") => match &nv.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Split a doc comment into the description and `* `name` - description` parameter lines.
+fn split_docs(lines: &[String]) -> (Option<String>, Vec<(String, String)>) {
+    let mut description = Vec::new();
+    let mut params = Vec::new();
+    let mut in_description = true;
+    for line in lines {
+        if let Some(param) = param_doc(line) {
+            params.push(param);
+            in_description = false;
+        } else if line.starts_with('#') {
+            in_description = false;
+        } else if in_description && !line.is_empty() {
+            description.push(line.as_str());
+        }
+    }
+    let description = (!description.is_empty()).then(|| description.join(" "));
+    (description, params)
+}
+
+fn param_doc(line: &str) -> Option<(String, String)> {
+    let rest = line
+        .strip_prefix("* `")
+        .or_else(|| line.strip_prefix("- `"))?;
+    let (name, desc) = rest.split_once('`')?;
+    let desc = desc
+        .trim_start()
+        .trim_start_matches(['-', ':'])
+        .trim()
+        .to_string();
+    Some((name.to_string(), desc))
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    generic_inner(ty, "Option")
+}
+
+fn generic_inner<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn schema(ty: &Type) -> syn::Result<TokenStream2> {
+    if let Some(inner) = generic_inner(ty, "Vec") {
+        let items = schema(inner)?;
+        return Ok(quote!({ "type": "array", "items": #items }));
+    }
+    let ident = match ty {
+        Type::Path(path) => path.path.segments.last().map(|s| &s.ident),
+        _ => None,
+    };
+    let kind = match ident.map(Ident::to_string).as_deref() {
+        Some("String") => "string",
+        Some("bool") => "boolean",
+        Some("f32" | "f64") => "number",
+        Some(
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+            | "u128" | "usize",
+        ) => "integer",
+        Some("Value") => return Ok(quote!({})),
+        _ => {
+            return Err(Error::new(
+                ty.span(),
+                "unsupported #[llm_tool] parameter type; use String, numbers, bool, serde_json::Value, Vec or Option",
+            ))
+        }
+    };
+    Ok(quote!({ "type": #kind }))
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
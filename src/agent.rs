@@ -0,0 +1,491 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ChatCompleteModel, ChatCompletionMessage, ChatCompletionRequestBuilder, ChatProvider, Tool,
+    ToolCall, ToolErrorKind, ToolFunction, ToolRegistry,
+};
+
+/// Steps an agent takes by default before giving up on a task.
+pub const DEFAULT_MAX_STEPS: usize = 10;
+
+/// Works on a task by letting the model call tools until it answers.
+///
+/// Each step is one model call followed by the tool calls it asked for; the results go back
+/// to the model in the next step. A run ends when the model replies without calling a tool,
+/// when a `StopCondition` is met, or after `max_steps` steps. Every model call, tool call and
+/// result is recorded in the `AgentRun`, for auditing.
+///
+/// ```no_run
+/// # use llm_sdk::{Agent, LlmSdk, Scratchpad, ToolRegistry};
+/// # async fn run(sdk: LlmSdk, tools: ToolRegistry) -> anyhow::Result<()> {
+/// let agent = Agent::new(sdk)
+///     .with_persona("You are a careful research assistant.")
+///     .with_tools(tools)
+///     .with_memory(Scratchpad::new())
+///     .with_max_steps(5);
+/// let run = agent.run("How many moons does Mars have?").await?;
+/// println!("{:?} after {} steps", run.answer, run.model_calls());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Agent {
+    provider: Arc<dyn ChatProvider>,
+    model: Option<ChatCompleteModel>,
+    persona: String,
+    tools: ToolRegistry,
+    memory: Option<Scratchpad>,
+    max_steps: usize,
+    stop_conditions: Vec<StopCondition>,
+}
+
+/// Ends a run early.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopCondition {
+    /// The model called this tool, e.g. a `final_answer` tool. Its arguments are the answer,
+    /// and it isn't run, so it needs no `ToolFunction`: add its definition with
+    /// `Agent::with_stop_tool`.
+    ToolCalled(String),
+    /// The reply contains this text, e.g. `FINAL ANSWER:`.
+    ReplyContains(String),
+}
+
+/// Notes an agent keeps across steps and runs, written with its `remember` tool and shown in
+/// its system prompt. Clones share the notes.
+#[derive(Debug, Clone, Default)]
+pub struct Scratchpad {
+    notes: Arc<Mutex<Vec<String>>>,
+}
+
+/// The outcome of `Agent::run`.
+#[derive(Debug, Clone)]
+pub struct AgentRun {
+    /// The final reply, or the arguments of the stop tool. `None` if the steps ran out.
+    pub answer: Option<String>,
+    pub stop: AgentStop,
+    pub steps: Vec<AgentStep>,
+    /// The whole conversation, system prompt included if there is one.
+    pub messages: Vec<ChatCompletionMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentStop {
+    /// The model replied without calling a tool.
+    Answered,
+    Condition(StopCondition),
+    MaxSteps,
+}
+
+/// One entry of the trace of a run. `step` counts model calls from 1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentStep {
+    ModelCall {
+        step: usize,
+        /// The model that answered, as reported by the provider.
+        model: String,
+        content: Option<String>,
+        tool_calls: Vec<ToolCall>,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+    },
+    ToolCall {
+        step: usize,
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    ToolResult {
+        step: usize,
+        id: String,
+        /// What the model was sent: the output, or the error as JSON.
+        output: String,
+        error: Option<ToolErrorKind>,
+    },
+}
+
+/// Appends a note to the scratchpad.
+struct RememberTool(Scratchpad);
+
+#[derive(Deserialize)]
+struct RememberArgs {
+    note: String,
+}
+
+impl Agent {
+    /// An agent using `provider`, e.g. an `LlmSdk`, with no persona or tools.
+    pub fn new(provider: impl ChatProvider) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            model: None,
+            persona: String::new(),
+            tools: ToolRegistry::new(),
+            memory: None,
+            max_steps: DEFAULT_MAX_STEPS,
+            stop_conditions: Vec::new(),
+        }
+    }
+
+    pub fn with_model(mut self, model: ChatCompleteModel) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// The system prompt: who the agent is and how it should work.
+    pub fn with_persona(mut self, persona: impl Into<String>) -> Self {
+        self.persona = persona.into();
+        self
+    }
+
+    /// The tools the agent may call, with their timeouts and limits, see `ToolRegistry`.
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Give the agent a `remember` tool writing to `memory`.
+    pub fn with_memory(mut self, memory: Scratchpad) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    pub fn with_stop_condition(mut self, condition: StopCondition) -> Self {
+        self.stop_conditions.push(condition);
+        self
+    }
+
+    /// Offer `tool` to the model and end the run when it is called, see `StopCondition::ToolCalled`.
+    pub fn with_stop_tool(mut self, tool: Tool) -> Self {
+        self.stop_conditions
+            .push(StopCondition::ToolCalled(tool.name().to_string()));
+        self.tools.register(StopTool(tool));
+        self
+    }
+
+    pub fn memory(&self) -> Option<&Scratchpad> {
+        self.memory.as_ref()
+    }
+
+    /// Work on `task` until the model answers, a stop condition is met or the steps run out.
+    /// Tool failures are reported to the model; only a failed model call ends the run early.
+    pub async fn run(&self, task: impl Into<String>) -> Result<AgentRun> {
+        let mut tools = self.tools.clone();
+        if let Some(memory) = &self.memory {
+            tools.register(RememberTool(memory.clone()));
+        }
+        let definitions = tools.tools();
+        let system_prompt = self.system_prompt();
+        let mut messages = Vec::new();
+        if !system_prompt.is_empty() {
+            messages.push(ChatCompletionMessage::new_system(system_prompt, ""));
+        }
+        messages.push(ChatCompletionMessage::new_user(task.into(), ""));
+        let mut steps = Vec::new();
+        for step in 1..=self.max_steps {
+            let mut req = ChatCompletionRequestBuilder::default();
+            req.messages(messages.clone()).tools(definitions.clone());
+            if let Some(model) = &self.model {
+                req.model(model.clone());
+            }
+            let res = self.provider.complete(req.build()?).await?;
+            let (model, usage) = (res.model.clone(), res.usage.clone());
+            let message = res
+                .into_assistant_message()
+                .ok_or_else(|| anyhow!("the model returned no choices"))?;
+            let ChatCompletionMessage::Assistant(reply) = &message else {
+                unreachable!("choices are assistant messages");
+            };
+            let content = reply.content().map(str::to_string);
+            let calls = reply.tool_calls().to_vec();
+            steps.push(AgentStep::ModelCall {
+                step,
+                model,
+                content: content.clone(),
+                tool_calls: calls.clone(),
+                prompt_tokens: usage.prompt_tokens,
+                completion_tokens: usage.completion_tokens,
+            });
+            messages.push(message);
+
+            let stopped = self.stop_conditions.iter().find_map(|condition| {
+                let answer = condition.answer(content.as_deref(), &calls)?;
+                Some((answer, condition))
+            });
+            if let Some((answer, condition)) = stopped {
+                return Ok(AgentRun {
+                    answer: Some(answer),
+                    stop: AgentStop::Condition(condition.clone()),
+                    steps,
+                    messages,
+                });
+            }
+            if calls.is_empty() {
+                return Ok(AgentRun {
+                    answer: content,
+                    stop: AgentStop::Answered,
+                    steps,
+                    messages,
+                });
+            }
+
+            steps.extend(calls.iter().map(|call| AgentStep::ToolCall {
+                step,
+                id: call.id.clone(),
+                name: call.function.name.clone(),
+                arguments: call.function.arguments.clone(),
+            }));
+            let outputs = tools.call_all(&calls).await;
+            steps.extend(calls.iter().zip(&outputs).map(|(call, output)| {
+                let (output, error) = match output {
+                    Ok(output) => (output.clone(), None),
+                    Err(e) => (e.to_content(), Some(e.kind)),
+                };
+                AgentStep::ToolResult {
+                    step,
+                    id: call.id.clone(),
+                    output,
+                    error,
+                }
+            }));
+            messages.extend(tools.to_messages(&calls, outputs));
+        }
+        Ok(AgentRun {
+            answer: None,
+            stop: AgentStop::MaxSteps,
+            steps,
+            messages,
+        })
+    }
+
+    fn system_prompt(&self) -> String {
+        let Some(memory) = &self.memory else {
+            return self.persona.clone();
+        };
+        let mut prompt = format!(
+            "{}\n\nUse the `remember` tool to note anything worth keeping for later steps and tasks.",
+            self.persona
+        );
+        let notes = memory.notes();
+        if !notes.is_empty() {
+            prompt.push_str("\n\nYour notes so far:");
+            for note in notes {
+                prompt.push_str("\n- ");
+                prompt.push_str(&note);
+            }
+        }
+        prompt.trim_start().to_string()
+    }
+}
+
+impl StopCondition {
+    /// The answer if the reply meets this condition.
+    fn answer(&self, content: Option<&str>, calls: &[ToolCall]) -> Option<String> {
+        match self {
+            StopCondition::ToolCalled(name) => calls
+                .iter()
+                .find(|call| &call.function.name == name)
+                .map(|call| call.function.arguments.clone()),
+            StopCondition::ReplyContains(text) => content
+                .filter(|content| content.contains(text.as_str()))
+                .map(str::to_string),
+        }
+    }
+}
+
+impl Scratchpad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn notes(&self) -> Vec<String> {
+        self.notes.lock().unwrap().clone()
+    }
+
+    pub fn push(&self, note: impl Into<String>) {
+        self.notes.lock().unwrap().push(note.into());
+    }
+
+    pub fn clear(&self) {
+        self.notes.lock().unwrap().clear();
+    }
+}
+
+impl AgentRun {
+    /// How many times the model was called.
+    pub fn model_calls(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| matches!(step, AgentStep::ModelCall { .. }))
+            .count()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ToolFunction for RememberTool {
+    fn tool(&self) -> Tool {
+        Tool::new_function(
+            "remember",
+            Some("Save a note to your scratchpad, kept for later steps and tasks.".to_string()),
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "note": { "type": "string", "description": "What to remember" }
+                },
+                "required": ["note"]
+            }),
+        )
+    }
+
+    async fn call(&self, arguments: &str) -> Result<serde_json::Value> {
+        let args: RememberArgs = serde_json::from_str(arguments)?;
+        self.0.push(args.note);
+        Ok("noted".into())
+    }
+}
+
+/// The definition of a stop tool; a run ends before it would be called.
+struct StopTool(Tool);
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ToolFunction for StopTool {
+    fn tool(&self) -> Tool {
+        self.0.clone()
+    }
+
+    async fn call(&self, _arguments: &str) -> Result<serde_json::Value> {
+        Ok(serde_json::Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        testing::{completion_json, ScriptedClient},
+        LlmSdk,
+    };
+
+    fn provider(client: ScriptedClient) -> LlmSdk {
+        LlmSdk::new("sk-test".to_string()).with_http_client(client)
+    }
+
+    struct AddTool;
+
+    #[async_trait]
+    impl ToolFunction for AddTool {
+        fn tool(&self) -> Tool {
+            Tool::new_function("add", None, json!({ "type": "object" }))
+        }
+
+        async fn call(&self, arguments: &str) -> Result<Value> {
+            let numbers: Vec<i64> = serde_json::from_str(arguments)?;
+            Ok(json!(numbers.iter().sum::<i64>()))
+        }
+    }
+
+    fn tool_calls(calls: &[(&str, &str, &str)]) -> Value {
+        let calls: Vec<_> = calls
+            .iter()
+            .map(|(id, name, arguments)| {
+                json!({
+                    "id": id,
+                    "type": "function",
+                    "function": { "name": name, "arguments": arguments }
+                })
+            })
+            .collect();
+        let mut reply = completion_json("", "tool_calls", (20, 5));
+        reply["choices"][0]["message"] =
+            json!({ "role": "assistant", "content": null, "tool_calls": calls });
+        reply
+    }
+
+    #[tokio::test]
+    async fn agent_should_call_tools_until_it_answers() -> Result<()> {
+        let client = ScriptedClient::replying([
+            tool_calls(&[
+                ("call_1", "add", "[1, 2]"),
+                ("call_2", "remember", r#"{"note": "1 + 2 = 3"}"#),
+                ("call_3", "delete_files", "{}"),
+            ]),
+            completion_json("The sum is 3.", "stop", (20, 5)),
+        ]);
+        let bodies = client.bodies.clone();
+        let memory = Scratchpad::new();
+        let agent = Agent::new(provider(client))
+            .with_persona("You add numbers.")
+            .with_tools(ToolRegistry::new().with_tool(AddTool))
+            .with_memory(memory.clone());
+        let run = agent.run("What is 1 + 2?").await?;
+
+        assert_eq!(run.answer.as_deref(), Some("The sum is 3."));
+        assert_eq!(run.stop, AgentStop::Answered);
+        assert_eq!(run.model_calls(), 2);
+        assert_eq!(run.steps.len(), 8);
+        assert!(matches!(
+            &run.steps[4..7],
+            [
+                AgentStep::ToolResult { output: sum, error: None, .. },
+                AgentStep::ToolResult { error: None, .. },
+                AgentStep::ToolResult { error: Some(ToolErrorKind::UnknownTool), .. },
+            ] if sum == "3"
+        ));
+        assert_eq!(memory.notes(), ["1 + 2 = 3"]);
+        let trace = serde_json::to_value(&run.steps)?;
+        assert_eq!(trace[0]["type"], "model_call");
+        assert_eq!(trace[1]["type"], "tool_call");
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies[0]["messages"].as_array().unwrap().len(), 2);
+        // the reply with its tool calls, then one result per call
+        assert_eq!(bodies[1]["messages"].as_array().unwrap().len(), 6);
+        assert_eq!(bodies[1]["messages"][3]["content"], "3");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn agent_should_stop_on_conditions_and_max_steps() -> Result<()> {
+        let looping = ScriptedClient::replying([
+            tool_calls(&[("call_1", "add", "[1]")]),
+            tool_calls(&[("call_2", "add", "[2]")]),
+        ]);
+        let agent = Agent::new(provider(looping))
+            .with_tools(ToolRegistry::new().with_tool(AddTool))
+            .with_max_steps(2);
+        let run = agent.run("Keep adding").await?;
+        assert_eq!(run.stop, AgentStop::MaxSteps);
+        assert_eq!(run.answer, None);
+        // no persona or memory, so no system message
+        assert_eq!(run.messages[0].content(), Some("Keep adding"));
+
+        let finishing = ScriptedClient::replying([tool_calls(&[(
+            "call_1",
+            "final_answer",
+            r#"{"answer": 42}"#,
+        )])]);
+        let final_answer = Tool::new_function("final_answer", None, json!({ "type": "object" }));
+        let run = Agent::new(provider(finishing))
+            .with_stop_tool(final_answer)
+            .run("What is the answer?")
+            .await?;
+        assert_eq!(run.answer.as_deref(), Some(r#"{"answer": 42}"#));
+        assert_eq!(
+            run.stop,
+            AgentStop::Condition(StopCondition::ToolCalled("final_answer".into()))
+        );
+        Ok(())
+    }
+}
@@ -1,9 +1,22 @@
-use crate::IntoRequest;
+use crate::{
+    repair_json,
+    validation::{is_valid_function_name, Validator},
+    Budget, CompletionTiming, ContentPart, FallbackPolicy, ImageContent, IntoRequest,
+    ProviderPreferences, RedactionMap, RequestDefaults, SearchContextSize, SpeechVoice,
+    UserContent, Validate, ValidationError, IDEMPOTENCY_KEY,
+};
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use derive_builder::Builder;
 use reqwest::{Client, RequestBuilder};
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{self, DeserializeOwned},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
-#[derive(Debug, Clone, Serialize, Builder)]
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
 pub struct ChatCompletionRequest {
     /// A list of messages comprising the conversation so far.
     #[builder(setter(into))]
@@ -27,9 +40,45 @@ pub struct ChatCompletionRequest {
     // logit_bias: Option<HashMap<String, f32>>,
 
     /// The maximum number of tokens to generate in the chat completion.
+    /// Sent as `max_completion_tokens` to reasoning models, which reject `max_tokens`.
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<usize>,
+    /// An upper bound for the number of tokens generated, including reasoning tokens.
+    /// Replaces `max_tokens` on newer models.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<usize>,
+    /// How much reasoning o-series models do before answering. Less is faster and cheaper.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<ReasoningEffort>,
+    /// The output types the model should generate, e.g. text and audio for audio models.
+    #[builder(default, setter(into))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    modalities: Vec<Modality>,
+    /// The voice and format of the audio output. Required when `modalities` includes audio.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio: Option<AudioOutput>,
+    /// Search the web before answering, for the search models such as `gpt-4o-search-preview`.
+    /// Cited pages are returned in `AssistantMessage::annotations`.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    web_search_options: Option<WebSearchOptions>,
+    /// The processing tier: `Flex` is cheaper but slower and may be unavailable at times.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service_tier: Option<ServiceTier>,
+    /// Whether to store the completion for distillation and evals, see stored completions.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<bool>,
+    /// Up to 16 key-value pairs to filter stored completions by.
+    /// Keys are up to 64 characters and values up to 512.
+    #[builder(default, setter(into))]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
     /// How many chat completion choices to generate for each input message.
     /// Note that you will be charged based on the number of generated tokens across all of the choices. Keep n as 1 to minimize costs.
     #[builder(default, setter(strip_option))]
@@ -50,17 +99,20 @@ pub struct ChatCompletionRequest {
     /// Determinism is not guaranteed, and you should refer to the system_fingerprint response parameter to monitor changes in the backend.
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    seed: Option<String>,
+    seed: Option<i64>,
     /// Up to 4 sequences where the API will stop generating further tokens.
-    // TODO: make this as an enum
-    #[builder(default, setter(strip_option))]
+    #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    stop: Option<String>,
+    stop: Option<Stop>,
     /// If set, partial message deltas will be sent, like in ChatGPT.
     /// Tokens will be sent as data-only server-sent events as they become available, with the stream terminated by a data: [DONE] message.
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     stream: Option<bool>,
+    /// Options for streaming response. Only set this when you set stream: true.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
     /// What sampling temperature to use, between 0 and 2. Higher values like 0.8 will make the output more random,
     /// while lower values like 0.2 will make it more focused and deterministic.
     /// We generally recommend altering this or top_p but not both.
@@ -76,35 +128,105 @@ pub struct ChatCompletionRequest {
     /// A list of tools the model may call. Currently, only functions are supported as a tool.
     /// Use this to provide a list of functions the model may generate JSON inputs for.
     #[builder(default, setter(into))]
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     tools: Vec<Tool>,
     /// Controls which (if any) function is called by the model.
     /// none means the model will not call a function and instead generates a message.
     /// auto means the model can pick between generating a message or calling a function.
+    /// required means the model must call one or more functions.
     /// Specifying a particular function via {"type: "function", "function": {"name": "my_function"}} forces the model to call that function.
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_choice: Option<ToolChoice>,
+    /// Whether to enable parallel function calling during tool use.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parallel_tool_calls: Option<bool>,
     /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
+    /// A user message is appended with `user_message`.
     #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<String>,
+    /// OpenRouter only: how to route the request across the providers serving the model.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<ProviderPreferences>,
+    /// OpenRouter only: transforms applied to the prompt, e.g. `middle-out` to fit the context window.
+    #[builder(default, setter(into))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    transforms: Vec<String>,
+    /// Sent as the `Idempotency-Key` header instead of a generated one, see
+    /// `LlmSdk::with_idempotency_keys`. Reuse it when retrying the call yourself. A fallback
+    /// model is sent `{key}-{model}`.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip)]
+    idempotency_key: Option<String>,
+    /// Whether the SDK response cache may serve this request. Not sent to the API.
+    /// Defaults to caching only deterministic requests (temperature 0 or a seed set).
+    /// `false` also keeps it out of the semantic cache, which otherwise serves any request.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip)]
+    cache: Option<bool>,
+    /// A budget this request counts against, on top of the one of the `LlmSdk`. Not sent to the API.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip)]
+    budget: Option<Budget>,
+    /// Models to try if this one fails, instead of the policy of the `LlmSdk`. Not sent to the API.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip)]
+    fallback: Option<FallbackPolicy>,
+    /// Parameters the SDK has no field for, e.g. provider-specific or newly launched ones,
+    /// sent as top-level fields of the body. One named like a field is sent instead of it.
+    /// Add them with `extra_param`.
+    #[builder(default, setter(into))]
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
-// #[serde(rename_all = "snake_case", tag = "type", content = "function")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum ToolChoice {
     #[default]
     None,
     Auto,
-    // TODO: we need something like this: #[serde(tag = "type", content = "function")]
+    Required,
+    /// Serialized as {"type": "function", "function": {"name": "my_function"}}.
     Function {
         name: String,
     },
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Serialize)]
+struct NamedToolChoice<'a> {
+    r#type: ToolType,
+    function: NamedFunction<'a>,
+}
+
+#[derive(Serialize)]
+struct NamedFunction<'a> {
+    name: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ToolChoiceRepr {
+    Mode(String),
+    Named { function: NamedFunctionOwned },
+}
+
+#[derive(Deserialize)]
+struct NamedFunctionOwned {
+    name: String,
+}
+
+/// One stop sequence or a list of them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Stop {
+    Text(String),
+    Texts(Vec<String>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tool {
     /// The type of the tool. Currently, only function is supported.
     r#type: ToolType,
@@ -122,21 +244,45 @@ pub struct FunctionInfo {
     parameters: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// If set, an additional chunk will be streamed before the data: [DONE] message.
+    /// The usage field on this chunk shows the token usage statistics for the entire request,
+    /// and the choices field will always be an empty array.
+    pub include_usage: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatResponseFormatObject {
     r#type: ChatResponseFormat,
+    /// The schema the output must follow, when the type is json_schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<JsonSchemaFormat>,
 }
 
-#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ChatResponseFormat {
     Text,
     #[default]
+    #[serde(rename = "json_object")]
     Json,
+    JsonSchema,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JsonSchemaFormat {
+    /// The name of the response format. Must be a-z, A-Z, 0-9, or contain underscores and dashes, with a maximum length of 64.
+    pub name: String,
+    /// The JSON Schema the output must follow.
+    pub schema: serde_json::Value,
+    /// Whether to enforce the schema exactly. Strict mode only supports a subset of JSON Schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 // https://serde.rs/enum-representations.html
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "role")]
 pub enum ChatCompletionMessage {
     /// A message from a system.
@@ -149,21 +295,100 @@ pub enum ChatCompletionMessage {
     Tool(ToolMessage),
 }
 
-#[derive(Debug, Clone, Serialize, Copy, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Modality {
+    Text,
+    Audio,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AudioOutput {
+    pub voice: SpeechVoice,
+    pub format: AudioOutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioOutputFormat {
+    #[default]
+    Wav,
+    Mp3,
+    Flac,
+    Opus,
+    Pcm16,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceTier {
+    /// The scale tier if the project has one, the default tier otherwise.
+    Auto,
+    Default,
+    Flex,
+    Scale,
+    Priority,
+    /// A tier of another provider, e.g. Groq's `on_demand`. Not to be sent.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebSearchOptions {
+    /// How much context is retrieved from the web for the answer. Defaults to medium.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_context_size: Option<SearchContextSize>,
+    /// Where the user is, to refine the search.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_location: Option<UserLocation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", content = "approximate", rename_all = "snake_case")]
+pub enum UserLocation {
+    Approximate(ApproximateLocation),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ApproximateLocation {
+    /// Free text, e.g. `San Francisco`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// The two-letter ISO country code, e.g. `US`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Free text, e.g. `California`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// The IANA timezone, e.g. `America/Los_Angeles`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
 pub enum ChatCompleteModel {
     #[default]
-    #[serde(rename = "gpt-3.5-turbo-1106")]
     Gpt3Turbo,
-    #[serde(rename = "gpt-3.5-turbo-instruct")]
     Gpt3TurboInstruct,
-    #[serde(rename = "gpt-4-1106-preview")]
     Gpt4Turbo,
-    #[serde(rename = "gpt-4-vision-preview")]
     Gpt4TurboVision,
+    /// A fine-tuned model id, e.g. `ft:gpt-3.5-turbo-0125:my-org:custom-suffix:abc123`.
+    FineTuned(String),
+    /// Any other model id, e.g. an OpenRouter slug like `anthropic/claude-3.5-sonnet`.
+    Other(String),
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemMessage {
     /// The contents of the system message.
     content: String,
@@ -172,10 +397,10 @@ pub struct SystemMessage {
     name: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserMessage {
-    /// The contents of the system message.
-    content: String,
+    /// The contents of the user message: text, or text and images for vision models.
+    content: UserContent,
     /// An optional name for the participant. Provides the model information to differentiate between participants of the same role.
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
@@ -183,24 +408,74 @@ pub struct UserMessage {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssistantMessage {
-    /// The contents of the system message.
-    content: String,
+    /// The contents of the assistant message. Null when the model only calls tools.
+    /// Replies are always text; parts are for replaying images into the conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<UserContent>,
     /// An optional name for the participant. Provides the model information to differentiate between participants of the same role.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     name: Option<String>,
     /// The tool calls generated by the model, such as function calls.
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     tool_calls: Vec<ToolCall>,
+    /// The audio reply of audio models. Only its id is sent back in later turns.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_audio_id"
+    )]
+    audio: Option<AssistantAudio>,
+    /// The sources cited by the reply, e.g. web pages found by web search. Not sent back.
+    #[serde(default, skip_serializing)]
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Annotation {
+    UrlCitation {
+        url_citation: UrlCitation,
+    },
+    /// An annotation type this SDK doesn't know yet.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct UrlCitation {
+    /// The index of the first character of the cited text in the content.
+    pub start_index: usize,
+    /// The index after the last character of the cited text in the content.
+    pub end_index: usize,
+    /// The title of the web page.
+    #[serde(default)]
+    pub title: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssistantAudio {
+    /// Refers to the audio in later turns of the conversation.
+    pub id: String,
+    /// The base64 encoded audio, in the format requested.
+    #[serde(default)]
+    pub data: String,
+    /// What the audio says.
+    #[serde(default)]
+    pub transcript: String,
+    /// The Unix timestamp (in seconds) after which `id` can no longer be used.
+    #[serde(default)]
+    pub expires_at: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
     /// The ID of the tool call.
-    id: String,
+    pub id: String,
     /// The type of the tool. Currently, only function is supported.
-    r#type: ToolType,
+    pub r#type: ToolType,
     /// The function that the model called.
-    function: FunctionCall,
+    pub function: FunctionCall,
 }
 
 #[derive(Debug, Clone, Serialize, Default, PartialEq, Eq, Copy, Deserialize)]
@@ -213,15 +488,15 @@ pub enum ToolType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionCall {
     /// The name of the function to call.
-    name: String,
+    pub name: String,
     /// The arguments to call the function with, as generated by the model in JSON format.
     /// Note that the model does not always generate valid JSON,
     /// and may hallucinate parameters not defined by your function schema.
     /// Validate the arguments in your code before calling your function.
-    arguments: String,
+    pub arguments: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolMessage {
     /// The contents of the system message.
     content: String,
@@ -241,14 +516,35 @@ pub struct ChatCompletionResponse {
     pub model: String,
     /// This fingerprint represents the backend configuration that the model runs with.
     /// Can be used in conjunction with the seed request parameter to understand when backend changes have been made that might impact determinism.
-    pub system_fingerprint: String,
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
     /// The object type, which is always chat.completion.
-    pub object: String,
+    pub object: ObjectType,
+    /// The provider that served the request, when routed through OpenRouter.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// The tier that processed the request, when `service_tier` was set.
+    #[serde(default)]
+    pub service_tier: Option<ServiceTier>,
+    /// The metadata of a stored completion, see `LlmSdk::retrieve_stored_completion`.
+    #[serde(default)]
+    pub metadata: Option<BTreeMap<String, String>>,
     /// Usage statistics for the completion request.
     pub usage: ChatCompleteUsage,
+    /// The fallback model that served the request, `None` if the requested model did.
+    /// See `FallbackPolicy`. Not part of the API response.
+    #[serde(skip)]
+    pub served_by_fallback: Option<ChatCompleteModel>,
+    /// What the scrubbers of the SDK replaced in the request, see `Scrubber`.
+    /// Not part of the API response.
+    #[serde(skip)]
+    pub redactions: RedactionMap,
+    /// How long the call took, `None` when served from a cache. Not part of the API response.
+    #[serde(skip)]
+    pub timing: Option<CompletionTiming>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChatCompleteUsage {
     /// Number of tokens in the generated completion.
     pub completion_tokens: usize,
@@ -256,6 +552,87 @@ pub struct ChatCompleteUsage {
     pub prompt_tokens: usize,
     /// Total number of tokens used in the request (prompt + completion).
     pub total_tokens: usize,
+    /// The cost of the request in credits, when routed through OpenRouter.
+    #[serde(default)]
+    pub cost: Option<f64>,
+    /// A breakdown of the prompt tokens.
+    #[serde(default)]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    /// A breakdown of the completion tokens.
+    #[serde(default)]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PromptTokensDetails {
+    /// Prompt tokens served from the prompt cache, billed at a discount.
+    #[serde(default)]
+    pub cached_tokens: usize,
+    /// Audio input tokens.
+    #[serde(default)]
+    pub audio_tokens: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CompletionTokensDetails {
+    /// Tokens generated by the model for reasoning, billed but not part of the reply.
+    #[serde(default)]
+    pub reasoning_tokens: usize,
+    /// Audio output tokens.
+    #[serde(default)]
+    pub audio_tokens: usize,
+    /// Tokens of the predicted output that appeared in the completion.
+    #[serde(default)]
+    pub accepted_prediction_tokens: usize,
+    /// Tokens of the predicted output that didn't appear in the completion, still billed.
+    #[serde(default)]
+    pub rejected_prediction_tokens: usize,
+}
+
+impl ChatCompleteUsage {
+    /// Prompt tokens served from the prompt cache.
+    pub fn cached_tokens(&self) -> usize {
+        self.prompt_tokens_details
+            .map_or(0, |details| details.cached_tokens)
+    }
+
+    /// Completion tokens spent on reasoning.
+    pub fn reasoning_tokens(&self) -> usize {
+        self.completion_tokens_details
+            .map_or(0, |details| details.reasoning_tokens)
+    }
+
+    /// The share of the prompt served from the prompt cache, `None` without prompt tokens.
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        (self.prompt_tokens > 0).then(|| self.cached_tokens() as f64 / self.prompt_tokens as f64)
+    }
+
+    /// Add the usage of another request.
+    pub(crate) fn add(&mut self, other: &ChatCompleteUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cost = match (self.cost, other.cost) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+        };
+        if let Some(other) = other.prompt_tokens_details {
+            let details = self
+                .prompt_tokens_details
+                .get_or_insert_with(Default::default);
+            details.cached_tokens += other.cached_tokens;
+            details.audio_tokens += other.audio_tokens;
+        }
+        if let Some(other) = other.completion_tokens_details {
+            let details = self
+                .completion_tokens_details
+                .get_or_insert_with(Default::default);
+            details.reasoning_tokens += other.reasoning_tokens;
+            details.audio_tokens += other.audio_tokens;
+            details.accepted_prediction_tokens += other.accepted_prediction_tokens;
+            details.rejected_prediction_tokens += other.rejected_prediction_tokens;
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -270,6 +647,68 @@ pub struct ChatCompletionChoice {
     pub index: usize,
     /// A chat completion message generated by the model.
     pub message: AssistantMessage,
+    /// Why the content filter of the provider flagged the choice, when it reports it, e.g.
+    /// Azure OpenAI. Tells what was filtered when `finish_reason` is `ContentFilter`.
+    #[serde(default)]
+    pub content_filter_results: Option<ContentFilterResults>,
+}
+
+/// The verdict of a content filter for each category it checks.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct ContentFilterResults {
+    #[serde(default)]
+    pub hate: Option<SeverityFilterResult>,
+    #[serde(default)]
+    pub self_harm: Option<SeverityFilterResult>,
+    #[serde(default)]
+    pub sexual: Option<SeverityFilterResult>,
+    #[serde(default)]
+    pub violence: Option<SeverityFilterResult>,
+    /// Whether the prompt tried to get around the rules of the model.
+    #[serde(default)]
+    pub jailbreak: Option<DetectionFilterResult>,
+    #[serde(default)]
+    pub profanity: Option<DetectionFilterResult>,
+    /// Whether the reply contains known text, e.g. song lyrics or articles.
+    #[serde(default)]
+    pub protected_material_text: Option<DetectionFilterResult>,
+    /// Whether the reply contains code of public repositories.
+    #[serde(default)]
+    pub protected_material_code: Option<DetectionFilterResult>,
+    /// Categories this SDK doesn't know, e.g. `custom_blocklists`, or the `error` of a filter
+    /// that failed to run.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub struct SeverityFilterResult {
+    /// Whether the content was withheld because of this category.
+    pub filtered: bool,
+    #[serde(default)]
+    pub severity: ContentFilterSeverity,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub struct DetectionFilterResult {
+    /// Whether the content was withheld because of this category.
+    pub filtered: bool,
+    #[serde(default)]
+    pub detected: bool,
+}
+
+/// How harmful the content of a category is, from least to most.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentFilterSeverity {
+    #[default]
+    Safe,
+    Low,
+    Medium,
+    High,
+    /// A severity this SDK doesn't know yet, ranked above `High` to err on the safe side.
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq, Copy)]
@@ -282,131 +721,1545 @@ pub enum FinishReason {
     ToolCalls,
 }
 
+/// The `object` field carried by every API payload.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(from = "String", into = "String")]
+pub enum ObjectType {
+    ChatCompletion,
+    ChatCompletionChunk,
+    TextCompletion,
+    Embedding,
+    List,
+    VectorStore,
+    VectorStoreFile,
+    VectorStoreFileBatch,
+    FineTuningJob,
+    File,
+    Upload,
+    UploadPart,
+    Page,
+    Bucket,
+    Response,
+    Model,
+    /// Any object type this SDK doesn't know about yet.
+    Other(String),
+}
+
+impl ObjectType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ObjectType::ChatCompletion => "chat.completion",
+            ObjectType::ChatCompletionChunk => "chat.completion.chunk",
+            ObjectType::TextCompletion => "text_completion",
+            ObjectType::Embedding => "embedding",
+            ObjectType::List => "list",
+            ObjectType::VectorStore => "vector_store",
+            ObjectType::VectorStoreFile => "vector_store.file",
+            ObjectType::VectorStoreFileBatch => "vector_store.files_batch",
+            ObjectType::FineTuningJob => "fine_tuning.job",
+            ObjectType::File => "file",
+            ObjectType::Upload => "upload",
+            ObjectType::UploadPart => "upload.part",
+            ObjectType::Page => "page",
+            ObjectType::Bucket => "bucket",
+            ObjectType::Response => "response",
+            ObjectType::Model => "model",
+            ObjectType::Other(s) => s,
+        }
+    }
+
+    /// Deserialize a payload after making sure its `object` field is of this type,
+    /// so a proxy returning the wrong kind of payload gives a clear error instead of a serde one.
+    pub(crate) fn parse<T: DeserializeOwned>(&self, value: serde_json::Value) -> Result<T> {
+        match value.get("object").and_then(|v| v.as_str()) {
+            Some(object) if object == self.as_str() => Ok(serde_json::from_value(value)?),
+            Some(object) => bail!(
+                "expected a `{}` payload but got `{}`",
+                self.as_str(),
+                object
+            ),
+            None => bail!(
+                "expected a `{}` payload but got one without an object type",
+                self.as_str()
+            ),
+        }
+    }
+}
+
+impl From<String> for ObjectType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "chat.completion" => ObjectType::ChatCompletion,
+            "chat.completion.chunk" => ObjectType::ChatCompletionChunk,
+            "text_completion" => ObjectType::TextCompletion,
+            "embedding" => ObjectType::Embedding,
+            "list" => ObjectType::List,
+            "vector_store" => ObjectType::VectorStore,
+            "vector_store.file" => ObjectType::VectorStoreFile,
+            "vector_store.files_batch" => ObjectType::VectorStoreFileBatch,
+            "fine_tuning.job" => ObjectType::FineTuningJob,
+            "file" => ObjectType::File,
+            "upload" => ObjectType::Upload,
+            "upload.part" => ObjectType::UploadPart,
+            "page" => ObjectType::Page,
+            "bucket" => ObjectType::Bucket,
+            "response" => ObjectType::Response,
+            "model" => ObjectType::Model,
+            _ => ObjectType::Other(s),
+        }
+    }
+}
+
+impl From<ObjectType> for String {
+    fn from(object: ObjectType) -> Self {
+        match object {
+            ObjectType::Other(s) => s,
+            _ => object.as_str().to_string(),
+        }
+    }
+}
+
 // https://platform.openai.com/docs/api-reference/chat/create
 impl IntoRequest for ChatCompletionRequest {
-    fn into_request(self, client: Client) -> RequestBuilder {
-        client
-            .post("https://api.openai.com/v1/chat/completions")
-            .json(&self)
+    fn into_request(mut self, base_url: &str, client: Client) -> RequestBuilder {
+        if self.model().is_reasoning() {
+            self.max_completion_tokens = self.max_completion_tokens();
+            self.max_tokens = None;
+        }
+        let req = json_with_extra(
+            client.post(format!("{}/chat/completions", base_url)),
+            &self,
+            &self.extra,
+        );
+        match &self.idempotency_key {
+            Some(key) => req.header(IDEMPOTENCY_KEY, key),
+            None => req,
+        }
     }
 }
 
-impl ChatCompletionMessage {
-    pub fn new_system(content: impl Into<String>, name: &str) -> ChatCompletionMessage {
-        ChatCompletionMessage::System(SystemMessage {
-            content: content.into(),
-            name: Self::get_name(name),
-        })
+/// Set `body` as the JSON body. With extra parameters it goes through a JSON object, which
+/// keeps the last value of a key, so an extra parameter replaces the field of the same name
+/// rather than duplicating it; without, the field order is kept.
+pub(crate) fn json_with_extra(
+    req: RequestBuilder,
+    body: &impl Serialize,
+    extra: &serde_json::Map<String, serde_json::Value>,
+) -> RequestBuilder {
+    if extra.is_empty() {
+        return req.json(body);
+    }
+    match serde_json::to_value(body) {
+        Ok(value) => req.json(&value),
+        // `json` fails the same way, and reports it when the request is built
+        Err(_) => req.json(body),
     }
+}
 
-    pub fn new_user(content: impl Into<String>, name: &str) -> ChatCompletionMessage {
-        ChatCompletionMessage::User(UserMessage {
-            content: content.into(),
-            name: Self::get_name(name),
-        })
+impl ChatCompleteModel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ChatCompleteModel::Gpt3Turbo => "gpt-3.5-turbo-1106",
+            ChatCompleteModel::Gpt3TurboInstruct => "gpt-3.5-turbo-instruct",
+            ChatCompleteModel::Gpt4Turbo => "gpt-4-1106-preview",
+            ChatCompleteModel::Gpt4TurboVision => "gpt-4-vision-preview",
+            ChatCompleteModel::FineTuned(id) | ChatCompleteModel::Other(id) => id,
+        }
     }
 
-    fn get_name(name: &str) -> Option<String> {
-        if name.is_empty() {
-            None
-        } else {
-            Some(name.into())
+    /// The model a fine-tuned model was trained from, or the model itself.
+    pub fn base_model(&self) -> &str {
+        match self {
+            ChatCompleteModel::FineTuned(id) => id.split(':').nth(1).unwrap_or(id),
+            // OpenRouter slugs are prefixed with the vendor, e.g. `openai/gpt-4o`
+            ChatCompleteModel::Other(id) => id.rsplit('/').next().unwrap_or(id),
+            model => model.as_str(),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::LlmSdk;
-    use anyhow::Result;
+    /// The suffix given when the fine-tuning job was created, if any.
+    pub fn fine_tuned_suffix(&self) -> Option<&str> {
+        match self {
+            ChatCompleteModel::FineTuned(id) => id.split(':').nth(3).filter(|s| !s.is_empty()),
+            _ => None,
+        }
+    }
 
-    #[test]
-    #[ignore]
-    fn chat_completion_request_tool_choice_function_serialize_should_work() {
-        let req = ChatCompletionRequestBuilder::default()
-            .tool_choice(ToolChoice::Function {
-                // r#type: ToolType::Function,
-                name: "my_function".to_string(),
-            })
-            .messages(vec![])
-            .build()
-            .unwrap();
-        let json = serde_json::to_value(req).unwrap();
-        assert_eq!(
-            json,
-            serde_json::json!({
-                "messages": [],
-                "tool_choice": {
-                    "type": "function",
-                    "function": {
-                        "name": "my_function"
-                    }
+    /// Whether the model reasons before answering, i.e. an o-series or GPT-5 model.
+    /// These take `max_completion_tokens` and `reasoning_effort`.
+    pub fn is_reasoning(&self) -> bool {
+        let base = self.base_model();
+        ["o1", "o3", "o4", "gpt-5"]
+            .iter()
+            .any(|family| base == *family || base.starts_with(&format!("{}-", family)))
+    }
+
+    /// The maximum number of tokens (prompt + completion) the model can handle.
+    pub fn context_window(&self) -> usize {
+        match self {
+            ChatCompleteModel::Gpt3Turbo => 16_385,
+            ChatCompleteModel::Gpt3TurboInstruct => 4_096,
+            ChatCompleteModel::Gpt4Turbo | ChatCompleteModel::Gpt4TurboVision => 128_000,
+            ChatCompleteModel::FineTuned(_) | ChatCompleteModel::Other(_) => {
+                let base = self.base_model();
+                if base.starts_with("gpt-4") {
+                    128_000
+                } else if base.starts_with("gpt-3.5-turbo") {
+                    16_385
+                } else {
+                    4_096
                 }
-            })
-        )
+            }
+        }
+    }
+}
+
+impl FunctionCall {
+    /// Deserialize the arguments, repairing almost-JSON (single quotes, unquoted keys,
+    /// trailing commas...) if they aren't valid JSON. See `LlmSdk::repair_function_arguments`
+    /// to have a model fix arguments beyond that.
+    pub fn parse_arguments_lenient<T: DeserializeOwned>(&self) -> Result<T> {
+        match serde_json::from_str(&self.arguments) {
+            Ok(args) => Ok(args),
+            Err(e) => serde_json::from_str(&repair_json(&self.arguments)).map_err(|_| {
+                anyhow::anyhow!("invalid arguments for function `{}`: {}", self.name, e)
+            }),
+        }
     }
+}
 
-    #[test]
-    fn chat_completion_request_tool_choice_auto_serialize_should_work() {
-        let req = ChatCompletionRequestBuilder::default()
-            .tool_choice(ToolChoice::Auto)
-            .messages(vec![])
-            .build()
-            .unwrap();
-        let json = serde_json::to_value(req).unwrap();
-        assert_eq!(
-            json,
-            serde_json::json!({
-                "messages": [],
-                "tool_choice": "auto"
-            })
-        )
+impl ChatResponseFormatObject {
+    pub fn text() -> Self {
+        Self::new(ChatResponseFormat::Text)
     }
 
-    #[test]
-    fn chat_completion_request_serialize_should_work() {
-        // let messages = vec![
-        //     ChatCompletionMessage::new_system("I can answer any question you ask me.", ""),
-        //     ChatCompletionMessage::new_user("What is human life expectancy in the world?", "user1"),
-        // ];
-        // let req = ChatCompletionRequestBuilder::default()
-        //     .tool_choice(ToolChoice::Auto)
-        //     .messages(messages)
-        //     .build()
-        //     .unwrap();
+    /// JSON mode: the output is valid JSON, in no particular shape.
+    pub fn json() -> Self {
+        Self::new(ChatResponseFormat::Json)
+    }
 
-        let req = get_simple_completion_request();
-        let json = serde_json::to_value(req).unwrap();
-        assert_eq!(
-            json,
-            serde_json::json!({
-                "messages": [{
-                    "role": "system",
-                    "content": "I can answer any question you ask me.",
-                },
-                {
-                    "role": "user",
-                    "content": "What is human life expectancy in the world?",
-                    "name": "user1"
-                }],
-                "tool_choice": "auto"
+    /// Structured outputs: the output follows `schema`.
+    pub fn json_schema(name: impl Into<String>, schema: serde_json::Value, strict: bool) -> Self {
+        Self {
+            r#type: ChatResponseFormat::JsonSchema,
+            json_schema: Some(JsonSchemaFormat {
+                name: name.into(),
+                schema,
+                strict: Some(strict),
+            }),
+        }
+    }
+
+    /// Structured outputs following the schema of `T`, without strict mode since the
+    /// generated schema doesn't restrict itself to the subset strict mode supports.
+    #[cfg(feature = "schemars")]
+    pub fn json_schema_for<T: schemars::JsonSchema>() -> Self {
+        let name: String = T::schema_name()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
             })
-        )
+            .take(64)
+            .collect();
+        let schema = serde_json::to_value(schemars::schema_for!(T)).unwrap_or_default();
+        Self {
+            r#type: ChatResponseFormat::JsonSchema,
+            json_schema: Some(JsonSchemaFormat {
+                name,
+                schema,
+                strict: None,
+            }),
+        }
     }
 
-    #[tokio::test]
-    async fn simple_chat_completion_should_work() -> Result<()> {
-        let sdk = LlmSdk::new(std::env::var("OPENAI_API_KEY")?);
-        let req = get_simple_completion_request();
+    pub fn r#type(&self) -> &ChatResponseFormat {
+        &self.r#type
+    }
 
-        let res = sdk.chat_completion(req).await?;
-        assert_eq!(res.choices.len(), 1);
-        println!("res: {:#?}", res);
-        Ok(())
+    fn new(r#type: ChatResponseFormat) -> Self {
+        Self {
+            r#type,
+            json_schema: None,
+        }
     }
+}
 
-    fn get_simple_completion_request() -> ChatCompletionRequest {
+impl From<String> for ChatCompleteModel {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "gpt-3.5-turbo-1106" => ChatCompleteModel::Gpt3Turbo,
+            "gpt-3.5-turbo-instruct" => ChatCompleteModel::Gpt3TurboInstruct,
+            "gpt-4-1106-preview" => ChatCompleteModel::Gpt4Turbo,
+            "gpt-4-vision-preview" => ChatCompleteModel::Gpt4TurboVision,
+            _ if s.starts_with("ft:") => ChatCompleteModel::FineTuned(s),
+            _ => ChatCompleteModel::Other(s),
+        }
+    }
+}
+
+impl From<ChatCompleteModel> for String {
+    fn from(model: ChatCompleteModel) -> Self {
+        match model {
+            ChatCompleteModel::FineTuned(id) | ChatCompleteModel::Other(id) => id,
+            model => model.as_str().to_string(),
+        }
+    }
+}
+
+impl ContentFilterResults {
+    /// The categories the content was withheld for, e.g. `["violence"]`.
+    pub fn filtered(&self) -> Vec<&str> {
+        let categories = [
+            ("hate", self.hate.map(|result| result.filtered)),
+            ("self_harm", self.self_harm.map(|result| result.filtered)),
+            ("sexual", self.sexual.map(|result| result.filtered)),
+            ("violence", self.violence.map(|result| result.filtered)),
+            ("jailbreak", self.jailbreak.map(|result| result.filtered)),
+            ("profanity", self.profanity.map(|result| result.filtered)),
+            (
+                "protected_material_text",
+                self.protected_material_text.map(|result| result.filtered),
+            ),
+            (
+                "protected_material_code",
+                self.protected_material_code.map(|result| result.filtered),
+            ),
+        ];
+        let known = categories
+            .into_iter()
+            .filter(|(_, filtered)| *filtered == Some(true))
+            .map(|(category, _)| category);
+        let other = self
+            .other
+            .iter()
+            .filter(|(_, result)| result["filtered"] == true)
+            .map(|(category, _)| category.as_str());
+        known.chain(other).collect()
+    }
+
+    /// The highest severity of the severity-rated categories, `None` if none was rated.
+    pub fn max_severity(&self) -> Option<ContentFilterSeverity> {
+        [self.hate, self.self_harm, self.sexual, self.violence]
+            .into_iter()
+            .flatten()
+            .map(|result| result.severity)
+            .max()
+    }
+}
+
+impl ChatCompletionRequest {
+    pub(crate) fn push_message(&mut self, message: ChatCompletionMessage) {
+        self.messages.push(message);
+    }
+
+    pub(crate) fn set_response_format(&mut self, format: ChatResponseFormatObject) {
+        self.response_format = Some(format);
+    }
+
+    pub(crate) fn clear_response_format(&mut self) {
+        self.response_format = None;
+    }
+
+    /// Ask for a JSON object, unless a response format is set already.
+    pub(crate) fn default_response_format_json(&mut self) {
+        self.response_format
+            .get_or_insert_with(ChatResponseFormatObject::json);
+    }
+
+    pub(crate) fn messages_mut(&mut self) -> &mut Vec<ChatCompletionMessage> {
+        &mut self.messages
+    }
+
+    pub(crate) fn set_stream(&mut self, stream: bool) {
+        self.stream = Some(stream);
+    }
+
+    /// Ask for the usage chunk unless the caller configured stream_options themselves.
+    pub(crate) fn request_stream_usage(&mut self) {
+        self.stream_options.get_or_insert(StreamOptions {
+            include_usage: true,
+        });
+    }
+
+    /// Unlike the exact cache, the semantic cache is opt-out: it only needs to be set on the SDK.
+    pub(crate) fn is_semantically_cacheable(&self) -> bool {
+        self.stream != Some(true) && self.cache != Some(false)
+    }
+
+    pub(crate) fn is_cacheable(&self) -> bool {
+        if self.stream == Some(true) {
+            return false;
+        }
+        self.cache
+            .unwrap_or(self.temperature == Some(0.0) || self.seed.is_some())
+    }
+
+    pub fn messages(&self) -> &[ChatCompletionMessage] {
+        &self.messages
+    }
+
+    pub fn model(&self) -> ChatCompleteModel {
+        self.model.clone().unwrap_or_default()
+    }
+
+    pub fn max_tokens(&self) -> Option<usize> {
+        self.max_tokens
+    }
+
+    /// The completion token limit, from `max_completion_tokens` or else `max_tokens`.
+    pub fn max_completion_tokens(&self) -> Option<usize> {
+        self.max_completion_tokens.or(self.max_tokens)
+    }
+
+    pub fn reasoning_effort(&self) -> Option<ReasoningEffort> {
+        self.reasoning_effort
+    }
+
+    pub fn n(&self) -> Option<usize> {
+        self.n
+    }
+
+    pub fn temperature(&self) -> Option<f32> {
+        self.temperature
+    }
+
+    pub fn budget(&self) -> Option<&Budget> {
+        self.budget.as_ref()
+    }
+
+    pub fn fallback(&self) -> Option<&FallbackPolicy> {
+        self.fallback.as_ref()
+    }
+
+    /// Resend the request to a fallback model, under an idempotency key of its own derived
+    /// from the caller's, so the server doesn't replay the failure of the first model.
+    pub(crate) fn set_fallback_model(&mut self, model: ChatCompleteModel) {
+        if let Some(key) = &mut self.idempotency_key {
+            *key = format!("{}-{}", key, model.as_str());
+        }
+        self.model = Some(model);
+    }
+
+    pub(crate) fn set_n(&mut self, n: usize) {
+        self.n = Some(n);
+    }
+
+    pub(crate) fn set_temperature(&mut self, temperature: f32) {
+        self.temperature = Some(temperature);
+    }
+
+    /// Fill the fields `req` leaves unset from `defaults`.
+    pub(crate) fn apply_defaults(&mut self, defaults: &RequestDefaults) {
+        if self.model.is_none() {
+            self.model.clone_from(&defaults.model);
+        }
+        // reasoning models only take the default temperature
+        if self.temperature.is_none() && !self.model().is_reasoning() {
+            self.temperature = defaults.temperature;
+        }
+        if self.max_completion_tokens().is_none() {
+            self.max_tokens = defaults.max_tokens;
+        }
+        if self.user.is_none() {
+            self.user.clone_from(&defaults.user);
+        }
+    }
+
+    /// Lower the completion token limit to `cap`, keeping a smaller value set by the caller.
+    pub(crate) fn cap_max_tokens(&mut self, cap: usize) {
+        let limit = if self.max_completion_tokens.is_some() {
+            &mut self.max_completion_tokens
+        } else {
+            &mut self.max_tokens
+        };
+        *limit = Some(limit.map_or(cap, |max| max.min(cap)));
+    }
+}
+
+impl ChatCompletionRequestBuilder {
+    /// Append a message to the conversation.
+    pub fn message(&mut self, message: impl Into<ChatCompletionMessage>) -> &mut Self {
+        self.messages
+            .get_or_insert_with(Vec::new)
+            .push(message.into());
+        self
+    }
+
+    /// Append a system message.
+    pub fn system(&mut self, content: impl Into<String>) -> &mut Self {
+        self.message(ChatCompletionMessage::new_system(content, ""))
+    }
+
+    /// Append a user message.
+    pub fn user_message(&mut self, content: impl Into<UserContent>) -> &mut Self {
+        self.message(ChatCompletionMessage::new_user(content, ""))
+    }
+
+    /// Append a tool the model may call.
+    pub fn tool(&mut self, tool: Tool) -> &mut Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+
+    /// Send a parameter the SDK has no field for, e.g. `top_k` for a provider that supports it.
+    pub fn extra_param(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> &mut Self {
+        self.extra
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
+}
+
+impl FromIterator<ChatCompletionMessage> for ChatCompletionRequestBuilder {
+    fn from_iter<I: IntoIterator<Item = ChatCompletionMessage>>(iter: I) -> Self {
+        let mut builder = Self::default();
+        builder.messages(iter.into_iter().collect::<Vec<_>>());
+        builder
+    }
+}
+
+impl Validate for ChatCompletionRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::default();
+        v.check(!self.messages.is_empty(), "messages", "must not be empty");
+        v.range("temperature", self.temperature, 0.0, 2.0);
+        v.range("top_p", self.top_p, 0.0, 1.0);
+        v.range("frequency_penalty", self.frequency_penalty, -2.0, 2.0);
+        v.range("presence_penalty", self.presence_penalty, -2.0, 2.0);
+        v.check(self.n != Some(0), "n", "must be at least 1");
+        v.check(
+            self.service_tier != Some(ServiceTier::Unknown),
+            "service_tier",
+            "must be a known tier",
+        );
+        v.check(
+            self.metadata.len() <= 16,
+            "metadata",
+            format!("must have at most 16 pairs, got {}", self.metadata.len()),
+        );
+        for (key, value) in &self.metadata {
+            v.max_chars(&format!("metadata.{}", key), key, 64);
+            v.max_chars(&format!("metadata.{}.value", key), value, 512);
+        }
+        v.check(
+            !self.modalities.contains(&Modality::Audio) || self.audio.is_some(),
+            "audio",
+            "must be set when modalities include audio",
+        );
+        if let Some(stop) = &self.stop {
+            stop.validate_into(&mut v);
+        }
+        for (i, tool) in self.tools.iter().enumerate() {
+            v.check(
+                is_valid_function_name(&tool.function.name),
+                format!("tools[{}].function.name", i),
+                "must be 1-64 characters of a-z, A-Z, 0-9, underscores or dashes",
+            );
+        }
+        v.finish()
+    }
+}
+
+impl Stop {
+    pub fn len(&self) -> usize {
+        match self {
+            Stop::Text(_) => 1,
+            Stop::Texts(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn validate_into(&self, v: &mut Validator) {
+        v.check(
+            self.len() <= 4,
+            "stop",
+            format!("must have at most 4 sequences, got {}", self.len()),
+        );
+    }
+}
+
+impl From<String> for Stop {
+    fn from(s: String) -> Self {
+        Stop::Text(s)
+    }
+}
+
+impl From<&str> for Stop {
+    fn from(s: &str) -> Self {
+        Stop::Text(s.to_string())
+    }
+}
+
+impl From<Vec<String>> for Stop {
+    fn from(v: Vec<String>) -> Self {
+        Stop::Texts(v)
+    }
+}
+
+impl From<Vec<&str>> for Stop {
+    fn from(v: Vec<&str>) -> Self {
+        Stop::Texts(v.into_iter().map(String::from).collect())
+    }
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            ToolChoice::None => serializer.serialize_str("none"),
+            ToolChoice::Auto => serializer.serialize_str("auto"),
+            ToolChoice::Required => serializer.serialize_str("required"),
+            ToolChoice::Function { name } => NamedToolChoice {
+                r#type: ToolType::Function,
+                function: NamedFunction { name },
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        match ToolChoiceRepr::deserialize(deserializer)? {
+            ToolChoiceRepr::Mode(mode) => match mode.as_str() {
+                "none" => Ok(ToolChoice::None),
+                "auto" => Ok(ToolChoice::Auto),
+                "required" => Ok(ToolChoice::Required),
+                _ => Err(de::Error::unknown_variant(
+                    &mode,
+                    &["none", "auto", "required"],
+                )),
+            },
+            ToolChoiceRepr::Named { function } => Ok(ToolChoice::Function {
+                name: function.name,
+            }),
+        }
+    }
+}
+
+impl Tool {
+    pub fn new_function(
+        name: impl Into<String>,
+        description: Option<String>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        Self {
+            r#type: ToolType::Function,
+            function: FunctionInfo {
+                description,
+                name: name.into(),
+                parameters,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.function.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.function.description.as_deref()
+    }
+
+    pub fn parameters(&self) -> &serde_json::Value {
+        &self.function.parameters
+    }
+}
+
+impl ChatCompletionResponse {
+    /// The first choice, the only one unless `n` was set.
+    pub fn first_choice(&self) -> Option<&ChatCompletionChoice> {
+        self.choices.first()
+    }
+
+    /// The text of the first choice, `None` if the model only called tools.
+    pub fn text(&self) -> Option<&str> {
+        self.first_choice()
+            .and_then(|choice| choice.message.content())
+    }
+
+    /// The first choice as a message to append to the conversation, tool calls included.
+    pub fn into_assistant_message(self) -> Option<ChatCompletionMessage> {
+        self.choices.into_iter().next().map(Into::into)
+    }
+
+    /// The text of every choice, in order, skipping choices that only called tools.
+    pub fn choices_text(&self) -> Vec<&str> {
+        self.choices
+            .iter()
+            .filter_map(|choice| choice.message.content())
+            .collect()
+    }
+
+    /// The choice with the highest score, the first one on a tie.
+    pub fn best_by(
+        &self,
+        mut score: impl FnMut(&ChatCompletionChoice) -> f64,
+    ) -> Option<&ChatCompletionChoice> {
+        let mut best: Option<(&ChatCompletionChoice, f64)> = None;
+        for choice in &self.choices {
+            let value = score(choice);
+            if best.is_none_or(|(_, best)| value.total_cmp(&best).is_gt()) {
+                best = Some((choice, value));
+            }
+        }
+        best.map(|(choice, _)| choice)
+    }
+}
+
+/// The answer most samples agree on, see `LlmSdk::self_consistency`.
+#[derive(Debug, Clone)]
+pub struct Consensus<T> {
+    pub answer: T,
+    /// The samples that gave `answer`.
+    pub votes: usize,
+    /// Every parsed answer with its votes, most voted first.
+    pub tally: Vec<(T, usize)>,
+    /// The response with all the samples.
+    pub response: ChatCompletionResponse,
+}
+
+/// A reply generated over several requests, see `LlmSdk::complete_long`.
+#[derive(Debug, Clone)]
+pub struct LongCompletion {
+    /// The text of every request, stitched together.
+    pub text: String,
+    /// `Length` if the token cap stopped the generation, else why the model stopped.
+    pub finish_reason: FinishReason,
+    /// The usage of all the requests.
+    pub usage: ChatCompleteUsage,
+    pub requests: usize,
+}
+
+/// A JSON reply that may have been cut off by the token limit, see `LlmSdk::complete_json`.
+#[derive(Debug, Clone)]
+pub struct JsonCompletion {
+    pub value: serde_json::Value,
+    pub repair: JsonRepair,
+    /// The usage of all the requests.
+    pub usage: ChatCompleteUsage,
+}
+
+/// How a `JsonCompletion` was made whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonRepair {
+    /// The first reply was complete.
+    Intact,
+    /// The reply was cut off and the model continued it over `continuations` more requests.
+    Continued { continuations: usize },
+    /// The reply was still cut off at the token cap and was closed locally with `close_json`,
+    /// after `continuations` more requests: the values past the cut are missing.
+    Closed { continuations: usize },
+}
+
+impl JsonRepair {
+    /// Whether the reply had been cut off.
+    pub fn repaired(&self) -> bool {
+        *self != Self::Intact
+    }
+
+    /// Whether values may be missing from the reply.
+    pub fn is_lossy(&self) -> bool {
+        matches!(self, Self::Closed { .. })
+    }
+}
+
+impl<T> Consensus<T> {
+    /// The share of answered samples that gave `answer`, from 0 to 1.
+    pub fn agreement(&self) -> f64 {
+        let answered: usize = self.tally.iter().map(|(_, votes)| votes).sum();
+        self.votes as f64 / answered as f64
+    }
+}
+
+impl From<AssistantMessage> for ChatCompletionMessage {
+    fn from(msg: AssistantMessage) -> Self {
+        ChatCompletionMessage::Assistant(msg)
+    }
+}
+
+impl From<ToolMessage> for ChatCompletionMessage {
+    fn from(msg: ToolMessage) -> Self {
+        ChatCompletionMessage::Tool(msg)
+    }
+}
+
+impl From<ChatCompletionChoice> for ChatCompletionMessage {
+    fn from(choice: ChatCompletionChoice) -> Self {
+        choice.message.into()
+    }
+}
+
+impl AssistantMessage {
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_ref().and_then(UserContent::text)
+    }
+
+    /// The images of the message, see `ChatCompletionMessage::new_assistant_with_images`.
+    pub fn images(&self) -> Vec<&ImageContent> {
+        self.content
+            .as_ref()
+            .map(UserContent::images)
+            .unwrap_or_default()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.tool_calls
+    }
+
+    pub fn audio(&self) -> Option<&AssistantAudio> {
+        self.audio.as_ref()
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// The URL citations of the reply, in order of appearance.
+    pub fn citations(&self) -> Vec<&UrlCitation> {
+        self.annotations
+            .iter()
+            .filter_map(|annotation| match annotation {
+                Annotation::UrlCitation { url_citation } => Some(url_citation),
+                Annotation::Other => None,
+            })
+            .collect()
+    }
+
+    /// The content in Markdown, with a footnote after each cited passage and the sources
+    /// listed at the end. Sources cited several times share a footnote.
+    pub fn content_with_footnotes(&self) -> Option<String> {
+        let content = self.content()?;
+        let mut citations = self.citations();
+        if citations.is_empty() {
+            return Some(content.to_string());
+        }
+        citations.sort_by_key(|citation| citation.start_index);
+        let mut sources: Vec<&UrlCitation> = Vec::new();
+        let mut markers: Vec<(usize, usize)> = Vec::new();
+        for citation in citations {
+            let n = match sources.iter().position(|s| s.url == citation.url) {
+                Some(i) => i + 1,
+                None => {
+                    sources.push(citation);
+                    sources.len()
+                }
+            };
+            markers.push((citation.end_index, n));
+        }
+
+        markers.sort_by_key(|(end, _)| *end);
+        let mut out = String::with_capacity(content.len());
+        let mut markers = markers.into_iter().peekable();
+        for (i, c) in content.chars().enumerate() {
+            while let Some((_, n)) = markers.next_if(|(end, _)| *end <= i) {
+                out.push_str(&format!("[^{}]", n));
+            }
+            out.push(c);
+        }
+        for (_, n) in markers {
+            out.push_str(&format!("[^{}]", n));
+        }
+        out.push('\n');
+        for (i, source) in sources.iter().enumerate() {
+            let title = if source.title.is_empty() {
+                &source.url
+            } else {
+                &source.title
+            };
+            out.push_str(&format!("\n[^{}]: [{}]({})", i + 1, title, source.url));
+        }
+        Some(out)
+    }
+
+    pub(crate) fn texts_mut(&mut self) -> Vec<&mut String> {
+        self.content
+            .as_mut()
+            .map(UserContent::texts_mut)
+            .unwrap_or_default()
+    }
+}
+
+impl AssistantAudio {
+    /// The audio bytes.
+    pub fn decode(&self) -> Result<Vec<u8>> {
+        Ok(STANDARD.decode(&self.data)?)
+    }
+}
+
+fn serialize_audio_id<S: Serializer>(
+    audio: &Option<AssistantAudio>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct AudioRef<'a> {
+        id: &'a str,
+    }
+    audio
+        .as_ref()
+        .map(|audio| AudioRef { id: &audio.id })
+        .serialize(serializer)
+}
+
+impl ToolMessage {
+    pub fn new(content: impl Into<String>, tool_call_id: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            tool_call_id: tool_call_id.into(),
+        }
+    }
+
+    /// The result of the tool call `tool_call_id`, encoded as JSON. Strings are sent as is.
+    ///
+    /// See `ToolMessage::truncate` and `ToolMessage::paginate` to keep large results within
+    /// a token budget.
+    pub fn from_serialize(
+        tool_call_id: impl Into<String>,
+        result: &impl Serialize,
+    ) -> Result<Self> {
+        let content = match serde_json::to_value(result)? {
+            serde_json::Value::String(s) => s,
+            value => value.to_string(),
+        };
+        Ok(Self::new(content, tool_call_id))
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn tool_call_id(&self) -> &str {
+        &self.tool_call_id
+    }
+}
+
+impl ChatCompletionMessage {
+    pub fn new_system(content: impl Into<String>, name: &str) -> ChatCompletionMessage {
+        ChatCompletionMessage::System(SystemMessage {
+            content: content.into(),
+            name: Self::get_name(name),
+        })
+    }
+
+    pub fn new_user(content: impl Into<UserContent>, name: &str) -> ChatCompletionMessage {
+        ChatCompletionMessage::User(UserMessage {
+            content: content.into(),
+            name: Self::get_name(name),
+        })
+    }
+
+    /// Create an assistant message, e.g. to replay the tool calls of a previous response.
+    /// An empty content is sent as null.
+    pub fn new_assistant(
+        content: impl Into<String>,
+        name: &str,
+        tool_calls: Vec<ToolCall>,
+    ) -> ChatCompletionMessage {
+        let content = content.into();
+        ChatCompletionMessage::Assistant(AssistantMessage {
+            content: (!content.is_empty()).then_some(content.into()),
+            name: Self::get_name(name),
+            tool_calls,
+            audio: None,
+            annotations: Vec::new(),
+        })
+    }
+
+    /// Create an assistant message showing images, e.g. the ones it generated, so later turns
+    /// can refer to them in a generate, critique, regenerate loop.
+    ///
+    /// OpenAI only accepts text in assistant messages; for its models, replay the images
+    /// in a user message instead.
+    pub fn new_assistant_with_images(
+        content: impl Into<String>,
+        images: impl IntoIterator<Item = ImageContent>,
+    ) -> ChatCompletionMessage {
+        let content = content.into();
+        let text = (!content.is_empty()).then(|| ContentPart::text(content));
+        let parts = text
+            .into_iter()
+            .chain(images.into_iter().map(ContentPart::image))
+            .collect::<Vec<_>>();
+        ChatCompletionMessage::Assistant(AssistantMessage {
+            content: Some(parts.into()),
+            name: None,
+            tool_calls: Vec::new(),
+            audio: None,
+            annotations: Vec::new(),
+        })
+    }
+
+    /// Create a message with the result of the tool call `tool_call_id`.
+    pub fn new_tool(
+        content: impl Into<String>,
+        tool_call_id: impl Into<String>,
+    ) -> ChatCompletionMessage {
+        ChatCompletionMessage::Tool(ToolMessage {
+            content: content.into(),
+            tool_call_id: tool_call_id.into(),
+        })
+    }
+
+    /// Every text of the message, for rewriting it in place.
+    pub(crate) fn texts_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            ChatCompletionMessage::System(msg) => vec![&mut msg.content],
+            ChatCompletionMessage::User(msg) => msg.content.texts_mut(),
+            ChatCompletionMessage::Assistant(msg) => msg.texts_mut(),
+            ChatCompletionMessage::Tool(msg) => vec![&mut msg.content],
+        }
+    }
+
+    pub fn content(&self) -> Option<&str> {
+        match self {
+            ChatCompletionMessage::System(msg) => Some(&msg.content),
+            ChatCompletionMessage::User(msg) => msg.content.text(),
+            ChatCompletionMessage::Assistant(msg) => msg.content(),
+            ChatCompletionMessage::Tool(msg) => Some(&msg.content),
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ChatCompletionMessage::System(msg) => msg.name.as_deref(),
+            ChatCompletionMessage::User(msg) => msg.name.as_deref(),
+            ChatCompletionMessage::Assistant(msg) => msg.name.as_deref(),
+            ChatCompletionMessage::Tool(_) => None,
+        }
+    }
+
+    fn get_name(name: &str) -> Option<String> {
+        if name.is_empty() {
+            None
+        } else {
+            Some(name.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::completion_json, ImageDetail, InputAudio, InputAudioFormat, LlmSdk};
+
+    #[test]
+    fn chat_completion_request_tool_choice_function_serialize_should_work() {
+        let req = ChatCompletionRequestBuilder::default()
+            .tool_choice(ToolChoice::Function {
+                name: "my_function".to_string(),
+            })
+            .messages(vec![])
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(req).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "messages": [],
+                "tool_choice": {
+                    "type": "function",
+                    "function": {
+                        "name": "my_function"
+                    }
+                }
+            })
+        )
+    }
+
+    #[test]
+    fn chat_completion_request_tool_choice_auto_serialize_should_work() {
+        let req = ChatCompletionRequestBuilder::default()
+            .tool_choice(ToolChoice::Auto)
+            .messages(vec![])
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(req).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "messages": [],
+                "tool_choice": "auto"
+            })
+        )
+    }
+
+    #[test]
+    fn chat_completion_request_tool_choice_required_serialize_should_work() {
+        let req = ChatCompletionRequestBuilder::default()
+            .tool_choice(ToolChoice::Required)
+            .parallel_tool_calls(false)
+            .messages(vec![])
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(req).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "messages": [],
+                "tool_choice": "required",
+                "parallel_tool_calls": false
+            })
+        )
+    }
+
+    #[test]
+    fn chat_completion_request_serialize_should_work() {
+        // let messages = vec![
+        //     ChatCompletionMessage::new_system("I can answer any question you ask me.", ""),
+        //     ChatCompletionMessage::new_user("What is human life expectancy in the world?", "user1"),
+        // ];
+        // let req = ChatCompletionRequestBuilder::default()
+        //     .tool_choice(ToolChoice::Auto)
+        //     .messages(messages)
+        //     .build()
+        //     .unwrap();
+
+        let req = get_simple_completion_request();
+        let json = serde_json::to_value(req).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "messages": [{
+                    "role": "system",
+                    "content": "I can answer any question you ask me.",
+                },
+                {
+                    "role": "user",
+                    "content": "What is human life expectancy in the world?",
+                    "name": "user1"
+                }],
+                "tool_choice": "auto"
+            })
+        )
+    }
+
+    #[test]
+    fn chat_completion_request_stream_options_serialize_should_work() {
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![])
+            .stream(true)
+            .stream_options(StreamOptions {
+                include_usage: true,
+            })
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(req).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "messages": [],
+                "stream": true,
+                "stream_options": {
+                    "include_usage": true
+                }
+            })
+        )
+    }
+
+    #[test]
+    fn chat_completion_request_cacheable_should_default_to_deterministic() {
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![])
+            .build()
+            .unwrap();
+        assert!(!req.is_cacheable());
+
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![])
+            .temperature(0.0)
+            .build()
+            .unwrap();
+        assert!(req.is_cacheable());
+
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![])
+            .temperature(0.0)
+            .cache(false)
+            .build()
+            .unwrap();
+        assert!(!req.is_cacheable());
+        assert_eq!(
+            serde_json::to_value(req).unwrap(),
+            serde_json::json!({ "messages": [], "temperature": 0.0 })
+        );
+    }
+
+    #[test]
+    fn chat_completion_request_seed_serialize_should_be_numeric() {
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![])
+            .seed(42)
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(req).unwrap();
+        assert_eq!(json, serde_json::json!({ "messages": [], "seed": 42 }));
+        assert!(json["seed"].is_i64());
+    }
+
+    #[test]
+    fn chat_completion_request_deserialize_should_round_trip() {
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![
+                ChatCompletionMessage::new_system("I can answer any question you ask me.", ""),
+                ChatCompletionMessage::new_user("What's the weather in Paris?", "user1"),
+            ])
+            .model(ChatCompleteModel::Gpt4Turbo)
+            .tools(vec![Tool {
+                r#type: ToolType::Function,
+                function: FunctionInfo {
+                    description: Some("Get the current weather".to_string()),
+                    name: "get_weather".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": { "location": { "type": "string" } }
+                    }),
+                },
+            }])
+            .tool_choice(ToolChoice::Function {
+                name: "get_weather".to_string(),
+            })
+            .seed(42)
+            .temperature(0.5)
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(&req).unwrap();
+        let loaded: ChatCompletionRequest = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), json);
+        assert_eq!(loaded.model(), ChatCompleteModel::Gpt4Turbo);
+    }
+
+    #[test]
+    fn chat_completion_request_validate_should_report_every_violation() {
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![])
+            .temperature(2.5)
+            .top_p(0.5)
+            .n(0)
+            .stop(vec!["a", "b", "c", "d", "e"])
+            .tools(vec![Tool {
+                r#type: ToolType::Function,
+                function: FunctionInfo {
+                    description: None,
+                    name: "get weather".to_string(),
+                    parameters: serde_json::json!({ "type": "object", "properties": {} }),
+                },
+            }])
+            .build()
+            .unwrap();
+        let err = req.validate().unwrap_err();
+        let fields: Vec<_> = err.violations.iter().map(|v| v.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "messages",
+                "temperature",
+                "n",
+                "stop",
+                "tools[0].function.name"
+            ]
+        );
+        assert!(get_simple_completion_request().validate().is_ok());
+    }
+
+    #[test]
+    fn chat_completion_request_stop_serialize_should_work() {
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![ChatCompletionMessage::new_user("hi", "")])
+            .stop(vec!["\n", "END"])
+            .build()
+            .unwrap();
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(json["stop"], serde_json::json!(["\n", "END"]));
+    }
+
+    #[test]
+    fn chat_completion_request_builder_helpers_should_append() -> Result<()> {
+        let req = ChatCompletionRequestBuilder::default()
+            .system("You are a helpful assistant.")
+            .user_message("What's the weather in Boston?")
+            .tool(Tool::new_function(
+                "get_weather",
+                None,
+                serde_json::json!({ "type": "object" }),
+            ))
+            .user("user1")
+            .build()?;
+        let from_iter: ChatCompletionRequestBuilder = [
+            ChatCompletionMessage::new_system("You are a helpful assistant.", ""),
+            ChatCompletionMessage::new_user("What's the weather in Boston?", ""),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            serde_json::to_value(&req.messages)?,
+            serde_json::to_value(from_iter.build()?.messages)?
+        );
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            serde_json::json!({
+                "messages": [
+                    { "role": "system", "content": "You are a helpful assistant." },
+                    { "role": "user", "content": "What's the weather in Boston?" }
+                ],
+                "tools": [{
+                    "type": "function",
+                    "function": { "description": null, "name": "get_weather", "parameters": { "type": "object" } }
+                }],
+                "user": "user1"
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tool_choice_deserialize_should_work() {
+        for choice in [
+            ToolChoice::None,
+            ToolChoice::Auto,
+            ToolChoice::Required,
+            ToolChoice::Function {
+                name: "my_function".to_string(),
+            },
+        ] {
+            let json = serde_json::to_value(&choice).unwrap();
+            assert_eq!(serde_json::from_value::<ToolChoice>(json).unwrap(), choice);
+        }
+        assert!(serde_json::from_value::<ToolChoice>(serde_json::json!("sometimes")).is_err());
+    }
+
+    #[test]
+    fn assistant_message_with_null_content_should_deserialize() {
+        let msg: AssistantMessage = serde_json::from_value(serde_json::json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_abc",
+                "type": "function",
+                "function": { "name": "get_weather", "arguments": "{\"location\": \"Paris\"}" }
+            }]
+        }))
+        .unwrap();
+        assert_eq!(msg.content(), None);
+        assert_eq!(msg.tool_calls()[0].function.name, "get_weather");
+    }
+
+    #[test]
+    fn response_text_should_be_first_choice_content() {
+        let mut value = completion_json("Hello!", "stop", (9, 4));
+        let second = serde_json::json!({
+            "index": 1,
+            "message": { "role": "assistant", "content": "Hi!" },
+            "finish_reason": "stop"
+        });
+        value["choices"].as_array_mut().unwrap().push(second);
+        let res: ChatCompletionResponse = ObjectType::ChatCompletion.parse(value).unwrap();
+        assert_eq!(res.first_choice().unwrap().index, 0);
+        assert_eq!(res.text(), Some("Hello!"));
+    }
+
+    #[test]
+    fn into_assistant_message_should_keep_tool_calls() {
+        let mut value = completion_json("", "tool_calls", (9, 4));
+        value["choices"][0]["message"] = serde_json::json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_abc",
+                "type": "function",
+                "function": { "name": "get_weather", "arguments": "{}" }
+            }]
+        });
+        let res: ChatCompletionResponse = ObjectType::ChatCompletion.parse(value).unwrap();
+        let msg = res.into_assistant_message().unwrap();
+        assert_eq!(
+            serde_json::to_value(&msg).unwrap(),
+            serde_json::json!({
+                "role": "assistant",
+                "tool_calls": [{
+                    "id": "call_abc",
+                    "type": "function",
+                    "function": { "name": "get_weather", "arguments": "{}" }
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn tool_call_round_trip_messages_serialize_should_work() {
+        let call: ToolCall = serde_json::from_value(serde_json::json!({
+            "id": "call_abc",
+            "type": "function",
+            "function": { "name": "get_weather", "arguments": "{}" }
+        }))
+        .unwrap();
+        let messages = vec![
+            ChatCompletionMessage::new_assistant("", "", vec![call]),
+            ChatCompletionMessage::new_tool("22C and sunny", "call_abc"),
+        ];
+        assert_eq!(
+            serde_json::to_value(messages).unwrap(),
+            serde_json::json!([
+                {
+                    "role": "assistant",
+                    "tool_calls": [{
+                        "id": "call_abc",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": "{}" }
+                    }]
+                },
+                {
+                    "role": "tool",
+                    "content": "22C and sunny",
+                    "tool_call_id": "call_abc"
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn chat_complete_model_should_accept_fine_tuned_ids() {
+        let model: ChatCompleteModel = serde_json::from_value(serde_json::json!(
+            "ft:gpt-4o-mini-2024-07-18:org:weather:abc"
+        ))
+        .unwrap();
+        assert_eq!(model.base_model(), "gpt-4o-mini-2024-07-18");
+        assert_eq!(model.fine_tuned_suffix(), Some("weather"));
+        assert_eq!(model.context_window(), 128_000);
+        assert_eq!(
+            serde_json::to_value(&model).unwrap(),
+            "ft:gpt-4o-mini-2024-07-18:org:weather:abc"
+        );
+
+        let model: ChatCompleteModel =
+            serde_json::from_value(serde_json::json!("gpt-4-1106-preview")).unwrap();
+        assert_eq!(model, ChatCompleteModel::Gpt4Turbo);
+        let model: ChatCompleteModel =
+            serde_json::from_value(serde_json::json!("openai/gpt-4o")).unwrap();
+        assert_eq!(model, ChatCompleteModel::Other("openai/gpt-4o".to_string()));
+        assert_eq!(model.context_window(), 128_000);
+    }
+
+    #[test]
+    fn object_type_should_round_trip() {
+        let object: ObjectType =
+            serde_json::from_value(serde_json::json!("chat.completion")).unwrap();
+        assert_eq!(object, ObjectType::ChatCompletion);
+        let object: ObjectType = serde_json::from_value(serde_json::json!("thread.run")).unwrap();
+        assert_eq!(object, ObjectType::Other("thread.run".to_string()));
+        assert_eq!(
+            serde_json::to_value(object).unwrap(),
+            serde_json::json!("thread.run")
+        );
+    }
+
+    #[test]
+    fn object_type_parse_should_reject_wrong_payload_kind() {
+        let chunk = serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1700000000,
+            "model": "gpt-3.5-turbo-1106",
+            "choices": []
+        });
+        let err = ObjectType::ChatCompletion
+            .parse::<ChatCompletionResponse>(chunk)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a `chat.completion` payload but got `chat.completion.chunk`"
+        );
+    }
+
+    #[test]
+    fn parse_arguments_lenient_should_repair_json() -> Result<()> {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Args {
+            location: String,
+            days: Vec<u8>,
+        }
+        let call = FunctionCall {
+            name: "get_weather".to_string(),
+            arguments: "{location: 'Paris', days: [1, 2,],}".to_string(),
+        };
+        assert_eq!(
+            call.parse_arguments_lenient::<Args>()?,
+            Args {
+                location: "Paris".to_string(),
+                days: vec![1, 2]
+            }
+        );
+        let call = FunctionCall {
+            name: "get_weather".to_string(),
+            arguments: "{location: ".to_string(),
+        };
+        let err = call.parse_arguments_lenient::<Args>().unwrap_err();
+        assert!(err.to_string().contains("get_weather"));
+        Ok(())
+    }
+
+    #[test]
+    fn response_format_should_serialize() -> Result<()> {
+        assert_eq!(
+            serde_json::to_value(ChatResponseFormatObject::json())?,
+            serde_json::json!({ "type": "json_object" })
+        );
+        let schema = serde_json::json!({ "type": "object" });
+        assert_eq!(
+            serde_json::to_value(ChatResponseFormatObject::json_schema(
+                "answer", schema, true
+            ))?,
+            serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": "answer", "schema": { "type": "object" }, "strict": true }
+            })
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn response_format_should_use_schema_of_type() -> Result<()> {
+        #[allow(dead_code)]
+        #[derive(schemars::JsonSchema)]
+        struct Person {
+            name: String,
+            age: Option<u8>,
+        }
+        let value = serde_json::to_value(ChatResponseFormatObject::json_schema_for::<Person>())?;
+        assert_eq!(value["json_schema"]["name"], "Person");
+        assert_eq!(
+            value["json_schema"]["schema"]["required"],
+            serde_json::json!(["name"])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn simple_chat_completion_should_work() -> Result<()> {
+        let sdk = LlmSdk::new(std::env::var("OPENAI_API_KEY")?);
+        let req = get_simple_completion_request();
+
+        let res = sdk.chat_completion(req).await?;
+        assert_eq!(res.choices.len(), 1);
+        println!("res: {:#?}", res);
+        Ok(())
+    }
+
+    fn get_simple_completion_request() -> ChatCompletionRequest {
         let messages = vec![
             ChatCompletionMessage::new_system("I can answer any question you ask me.", ""),
             ChatCompletionMessage::new_user("What is human life expectancy in the world?", "user1"),
@@ -417,4 +2270,351 @@ mod tests {
             .build()
             .unwrap()
     }
+
+    #[test]
+    fn reasoning_model_request_should_send_max_completion_tokens() -> Result<()> {
+        let body = |model: &str| -> Result<serde_json::Value> {
+            let req = ChatCompletionRequestBuilder::default()
+                .model(ChatCompleteModel::Other(model.to_string()))
+                .user_message("hi")
+                .max_tokens(500)
+                .reasoning_effort(ReasoningEffort::Low)
+                .build()?;
+            let req = req
+                .into_request(crate::OPENAI_BASE_URL, Client::new())
+                .build()?;
+            Ok(serde_json::from_slice(
+                req.body().unwrap().as_bytes().unwrap(),
+            )?)
+        };
+        let o3 = body("o3-mini")?;
+        assert_eq!(o3["max_completion_tokens"], 500);
+        assert_eq!(o3["reasoning_effort"], "low");
+        assert!(o3.get("max_tokens").is_none());
+        let gpt4 = body("gpt-4o")?;
+        assert_eq!(gpt4["max_tokens"], 500);
+        assert!(gpt4.get("max_completion_tokens").is_none());
+        assert!(!ChatCompleteModel::Other("o10-preview".to_string()).is_reasoning());
+        Ok(())
+    }
+
+    #[test]
+    fn chat_complete_usage_should_deserialize_details() -> Result<()> {
+        let usage: ChatCompleteUsage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 2048,
+            "completion_tokens": 140,
+            "total_tokens": 2188,
+            "prompt_tokens_details": { "cached_tokens": 1536, "audio_tokens": 0 },
+            "completion_tokens_details": {
+                "reasoning_tokens": 128,
+                "audio_tokens": 0,
+                "accepted_prediction_tokens": 0,
+                "rejected_prediction_tokens": 3
+            }
+        }))?;
+        assert_eq!(usage.reasoning_tokens(), 128);
+        assert_eq!(usage.cached_tokens(), 1536);
+        assert_eq!(usage.cache_hit_ratio(), Some(0.75));
+        let details = usage.completion_tokens_details.unwrap();
+        assert_eq!(details.rejected_prediction_tokens, 3);
+
+        let usage: ChatCompleteUsage = serde_json::from_value(serde_json::json!({
+            "prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0
+        }))?;
+        assert_eq!(usage.cached_tokens(), 0);
+        assert_eq!(usage.cache_hit_ratio(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn audio_chat_completion_should_round_trip() -> Result<()> {
+        let req = ChatCompletionRequestBuilder::default()
+            .model(ChatCompleteModel::Other("gpt-4o-audio-preview".to_string()))
+            .modalities(vec![Modality::Text, Modality::Audio])
+            .audio(AudioOutput {
+                voice: SpeechVoice::Coral,
+                format: AudioOutputFormat::Wav,
+            })
+            .user_message(vec![
+                ContentPart::text("What is in this recording?"),
+                ContentPart::audio(InputAudio::from_bytes(b"RIFF", InputAudioFormat::Wav)),
+            ])
+            .build()?;
+        let body = serde_json::to_value(&req)?;
+        assert_eq!(body["modalities"], serde_json::json!(["text", "audio"]));
+        assert_eq!(
+            body["audio"],
+            serde_json::json!({ "voice": "coral", "format": "wav" })
+        );
+        assert_eq!(
+            body["messages"][0]["content"][1],
+            serde_json::json!({
+                "type": "input_audio",
+                "input_audio": { "data": "UklGRg==", "format": "wav" }
+            })
+        );
+
+        let mut value = completion_json("", "stop", (20, 30));
+        value["choices"][0]["message"] = serde_json::json!({
+            "role": "assistant",
+            "content": null,
+            "audio": {
+                "id": "audio_abc",
+                "data": "UklGRg==",
+                "expires_at": 1729018505,
+                "transcript": "A dog barking."
+            }
+        });
+        let res: ChatCompletionResponse = serde_json::from_value(value)?;
+        let audio = res.choices[0].message.audio().unwrap();
+        assert_eq!(audio.transcript, "A dog barking.");
+        assert_eq!(audio.decode()?, b"RIFF");
+
+        let msg = res.into_assistant_message().unwrap();
+        assert_eq!(
+            serde_json::to_value(&msg)?,
+            serde_json::json!({ "role": "assistant", "audio": { "id": "audio_abc" } })
+        );
+
+        let missing_audio = ChatCompletionRequestBuilder::default()
+            .modalities(vec![Modality::Audio])
+            .user_message("hi")
+            .build()?;
+        assert!(missing_audio.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn web_search_citations_should_render_as_footnotes() -> Result<()> {
+        let req = ChatCompletionRequestBuilder::default()
+            .model(ChatCompleteModel::Other(
+                "gpt-4o-search-preview".to_string(),
+            ))
+            .web_search_options(WebSearchOptions {
+                search_context_size: Some(SearchContextSize::Low),
+                user_location: Some(UserLocation::Approximate(ApproximateLocation {
+                    country: Some("US".to_string()),
+                    ..Default::default()
+                })),
+            })
+            .user_message("What's new in Rust 1.80?")
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?["web_search_options"],
+            serde_json::json!({
+                "search_context_size": "low",
+                "user_location": { "type": "approximate", "approximate": { "country": "US" } }
+            })
+        );
+
+        let blog = "https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html";
+        let docs = "https://doc.rust-lang.org/std/sync/struct.LazyLock.html";
+        let message: AssistantMessage = serde_json::from_value(serde_json::json!({
+            "role": "assistant",
+            "content": "Rust 1.80 shipped in July. It added LazyLock.",
+            "annotations": [
+                { "type": "url_citation", "url_citation": {
+                    "start_index": 0, "end_index": 26, "title": "Announcing Rust 1.80", "url": blog
+                }},
+                { "type": "url_citation", "url_citation": {
+                    "start_index": 27, "end_index": 45, "title": "Announcing Rust 1.80", "url": blog
+                }},
+                { "type": "url_citation", "url_citation": {
+                    "start_index": 27, "end_index": 45, "url": docs
+                }},
+                { "type": "file_citation", "file_citation": { "file_id": "file-1" } }
+            ]
+        }))?;
+        assert_eq!(message.citations().len(), 3);
+        assert_eq!(message.annotations()[3], Annotation::Other);
+        assert_eq!(
+            message.content_with_footnotes().unwrap(),
+            format!(
+                "Rust 1.80 shipped in July.[^1] It added LazyLock.[^1][^2]\n\n\
+                 [^1]: [Announcing Rust 1.80]({blog})\n[^2]: [{docs}]({docs})"
+            )
+        );
+        assert!(serde_json::to_value(ChatCompletionMessage::from(message))?
+            .get("annotations")
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn stored_flex_request_should_serialize() -> Result<()> {
+        let req = ChatCompletionRequestBuilder::default()
+            .user_message("hi")
+            .service_tier(ServiceTier::Flex)
+            .store(true)
+            .metadata([("team".to_string(), "search".to_string())])
+            .build()?;
+        let body = serde_json::to_value(&req)?;
+        assert_eq!(body["service_tier"], "flex");
+        assert_eq!(body["store"], true);
+        assert_eq!(body["metadata"], serde_json::json!({ "team": "search" }));
+
+        let mut value = completion_json("Hi!", "stop", (8, 2));
+        value["service_tier"] = "flex".into();
+        let res: ChatCompletionResponse = serde_json::from_value(value)?;
+        assert_eq!(res.service_tier, Some(ServiceTier::Flex));
+        let mut value = completion_json("Hi!", "stop", (8, 2));
+        value["service_tier"] = "on_demand".into();
+        let res: ChatCompletionResponse = serde_json::from_value(value)?;
+        assert_eq!(res.service_tier, Some(ServiceTier::Unknown));
+
+        let too_many = ChatCompletionRequestBuilder::default()
+            .user_message("hi")
+            .metadata(
+                (0..17)
+                    .map(|i| (i.to_string(), String::new()))
+                    .collect::<BTreeMap<_, _>>(),
+            )
+            .build()?;
+        assert!(too_many.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn extra_params_should_be_sent_as_top_level_fields() -> Result<()> {
+        let req = ChatCompletionRequestBuilder::default()
+            .user_message("hi")
+            .temperature(0.5)
+            .extra_param("top_k", 40)
+            .extra_param("temperature", 2.5)
+            .build()?;
+        let http = req
+            .clone()
+            .into_request(crate::OPENAI_BASE_URL, Client::new())
+            .build()?;
+        let body: serde_json::Value =
+            serde_json::from_slice(http.body().unwrap().as_bytes().unwrap())?;
+        assert_eq!(body["top_k"], 40);
+        // replaces the field, so a value the SDK doesn't allow can still be sent
+        assert_eq!(body["temperature"], 2.5);
+
+        // unknown fields are kept when a stored request is read back
+        let req: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "messages": [{ "role": "user", "content": "hi" }],
+            "min_p": 0.1
+        }))?;
+        assert_eq!(serde_json::to_value(&req)?["min_p"], 0.1);
+
+        // a body that can't be serialized fails the request rather than sending `null`
+        struct Unserializable;
+        impl Serialize for Unserializable {
+            fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("no way"))
+            }
+        }
+        let extra = serde_json::Map::from_iter([("top_k".to_string(), 40.into())]);
+        let req = json_with_extra(
+            Client::new().post("http://localhost"),
+            &Unserializable,
+            &extra,
+        );
+        assert!(req.build().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn chat_completion_messages_deserialize_should_round_trip() -> Result<()> {
+        let history = serde_json::json!([
+            { "role": "system", "content": "You describe photos.", "name": "describer" },
+            {
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "What's in this photo, and the weather there?" },
+                    {
+                        "type": "image_url",
+                        "image_url": { "url": "https://example.com/paris.jpg", "detail": "low" }
+                    }
+                ]
+            },
+            {
+                "role": "assistant",
+                "tool_calls": [{
+                    "id": "call_abc",
+                    "type": "function",
+                    "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+                }]
+            },
+            { "role": "tool", "content": "22C and sunny", "tool_call_id": "call_abc" },
+            { "role": "assistant", "content": "The Eiffel Tower, on a sunny day." }
+        ]);
+        let messages: Vec<ChatCompletionMessage> = serde_json::from_value(history.clone())?;
+        assert_eq!(serde_json::to_value(&messages)?, history);
+
+        assert_eq!(messages[0].name(), Some("describer"));
+        assert_eq!(
+            messages[1].content(),
+            Some("What's in this photo, and the weather there?")
+        );
+        let built = ChatCompletionMessage::new_user(
+            vec![
+                ContentPart::text("What's in this photo, and the weather there?"),
+                ContentPart::image(
+                    ImageContent::new("https://example.com/paris.jpg")
+                        .with_detail(ImageDetail::Low),
+                ),
+            ],
+            "",
+        );
+        assert_eq!(
+            serde_json::to_value(&messages[1])?,
+            serde_json::to_value(built)?
+        );
+        let ChatCompletionMessage::Assistant(assistant) = &messages[2] else {
+            panic!("expected an assistant message");
+        };
+        assert_eq!(assistant.content(), None);
+        assert_eq!(assistant.tool_calls()[0].function.name, "get_weather");
+        let ChatCompletionMessage::Tool(tool) = &messages[3] else {
+            panic!("expected a tool message");
+        };
+        assert_eq!(tool.tool_call_id(), "call_abc");
+
+        let err = serde_json::from_value::<ChatCompletionMessage>(serde_json::json!({
+            "role": "narrator",
+            "content": "Meanwhile..."
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown variant `narrator`"));
+        Ok(())
+    }
+
+    #[test]
+    fn content_filter_results_should_tell_what_was_filtered() -> Result<()> {
+        let mut value = completion_json("", "content_filter", (12, 0));
+        value["choices"][0]["message"]["content"] = serde_json::Value::Null;
+        value["choices"][0]["content_filter_results"] = serde_json::json!({
+            "hate": { "filtered": false, "severity": "safe" },
+            "self_harm": { "filtered": false, "severity": "low" },
+            "violence": { "filtered": true, "severity": "high" },
+            "protected_material_text": { "filtered": false, "detected": false },
+            "custom_blocklists": {
+                "filtered": true,
+                "details": [{ "filtered": true, "id": "competitors" }]
+            }
+        });
+        let res: ChatCompletionResponse = serde_json::from_value(value)?;
+        let choice = &res.choices[0];
+        assert_eq!(choice.finish_reason, FinishReason::ContentFilter);
+        let results = choice.content_filter_results.as_ref().unwrap();
+        assert_eq!(
+            results.violence,
+            Some(SeverityFilterResult {
+                filtered: true,
+                severity: ContentFilterSeverity::High
+            })
+        );
+        assert_eq!(results.sexual, None);
+        assert_eq!(results.filtered(), ["violence", "custom_blocklists"]);
+        assert_eq!(results.max_severity(), Some(ContentFilterSeverity::High));
+
+        // providers without a content filter report nothing
+        let res: ChatCompletionResponse =
+            serde_json::from_value(completion_json("Hi!", "stop", (12, 2)))?;
+        assert!(res.choices[0].content_filter_results.is_none());
+        Ok(())
+    }
 }
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::{ChatCompleteUsage, ContentFilterResults, FinishReason, ObjectType, ToolType};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionChunk {
+    /// A unique identifier for the chat completion. Each chunk has the same ID.
+    pub id: String,
+    /// A list of chat completion choices. Can be more than one if n is greater than 1.
+    pub choices: Vec<ChunkChoice>,
+    /// The Unix timestamp (in seconds) of when the chat completion was created. Each chunk has the same timestamp.
+    pub created: usize,
+    /// The model to generate the completion.
+    pub model: String,
+    /// This fingerprint represents the backend configuration that the model runs with.
+    /// Can be used in conjunction with the seed request parameter to understand when backend changes have been made that might impact determinism.
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
+    /// The object type, which is always chat.completion.chunk.
+    pub object: ObjectType,
+    /// Usage statistics for the whole request. Only present on the final chunk, which has no choices.
+    #[serde(default)]
+    pub usage: Option<ChatCompleteUsage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChunkChoice {
+    /// The index of the choice in the list of choices.
+    pub index: usize,
+    /// A chat completion delta generated by streamed model responses.
+    pub delta: Delta,
+    /// The reason the model stopped generating tokens. Only present on the last chunk of a choice.
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+    /// The verdict of the content filter of the provider on the text so far, e.g. Azure OpenAI.
+    #[serde(default)]
+    pub content_filter_results: Option<ContentFilterResults>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Delta {
+    /// The role of the author of this message. Only present on the first chunk.
+    #[serde(default)]
+    pub role: Option<String>,
+    /// The contents of the chunk message.
+    #[serde(default)]
+    pub content: Option<String>,
+    /// A fragment of the refusal message, when the model declines to answer.
+    #[serde(default)]
+    pub refusal: Option<String>,
+    /// Fragments of the tool calls generated by the model.
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub tool_calls: Vec<ToolCallDelta>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolCallDelta {
+    /// The index of the tool call this fragment belongs to.
+    pub index: usize,
+    /// The ID of the tool call. Only present on the first fragment.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// The type of the tool. Only present on the first fragment.
+    #[serde(default)]
+    pub r#type: Option<ToolType>,
+    /// The function fragment that the model called.
+    #[serde(default)]
+    pub function: Option<FunctionCallDelta>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FunctionCallDelta {
+    /// The name of the function to call. Only present on the first fragment.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// A fragment of the arguments to call the function with.
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+impl ChatCompletionChunk {
+    /// Whether this is the trailing usage chunk sent when stream_options.include_usage is set.
+    pub fn is_usage(&self) -> bool {
+        self.choices.is_empty() && self.usage.is_some()
+    }
+}
+
+/// Some OpenAI-compatible servers send `"tool_calls": null` instead of leaving it out.
+fn null_as_empty<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Option::<Vec<T>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn chunk(payload: &str) -> Result<ChatCompletionChunk> {
+        ObjectType::ChatCompletionChunk.parse(serde_json::from_str(payload)?)
+    }
+
+    #[test]
+    fn chunk_should_deserialize_content_stream() -> Result<()> {
+        let first = chunk(
+            r#"{"id":"chatcmpl-9lMgfRSWPHcw51s6wxKT1YEO2CKpd","object":"chat.completion.chunk","created":1721075653,"model":"gpt-4o-2024-05-13","system_fingerprint":"fp_dd932ca5d1","choices":[{"index":0,"delta":{"role":"assistant","content":"","refusal":null},"logprobs":null,"finish_reason":null}],"usage":null}"#,
+        )?;
+        let delta = &first.choices[0].delta;
+        assert_eq!(delta.role.as_deref(), Some("assistant"));
+        assert_eq!(delta.content.as_deref(), Some(""));
+        assert!(delta.refusal.is_none());
+        assert!(first.usage.is_none());
+
+        let content = chunk(
+            r#"{"id":"chatcmpl-9lMgfRSWPHcw51s6wxKT1YEO2CKpd","object":"chat.completion.chunk","created":1721075653,"model":"gpt-4o-2024-05-13","system_fingerprint":"fp_dd932ca5d1","choices":[{"index":0,"delta":{"content":"Hello"},"logprobs":null,"finish_reason":null}],"usage":null}"#,
+        )?;
+        assert_eq!(content.choices[0].delta.content.as_deref(), Some("Hello"));
+        assert!(content.choices[0].delta.role.is_none());
+        assert!(content.choices[0].finish_reason.is_none());
+
+        let last = chunk(
+            r#"{"id":"chatcmpl-9lMgfRSWPHcw51s6wxKT1YEO2CKpd","object":"chat.completion.chunk","created":1721075653,"model":"gpt-4o-2024-05-13","system_fingerprint":"fp_dd932ca5d1","choices":[{"index":0,"delta":{},"logprobs":null,"finish_reason":"stop"}],"usage":null}"#,
+        )?;
+        assert_eq!(last.choices[0].finish_reason, Some(FinishReason::Stop));
+        assert!(last.choices[0].delta.content.is_none());
+        assert!(!last.is_usage());
+
+        let usage = chunk(
+            r#"{"id":"chatcmpl-9lMgfRSWPHcw51s6wxKT1YEO2CKpd","object":"chat.completion.chunk","created":1721075653,"model":"gpt-4o-2024-05-13","system_fingerprint":"fp_dd932ca5d1","choices":[],"usage":{"prompt_tokens":9,"completion_tokens":2,"total_tokens":11,"prompt_tokens_details":{"cached_tokens":0},"completion_tokens_details":{"reasoning_tokens":0}}}"#,
+        )?;
+        assert!(usage.is_usage());
+        assert_eq!(usage.usage.unwrap().completion_tokens, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn chunk_should_deserialize_refusal() -> Result<()> {
+        let first = chunk(
+            r#"{"id":"chatcmpl-A1b2C3","object":"chat.completion.chunk","created":1723575416,"model":"gpt-4o-2024-08-06","system_fingerprint":"fp_2a322c9ffc","choices":[{"index":0,"delta":{"role":"assistant","content":null,"refusal":""},"logprobs":null,"finish_reason":null}]}"#,
+        )?;
+        assert_eq!(first.choices[0].delta.refusal.as_deref(), Some(""));
+        assert!(first.choices[0].delta.content.is_none());
+
+        let refusal = chunk(
+            r#"{"id":"chatcmpl-A1b2C3","object":"chat.completion.chunk","created":1723575416,"model":"gpt-4o-2024-08-06","system_fingerprint":"fp_2a322c9ffc","choices":[{"index":0,"delta":{"refusal":"I'm sorry, I can't help with that."},"logprobs":null,"finish_reason":null}]}"#,
+        )?;
+        assert_eq!(
+            refusal.choices[0].delta.refusal.as_deref(),
+            Some("I'm sorry, I can't help with that.")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn chunk_should_deserialize_parallel_tool_call_deltas() -> Result<()> {
+        let second_call = chunk(
+            r#"{"id":"chatcmpl-9lNJw5aZUcnm4WG3ZJzzI2pQMlyuK","object":"chat.completion.chunk","created":1721078068,"model":"gpt-4o-mini-2024-07-18","system_fingerprint":"fp_8b761cb050","choices":[{"index":0,"delta":{"tool_calls":[{"index":1,"id":"call_Gh4tGqDKYNwiXtnR9pyQDVYO","type":"function","function":{"name":"get_weather","arguments":""}}]},"logprobs":null,"finish_reason":null}]}"#,
+        )?;
+        let call = &second_call.choices[0].delta.tool_calls[0];
+        assert_eq!(call.index, 1);
+        assert_eq!(call.id.as_deref(), Some("call_Gh4tGqDKYNwiXtnR9pyQDVYO"));
+        assert_eq!(call.r#type, Some(ToolType::Function));
+        let function = call.function.as_ref().unwrap();
+        assert_eq!(function.name.as_deref(), Some("get_weather"));
+        assert_eq!(function.arguments.as_deref(), Some(""));
+
+        let fragment = chunk(
+            r#"{"id":"chatcmpl-9lNJw5aZUcnm4WG3ZJzzI2pQMlyuK","object":"chat.completion.chunk","created":1721078068,"model":"gpt-4o-mini-2024-07-18","system_fingerprint":"fp_8b761cb050","choices":[{"index":0,"delta":{"tool_calls":[{"index":1,"function":{"arguments":"{\"location\": \"Tok"}}]},"logprobs":null,"finish_reason":null}]}"#,
+        )?;
+        let call = &fragment.choices[0].delta.tool_calls[0];
+        assert!(call.id.is_none() && call.r#type.is_none());
+        assert!(call.function.as_ref().unwrap().name.is_none());
+
+        let done = chunk(
+            r#"{"id":"chatcmpl-9lNJw5aZUcnm4WG3ZJzzI2pQMlyuK","object":"chat.completion.chunk","created":1721078068,"model":"gpt-4o-mini-2024-07-18","system_fingerprint":"fp_8b761cb050","choices":[{"index":0,"delta":{},"logprobs":null,"finish_reason":"tool_calls"}]}"#,
+        )?;
+        assert_eq!(done.choices[0].finish_reason, Some(FinishReason::ToolCalls));
+        Ok(())
+    }
+
+    #[test]
+    fn chunk_should_accept_compatible_server_quirks() -> Result<()> {
+        // OpenRouter and vLLM send nulls for absent fields and extra ones OpenAI doesn't
+        let chunk = chunk(
+            r#"{"id":"gen-1721078068-abc","provider":"Together","model":"meta-llama/llama-3.1-8b-instruct","object":"chat.completion.chunk","created":1721078068,"choices":[{"index":0,"delta":{"role":"assistant","content":"Hi","tool_calls":null},"finish_reason":null,"native_finish_reason":null,"logprobs":null}],"system_fingerprint":null}"#,
+        )?;
+        let delta = &chunk.choices[0].delta;
+        assert!(delta.tool_calls.is_empty());
+        assert_eq!(delta.content.as_deref(), Some("Hi"));
+        assert!(chunk.system_fingerprint.is_none());
+        Ok(())
+    }
+}
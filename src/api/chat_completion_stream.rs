@@ -0,0 +1,461 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{
+    BoxStream, ChatCompleteUsage, ChatCompletionChunk, FinishReason, FunctionCall, MaybeSend,
+    ObjectType, SdkError, ToolCall, ToolCallDelta, ToolType,
+};
+use anyhow::Result;
+use futures::{stream, Stream, StreamExt};
+use tokio::sync::mpsc;
+
+pub type ChatCompletionStream = BoxStream<ChatCompletionChunk>;
+
+/// One thing that happened in a streamed chat completion, for consumers that handle events
+/// one at a time rather than chunks, see `chunk_events`.
+#[derive(Debug, Clone)]
+pub enum ChunkEvent {
+    /// Generated text of a choice.
+    TextDelta { choice: usize, text: String },
+    /// A fragment of a tool call of a choice; `ToolCallAccumulator` stitches whole calls from chunks.
+    ToolCallDelta { choice: usize, delta: ToolCallDelta },
+    /// A choice is finished.
+    FinishReason { choice: usize, reason: FinishReason },
+    /// The token usage, sent last when `stream_options.include_usage` is set.
+    Usage(ChatCompleteUsage),
+    /// The stream completed; always the last event unless it failed.
+    Done,
+}
+
+impl ChunkEvent {
+    /// The events of a chunk, in order: per choice its text, tool call fragments and finish
+    /// reason, then the usage.
+    pub fn from_chunk(chunk: ChatCompletionChunk) -> Vec<ChunkEvent> {
+        let mut events = Vec::new();
+        for choice in chunk.choices {
+            let index = choice.index;
+            if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+                events.push(ChunkEvent::TextDelta {
+                    choice: index,
+                    text,
+                });
+            }
+            events.extend(choice.delta.tool_calls.into_iter().map(|delta| {
+                ChunkEvent::ToolCallDelta {
+                    choice: index,
+                    delta,
+                }
+            }));
+            if let Some(reason) = choice.finish_reason {
+                events.push(ChunkEvent::FinishReason {
+                    choice: index,
+                    reason,
+                });
+            }
+        }
+        events.extend(chunk.usage.map(ChunkEvent::Usage));
+        events
+    }
+}
+
+/// The events of every chunk of `stream`, followed by `ChunkEvent::Done`. An error ends the
+/// stream without `Done`.
+pub fn chunk_events(stream: ChatCompletionStream) -> BoxStream<ChunkEvent> {
+    let state = Some((stream, VecDeque::new()));
+    Box::pin(stream::unfold(state, |state| async move {
+        let (mut stream, mut pending) = state?;
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((Ok(event), Some((stream, pending))));
+            }
+            match stream.next().await {
+                Some(Ok(chunk)) => pending.extend(ChunkEvent::from_chunk(chunk)),
+                Some(Err(e)) => return Some((Err(e), None)),
+                None => return Some((Ok(ChunkEvent::Done), None)),
+            }
+        }
+    }))
+}
+
+/// Send the events of `stream` to `tx`, see `chunk_events`, e.g. from a spawned task for a
+/// GUI or game loop that drains the receiver every frame. An error is sent as the last item.
+/// Returns early once the receiver is dropped.
+pub async fn to_channel(stream: ChatCompletionStream, tx: mpsc::Sender<Result<ChunkEvent>>) {
+    let mut events = chunk_events(stream);
+    while let Some(event) = events.next().await {
+        if tx.send(event).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Stitches streamed tool call fragments back together.
+///
+/// Fragments are grouped per choice and per tool call index; the complete
+/// tool calls of a choice are returned once its finish reason arrives.
+#[derive(Debug, Default)]
+pub struct ToolCallAccumulator {
+    calls: BTreeMap<(usize, usize), ToolCall>,
+}
+
+impl ToolCallAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk into the accumulator and return the tool calls completed by it.
+    pub fn push(&mut self, chunk: &ChatCompletionChunk) -> Vec<ToolCall> {
+        let mut completed = Vec::new();
+        for choice in &chunk.choices {
+            for delta in &choice.delta.tool_calls {
+                self.push_delta(choice.index, delta);
+            }
+            if choice.finish_reason.is_some() {
+                completed.extend(self.take_choice(choice.index));
+            }
+        }
+        completed
+    }
+
+    /// Return every tool call that is still pending, e.g. when the stream ended without a finish reason.
+    pub fn finish(self) -> Vec<ToolCall> {
+        self.calls.into_values().collect()
+    }
+
+    fn push_delta(&mut self, choice: usize, delta: &ToolCallDelta) {
+        let call = self
+            .calls
+            .entry((choice, delta.index))
+            .or_insert_with(|| ToolCall {
+                id: String::new(),
+                r#type: ToolType::Function,
+                function: FunctionCall {
+                    name: String::new(),
+                    arguments: String::new(),
+                },
+            });
+        if let Some(id) = &delta.id {
+            call.id.push_str(id);
+        }
+        if let Some(r#type) = delta.r#type {
+            call.r#type = r#type;
+        }
+        if let Some(function) = &delta.function {
+            if let Some(name) = &function.name {
+                call.function.name.push_str(name);
+            }
+            if let Some(arguments) = &function.arguments {
+                call.function.arguments.push_str(arguments);
+            }
+        }
+    }
+
+    fn take_choice(&mut self, choice: usize) -> Vec<ToolCall> {
+        let keys: Vec<_> = self
+            .calls
+            .range((choice, 0)..=(choice, usize::MAX))
+            .map(|(k, _)| *k)
+            .collect();
+        keys.into_iter()
+            .filter_map(|k| self.calls.remove(&k))
+            .collect()
+    }
+}
+
+pub(crate) enum SseEvent {
+    Data(String),
+    Done,
+}
+
+struct SseDecoder<S> {
+    inner: S,
+    buf: Vec<u8>,
+    /// Whether `inner` has ended; the events left in `buf` are still decoded.
+    eof: bool,
+    done: bool,
+    /// The content of the first choice received so far, reported if the stream is cut short.
+    partial: String,
+    /// Whether a finish reason or the usage chunk has been seen.
+    finished: bool,
+}
+
+/// Take the next complete event out of `buf`, skipping comments and keep-alives.
+pub(crate) fn next_sse_event(buf: &mut Vec<u8>) -> Option<SseEvent> {
+    loop {
+        let pos = buf.windows(2).position(|w| w == b"\n\n")?;
+        let raw: Vec<u8> = buf.drain(..pos + 2).collect();
+        let raw = String::from_utf8_lossy(&raw);
+        let data: Vec<&str> = raw
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| data.trim())
+            .collect();
+        if data.is_empty() {
+            // comments and keep-alives carry no data
+            continue;
+        }
+        let data = data.join("\n");
+        if data == "[DONE]" {
+            return Some(SseEvent::Done);
+        }
+        return Some(SseEvent::Data(data));
+    }
+}
+
+impl<S> SseDecoder<S> {
+    fn observe(&mut self, chunk: &ChatCompletionChunk) {
+        for choice in &chunk.choices {
+            if choice.index == 0 {
+                self.partial
+                    .push_str(choice.delta.content.as_deref().unwrap_or_default());
+            }
+            self.finished |= choice.finish_reason.is_some();
+        }
+        self.finished |= chunk.is_usage();
+    }
+
+    fn interrupted(&self, reason: impl Into<String>) -> anyhow::Error {
+        SdkError::StreamInterrupted {
+            partial: self.partial.clone(),
+            reason: reason.into(),
+        }
+        .into()
+    }
+}
+
+/// Decode a server-sent event byte stream into chat completion chunks.
+///
+/// A transport error, or the connection closing before the model finished,
+/// is reported as `SdkError::StreamInterrupted` with the text received so far.
+pub(crate) fn decode_chunks<S, B, E>(inner: S) -> ChatCompletionStream
+where
+    S: Stream<Item = Result<B, E>> + Unpin + MaybeSend + 'static,
+    B: AsRef<[u8]>,
+    E: Into<anyhow::Error>,
+{
+    let decoder = SseDecoder {
+        inner,
+        buf: Vec::new(),
+        eof: false,
+        done: false,
+        partial: String::new(),
+        finished: false,
+    };
+    let stream = stream::unfold(decoder, |mut decoder| async move {
+        loop {
+            if decoder.done {
+                return None;
+            }
+            match next_sse_event(&mut decoder.buf) {
+                Some(SseEvent::Data(data)) => {
+                    let chunk = serde_json::from_str(&data)
+                        .map_err(Into::into)
+                        .and_then(|value| ObjectType::ChatCompletionChunk.parse(value));
+                    if let Ok(chunk) = &chunk {
+                        decoder.observe(chunk);
+                    }
+                    return Some((chunk, decoder));
+                }
+                Some(SseEvent::Done) => return None,
+                None => {}
+            }
+            if decoder.eof {
+                decoder.done = true;
+                if decoder.finished {
+                    return None;
+                }
+                let err = decoder.interrupted("connection closed before the stream finished");
+                return Some((Err(err), decoder));
+            }
+            match decoder.inner.next().await {
+                Some(Ok(bytes)) => {
+                    let bytes = bytes.as_ref().iter().filter(|b| **b != b'\r');
+                    decoder.buf.extend(bytes);
+                }
+                Some(Err(e)) => {
+                    decoder.done = true;
+                    let err = decoder.interrupted(e.into().to_string());
+                    return Some((Err(err), decoder));
+                }
+                None => {
+                    // the last event may not be followed by a blank line
+                    decoder.buf.extend(b"\n\n");
+                    decoder.eof = true;
+                }
+            }
+        }
+    });
+    Box::pin(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FinishReason;
+    use futures::TryStreamExt;
+
+    const TOOL_CALL_EVENTS: &str = r#"data: {"id":"chatcmpl-1","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","system_fingerprint":"fp_eeff13170a","choices":[{"index":0,"delta":{"role":"assistant","content":null,"tool_calls":[{"index":0,"id":"call_abc","type":"function","function":{"name":"get_weather","arguments":""}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"loc"}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"ation\": \"Paris\"}"}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}
+
+data: [DONE]
+
+"#;
+
+    #[tokio::test]
+    async fn decode_chunks_should_handle_split_events() -> Result<()> {
+        let bytes = TOOL_CALL_EVENTS.as_bytes();
+        let parts: Vec<Result<Vec<u8>>> = bytes.chunks(7).map(|c| Ok(c.to_vec())).collect();
+        let chunks: Vec<_> = decode_chunks(stream::iter(parts)).try_collect().await?;
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0].object, ObjectType::ChatCompletionChunk);
+        assert_eq!(
+            chunks[0].system_fingerprint.as_deref(),
+            Some("fp_eeff13170a")
+        );
+        assert_eq!(
+            chunks[3].choices[0].finish_reason,
+            Some(FinishReason::ToolCalls)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn decode_chunks_should_surface_usage_chunk() -> Result<()> {
+        let events = r#"data: {"id":"chatcmpl-2","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[{"index":0,"delta":{"content":"Hi"},"finish_reason":"stop"}],"usage":null}
+
+data: {"id":"chatcmpl-2","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[],"usage":{"prompt_tokens":9,"completion_tokens":1,"total_tokens":10}}
+
+data: [DONE]
+
+"#;
+        let parts = vec![Ok::<_, anyhow::Error>(events)];
+        let chunks: Vec<_> = decode_chunks(stream::iter(parts)).try_collect().await?;
+        assert_eq!(chunks.len(), 2);
+        assert!(!chunks[0].is_usage());
+        assert!(chunks[1].is_usage());
+        assert_eq!(chunks[1].usage.as_ref().unwrap().total_tokens, 10);
+        Ok(())
+    }
+
+    const CONTENT_EVENTS: &str = r#"data: {"id":"chatcmpl-3","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-3","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[{"index":0,"delta":{"content":"lo"},"finish_reason":null}]}
+
+"#;
+
+    fn interrupted_partial(err: &anyhow::Error) -> Option<&str> {
+        match err.downcast_ref::<SdkError>() {
+            Some(SdkError::StreamInterrupted { partial, .. }) => Some(partial),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn decode_chunks_should_report_interrupted_stream() {
+        let parts = vec![
+            Ok(CONTENT_EVENTS.to_string()),
+            Err(anyhow::anyhow!("connection reset")),
+        ];
+        let results: Vec<_> = decode_chunks(stream::iter(parts)).collect().await;
+        assert_eq!(results.len(), 3);
+        let err = results[2].as_ref().unwrap_err();
+        assert_eq!(interrupted_partial(err), Some("Hello"));
+        assert!(err.to_string().contains("connection reset"));
+
+        // the server hung up without a finish reason or [DONE]
+        let parts = vec![Ok::<_, anyhow::Error>(CONTENT_EVENTS)];
+        let results: Vec<_> = decode_chunks(stream::iter(parts)).collect().await;
+        assert_eq!(
+            interrupted_partial(results[2].as_ref().unwrap_err()),
+            Some("Hello")
+        );
+    }
+
+    #[tokio::test]
+    async fn decode_chunks_should_decode_a_last_event_without_blank_line() -> Result<()> {
+        let events = format!(
+            "{}{}",
+            CONTENT_EVENTS,
+            r#"data: {"id":"chatcmpl-3","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#
+        );
+        let parts = vec![Ok::<_, anyhow::Error>(events)];
+        let chunks: Vec<_> = decode_chunks(stream::iter(parts)).try_collect().await?;
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].choices[0].finish_reason, Some(FinishReason::Stop));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn to_channel_should_send_typed_events() {
+        let events = format!(
+            "{}{}",
+            CONTENT_EVENTS,
+            r#"data: {"id":"chatcmpl-3","object":"chat.completion.chunk","created":1700000000,"model":"gpt-3.5-turbo-1106","choices":[{"index":0,"delta":{},"finish_reason":"stop"}],"usage":{"prompt_tokens":9,"completion_tokens":2,"total_tokens":11}}
+
+data: [DONE]
+
+"#
+        );
+        let (tx, mut rx) = mpsc::channel(16);
+        to_channel(
+            decode_chunks(stream::iter(vec![Ok::<_, anyhow::Error>(events)])),
+            tx,
+        )
+        .await;
+        let mut received = Vec::new();
+        while let Some(event) = rx.recv().await {
+            received.push(event.unwrap());
+        }
+        assert!(matches!(
+            &received[..],
+            [
+                ChunkEvent::TextDelta { choice: 0, text: hel },
+                ChunkEvent::TextDelta { text: lo, .. },
+                ChunkEvent::FinishReason { reason: FinishReason::Stop, .. },
+                ChunkEvent::Usage(usage),
+                ChunkEvent::Done,
+            ] if hel == "Hel" && lo == "lo" && usage.total_tokens == 11
+        ));
+
+        let parts = vec![Ok::<_, anyhow::Error>(TOOL_CALL_EVENTS)];
+        let (tx, mut rx) = mpsc::channel(16);
+        to_channel(decode_chunks(stream::iter(parts)), tx).await;
+        let mut deltas = 0;
+        while let Some(event) = rx.recv().await {
+            deltas += matches!(event, Ok(ChunkEvent::ToolCallDelta { .. })) as usize;
+        }
+        assert_eq!(deltas, 3);
+
+        // the stream ends in an error rather than Done
+        let (tx, mut rx) = mpsc::channel(16);
+        let parts = vec![Ok::<_, anyhow::Error>(CONTENT_EVENTS)];
+        to_channel(decode_chunks(stream::iter(parts)), tx).await;
+        let mut last = None;
+        while let Some(event) = rx.recv().await {
+            last = Some(event);
+        }
+        assert!(last.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn tool_call_accumulator_should_stitch_arguments() -> Result<()> {
+        let parts = vec![Ok::<_, anyhow::Error>(TOOL_CALL_EVENTS)];
+        let chunks: Vec<_> = decode_chunks(stream::iter(parts)).try_collect().await?;
+        let mut acc = ToolCallAccumulator::new();
+        let mut calls = Vec::new();
+        for chunk in &chunks[..3] {
+            assert!(acc.push(chunk).is_empty());
+        }
+        calls.extend(acc.push(&chunks[3]));
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_abc");
+        assert_eq!(calls[0].function.name, "get_weather");
+        assert_eq!(calls[0].function.arguments, r#"{"location": "Paris"}"#);
+        assert!(acc.finish().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use derive_builder::Builder;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    validation::Validator, ChatCompleteUsage, FinishReason, IntoRequest, ObjectType, Stop,
+    Validate, ValidationError,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable")]
+pub struct CreateCompletionRequest {
+    /// ID of the model to use.
+    #[builder(default)]
+    #[serde(default)]
+    model: CompletionModel,
+    /// The prompt(s) to generate completions for, encoded as a string or array of strings.
+    #[builder(setter(into))]
+    prompt: Prompt,
+    /// The suffix that comes after a completion of inserted text.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    /// The maximum number of tokens that can be generated in the completion.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<usize>,
+    /// What sampling temperature to use, between 0 and 2. Higher values like 0.8 will make the output more random,
+    /// while lower values like 0.2 will make it more focused and deterministic.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    /// An alternative to sampling with temperature, called nucleus sampling,
+    /// where the model considers the results of the tokens with top_p probability mass.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    /// How many completions to generate for each prompt.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<usize>,
+    /// Include the log probabilities on the logprobs most likely output tokens, as well the chosen tokens.
+    /// The maximum value for logprobs is 5.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logprobs: Option<u8>,
+    /// Echo back the prompt in addition to the completion.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    echo: Option<bool>,
+    /// Up to 4 sequences where the API will stop generating further tokens.
+    /// The returned text will not contain the stop sequence.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Stop>,
+    /// Number between -2.0 and 2.0. Positive values penalize new tokens based on whether they appear in the text so far.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    /// Number between -2.0 and 2.0. Positive values penalize new tokens based on their existing frequency in the text so far.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    /// Generates best_of completions server-side and returns the "best" (the one with the highest log probability per token).
+    /// When used with n, best_of controls the number of candidate completions and n specifies how many to return.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best_of: Option<usize>,
+    /// If specified, our system will make a best effort to sample deterministically.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+pub enum CompletionModel {
+    #[default]
+    #[serde(rename = "gpt-3.5-turbo-instruct")]
+    Gpt3TurboInstruct,
+    #[serde(rename = "babbage-002")]
+    Babbage002,
+    #[serde(rename = "davinci-002")]
+    Davinci002,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Prompt {
+    Text(String),
+    Texts(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateCompletionResponse {
+    /// A unique identifier for the completion.
+    pub id: String,
+    /// The list of completion choices the model generated for the input prompt.
+    pub choices: Vec<CompletionChoice>,
+    /// The Unix timestamp (in seconds) of when the completion was created.
+    pub created: usize,
+    /// The model used for completion.
+    pub model: String,
+    /// This fingerprint represents the backend configuration that the model runs with.
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
+    /// The object type, which is always "text_completion"
+    pub object: ObjectType,
+    /// Usage statistics for the completion request.
+    pub usage: ChatCompleteUsage,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompletionChoice {
+    /// The reason the model stopped generating tokens.
+    pub finish_reason: FinishReason,
+    /// The index of the choice in the list of choices.
+    pub index: usize,
+    /// The log probabilities of the chosen tokens, if logprobs was requested.
+    #[serde(default)]
+    pub logprobs: Option<CompletionLogprobs>,
+    /// The generated text.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompletionLogprobs {
+    pub tokens: Vec<String>,
+    pub token_logprobs: Vec<Option<f32>>,
+    pub top_logprobs: Vec<Option<HashMap<String, f32>>>,
+    pub text_offset: Vec<usize>,
+}
+
+// https://platform.openai.com/docs/api-reference/completions/create
+impl IntoRequest for CreateCompletionRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.post(format!("{}/completions", base_url)).json(&self)
+    }
+}
+
+impl CreateCompletionRequest {
+    pub fn new(prompt: impl Into<Prompt>) -> Self {
+        CreateCompletionRequestBuilder::default()
+            .prompt(prompt)
+            .build()
+            .unwrap()
+    }
+}
+
+impl Validate for CreateCompletionRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::default();
+        v.range("temperature", self.temperature, 0.0, 2.0);
+        v.range("top_p", self.top_p, 0.0, 1.0);
+        v.range("frequency_penalty", self.frequency_penalty, -2.0, 2.0);
+        v.range("presence_penalty", self.presence_penalty, -2.0, 2.0);
+        v.check(self.n != Some(0), "n", "must be at least 1");
+        v.check(
+            self.logprobs.is_none_or(|n| n <= 5),
+            "logprobs",
+            "must be at most 5",
+        );
+        if let (Some(best_of), n) = (self.best_of, self.n.unwrap_or(1)) {
+            v.check(
+                best_of >= n,
+                "best_of",
+                "must be greater than or equal to n",
+            );
+        }
+        if let Some(stop) = &self.stop {
+            stop.validate_into(&mut v);
+        }
+        v.finish()
+    }
+}
+
+impl From<String> for Prompt {
+    fn from(s: String) -> Self {
+        Prompt::Text(s)
+    }
+}
+
+impl From<&str> for Prompt {
+    fn from(s: &str) -> Self {
+        Prompt::Text(s.to_string())
+    }
+}
+
+impl From<Vec<String>> for Prompt {
+    fn from(v: Vec<String>) -> Self {
+        Prompt::Texts(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LlmSdk;
+
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn create_completion_request_should_serialize() -> Result<()> {
+        let req = CreateCompletionRequest::new("Say this is a test");
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "model": "gpt-3.5-turbo-instruct",
+                "prompt": "Say this is a test",
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn create_completion_request_custom_should_serialize() -> Result<()> {
+        let req = CreateCompletionRequestBuilder::default()
+            .model(CompletionModel::Davinci002)
+            .prompt(vec!["a".to_string(), "b".to_string()])
+            .suffix("end")
+            .logprobs(2)
+            .echo(true)
+            .best_of(3)
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "model": "davinci-002",
+                "prompt": ["a", "b"],
+                "suffix": "end",
+                "logprobs": 2,
+                "echo": true,
+                "best_of": 3,
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn create_completion_response_should_deserialize() -> Result<()> {
+        let res: CreateCompletionResponse = ObjectType::TextCompletion.parse(json!({
+            "id": "cmpl-uqkvlQyYK7bGYrRHQ0eXlWi7",
+            "object": "text_completion",
+            "created": 1589478378,
+            "model": "gpt-3.5-turbo-instruct",
+            "system_fingerprint": "fp_44709d6fcb",
+            "choices": [{
+                "text": "\n\nThis is indeed a test",
+                "index": 0,
+                "logprobs": null,
+                "finish_reason": "length"
+            }],
+            "usage": {
+                "prompt_tokens": 5,
+                "completion_tokens": 7,
+                "total_tokens": 12
+            }
+        }))?;
+        assert_eq!(res.choices[0].finish_reason, FinishReason::Length);
+        assert_eq!(res.choices[0].text, "\n\nThis is indeed a test");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn create_completion_should_work() -> Result<()> {
+        let sdk = LlmSdk::new(std::env::var("OPENAI_API_KEY")?);
+        let req = CreateCompletionRequest::new("Say this is a test");
+        let res = sdk.create_completion(req).await?;
+        assert_eq!(res.choices.len(), 1);
+        Ok(())
+    }
+}
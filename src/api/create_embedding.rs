@@ -0,0 +1,174 @@
+use derive_builder::Builder;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChatCompleteUsage, IntoRequest, ObjectType};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable")]
+pub struct CreateEmbeddingRequest {
+    /// Input text to embed, encoded as a string or array of strings.
+    #[builder(setter(into))]
+    input: EmbeddingInput,
+    /// ID of the model to use.
+    #[builder(default)]
+    #[serde(default)]
+    model: EmbeddingModel,
+    /// The number of dimensions the resulting output embeddings should have.
+    /// Only supported in text-embedding-3 and later models.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+    /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+pub enum EmbeddingModel {
+    #[default]
+    #[serde(rename = "text-embedding-3-small")]
+    TextEmbedding3Small,
+    #[serde(rename = "text-embedding-3-large")]
+    TextEmbedding3Large,
+    #[serde(rename = "text-embedding-ada-002")]
+    TextEmbeddingAda002,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Text(String),
+    Texts(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateEmbeddingResponse {
+    /// The object type, which is always "list".
+    pub object: ObjectType,
+    /// The embeddings, one per input.
+    pub data: Vec<Embedding>,
+    /// The model used for the embeddings.
+    pub model: String,
+    /// Usage statistics for the request.
+    pub usage: EmbeddingUsage,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Embedding {
+    /// The index of the input this embedding belongs to.
+    pub index: usize,
+    /// The embedding vector.
+    pub embedding: Vec<f32>,
+    /// The object type, which is always "embedding".
+    pub object: ObjectType,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct EmbeddingUsage {
+    /// Number of tokens in the input.
+    pub prompt_tokens: usize,
+    /// Total number of tokens used by the request.
+    pub total_tokens: usize,
+}
+
+// https://platform.openai.com/docs/api-reference/embeddings/create
+impl IntoRequest for CreateEmbeddingRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.post(format!("{}/embeddings", base_url)).json(&self)
+    }
+}
+
+impl CreateEmbeddingRequest {
+    pub fn new(input: impl Into<EmbeddingInput>) -> Self {
+        CreateEmbeddingRequestBuilder::default()
+            .input(input)
+            .build()
+            .unwrap()
+    }
+}
+
+impl CreateEmbeddingResponse {
+    /// The embedding vectors in the order of the inputs.
+    pub fn into_vectors(mut self) -> Vec<Vec<f32>> {
+        self.data.sort_by_key(|e| e.index);
+        self.data.into_iter().map(|e| e.embedding).collect()
+    }
+}
+
+impl From<EmbeddingUsage> for ChatCompleteUsage {
+    fn from(usage: EmbeddingUsage) -> Self {
+        ChatCompleteUsage {
+            completion_tokens: 0,
+            prompt_tokens: usage.prompt_tokens,
+            total_tokens: usage.total_tokens,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<String> for EmbeddingInput {
+    fn from(s: String) -> Self {
+        EmbeddingInput::Text(s)
+    }
+}
+
+impl From<&str> for EmbeddingInput {
+    fn from(s: &str) -> Self {
+        EmbeddingInput::Text(s.to_string())
+    }
+}
+
+impl From<Vec<String>> for EmbeddingInput {
+    fn from(v: Vec<String>) -> Self {
+        EmbeddingInput::Texts(v)
+    }
+}
+
+impl From<Vec<&str>> for EmbeddingInput {
+    fn from(v: Vec<&str>) -> Self {
+        EmbeddingInput::Texts(v.into_iter().map(String::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn create_embedding_request_should_serialize() -> Result<()> {
+        let req = CreateEmbeddingRequestBuilder::default()
+            .input(vec!["hello", "world"])
+            .model(EmbeddingModel::TextEmbedding3Large)
+            .dimensions(256)
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "input": ["hello", "world"],
+                "model": "text-embedding-3-large",
+                "dimensions": 256
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn create_embedding_response_should_order_vectors() -> Result<()> {
+        let res: CreateEmbeddingResponse = ObjectType::List.parse(json!({
+            "object": "list",
+            "data": [
+                { "object": "embedding", "index": 1, "embedding": [0.5, 0.5] },
+                { "object": "embedding", "index": 0, "embedding": [1.0, 0.0] }
+            ],
+            "model": "text-embedding-3-small",
+            "usage": { "prompt_tokens": 2, "total_tokens": 2 }
+        }))?;
+        assert_eq!(res.usage.prompt_tokens, 2);
+        assert_eq!(res.into_vectors(), vec![vec![1.0, 0.0], vec![0.5, 0.5]]);
+        Ok(())
+    }
+}
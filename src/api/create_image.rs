@@ -1,17 +1,33 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
 use derive_builder::Builder;
 use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 
-use crate::IntoRequest;
+use crate::{
+    json_with_extra, validation::Validator, ChatCompleteModel, ChatCompletionMessage, ContentPart,
+    ImageContent, IntoRequest, RequestDefaults, Validate, ValidationError,
+};
+
+/// The system prompt of `PromptEnhancer::default`.
+pub const DEFAULT_PROMPT_ENHANCER: &str = "You rewrite prompts for an image generation model. \
+Keep the subject and intent of the prompt, and add concrete details about composition, \
+lighting, style, colors and mood. Reply with only the rewritten prompt, without quotes or commentary.";
 
-#[derive(Debug, Clone, Serialize, Builder)]
-#[builder(pattern = "mutable")]
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable", build_fn(validate = "Self::validate"))]
 pub struct CreateImageRequest {
-    /// A text description of the desired image(s). The maximum length is 1000 characters for dall-e-2 and 4000 characters for dall-e-3.
+    /// A text description of the desired image(s). The maximum length is 1000 characters for dall-e-2,
+    /// 4000 characters for dall-e-3 and 32000 characters for gpt-image-1.
     #[builder(setter(into))]
     prompt: String,
     /// The model to use for image generation.
     #[builder(default)]
+    #[serde(default)]
     model: ImageModel,
     /// The number of images to generate. Must be between 1 and 10. For dall-e-3, only n=1 is supported.
     #[builder(default, setter(strip_option))]
@@ -19,16 +35,19 @@ pub struct CreateImageRequest {
     n: Option<usize>,
     /// The quality of the image that will be generated.
     /// hd creates images with finer details and greater consistency across the image.
-    /// This param is only supported for dall-e-3.
+    /// dall-e-3 supports standard and hd, gpt-image-1 supports low, medium, high and auto.
+    /// This param is not supported for dall-e-2.
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     quality: Option<ImageQuality>,
     /// The format in which the generated images are returned. Must be one of url or b64_json.
+    /// Not supported for gpt-image-1, which always returns b64_json.
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     response_format: Option<ImageResponseFormat>,
     /// The size of the generated images. Must be one of 256x256, 512x512, or 1024x1024 for dall-e-2.
     /// Must be one of 1024x1024, 1792x1024, or 1024x1792 for dall-e-3 models.
+    /// Must be one of 1024x1024, 1536x1024, 1024x1536 or auto for gpt-image-1.
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     size: Option<ImageSize>,
@@ -38,20 +57,54 @@ pub struct CreateImageRequest {
     #[builder(default, setter(strip_option))]
     #[serde(skip_serializing_if = "Option::is_none")]
     style: Option<ImageStyle>,
+    /// The transparency of the background. Transparent needs the png or webp output format.
+    /// This param is only supported for gpt-image-1.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background: Option<ImageBackground>,
+    /// The format of the generated images. This param is only supported for gpt-image-1.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_format: Option<ImageOutputFormat>,
+    /// The compression level (0-100%) of the generated images, for the webp or jpeg output formats.
+    /// This param is only supported for gpt-image-1.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_compression: Option<u8>,
+    /// The content moderation level. low is less restrictive than the default auto.
+    /// This param is only supported for gpt-image-1.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    moderation: Option<ImageModeration>,
     /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
     #[builder(default, setter(strip_option, into))]
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<String>,
+    /// Check the prompt with the moderations endpoint first and fail with
+    /// `SdkError::BlockedByModeration` if it is flagged. Not sent to the API.
+    #[builder(default)]
+    #[serde(skip)]
+    moderate_prompt: bool,
+    /// Parameters the SDK has no field for, e.g. provider-specific or newly launched ones,
+    /// sent as top-level fields of the body. One named like a field is sent instead of it.
+    /// Add them with `extra_param`.
+    #[builder(default, setter(into))]
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
 pub enum ImageModel {
+    #[serde(rename = "dall-e-2")]
+    DallE2,
     #[serde(rename = "dall-e-3")]
     #[default]
     DallE3,
+    #[serde(rename = "gpt-image-1")]
+    GptImage1,
 }
 
-#[derive(Debug, Clone, Serialize, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum ImageQuality {
     #[serde(rename = "default")]
@@ -59,9 +112,13 @@ pub enum ImageQuality {
     Standard,
     #[serde(rename = "hd")]
     Hd,
+    Low,
+    Medium,
+    High,
+    Auto,
 }
 
-#[derive(Debug, Clone, Serialize, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum ImageResponseFormat {
     #[default]
@@ -69,8 +126,12 @@ pub enum ImageResponseFormat {
     B64Json,
 }
 
-#[derive(Debug, Clone, Serialize, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
 pub enum ImageSize {
+    #[serde(rename = "256x256")]
+    Small,
+    #[serde(rename = "512x512")]
+    Medium,
     #[serde(rename = "1024x1024")]
     #[default]
     Large,
@@ -78,9 +139,15 @@ pub enum ImageSize {
     LargeWide,
     #[serde(rename = "1024x1792")]
     LargeTall,
+    #[serde(rename = "1536x1024")]
+    Landscape,
+    #[serde(rename = "1024x1536")]
+    Portrait,
+    #[serde(rename = "auto")]
+    Auto,
 }
 
-#[derive(Debug, Clone, Serialize, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
 pub enum ImageStyle {
     #[serde(rename = "vivid")]
     #[default]
@@ -89,6 +156,46 @@ pub enum ImageStyle {
     Natural,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageBackground {
+    Transparent,
+    Opaque,
+    #[default]
+    Auto,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageOutputFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageModeration {
+    Low,
+    #[default]
+    Auto,
+}
+
+/// The parameters whose validity depends on the model, from a request or its builder.
+struct ImageOptions {
+    model: ImageModel,
+    n: Option<usize>,
+    size: Option<ImageSize>,
+    quality: Option<ImageQuality>,
+    style: Option<ImageStyle>,
+    response_format: Option<ImageResponseFormat>,
+    background: Option<ImageBackground>,
+    output_format: Option<ImageOutputFormat>,
+    output_compression: Option<u8>,
+    moderation: Option<ImageModeration>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CreateImageResponse {
     pub created: u64,
@@ -100,19 +207,50 @@ pub struct ImageObject {
     /// The base64-encoded JSON of the generated image, if response_format is b64_json.
     pub b64_json: Option<String>,
 
-    // The URL of the generated image, if response_format is url (default).
+    /// The URL of the generated image, if response_format is url (default).
     pub url: Option<String>,
 
-    // The prompt that was used to generate the image, if there was any revision to the prompt.
-    pub revised_prompt: String,
+    /// The prompt that was used to generate the image, if there was any revision to the prompt.
+    /// Only returned by dall-e-3.
+    #[serde(default)]
+    pub revised_prompt: Option<String>,
+}
+
+/// The format and size of an image, read from its header, see `image_metadata`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageMetadata {
+    /// e.g. `image/png`.
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    /// The size of the encoded image in bytes.
+    pub len: usize,
+}
+
+/// How `LlmSdk::create_image_enhanced` rewrites a prompt with a chat model before generating.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptEnhancer {
+    /// The chat model, `LlmSdk::default_model` if `None`.
+    pub model: Option<ChatCompleteModel>,
+    pub system_prompt: String,
+}
+
+/// The image generated from a rewritten prompt, see `LlmSdk::create_image_enhanced`.
+#[derive(Debug, Clone)]
+pub struct EnhancedImageResponse {
+    /// The prompt sent to the image model.
+    pub prompt: String,
+    pub response: CreateImageResponse,
 }
 
 // https://platform.openai.com/docs/api-reference/images/create
 impl IntoRequest for CreateImageRequest {
-    fn into_request(self, client: Client) -> RequestBuilder {
-        client
-            .post("https://api.openai.com/v1/images/generations")
-            .json(&self)
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        json_with_extra(
+            client.post(format!("{}/images/generations", base_url)),
+            &self,
+            &self.extra,
+        )
     }
 }
 
@@ -123,6 +261,409 @@ impl CreateImageRequest {
             .build()
             .unwrap()
     }
+
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    pub fn model(&self) -> ImageModel {
+        self.model
+    }
+
+    pub(crate) fn set_prompt(&mut self, prompt: String) {
+        self.prompt = prompt;
+    }
+
+    /// Fill the fields `req` leaves unset from `defaults`. The model always has a value, so
+    /// only the user applies.
+    pub(crate) fn apply_defaults(&mut self, defaults: &RequestDefaults) {
+        if self.user.is_none() {
+            self.user.clone_from(&defaults.user);
+        }
+    }
+
+    /// The prompt, if it should be moderated before generating.
+    pub(crate) fn prompt_to_moderate(&self) -> Option<&str> {
+        self.moderate_prompt.then_some(self.prompt.as_str())
+    }
+}
+
+impl CreateImageRequestBuilder {
+    /// Send a parameter the SDK has no field for, e.g. one launched after this version.
+    pub fn extra_param(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> &mut Self {
+        self.extra
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut v = Validator::default();
+        validate_options(
+            &mut v,
+            ImageOptions {
+                model: self.model.unwrap_or_default(),
+                n: self.n.flatten(),
+                size: self.size.flatten(),
+                quality: self.quality.flatten(),
+                style: self.style.flatten(),
+                response_format: self.response_format.flatten(),
+                background: self.background.flatten(),
+                output_format: self.output_format.flatten(),
+                output_compression: self.output_compression.flatten(),
+                moderation: self.moderation.flatten(),
+            },
+        );
+        v.finish().map_err(|e| e.to_string())
+    }
+}
+
+impl Validate for CreateImageRequest {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::default();
+        v.max_chars("prompt", &self.prompt, self.model.max_prompt_chars());
+        validate_options(
+            &mut v,
+            ImageOptions {
+                model: self.model,
+                n: self.n,
+                size: self.size,
+                quality: self.quality,
+                style: self.style,
+                response_format: self.response_format,
+                background: self.background,
+                output_format: self.output_format,
+                output_compression: self.output_compression,
+                moderation: self.moderation,
+            },
+        );
+        v.finish()
+    }
+}
+
+fn validate_options(v: &mut Validator, options: ImageOptions) {
+    let model = options.model;
+    let n = options.n.unwrap_or(1);
+    let size = options.size.unwrap_or_default();
+    v.check(
+        model.supports_size(size),
+        "size",
+        format!("{:?} is not supported by {:?}", size, model),
+    );
+    if let Some(quality) = options.quality {
+        v.check(
+            model.supports_quality(quality),
+            "quality",
+            format!("{:?} is not supported by {:?}", quality, model),
+        );
+    }
+    match model {
+        ImageModel::DallE3 => {
+            v.check(
+                n == 1,
+                "n",
+                format!("dall-e-3 only supports n=1, got {}", n),
+            );
+        }
+        ImageModel::DallE2 | ImageModel::GptImage1 => {
+            v.check(
+                (1..=10).contains(&n),
+                "n",
+                format!("{:?} supports n between 1 and 10, got {}", model, n),
+            );
+        }
+    }
+    if model != ImageModel::DallE3 {
+        v.check(
+            options.style.is_none(),
+            "style",
+            "is only supported for dall-e-3",
+        );
+    }
+    if model == ImageModel::GptImage1 {
+        v.check(
+            options.response_format.is_none(),
+            "response_format",
+            "is not supported for gpt-image-1, which always returns b64_json",
+        );
+        let format = options.output_format.unwrap_or_default();
+        if let Some(compression) = options.output_compression {
+            v.check(
+                compression <= 100,
+                "output_compression",
+                "must be at most 100",
+            );
+            v.check(
+                format != ImageOutputFormat::Png,
+                "output_compression",
+                "is only supported for the jpeg and webp output formats",
+            );
+        }
+        v.check(
+            options.background != Some(ImageBackground::Transparent)
+                || format != ImageOutputFormat::Jpeg,
+            "background",
+            "transparent needs the png or webp output format",
+        );
+    } else {
+        let only_gpt_image = [
+            ("background", options.background.is_some()),
+            ("output_format", options.output_format.is_some()),
+            ("output_compression", options.output_compression.is_some()),
+            ("moderation", options.moderation.is_some()),
+        ];
+        for (field, set) in only_gpt_image {
+            v.check(!set, field, "is only supported for gpt-image-1");
+        }
+    }
+}
+
+impl Default for PromptEnhancer {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROMPT_ENHANCER)
+    }
+}
+
+impl PromptEnhancer {
+    pub fn new(system_prompt: impl Into<String>) -> Self {
+        Self {
+            model: None,
+            system_prompt: system_prompt.into(),
+        }
+    }
+
+    pub fn with_model(mut self, model: ChatCompleteModel) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// The chat messages asking to rewrite `prompt` for `model`, within its prompt limit.
+    pub(crate) fn messages(&self, prompt: &str, model: ImageModel) -> Vec<ChatCompletionMessage> {
+        let system = format!(
+            "{}\n\nThe rewritten prompt must be at most {} characters.",
+            self.system_prompt,
+            model.max_prompt_chars()
+        );
+        vec![
+            ChatCompletionMessage::new_system(system, ""),
+            ChatCompletionMessage::new_user(prompt, ""),
+        ]
+    }
+}
+
+impl ImageModel {
+    /// The longest prompt the model accepts, in characters.
+    pub fn max_prompt_chars(&self) -> usize {
+        match self {
+            ImageModel::DallE2 => 1000,
+            ImageModel::DallE3 => 4000,
+            ImageModel::GptImage1 => 32000,
+        }
+    }
+
+    /// Whether the model can generate images of the given size.
+    pub fn supports_size(&self, size: ImageSize) -> bool {
+        match self {
+            ImageModel::DallE2 => matches!(
+                size,
+                ImageSize::Small | ImageSize::Medium | ImageSize::Large
+            ),
+            ImageModel::DallE3 => matches!(
+                size,
+                ImageSize::Large | ImageSize::LargeWide | ImageSize::LargeTall
+            ),
+            ImageModel::GptImage1 => matches!(
+                size,
+                ImageSize::Large | ImageSize::Landscape | ImageSize::Portrait | ImageSize::Auto
+            ),
+        }
+    }
+
+    /// Whether the model accepts the given quality.
+    pub fn supports_quality(&self, quality: ImageQuality) -> bool {
+        match self {
+            ImageModel::DallE2 => false,
+            ImageModel::DallE3 => matches!(quality, ImageQuality::Standard | ImageQuality::Hd),
+            ImageModel::GptImage1 => matches!(
+                quality,
+                ImageQuality::Low | ImageQuality::Medium | ImageQuality::High | ImageQuality::Auto
+            ),
+        }
+    }
+}
+
+impl CreateImageResponse {
+    /// Every image as a content part, to show them to a vision model in a later message.
+    pub fn content_parts(&self) -> Vec<ContentPart> {
+        self.data
+            .iter()
+            .filter_map(ImageObject::to_image_content)
+            .map(ContentPart::image)
+            .collect()
+    }
+
+    /// Download or decode every image and write it into `dir` as `{index}.{ext}`,
+    /// with the extension detected from the image content. Returns the written paths.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn save_all(&self, client: &Client, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        tokio::fs::create_dir_all(dir).await?;
+        let mut paths = Vec::with_capacity(self.data.len());
+        for (i, image) in self.data.iter().enumerate() {
+            let bytes = image.fetch_bytes(client).await?;
+            let ext = image_extension(&bytes).unwrap_or("bin");
+            let path = dir.join(format!("{}.{}", i, ext));
+            tokio::fs::write(&path, &bytes).await?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+impl ImageObject {
+    /// The image as chat content: its URL, or `b64_json` embedded as a `data:` URL.
+    pub fn to_image_content(&self) -> Option<ImageContent> {
+        if let Some(url) = &self.url {
+            return Some(ImageContent::new(url));
+        }
+        let b64 = self.b64_json.as_ref()?;
+        // 16 base64 characters decode to the 12 bytes the magic numbers need
+        let head = b64.get(..16).and_then(|head| STANDARD.decode(head).ok());
+        let mime_type = head
+            .as_deref()
+            .and_then(image_mime_type)
+            .unwrap_or("image/png");
+        Some(ImageContent::from_base64(b64, mime_type))
+    }
+
+    /// The bytes of a `b64_json` image. Fails for a `url` image, see `fetch_bytes`.
+    pub fn decode_bytes(&self) -> Result<Bytes> {
+        let b64 = self
+            .b64_json
+            .as_ref()
+            .ok_or_else(|| anyhow!("image has no b64_json, download it with fetch_bytes"))?;
+        Ok(STANDARD.decode(b64)?.into())
+    }
+
+    /// The format and dimensions of a `b64_json` image, without decoding its pixels.
+    pub fn metadata(&self) -> Result<ImageMetadata> {
+        let bytes = self.decode_bytes()?;
+        image_metadata(&bytes).ok_or_else(|| anyhow!("unsupported or truncated image"))
+    }
+
+    /// Decode a `b64_json` image for further processing, e.g. thumbnails or overlays.
+    #[cfg(feature = "image")]
+    pub fn to_dynamic_image(&self) -> Result<image::DynamicImage> {
+        Ok(image::load_from_memory(&self.decode_bytes()?)?)
+    }
+
+    /// Get the image bytes, decoding `b64_json` or downloading `url`, whichever is present.
+    pub async fn fetch_bytes(&self, client: &Client) -> Result<Bytes> {
+        if let Some(b64) = &self.b64_json {
+            return Ok(STANDARD.decode(b64)?.into());
+        }
+        let url = self
+            .url
+            .as_ref()
+            .ok_or_else(|| anyhow!("image has neither url nor b64_json"))?;
+        let res = client.get(url).send().await?.error_for_status()?;
+        Ok(res.bytes().await?)
+    }
+}
+
+/// Detect the MIME type of image bytes from their magic number.
+pub fn image_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        _ => None,
+    }
+}
+
+/// Detect the format and dimensions of image bytes from their header: PNG, JPEG, WebP or GIF.
+pub fn image_metadata(bytes: &[u8]) -> Option<ImageMetadata> {
+    let mime_type = image_mime_type(bytes)?;
+    let (width, height) = match mime_type {
+        "image/png" => (be32(bytes, 16)?, be32(bytes, 20)?),
+        "image/jpeg" => jpeg_dimensions(bytes)?,
+        "image/webp" => webp_dimensions(bytes)?,
+        _ => (le16(bytes, 6)?, le16(bytes, 8)?),
+    };
+    Some(ImageMetadata {
+        mime_type,
+        width,
+        height,
+        len: bytes.len(),
+    })
+}
+
+/// The size in the first start-of-frame segment.
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut i = 2;
+    loop {
+        if *bytes.get(i)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(i + 1)?;
+        // markers may be padded with 0xFF
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        // SOF0 to SOF15, except DHT, JPG and DAC which share the range
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            return Some((be16(bytes, i + 7)?, be16(bytes, i + 5)?));
+        }
+        i += 2 + be16(bytes, i + 2)? as usize;
+    }
+}
+
+fn webp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        b"VP8 " => Some((le16(bytes, 26)? & 0x3FFF, le16(bytes, 28)? & 0x3FFF)),
+        b"VP8L" => {
+            let bits = le32(bytes, 21)?;
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le24(bytes, 24)? + 1, le24(bytes, 27)? + 1)),
+        _ => None,
+    }
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 2)?;
+    Some(u16::from_be_bytes([b[0], b[1]]) as u32)
+}
+
+fn be32(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 4)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn le16(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 2)?;
+    Some(u16::from_le_bytes([b[0], b[1]]) as u32)
+}
+
+fn le24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], 0]))
+}
+
+fn le32(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    image_mime_type(bytes).and_then(|mime| mime.strip_prefix("image/"))
 }
 
 // impl Default for ImageModel {
@@ -193,6 +734,295 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn moderate_prompt_should_not_be_serialized() -> Result<()> {
+        let req = CreateImageRequestBuilder::default()
+            .prompt("hello world")
+            .moderate_prompt(true)
+            .build()?;
+        assert_eq!(req.prompt_to_moderate(), Some("hello world"));
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({ "prompt": "hello world", "model": "dall-e-3" })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn create_image_request_deserialize_should_round_trip() -> Result<()> {
+        let req = CreateImageRequestBuilder::default()
+            .prompt("hello world")
+            .n(1)
+            .quality(ImageQuality::Hd)
+            .response_format(ImageResponseFormat::B64Json)
+            .size(ImageSize::LargeWide)
+            .style(ImageStyle::Natural)
+            .user("user1")
+            .build()?;
+        let json = serde_json::to_value(&req)?;
+        let loaded: CreateImageRequest = serde_json::from_value(json.clone())?;
+        assert_eq!(serde_json::to_value(&loaded)?, json);
+
+        let loaded: CreateImageRequest = serde_json::from_value(json!({ "prompt": "hi" }))?;
+        assert_eq!(loaded.model, ImageModel::DallE3);
+        Ok(())
+    }
+
+    #[test]
+    fn create_image_request_should_validate_per_model() -> Result<()> {
+        let req = CreateImageRequestBuilder::default()
+            .prompt("hello world")
+            .model(ImageModel::DallE2)
+            .n(4)
+            .size(ImageSize::Small)
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "prompt": "hello world",
+                "model": "dall-e-2",
+                "n": 4,
+                "size": "256x256",
+            })
+        );
+
+        let err = CreateImageRequestBuilder::default()
+            .prompt("hello world")
+            .n(2)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("dall-e-3 only supports n=1"));
+
+        assert!(CreateImageRequestBuilder::default()
+            .prompt("hello world")
+            .size(ImageSize::Small)
+            .build()
+            .is_err());
+        assert!(CreateImageRequestBuilder::default()
+            .prompt("hello world")
+            .model(ImageModel::DallE2)
+            .size(ImageSize::LargeWide)
+            .build()
+            .is_err());
+        assert!(CreateImageRequestBuilder::default()
+            .prompt("hello world")
+            .model(ImageModel::DallE2)
+            .quality(ImageQuality::Hd)
+            .build()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn gpt_image_1_request_should_serialize() -> Result<()> {
+        let req = CreateImageRequestBuilder::default()
+            .prompt("a sticker of a red panda")
+            .model(ImageModel::GptImage1)
+            .n(2)
+            .size(ImageSize::Portrait)
+            .quality(ImageQuality::High)
+            .background(ImageBackground::Transparent)
+            .output_format(ImageOutputFormat::Webp)
+            .output_compression(80)
+            .moderation(ImageModeration::Low)
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "prompt": "a sticker of a red panda",
+                "model": "gpt-image-1",
+                "n": 2,
+                "quality": "high",
+                "size": "1024x1536",
+                "background": "transparent",
+                "output_format": "webp",
+                "output_compression": 80,
+                "moderation": "low",
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn gpt_image_1_request_should_validate_options() {
+        let build = |f: fn(&mut CreateImageRequestBuilder)| {
+            let mut builder = CreateImageRequestBuilder::default();
+            builder.prompt("hello world").model(ImageModel::GptImage1);
+            f(&mut builder);
+            builder.build().map_err(|e| e.to_string())
+        };
+        assert!(build(|b| {
+            b.size(ImageSize::Auto).quality(ImageQuality::Auto);
+        })
+        .is_ok());
+        let errors = [
+            build(|b| {
+                b.response_format(ImageResponseFormat::Url);
+            }),
+            build(|b| {
+                b.output_compression(50);
+            }),
+            build(|b| {
+                b.output_format(ImageOutputFormat::Jpeg)
+                    .background(ImageBackground::Transparent);
+            }),
+            build(|b| {
+                b.size(ImageSize::LargeWide);
+            }),
+            build(|b| {
+                b.quality(ImageQuality::Hd);
+            }),
+            build(|b| {
+                b.style(ImageStyle::Vivid);
+            }),
+        ];
+        for (err, field) in errors.iter().zip([
+            "response_format",
+            "output_compression",
+            "background",
+            "size",
+            "quality",
+            "style",
+        ]) {
+            assert!(err.as_ref().unwrap_err().contains(field), "{:?}", err);
+        }
+
+        let err = CreateImageRequestBuilder::default()
+            .prompt("hello world")
+            .output_format(ImageOutputFormat::Png)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("only supported for gpt-image-1"));
+    }
+
+    #[test]
+    fn image_object_without_revised_prompt_should_deserialize() -> Result<()> {
+        let res: CreateImageResponse = serde_json::from_value(json!({
+            "created": 1589478378,
+            "data": [{ "url": "https://example.com/image.png" }]
+        }))?;
+        assert!(res.data[0].revised_prompt.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn save_all_should_decode_b64_images() -> Result<()> {
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        let res = CreateImageResponse {
+            created: 0,
+            data: vec![ImageObject {
+                b64_json: Some(STANDARD.encode(png)),
+                url: None,
+                revised_prompt: None,
+            }],
+        };
+        let dir = std::env::temp_dir().join("llm-sdk-save-all");
+        let paths = res.save_all(&Client::new(), &dir).await?;
+        assert_eq!(paths, vec![dir.join("0.png")]);
+        assert_eq!(fs::read(&paths[0])?, png);
+        Ok(())
+    }
+
+    #[test]
+    fn content_parts_should_reference_generated_images() -> Result<()> {
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10, b'J', b'F', b'I', b'F', 0, 1,
+        ];
+        let res = CreateImageResponse {
+            created: 0,
+            data: vec![
+                ImageObject {
+                    b64_json: None,
+                    url: Some("https://example.com/image.png".to_string()),
+                    revised_prompt: None,
+                },
+                ImageObject {
+                    b64_json: Some(STANDARD.encode(jpeg)),
+                    url: None,
+                    revised_prompt: None,
+                },
+            ],
+        };
+        let parts = res.content_parts();
+        assert_eq!(
+            serde_json::to_value(&parts)?,
+            json!([
+                { "type": "image_url", "image_url": { "url": "https://example.com/image.png" } },
+                {
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:image/jpeg;base64,{}", STANDARD.encode(jpeg)) }
+                }
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn image_mime_type_should_detect_formats() {
+        assert_eq!(image_mime_type(b"\x89PNG\r\n"), Some("image/png"));
+        assert_eq!(image_mime_type(b"\xFF\xD8\xFF\xE0"), Some("image/jpeg"));
+        assert_eq!(image_mime_type(b"RIFF\0\0\0\0WEBPVP8"), Some("image/webp"));
+        assert_eq!(image_mime_type(b"hello"), None);
+    }
+
+    #[test]
+    fn image_metadata_should_read_dimensions_from_headers() -> Result<()> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&1024u32.to_be_bytes());
+        png.extend_from_slice(&1536u32.to_be_bytes());
+        let image = ImageObject {
+            b64_json: Some(STANDARD.encode(&png)),
+            url: None,
+            revised_prompt: None,
+        };
+        assert_eq!(
+            image.metadata()?,
+            ImageMetadata {
+                mime_type: "image/png",
+                width: 1024,
+                height: 1536,
+                len: 24
+            }
+        );
+
+        // an APP0 segment, then the start of frame: precision, height, width
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0, 4, 0, 0, 0xFF, 0xC0, 0, 11, 8, 0x02, 0x00, 0x01, 0x80,
+        ];
+        let gif = b"GIF89a\x00\x04\x00\x03";
+        let webp = b"RIFF\0\0\0\0WEBPVP8X\0\0\0\0\0\0\0\0\xff\x03\0\xff\x02\0";
+        let dimensions = |bytes: &[u8]| image_metadata(bytes).map(|m| (m.width, m.height));
+        assert_eq!(dimensions(&jpeg), Some((384, 512)));
+        assert_eq!(dimensions(gif), Some((1024, 768)));
+        assert_eq!(dimensions(webp), Some((1024, 768)));
+        assert_eq!(dimensions(&png[..20]), None);
+
+        let image = ImageObject {
+            b64_json: None,
+            url: Some("https://example.com/image.png".to_string()),
+            revised_prompt: None,
+        };
+        assert!(image.decode_bytes().is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn to_dynamic_image_should_decode_b64_images() -> Result<()> {
+        let mut png = Vec::new();
+        image::RgbImage::new(3, 2)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+        let image = ImageObject {
+            b64_json: Some(STANDARD.encode(&png)),
+            url: None,
+            revised_prompt: None,
+        };
+        let decoded = image.to_dynamic_image()?;
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+        assert_eq!(image.metadata()?.width, 3);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn create_image_should_work() -> Result<()> {
         println!("OPENAI_API_KEY1: {:#?}", std::env::var("OPENAI_API_KEY")?);
@@ -205,13 +1035,23 @@ mod tests {
         assert!(image.url.is_some());
         assert!(image.b64_json.is_none());
         println!("image: {:#?}", image);
-        fs::write(
-            "/tmp/llm-sdk/caterpillar.png",
-            reqwest::get(image.url.as_ref().unwrap())
-                .await?
-                .bytes()
-                .await?,
-        )?;
+        let paths = res.save_all(sdk.client(), "/tmp/llm-sdk").await?;
+        assert_eq!(paths.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn extra_params_should_be_sent_with_the_image_request() -> Result<()> {
+        let req = CreateImageRequestBuilder::default()
+            .prompt("a red panda")
+            .extra_param("partial_images", 2)
+            .build()?
+            .into_request("https://api.openai.com/v1", Client::new())
+            .build()?;
+        let body: serde_json::Value =
+            serde_json::from_slice(req.body().unwrap().as_bytes().unwrap())?;
+        assert_eq!(body["partial_images"], 2);
+        assert_eq!(body["prompt"], "a red panda");
         Ok(())
     }
 }
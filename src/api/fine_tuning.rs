@@ -0,0 +1,233 @@
+use derive_builder::Builder;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChatCompleteModel, IntoRequest, ObjectType, PageRequest};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable")]
+pub struct CreateFineTuningJobRequest {
+    /// The name of the model to fine-tune, e.g. `gpt-3.5-turbo-0125`.
+    #[builder(setter(into))]
+    model: String,
+    /// The ID of an uploaded file that contains training data.
+    #[builder(setter(into))]
+    training_file: String,
+    /// The ID of an uploaded file that contains validation data.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation_file: Option<String>,
+    /// A string of up to 18 characters that will be added to your fine-tuned model name.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    /// The seed controls the reproducibility of the job.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable", default)]
+pub struct ListFineTuningJobsRequest {
+    /// Identifier for the last job from the previous pagination request.
+    #[builder(setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+    /// Number of fine-tuning jobs to retrieve. Defaults to 20.
+    #[builder(setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetrieveFineTuningJobRequest {
+    fine_tuning_job_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CancelFineTuningJobRequest {
+    fine_tuning_job_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FineTuningJob {
+    /// The object identifier, which can be referenced in the API endpoints.
+    pub id: String,
+    /// The object type, which is always "fine_tuning.job".
+    pub object: ObjectType,
+    /// The Unix timestamp (in seconds) for when the fine-tuning job was created.
+    pub created_at: u64,
+    /// The Unix timestamp (in seconds) for when the fine-tuning job was finished.
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    /// The base model that is being fine-tuned.
+    pub model: String,
+    /// The name of the fine-tuned model that is being created. Null while the job is still running.
+    #[serde(default)]
+    pub fine_tuned_model: Option<ChatCompleteModel>,
+    /// The organization that owns the fine-tuning job.
+    pub organization_id: String,
+    /// The current status of the fine-tuning job.
+    pub status: FineTuningJobStatus,
+    /// The file ID used for training.
+    pub training_file: String,
+    /// The file ID used for validation.
+    #[serde(default)]
+    pub validation_file: Option<String>,
+    /// The compiled results file ID(s) for the fine-tuning job.
+    #[serde(default)]
+    pub result_files: Vec<String>,
+    /// The total number of billable tokens processed by this fine-tuning job.
+    #[serde(default)]
+    pub trained_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FineTuningJobStatus {
+    ValidatingFiles,
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl CreateFineTuningJobRequest {
+    pub fn new(model: impl Into<String>, training_file: impl Into<String>) -> Self {
+        CreateFineTuningJobRequestBuilder::default()
+            .model(model)
+            .training_file(training_file)
+            .build()
+            .unwrap()
+    }
+}
+
+impl RetrieveFineTuningJobRequest {
+    pub fn new(fine_tuning_job_id: impl Into<String>) -> Self {
+        Self {
+            fine_tuning_job_id: fine_tuning_job_id.into(),
+        }
+    }
+}
+
+impl CancelFineTuningJobRequest {
+    pub fn new(fine_tuning_job_id: impl Into<String>) -> Self {
+        Self {
+            fine_tuning_job_id: fine_tuning_job_id.into(),
+        }
+    }
+}
+
+impl FineTuningJobStatus {
+    /// Whether the job will not change status anymore.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            FineTuningJobStatus::Succeeded
+                | FineTuningJobStatus::Failed
+                | FineTuningJobStatus::Cancelled
+        )
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/fine-tuning/create
+impl IntoRequest for CreateFineTuningJobRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .post(format!("{}/fine_tuning/jobs", base_url))
+            .json(&self)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/fine-tuning/list
+impl IntoRequest for ListFineTuningJobsRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .get(format!("{}/fine_tuning/jobs", base_url))
+            .query(&self)
+    }
+}
+
+impl PageRequest for ListFineTuningJobsRequest {
+    type Item = FineTuningJob;
+
+    fn set_after(&mut self, after: String) {
+        self.after = Some(after);
+    }
+
+    fn item_id(item: &FineTuningJob) -> &str {
+        &item.id
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/fine-tuning/retrieve
+impl IntoRequest for RetrieveFineTuningJobRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.get(format!(
+            "{}/fine_tuning/jobs/{}",
+            base_url, self.fine_tuning_job_id
+        ))
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/fine-tuning/cancel
+impl IntoRequest for CancelFineTuningJobRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.post(format!(
+            "{}/fine_tuning/jobs/{}/cancel",
+            base_url, self.fine_tuning_job_id
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn create_fine_tuning_job_request_should_serialize() -> Result<()> {
+        let req = CreateFineTuningJobRequestBuilder::default()
+            .model("gpt-3.5-turbo-0125")
+            .training_file("file-abc123")
+            .suffix("support")
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "model": "gpt-3.5-turbo-0125",
+                "training_file": "file-abc123",
+                "suffix": "support"
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fine_tuning_job_should_deserialize() -> Result<()> {
+        let job: FineTuningJob = ObjectType::FineTuningJob.parse(json!({
+            "object": "fine_tuning.job",
+            "id": "ftjob-abc123",
+            "model": "gpt-3.5-turbo-0125",
+            "created_at": 1721764800,
+            "finished_at": 1721764900,
+            "fine_tuned_model": "ft:gpt-3.5-turbo-0125:my-org:support:7p4lURel",
+            "organization_id": "org-123",
+            "result_files": ["file-abc123"],
+            "status": "succeeded",
+            "validation_file": null,
+            "training_file": "file-abc123",
+            "hyperparameters": { "n_epochs": 4 },
+            "trained_tokens": 5768
+        }))?;
+        assert!(job.status.is_terminal());
+        let model = job.fine_tuned_model.unwrap();
+        assert_eq!(model.base_model(), "gpt-3.5-turbo-0125");
+        assert_eq!(model.fine_tuned_suffix(), Some("support"));
+        assert_eq!(model.context_window(), 16_385);
+        Ok(())
+    }
+}
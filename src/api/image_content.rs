@@ -0,0 +1,589 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::{image_mime_type, validation::Validator, ValidationError};
+
+/// The largest image a `ContentBuilder` accepts, the limit of the API.
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+/// The largest audio clip a `ContentBuilder` accepts, the limit of the audio endpoints.
+pub const MAX_INPUT_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+/// The image types vision models accept.
+const IMAGE_MIME_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/webp", "image/gif"];
+
+/// `UserContent` under a shorter name, for `Content::builder()`.
+pub type Content = UserContent;
+
+/// The content of a user or assistant message: plain text, or text and images for vision models.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum UserContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+/// Builds multi-part content, checking every part before it is sent.
+///
+/// Images must be PNG, JPEG, WebP or GIF of at most `MAX_IMAGE_BYTES`, and audio must be in
+/// the format it is declared as, at most `MAX_INPUT_AUDIO_BYTES`. A part that fails is
+/// reported by `build` along with every other, with the index of the part as the field.
+///
+/// ```no_run
+/// # use llm_sdk::{ChatCompletionMessage, Content, ImageDetail, InputAudioFormat};
+/// # fn run(recording: &[u8]) -> anyhow::Result<()> {
+/// let content = Content::builder()
+///     .text("Describe the photos, and answer the question in the recording.")
+///     .image_url("https://example.com/paris.jpg")
+///     .image_path("photos/lyon.png")
+///     .detail(ImageDetail::Low)
+///     .input_audio(recording, InputAudioFormat::Wav)
+///     .build()?;
+/// let message = ChatCompletionMessage::new_user(content, "");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ContentBuilder {
+    parts: Vec<ContentPart>,
+    validator: Validator,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    ImageUrl {
+        image_url: ImageContent,
+    },
+    /// Audio input, for audio models such as `gpt-4o-audio-preview`.
+    InputAudio {
+        input_audio: InputAudio,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InputAudio {
+    /// The base64 encoded audio data.
+    pub data: String,
+    pub format: InputAudioFormat,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputAudioFormat {
+    Wav,
+    Mp3,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageContent {
+    /// Either a URL of the image or the base64 encoded image data as a `data:` URL.
+    pub url: String,
+    /// Specifies the detail level of the image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageDetail {
+    #[default]
+    Auto,
+    Low,
+    High,
+}
+
+impl UserContent {
+    /// The text of the message; for multi-part content, the first text part.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            UserContent::Text(text) => Some(text),
+            UserContent::Parts(parts) => parts.iter().find_map(|part| match part {
+                ContentPart::Text { text } => Some(text.as_str()),
+                _ => None,
+            }),
+        }
+    }
+
+    pub(crate) fn texts_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            UserContent::Text(text) => vec![text],
+            UserContent::Parts(parts) => parts
+                .iter_mut()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// The image parts, in order.
+    pub fn images(&self) -> Vec<&ImageContent> {
+        match self {
+            UserContent::Text(_) => Vec::new(),
+            UserContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::ImageUrl { image_url } => Some(image_url),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl UserContent {
+    pub fn builder() -> ContentBuilder {
+        ContentBuilder::default()
+    }
+}
+
+impl ContentBuilder {
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.parts.push(ContentPart::text(text));
+        self
+    }
+
+    /// An image by URL: `https` for the API to fetch, or a base64 `data:` URL.
+    pub fn image_url(mut self, url: impl Into<String>) -> Self {
+        let url = url.into();
+        let field = self.field();
+        match url.strip_prefix("data:") {
+            Some(data) => {
+                let (mime_type, base64) = data.split_once(";base64,").unwrap_or((data, ""));
+                self.validator.check(
+                    IMAGE_MIME_TYPES.contains(&mime_type),
+                    &field,
+                    format!("unsupported image type `{}`", mime_type),
+                );
+                self.check_size(&field, base64.len() / 4 * 3, MAX_IMAGE_BYTES);
+            }
+            None => self.validator.check(
+                url.starts_with("https://") || url.starts_with("http://"),
+                &field,
+                "must be an http(s) or data: URL",
+            ),
+        }
+        self.parts.push(ContentPart::image(ImageContent::new(url)));
+        self
+    }
+
+    /// An image embedded as a `data:` URL, its type detected from the content.
+    pub fn image_bytes(self, bytes: &[u8]) -> Self {
+        let mime_type = image_mime_type(bytes);
+        self.push_image(bytes, mime_type)
+    }
+
+    /// A local image embedded as a `data:` URL, its type detected from the content or else
+    /// the file extension.
+    pub fn image_path(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match std::fs::read(path) {
+            Ok(bytes) => {
+                let mime_type = image_mime_type(&bytes).or_else(|| mime_type_from_extension(path));
+                self.push_image(&bytes, mime_type)
+            }
+            Err(e) => {
+                let field = self.field();
+                self.validator.check(
+                    false,
+                    field,
+                    format!("can't read {}: {}", path.display(), e),
+                );
+                self.parts.push(ContentPart::image(ImageContent::new("")));
+                self
+            }
+        }
+    }
+
+    /// The detail level of the image added last.
+    pub fn detail(mut self, detail: ImageDetail) -> Self {
+        match self.parts.last_mut() {
+            Some(ContentPart::ImageUrl { image_url }) => image_url.detail = Some(detail),
+            _ => {
+                let field = format!("content[{}]", self.parts.len().saturating_sub(1));
+                self.validator
+                    .check(false, field, "a detail level applies to an image only");
+            }
+        }
+        self
+    }
+
+    pub fn input_audio(mut self, bytes: &[u8], format: InputAudioFormat) -> Self {
+        let field = self.field();
+        let matches = match format {
+            InputAudioFormat::Wav => {
+                bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE")
+            }
+            // an ID3 tag, or the sync word of an MPEG audio frame
+            InputAudioFormat::Mp3 => {
+                bytes.starts_with(b"ID3") || matches!(bytes, [0xFF, b, ..] if b & 0xE0 == 0xE0)
+            }
+        };
+        let name = match format {
+            InputAudioFormat::Wav => "WAV",
+            InputAudioFormat::Mp3 => "MP3",
+        };
+        self.validator
+            .check(matches, &field, format!("is not {} audio", name));
+        self.check_size(&field, bytes.len(), MAX_INPUT_AUDIO_BYTES);
+        self.parts
+            .push(ContentPart::audio(InputAudio::from_bytes(bytes, format)));
+        self
+    }
+
+    /// The content, or every violation of its parts.
+    pub fn build(mut self) -> Result<UserContent, ValidationError> {
+        self.validator.check(
+            !self.parts.is_empty(),
+            "content",
+            "must have at least one part",
+        );
+        self.validator.finish()?;
+        Ok(UserContent::Parts(self.parts))
+    }
+
+    /// The field of the part added next.
+    fn field(&self) -> String {
+        format!("content[{}]", self.parts.len())
+    }
+
+    fn push_image(mut self, bytes: &[u8], mime_type: Option<&str>) -> Self {
+        let field = self.field();
+        let mime_type = mime_type.filter(|mime_type| IMAGE_MIME_TYPES.contains(mime_type));
+        self.validator.check(
+            mime_type.is_some(),
+            &field,
+            "is not a PNG, JPEG, WebP or GIF image",
+        );
+        self.check_size(&field, bytes.len(), MAX_IMAGE_BYTES);
+        let mime_type = mime_type.unwrap_or("application/octet-stream");
+        self.parts.push(ContentPart::image(ImageContent::from_bytes(
+            bytes, mime_type,
+        )));
+        self
+    }
+
+    fn check_size(&mut self, field: &str, len: usize, max: usize) {
+        self.validator.check(
+            len <= max,
+            field,
+            format!("must be at most {} bytes, got {}", max, len),
+        );
+    }
+}
+
+impl ContentPart {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    pub fn image(image: ImageContent) -> Self {
+        ContentPart::ImageUrl { image_url: image }
+    }
+
+    pub fn audio(audio: InputAudio) -> Self {
+        ContentPart::InputAudio { input_audio: audio }
+    }
+}
+
+impl InputAudio {
+    pub fn from_bytes(bytes: &[u8], format: InputAudioFormat) -> Self {
+        Self {
+            data: STANDARD.encode(bytes),
+            format,
+        }
+    }
+
+    /// Read a local `.wav` or `.mp3` file, the format given by the extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("wav") => InputAudioFormat::Wav,
+            Some("mp3") => InputAudioFormat::Mp3,
+            _ => return Err(anyhow!("unsupported audio type: {}", path.display())),
+        };
+        Ok(Self::from_bytes(&std::fs::read(path)?, format))
+    }
+}
+
+impl ImageContent {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: ImageDetail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Embed image bytes as a base64 `data:` URL.
+    pub fn from_bytes(bytes: &[u8], mime_type: &str) -> Self {
+        Self::from_base64(&STANDARD.encode(bytes), mime_type)
+    }
+
+    /// Embed base64 image data, e.g. `b64_json` of a generated image, as a `data:` URL.
+    pub fn from_base64(data: &str, mime_type: &str) -> Self {
+        Self::new(format!("data:{};base64,{}", mime_type, data))
+    }
+
+    /// Read a local image and embed it as a `data:` URL.
+    ///
+    /// The MIME type is detected from the content, falling back to the file extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let mime_type = image_mime_type(&bytes)
+            .or_else(|| mime_type_from_extension(path))
+            .ok_or_else(|| anyhow!("unsupported image type: {}", path.display()))?;
+        Ok(Self::from_bytes(&bytes, mime_type))
+    }
+
+    /// Read a local image, shrink it to what the model looks at for `detail` and embed it as a PNG.
+    ///
+    /// `Low` fits the image in 512x512. `High` and `Auto` fit it in 2048x2048,
+    /// then scale the shortest side down to 768px. Smaller images are not scaled up.
+    #[cfg(feature = "image")]
+    pub fn from_path_with_detail(path: impl AsRef<Path>, detail: ImageDetail) -> Result<Self> {
+        let img = image::open(path)?;
+        let img = match detail {
+            ImageDetail::Low => shrink_to_fit(img, 512),
+            ImageDetail::High | ImageDetail::Auto => {
+                let img = shrink_to_fit(img, 2048);
+                let (width, height) = (img.width(), img.height());
+                let shortest = width.min(height);
+                if shortest > 768 {
+                    let scale = 768.0 / shortest as f64;
+                    img.resize(
+                        (width as f64 * scale).round() as u32,
+                        (height as f64 * scale).round() as u32,
+                        image::imageops::FilterType::Triangle,
+                    )
+                } else {
+                    img
+                }
+            }
+        };
+        let mut png = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png, image::ImageFormat::Png)?;
+        Ok(Self::from_bytes(png.get_ref(), "image/png").with_detail(detail))
+    }
+}
+
+/// Fit `img` in `max`x`max`, keeping its aspect ratio, unless it fits already.
+#[cfg(feature = "image")]
+fn shrink_to_fit(img: image::DynamicImage, max: u32) -> image::DynamicImage {
+    if img.width() > max || img.height() > max {
+        img.resize(max, max, image::imageops::FilterType::Triangle)
+    } else {
+        img
+    }
+}
+
+fn mime_type_from_extension(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        _ => None,
+    }
+}
+
+impl From<String> for UserContent {
+    fn from(s: String) -> Self {
+        UserContent::Text(s)
+    }
+}
+
+impl From<&str> for UserContent {
+    fn from(s: &str) -> Self {
+        UserContent::Text(s.to_string())
+    }
+}
+
+impl From<Vec<ContentPart>> for UserContent {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        UserContent::Parts(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChatCompletionMessage;
+    use serde_json::json;
+
+    #[test]
+    fn assistant_message_with_images_should_round_trip() -> Result<()> {
+        let message = ChatCompletionMessage::new_assistant_with_images(
+            "Here is the logo.",
+            [ImageContent::new("https://example.com/logo.png")],
+        );
+        let json = serde_json::to_value(&message)?;
+        assert_eq!(
+            json,
+            json!({
+                "role": "assistant",
+                "content": [
+                    { "type": "text", "text": "Here is the logo." },
+                    { "type": "image_url", "image_url": { "url": "https://example.com/logo.png" } }
+                ]
+            })
+        );
+        let ChatCompletionMessage::Assistant(loaded) = serde_json::from_value(json)? else {
+            panic!("expected an assistant message");
+        };
+        assert_eq!(loaded.content(), Some("Here is the logo."));
+        assert_eq!(
+            loaded.images(),
+            [&ImageContent::new("https://example.com/logo.png")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn user_message_with_image_should_serialize() -> Result<()> {
+        let message = ChatCompletionMessage::new_user(
+            vec![
+                ContentPart::text("What's in this image?"),
+                ContentPart::image(
+                    ImageContent::new("https://example.com/cat.png").with_detail(ImageDetail::Low),
+                ),
+            ],
+            "",
+        );
+        assert_eq!(
+            serde_json::to_value(&message)?,
+            json!({
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "What's in this image?" },
+                    {
+                        "type": "image_url",
+                        "image_url": { "url": "https://example.com/cat.png", "detail": "low" }
+                    }
+                ]
+            })
+        );
+        assert_eq!(message.content(), Some("What's in this image?"));
+        Ok(())
+    }
+
+    #[test]
+    fn image_content_from_path_should_embed_data_url() -> Result<()> {
+        let path = std::env::temp_dir().join("llm-sdk-image-content.img");
+        std::fs::write(&path, [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A])?;
+        let image = ImageContent::from_path(&path)?;
+        assert_eq!(image.url, "data:image/png;base64,iVBORw0KGgo=");
+
+        let path = std::env::temp_dir().join("llm-sdk-image-content.txt");
+        std::fs::write(&path, "not an image")?;
+        assert!(ImageContent::from_path(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn content_builder_should_serialize_every_part() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "llm-sdk-content-builder-{}.gif",
+            std::process::id()
+        ));
+        std::fs::write(&path, b"GIF89a")?;
+        let content = Content::builder()
+            .text("Describe these, then answer the recording.")
+            .image_url("https://example.com/cat.png")
+            .detail(ImageDetail::High)
+            .image_path(&path)
+            .image_bytes(&[0xFF, 0xD8, 0xFF, 0xE0])
+            .input_audio(b"ID3\x04", InputAudioFormat::Mp3)
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&content)?,
+            json!([
+                { "type": "text", "text": "Describe these, then answer the recording." },
+                {
+                    "type": "image_url",
+                    "image_url": { "url": "https://example.com/cat.png", "detail": "high" }
+                },
+                { "type": "image_url", "image_url": { "url": "data:image/gif;base64,R0lGODlh" } },
+                { "type": "image_url", "image_url": { "url": "data:image/jpeg;base64,/9j/4A==" } },
+                { "type": "input_audio", "input_audio": { "data": "SUQzBA==", "format": "mp3" } }
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn content_builder_should_report_invalid_parts() {
+        let err = Content::builder()
+            .detail(ImageDetail::Low)
+            .text("What's this?")
+            .image_url("ftp://example.com/cat.png")
+            .image_url("data:image/bmp;base64,Qk0=")
+            .image_bytes(b"%PDF-1.7")
+            .image_path("/nonexistent/cat.png")
+            .input_audio(b"ID3\x04", InputAudioFormat::Wav)
+            .build()
+            .unwrap_err();
+        let fields: Vec<_> = err.violations.iter().map(|v| v.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "content[0]",
+                "content[1]",
+                "content[2]",
+                "content[3]",
+                "content[4]",
+                "content[5]"
+            ]
+        );
+        assert_eq!(err.violations[5].message, "is not WAV audio");
+
+        let too_large = "A".repeat(MAX_IMAGE_BYTES / 3 * 4 + 4);
+        let err = Content::builder()
+            .image_url(format!("data:image/png;base64,{}", too_large))
+            .build()
+            .err()
+            .unwrap();
+        assert!(err.violations[0].message.starts_with("must be at most"));
+        assert!(Content::builder().build().is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_content_from_path_with_detail_should_downsize() -> Result<()> {
+        let path = std::env::temp_dir().join("llm-sdk-image-content-large.png");
+        image::RgbImage::new(1024, 2048).save(&path)?;
+        let image = ImageContent::from_path_with_detail(&path, ImageDetail::Low)?;
+        let data = image.url.strip_prefix("data:image/png;base64,").unwrap();
+        let resized = image::load_from_memory(&STANDARD.decode(data)?)?;
+        assert_eq!((resized.width(), resized.height()), (256, 512));
+        assert_eq!(image.detail, Some(ImageDetail::Low));
+
+        // a small image is left as is
+        let path = std::env::temp_dir().join("llm-sdk-image-content-small.png");
+        image::RgbImage::new(100, 50).save(&path)?;
+        for detail in [ImageDetail::Low, ImageDetail::High] {
+            let image = ImageContent::from_path_with_detail(&path, detail)?;
+            let data = image.url.strip_prefix("data:image/png;base64,").unwrap();
+            let kept = image::load_from_memory(&STANDARD.decode(data)?)?;
+            assert_eq!((kept.width(), kept.height()), (100, 50));
+        }
+        Ok(())
+    }
+}
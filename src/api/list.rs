@@ -0,0 +1,85 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{BoxStream, IntoRequest, MaybeSend, ObjectType};
+
+/// A page of objects returned by a list endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct List<T> {
+    /// The object type, which is always list.
+    pub object: ObjectType,
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub first_id: Option<String>,
+    #[serde(default)]
+    pub last_id: Option<String>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+/// Sort order by the created_at timestamp of the objects.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ListOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// A list request paged with an `after` cursor, see `LlmSdk::paginate`.
+pub trait PageRequest: IntoRequest + Clone + MaybeSend + 'static {
+    type Item: DeserializeOwned + MaybeSend + 'static;
+
+    /// Ask for the objects after the one with this id.
+    fn set_after(&mut self, after: String);
+
+    /// The id of an object, the cursor for the page after it.
+    fn item_id(item: &Self::Item) -> &str;
+}
+
+/// Every object of a list endpoint, fetched a page at a time as the stream is polled.
+///
+/// Created by `LlmSdk::paginate`. A failed page ends the stream after its error.
+pub struct Paginator<T> {
+    inner: BoxStream<T>,
+}
+
+impl<T> Paginator<T> {
+    pub(crate) fn new(inner: BoxStream<T>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T> Stream for Paginator<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl<T> List<T> {
+    /// The cursor for the next page, `None` on the last one.
+    pub(crate) fn next_cursor(&self, item_id: impl Fn(&T) -> &str) -> Option<String> {
+        if !self.has_more {
+            return None;
+        }
+        self.last_id
+            .clone()
+            .or_else(|| self.data.last().map(|item| item_id(item).to_string()))
+    }
+}
+
+/// Returned by delete endpoints.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeletionStatus {
+    pub id: String,
+    pub object: ObjectType,
+    pub deleted: bool,
+}
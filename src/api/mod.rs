@@ -1,5 +1,39 @@
 mod chat_completion;
+mod chat_completion_chunk;
+mod chat_completion_stream;
+mod create_completion;
+mod create_embedding;
 mod create_image;
+mod fine_tuning;
+mod image_content;
+mod list;
+mod model;
+mod moderation;
+mod openrouter;
+mod organization;
+mod raw;
+mod responses;
+mod speech;
+mod stored_completion;
+mod upload;
+mod vector_store;
 
 pub use chat_completion::*;
+pub use chat_completion_chunk::*;
+pub use chat_completion_stream::*;
+pub use create_completion::*;
+pub use create_embedding::*;
 pub use create_image::*;
+pub use fine_tuning::*;
+pub use image_content::*;
+pub use list::*;
+pub use model::*;
+pub use moderation::*;
+pub use openrouter::*;
+pub use organization::*;
+pub use raw::*;
+pub use responses::*;
+pub use speech::*;
+pub use stored_completion::*;
+pub use upload::*;
+pub use vector_store::*;
@@ -0,0 +1,90 @@
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+
+use crate::{IntoRequest, ObjectType, PageRequest};
+
+#[derive(Debug, Clone, Default)]
+pub struct ListModelsRequest;
+
+#[derive(Debug, Clone)]
+pub struct RetrieveModelRequest {
+    model: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Model {
+    /// The model identifier, which can be referenced in the API endpoints.
+    pub id: String,
+    /// The object type, which is always model.
+    pub object: ObjectType,
+    /// The Unix timestamp (in seconds) when the model was created.
+    #[serde(default)]
+    pub created: u64,
+    /// The organization that owns the model.
+    #[serde(default)]
+    pub owned_by: String,
+}
+
+impl ListModelsRequest {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl RetrieveModelRequest {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+        }
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/models/list
+impl IntoRequest for ListModelsRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.get(format!("{}/models", base_url))
+    }
+}
+
+impl PageRequest for ListModelsRequest {
+    type Item = Model;
+
+    fn set_after(&mut self, after: String) {
+        // the models endpoint returns every model at once
+        let _ = after;
+    }
+
+    fn item_id(item: &Model) -> &str {
+        &item.id
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/models/retrieve
+impl IntoRequest for RetrieveModelRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.get(format!("{}/models/{}", base_url, self.model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::List;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn list_models_response_should_deserialize() -> Result<()> {
+        let list: List<Model> = ObjectType::List.parse(json!({
+            "object": "list",
+            "data": [
+                { "id": "gpt-4o", "object": "model", "created": 1715367049, "owned_by": "system" },
+                { "id": "ft:gpt-4o-mini:acme::abc123", "object": "model", "created": 1721172717, "owned_by": "acme" }
+            ]
+        }))?;
+        let ids: Vec<_> = list.data.iter().map(|model| model.id.as_str()).collect();
+        assert_eq!(ids, ["gpt-4o", "ft:gpt-4o-mini:acme::abc123"]);
+        assert_eq!(list.data[1].owned_by, "acme");
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use derive_builder::Builder;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::IntoRequest;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable")]
+pub struct CreateModerationRequest {
+    /// The text to classify, as a string or array of strings.
+    #[builder(setter(into))]
+    input: ModerationInput,
+    /// The moderation model to use.
+    #[builder(default)]
+    #[serde(default)]
+    model: ModerationModel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ModerationInput {
+    Text(String),
+    Texts(Vec<String>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+pub enum ModerationModel {
+    #[default]
+    #[serde(rename = "omni-moderation-latest")]
+    OmniModerationLatest,
+    #[serde(rename = "text-moderation-latest")]
+    TextModerationLatest,
+    #[serde(rename = "text-moderation-stable")]
+    TextModerationStable,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateModerationResponse {
+    /// The unique identifier for the moderation request.
+    pub id: String,
+    /// The model used to generate the moderation results.
+    pub model: String,
+    /// The results, one per input.
+    pub results: Vec<ModerationResult>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModerationResult {
+    /// Whether any of the categories are flagged.
+    pub flagged: bool,
+    /// Whether each category, e.g. `violence` or `self-harm/intent`, is flagged.
+    pub categories: BTreeMap<String, bool>,
+    /// The model's confidence for each category, between 0 and 1.
+    pub category_scores: BTreeMap<String, f64>,
+}
+
+impl CreateModerationRequest {
+    pub fn new(input: impl Into<ModerationInput>) -> Self {
+        CreateModerationRequestBuilder::default()
+            .input(input)
+            .build()
+            .unwrap()
+    }
+}
+
+impl From<String> for ModerationInput {
+    fn from(text: String) -> Self {
+        ModerationInput::Text(text)
+    }
+}
+
+impl From<&str> for ModerationInput {
+    fn from(text: &str) -> Self {
+        ModerationInput::Text(text.to_string())
+    }
+}
+
+impl From<Vec<String>> for ModerationInput {
+    fn from(texts: Vec<String>) -> Self {
+        ModerationInput::Texts(texts)
+    }
+}
+
+impl ModerationResult {
+    /// The names of the flagged categories.
+    pub fn flagged_categories(&self) -> Vec<String> {
+        self.categories
+            .iter()
+            .filter(|(_, flagged)| **flagged)
+            .map(|(category, _)| category.clone())
+            .collect()
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/moderations/create
+impl IntoRequest for CreateModerationRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.post(format!("{}/moderations", base_url)).json(&self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn create_moderation_request_should_serialize() -> Result<()> {
+        let req = CreateModerationRequest::new("a cat in a hat");
+        assert_eq!(
+            serde_json::to_value(req)?,
+            json!({ "input": "a cat in a hat", "model": "omni-moderation-latest" })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn create_moderation_response_should_deserialize() -> Result<()> {
+        let res: CreateModerationResponse = serde_json::from_value(json!({
+            "id": "modr-970d409ef3bef3b70c73d8232df86e7d",
+            "model": "omni-moderation-latest",
+            "results": [{
+                "flagged": true,
+                "categories": { "sexual": false, "violence": true, "violence/graphic": true },
+                "category_scores": { "sexual": 0.0001, "violence": 0.86, "violence/graphic": 0.59 },
+                "category_applied_input_types": { "violence": ["text"] }
+            }]
+        }))?;
+        let result = &res.results[0];
+        assert!(result.flagged);
+        assert_eq!(
+            result.flagged_categories(),
+            ["violence", "violence/graphic"]
+        );
+        assert_eq!(result.category_scores["violence"], 0.86);
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+/// OpenRouter's OpenAI-compatible endpoint, see `LlmSdk::openrouter`.
+pub const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+/// How OpenRouter routes a request across the providers serving a model.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProviderPreferences {
+    /// Provider slugs to try in order, e.g. `["anthropic", "openai"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    /// Whether to fall back to other providers when the preferred ones are unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    /// Only use providers that support every parameter in the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    /// Whether to use providers that may store or train on the data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_collection: Option<DataCollection>,
+    /// Provider slugs to skip for this request.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Only use providers serving the model at one of these quantization levels, e.g. `fp8`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quantizations: Vec<String>,
+    /// Sort providers by this attribute instead of OpenRouter's load balancing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<ProviderSort>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataCollection {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderSort {
+    Price,
+    Throughput,
+    Latency,
+}
+
+impl ProviderPreferences {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn order(mut self, providers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.order = providers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn allow_fallbacks(mut self, allow: bool) -> Self {
+        self.allow_fallbacks = Some(allow);
+        self
+    }
+
+    pub fn require_parameters(mut self, require: bool) -> Self {
+        self.require_parameters = Some(require);
+        self
+    }
+
+    pub fn data_collection(mut self, data_collection: DataCollection) -> Self {
+        self.data_collection = Some(data_collection);
+        self
+    }
+
+    pub fn ignore(mut self, providers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.ignore = providers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn sort(mut self, sort: ProviderSort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::completion_json, ChatCompleteModel, ChatCompletionMessage,
+        ChatCompletionRequestBuilder, ChatCompletionResponse, IntoRequest, ObjectType,
+    };
+    use anyhow::Result;
+    use reqwest::Client;
+    use serde_json::json;
+
+    #[test]
+    fn openrouter_request_should_serialize() -> Result<()> {
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![ChatCompletionMessage::new_user("hi", "")])
+            .model(ChatCompleteModel::from(
+                "anthropic/claude-3.5-sonnet".to_string(),
+            ))
+            .provider(
+                ProviderPreferences::new()
+                    .order(["anthropic", "amazon-bedrock"])
+                    .allow_fallbacks(false)
+                    .data_collection(DataCollection::Deny),
+            )
+            .transforms(vec!["middle-out".to_string()])
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "messages": [{ "role": "user", "content": "hi" }],
+                "model": "anthropic/claude-3.5-sonnet",
+                "provider": {
+                    "order": ["anthropic", "amazon-bedrock"],
+                    "allow_fallbacks": false,
+                    "data_collection": "deny"
+                },
+                "transforms": ["middle-out"]
+            })
+        );
+        let req = req
+            .into_request(OPENROUTER_BASE_URL, Client::new())
+            .build()?;
+        assert_eq!(
+            req.url().as_str(),
+            "https://openrouter.ai/api/v1/chat/completions"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn openrouter_response_should_deserialize() -> Result<()> {
+        let mut value = completion_json("Hello!", "stop", (8, 3));
+        value["provider"] = json!("Anthropic");
+        value["usage"]["cost"] = json!(0.000069);
+        let res: ChatCompletionResponse = ObjectType::ChatCompletion.parse(value)?;
+        assert_eq!(res.provider.as_deref(), Some("Anthropic"));
+        assert_eq!(res.system_fingerprint, None);
+        assert_eq!(res.usage.cost, Some(0.000069));
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use derive_builder::Builder;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{IntoRequest, ObjectType};
+
+/// Token usage of the completions API, bucketed by time. Requires an admin API key.
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "mutable")]
+pub struct CompletionsUsageRequest {
+    /// Start time (Unix seconds) of the query time range, inclusive.
+    start_time: u64,
+    /// End time (Unix seconds) of the query time range, exclusive.
+    #[builder(default, setter(strip_option))]
+    end_time: Option<u64>,
+    /// Width of each time bucket in the response. Defaults to 1d.
+    #[builder(default, setter(strip_option))]
+    bucket_width: Option<BucketWidth>,
+    /// Only return usage for these projects.
+    #[builder(default, setter(into))]
+    project_ids: Vec<String>,
+    /// Only return usage for these users.
+    #[builder(default, setter(into))]
+    user_ids: Vec<String>,
+    /// Only return usage for these API keys.
+    #[builder(default, setter(into))]
+    api_key_ids: Vec<String>,
+    /// Only return usage for these models.
+    #[builder(default, setter(into))]
+    models: Vec<String>,
+    /// If true, only batch jobs; if false, only non-batch jobs.
+    #[builder(default, setter(strip_option))]
+    batch: Option<bool>,
+    /// Split each bucket's results by these fields.
+    #[builder(default, setter(into))]
+    group_by: Vec<UsageGroupBy>,
+    /// Number of buckets to return.
+    #[builder(default, setter(strip_option))]
+    limit: Option<usize>,
+    /// A cursor for pagination, from `next_page` of the previous response.
+    #[builder(default, setter(strip_option, into))]
+    page: Option<String>,
+}
+
+/// Costs of the organization in USD, bucketed by day. Requires an admin API key.
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "mutable")]
+pub struct CostsRequest {
+    /// Start time (Unix seconds) of the query time range, inclusive.
+    start_time: u64,
+    /// End time (Unix seconds) of the query time range, exclusive.
+    #[builder(default, setter(strip_option))]
+    end_time: Option<u64>,
+    /// Only return costs for these projects.
+    #[builder(default, setter(into))]
+    project_ids: Vec<String>,
+    /// Split each bucket's results by these fields.
+    #[builder(default, setter(into))]
+    group_by: Vec<CostGroupBy>,
+    /// Number of buckets to return.
+    #[builder(default, setter(strip_option))]
+    limit: Option<usize>,
+    /// A cursor for pagination, from `next_page` of the previous response.
+    #[builder(default, setter(strip_option, into))]
+    page: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum BucketWidth {
+    #[serde(rename = "1m")]
+    Minute,
+    #[serde(rename = "1h")]
+    Hour,
+    #[default]
+    #[serde(rename = "1d")]
+    Day,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGroupBy {
+    ProjectId,
+    UserId,
+    ApiKeyId,
+    Model,
+    Batch,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CostGroupBy {
+    ProjectId,
+    LineItem,
+}
+
+/// A page of time buckets returned by the organization usage and costs endpoints.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Page<T> {
+    /// The object type, which is always "page".
+    pub object: ObjectType,
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub has_more: bool,
+    /// The cursor to pass as `page` to get the next page.
+    #[serde(default)]
+    pub next_page: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Bucket<T> {
+    /// The object type, which is always "bucket".
+    pub object: ObjectType,
+    /// Start of the bucket (Unix seconds), inclusive.
+    pub start_time: u64,
+    /// End of the bucket (Unix seconds), exclusive.
+    pub end_time: u64,
+    /// One result per group, or a single one without `group_by`.
+    pub results: Vec<T>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompletionsUsageResult {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Input tokens served from the prompt cache.
+    #[serde(default)]
+    pub input_cached_tokens: u64,
+    #[serde(default)]
+    pub input_audio_tokens: u64,
+    #[serde(default)]
+    pub output_audio_tokens: u64,
+    pub num_model_requests: u64,
+    /// Set when grouped by project.
+    #[serde(default)]
+    pub project_id: Option<String>,
+    /// Set when grouped by user.
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Set when grouped by API key.
+    #[serde(default)]
+    pub api_key_id: Option<String>,
+    /// Set when grouped by model.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Set when grouped by batch.
+    #[serde(default)]
+    pub batch: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CostResult {
+    pub amount: CostAmount,
+    /// Set when grouped by line item, e.g. `gpt-4o, input`.
+    #[serde(default)]
+    pub line_item: Option<String>,
+    /// Set when grouped by project.
+    #[serde(default)]
+    pub project_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CostAmount {
+    pub value: f64,
+    /// Lowercase ISO-4217 currency, e.g. `usd`.
+    pub currency: String,
+}
+
+impl CompletionsUsageRequest {
+    pub fn new(start_time: u64) -> Self {
+        CompletionsUsageRequestBuilder::default()
+            .start_time(start_time)
+            .build()
+            .unwrap()
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![("start_time", self.start_time.to_string())];
+        push_opt(&mut query, "end_time", self.end_time);
+        push_opt(&mut query, "bucket_width", self.bucket_width.map(enum_str));
+        push_all(&mut query, "project_ids[]", &self.project_ids);
+        push_all(&mut query, "user_ids[]", &self.user_ids);
+        push_all(&mut query, "api_key_ids[]", &self.api_key_ids);
+        push_all(&mut query, "models[]", &self.models);
+        push_opt(&mut query, "batch", self.batch);
+        let group_by: Vec<_> = self.group_by.iter().copied().map(enum_str).collect();
+        push_all(&mut query, "group_by[]", &group_by);
+        push_opt(&mut query, "limit", self.limit);
+        push_opt(&mut query, "page", self.page.as_ref());
+        query
+    }
+}
+
+impl CostsRequest {
+    pub fn new(start_time: u64) -> Self {
+        CostsRequestBuilder::default()
+            .start_time(start_time)
+            .build()
+            .unwrap()
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![("start_time", self.start_time.to_string())];
+        push_opt(&mut query, "end_time", self.end_time);
+        // costs are only bucketed by day
+        query.push(("bucket_width", enum_str(BucketWidth::Day)));
+        push_all(&mut query, "project_ids[]", &self.project_ids);
+        let group_by: Vec<_> = self.group_by.iter().copied().map(enum_str).collect();
+        push_all(&mut query, "group_by[]", &group_by);
+        push_opt(&mut query, "limit", self.limit);
+        push_opt(&mut query, "page", self.page.as_ref());
+        query
+    }
+}
+
+impl CostResult {
+    /// The cost in USD, if the amount is in USD.
+    pub fn usd(&self) -> Option<f64> {
+        self.amount
+            .currency
+            .eq_ignore_ascii_case("usd")
+            .then_some(self.amount.value)
+    }
+}
+
+fn push_opt(
+    query: &mut Vec<(&'static str, String)>,
+    key: &'static str,
+    value: Option<impl ToString>,
+) {
+    if let Some(value) = value {
+        query.push((key, value.to_string()));
+    }
+}
+
+fn push_all(query: &mut Vec<(&'static str, String)>, key: &'static str, values: &[String]) {
+    query.extend(values.iter().map(|value| (key, value.clone())));
+}
+
+/// The wire name of a unit enum variant.
+fn enum_str(value: impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        _ => unreachable!("unit variants serialize to strings"),
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/usage/completions
+impl IntoRequest for CompletionsUsageRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .get(format!("{}/organization/usage/completions", base_url))
+            .query(&self.query())
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/usage/costs
+impl IntoRequest for CostsRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .get(format!("{}/organization/costs", base_url))
+            .query(&self.query())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OPENAI_BASE_URL;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn completions_usage_request_should_encode_query() -> Result<()> {
+        let req = CompletionsUsageRequestBuilder::default()
+            .start_time(1730419200)
+            .bucket_width(BucketWidth::Hour)
+            .models(vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()])
+            .group_by(vec![UsageGroupBy::Model, UsageGroupBy::ProjectId])
+            .build()?;
+        let req = req.into_request(OPENAI_BASE_URL, Client::new()).build()?;
+        assert_eq!(
+            req.url().as_str(),
+            "https://api.openai.com/v1/organization/usage/completions?start_time=1730419200\
+             &bucket_width=1h&models%5B%5D=gpt-4o&models%5B%5D=gpt-4o-mini\
+             &group_by%5B%5D=model&group_by%5B%5D=project_id"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn costs_response_should_deserialize() -> Result<()> {
+        let page: Page<Bucket<CostResult>> = ObjectType::Page.parse(json!({
+            "object": "page",
+            "data": [{
+                "object": "bucket",
+                "start_time": 1730419200,
+                "end_time": 1730505600,
+                "results": [{
+                    "object": "organization.costs.result",
+                    "amount": { "value": 0.06, "currency": "usd" },
+                    "line_item": "gpt-4o, input",
+                    "project_id": null
+                }]
+            }],
+            "has_more": true,
+            "next_page": "page_AAAAAGdGxdEiJdKOAAAAAGcqsYA="
+        }))?;
+        assert!(page.has_more);
+        let result = &page.data[0].results[0];
+        assert_eq!(result.usd(), Some(0.06));
+        assert_eq!(result.line_item.as_deref(), Some("gpt-4o, input"));
+        Ok(())
+    }
+
+    #[test]
+    fn completions_usage_response_should_deserialize() -> Result<()> {
+        let page: Page<Bucket<CompletionsUsageResult>> = ObjectType::Page.parse(json!({
+            "object": "page",
+            "data": [{
+                "object": "bucket",
+                "start_time": 1730419200,
+                "end_time": 1730505600,
+                "results": [{
+                    "object": "organization.usage.completions.result",
+                    "input_tokens": 1000,
+                    "output_tokens": 500,
+                    "input_cached_tokens": 800,
+                    "num_model_requests": 5,
+                    "model": "gpt-4o-mini"
+                }]
+            }],
+            "has_more": false,
+            "next_page": null
+        }))?;
+        let result = &page.data[0].results[0];
+        assert_eq!(result.input_cached_tokens, 800);
+        assert_eq!(result.model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(page.data[0].end_time - page.data[0].start_time, 86400);
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use reqwest::{Client, Method, RequestBuilder};
+use serde_json::Value;
+
+use crate::IntoRequest;
+
+/// A request to an endpoint the SDK has no type for, see `LlmSdk::send_raw`.
+#[derive(Debug, Clone)]
+pub struct RawRequest {
+    pub method: Method,
+    /// The path below the base URL, e.g. `/responses`, with its query string if any.
+    pub path: String,
+    /// Sent as the JSON body, unless it is `null`.
+    pub body: Value,
+}
+
+impl RawRequest {
+    pub fn new(method: Method, path: impl Into<String>, body: Value) -> Self {
+        Self {
+            method,
+            path: path.into(),
+            body,
+        }
+    }
+
+    fn url(&self, base_url: &str) -> String {
+        format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            self.path.trim_start_matches('/')
+        )
+    }
+}
+
+impl IntoRequest for RawRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        let req = client.request(self.method.clone(), self.url(base_url));
+        match self.body {
+            Value::Null => req,
+            body => req.json(&body),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn raw_request_should_join_path_and_skip_null_body() -> Result<()> {
+        let req = RawRequest::new(Method::POST, "/evals", json!({ "name": "qa" }))
+            .into_request("https://api.openai.com/v1/", Client::new())
+            .build()?;
+        assert_eq!(req.url().as_str(), "https://api.openai.com/v1/evals");
+        assert_eq!(req.body().unwrap().as_bytes().unwrap(), br#"{"name":"qa"}"#);
+
+        let req = RawRequest::new(Method::GET, "evals?limit=2", Value::Null)
+            .into_request("https://api.openai.com/v1", Client::new())
+            .build()?;
+        assert_eq!(
+            req.url().as_str(),
+            "https://api.openai.com/v1/evals?limit=2"
+        );
+        assert!(req.body().is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,790 @@
+use anyhow::Result;
+use derive_builder::Builder;
+use futures::{stream, Stream, StreamExt};
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::chat_completion_stream::{next_sse_event, SseEvent},
+    BoxStream, ChatCompleteModel, ChatCompleteUsage, CompletionTokensDetails, ImageDetail,
+    IntoRequest, MaybeSend, ObjectType, PromptTokensDetails, SdkError,
+};
+
+pub type ResponseStream = BoxStream<ResponseStreamEvent>;
+
+/// A request to the Responses API, the successor of chat completions with built-in tools
+/// and server-side conversation state.
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable")]
+pub struct CreateResponseRequest {
+    /// ID of the model to use.
+    #[builder(default)]
+    model: ChatCompleteModel,
+    /// Text or a list of input items for the model.
+    #[builder(setter(into))]
+    input: ResponseInput,
+    /// A system (or developer) message inserted into the model's context.
+    /// Not carried over from the previous response when chaining with `previous_response_id`.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
+    /// The ID of the previous response, to continue that conversation without resending it.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_response_id: Option<String>,
+    /// Built-in tools and functions the model may call.
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ResponseTool>,
+    /// Whether the model may call several tools at once.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parallel_tool_calls: Option<bool>,
+    /// An upper bound for the number of generated tokens, including reasoning tokens.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<usize>,
+    /// What sampling temperature to use, between 0 and 2.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    /// An alternative to sampling with temperature, called nucleus sampling.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    /// Whether to store the response so it can be retrieved or chained later. Defaults to true.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<bool>,
+    /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    /// Set by `LlmSdk::create_response_stream`.
+    #[builder(default, setter(skip))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum ResponseInput {
+    Text(String),
+    Items(Vec<InputItem>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputItem {
+    /// A message to the model. Assistant messages replay earlier turns.
+    Message {
+        role: InputRole,
+        content: InputMessageContent,
+    },
+    /// The result of a function call the model made.
+    FunctionCallOutput { call_id: String, output: String },
+    /// A function call from an earlier response, replayed along with its output.
+    FunctionCall {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    /// An item of an earlier stored response, by ID.
+    ItemReference { id: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputRole {
+    User,
+    Assistant,
+    System,
+    Developer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum InputMessageContent {
+    Text(String),
+    Parts(Vec<InputContent>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputContent {
+    InputText {
+        text: String,
+    },
+    InputImage {
+        /// A URL or a base64 data URL.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image_url: Option<String>,
+        /// The ID of an uploaded file.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+        #[serde(default)]
+        detail: ImageDetail,
+    },
+    InputFile {
+        file_id: String,
+    },
+}
+
+/// A tool the model may use. Apart from functions, tools run on OpenAI's side and their calls
+/// show up in the response output.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseTool {
+    Function {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// The JSON schema of the arguments.
+        parameters: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+    WebSearch {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        search_context_size: Option<SearchContextSize>,
+    },
+    FileSearch {
+        vector_store_ids: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_num_results: Option<usize>,
+    },
+    CodeInterpreter {
+        container: CodeInterpreterContainer,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchContextSize {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+/// Where code interpreter runs: an existing container, or a new one with the given files.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum CodeInterpreterContainer {
+    Id(String),
+    Auto {
+        r#type: AutoContainer,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        file_ids: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoContainer {
+    Auto,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelResponse {
+    /// Unique identifier for this response, to pass as `previous_response_id`.
+    pub id: String,
+    /// The object type, which is always "response".
+    pub object: ObjectType,
+    /// The Unix timestamp (in seconds) of when the response was created.
+    pub created_at: u64,
+    pub status: ResponseStatus,
+    /// The model used for the response.
+    pub model: String,
+    /// The items generated by the model, in order.
+    #[serde(default)]
+    pub output: Vec<OutputItem>,
+    #[serde(default)]
+    pub previous_response_id: Option<String>,
+    /// Set when the status is failed.
+    #[serde(default)]
+    pub error: Option<ResponseError>,
+    /// Set when the status is incomplete.
+    #[serde(default)]
+    pub incomplete_details: Option<IncompleteDetails>,
+    /// Usage statistics. Not present until the response is complete.
+    #[serde(default)]
+    pub usage: Option<ResponseUsage>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseStatus {
+    Completed,
+    Failed,
+    InProgress,
+    Incomplete,
+    Cancelled,
+    Queued,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResponseError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IncompleteDetails {
+    /// Why the response stopped, e.g. max_output_tokens or content_filter.
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ResponseUsage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub total_tokens: usize,
+    #[serde(default)]
+    pub input_tokens_details: InputTokensDetails,
+    #[serde(default)]
+    pub output_tokens_details: OutputTokensDetails,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct InputTokensDetails {
+    /// Input tokens served from the prompt cache.
+    #[serde(default)]
+    pub cached_tokens: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct OutputTokensDetails {
+    #[serde(default)]
+    pub reasoning_tokens: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputItem {
+    Message {
+        id: String,
+        #[serde(default)]
+        content: Vec<OutputContent>,
+    },
+    FunctionCall {
+        #[serde(default)]
+        id: Option<String>,
+        /// The ID to answer with in `InputItem::FunctionCallOutput`.
+        call_id: String,
+        name: String,
+        /// The arguments as a JSON string.
+        arguments: String,
+    },
+    WebSearchCall {
+        id: String,
+        status: String,
+    },
+    FileSearchCall {
+        id: String,
+        status: String,
+        #[serde(default)]
+        queries: Vec<String>,
+    },
+    CodeInterpreterCall {
+        id: String,
+        status: String,
+        #[serde(default)]
+        code: Option<String>,
+    },
+    Reasoning {
+        id: String,
+    },
+    /// An item type this SDK doesn't know about yet.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputContent {
+    OutputText {
+        text: String,
+        /// Citations of web pages and files, as returned by the API.
+        #[serde(default)]
+        annotations: Vec<serde_json::Value>,
+    },
+    Refusal {
+        refusal: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// The events of a streamed response. Text arrives in `OutputTextDelta`s and the finished
+/// response in `Completed`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+pub enum ResponseStreamEvent {
+    #[serde(rename = "response.created")]
+    Created { response: ModelResponse },
+    #[serde(rename = "response.in_progress")]
+    InProgress { response: ModelResponse },
+    #[serde(rename = "response.completed")]
+    Completed { response: ModelResponse },
+    #[serde(rename = "response.failed")]
+    Failed { response: ModelResponse },
+    #[serde(rename = "response.incomplete")]
+    Incomplete { response: ModelResponse },
+    #[serde(rename = "response.output_item.added")]
+    OutputItemAdded {
+        output_index: usize,
+        item: OutputItem,
+    },
+    #[serde(rename = "response.output_item.done")]
+    OutputItemDone {
+        output_index: usize,
+        item: OutputItem,
+    },
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta {
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        delta: String,
+    },
+    #[serde(rename = "response.output_text.done")]
+    OutputTextDone {
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        text: String,
+    },
+    #[serde(rename = "response.refusal.delta")]
+    RefusalDelta { item_id: String, delta: String },
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta {
+        item_id: String,
+        output_index: usize,
+        delta: String,
+    },
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        item_id: String,
+        output_index: usize,
+        arguments: String,
+    },
+    #[serde(rename = "error")]
+    Error {
+        #[serde(default)]
+        code: Option<String>,
+        message: String,
+    },
+    /// Progress events of built-in tools and anything this SDK doesn't know about yet.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetrieveResponseRequest {
+    response_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteResponseRequest {
+    response_id: String,
+}
+
+impl CreateResponseRequest {
+    pub fn new(model: ChatCompleteModel, input: impl Into<ResponseInput>) -> Self {
+        CreateResponseRequestBuilder::default()
+            .model(model)
+            .input(input)
+            .build()
+            .unwrap()
+    }
+
+    pub fn model(&self) -> ChatCompleteModel {
+        self.model.clone()
+    }
+
+    pub(crate) fn set_stream(&mut self, stream: bool) {
+        self.stream = Some(stream);
+    }
+}
+
+impl From<String> for ResponseInput {
+    fn from(text: String) -> Self {
+        ResponseInput::Text(text)
+    }
+}
+
+impl From<&str> for ResponseInput {
+    fn from(text: &str) -> Self {
+        ResponseInput::Text(text.to_string())
+    }
+}
+
+impl From<Vec<InputItem>> for ResponseInput {
+    fn from(items: Vec<InputItem>) -> Self {
+        ResponseInput::Items(items)
+    }
+}
+
+impl InputItem {
+    pub fn user(text: impl Into<String>) -> Self {
+        Self::message(InputRole::User, text)
+    }
+
+    pub fn developer(text: impl Into<String>) -> Self {
+        Self::message(InputRole::Developer, text)
+    }
+
+    pub fn message(role: InputRole, text: impl Into<String>) -> Self {
+        InputItem::Message {
+            role,
+            content: InputMessageContent::Text(text.into()),
+        }
+    }
+
+    pub fn function_call_output(call_id: impl Into<String>, output: impl Into<String>) -> Self {
+        InputItem::FunctionCallOutput {
+            call_id: call_id.into(),
+            output: output.into(),
+        }
+    }
+}
+
+impl ResponseTool {
+    pub fn web_search() -> Self {
+        ResponseTool::WebSearch {
+            search_context_size: None,
+        }
+    }
+
+    pub fn file_search(vector_store_ids: Vec<String>) -> Self {
+        ResponseTool::FileSearch {
+            vector_store_ids,
+            max_num_results: None,
+        }
+    }
+
+    /// Code interpreter in a new container with access to the given files.
+    pub fn code_interpreter(file_ids: Vec<String>) -> Self {
+        ResponseTool::CodeInterpreter {
+            container: CodeInterpreterContainer::Auto {
+                r#type: AutoContainer::Auto,
+                file_ids,
+            },
+        }
+    }
+}
+
+impl ModelResponse {
+    /// The text of every output message, concatenated.
+    pub fn output_text(&self) -> String {
+        self.output
+            .iter()
+            .filter_map(|item| match item {
+                OutputItem::Message { content, .. } => Some(content),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|content| match content {
+                OutputContent::OutputText { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The refusal message, if the model refused to answer.
+    pub fn refusal(&self) -> Option<&str> {
+        self.output.iter().find_map(|item| match item {
+            OutputItem::Message { content, .. } => content.iter().find_map(|c| match c {
+                OutputContent::Refusal { refusal } => Some(refusal.as_str()),
+                _ => None,
+            }),
+            _ => None,
+        })
+    }
+
+    /// The function calls to run and answer with `InputItem::function_call_output`.
+    pub fn function_calls(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.output.iter().filter_map(|item| match item {
+            OutputItem::FunctionCall {
+                call_id,
+                name,
+                arguments,
+                ..
+            } => Some((call_id.as_str(), name.as_str(), arguments.as_str())),
+            _ => None,
+        })
+    }
+
+    /// A request continuing this conversation with `input`, using the same model.
+    pub fn follow_up(&self, input: impl Into<ResponseInput>) -> CreateResponseRequest {
+        CreateResponseRequestBuilder::default()
+            .model(ChatCompleteModel::from(self.model.clone()))
+            .input(input)
+            .previous_response_id(self.id.clone())
+            .build()
+            .unwrap()
+    }
+}
+
+impl From<ResponseUsage> for ChatCompleteUsage {
+    fn from(usage: ResponseUsage) -> Self {
+        ChatCompleteUsage {
+            completion_tokens: usage.output_tokens,
+            prompt_tokens: usage.input_tokens,
+            total_tokens: usage.total_tokens,
+            prompt_tokens_details: Some(PromptTokensDetails {
+                cached_tokens: usage.input_tokens_details.cached_tokens,
+                ..Default::default()
+            }),
+            completion_tokens_details: Some(CompletionTokensDetails {
+                reasoning_tokens: usage.output_tokens_details.reasoning_tokens,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+impl ResponseStreamEvent {
+    /// Whether this is the last event of the stream.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            ResponseStreamEvent::Completed { .. }
+                | ResponseStreamEvent::Failed { .. }
+                | ResponseStreamEvent::Incomplete { .. }
+                | ResponseStreamEvent::Error { .. }
+        )
+    }
+}
+
+impl RetrieveResponseRequest {
+    pub fn new(response_id: impl Into<String>) -> Self {
+        Self {
+            response_id: response_id.into(),
+        }
+    }
+}
+
+impl DeleteResponseRequest {
+    pub fn new(response_id: impl Into<String>) -> Self {
+        Self {
+            response_id: response_id.into(),
+        }
+    }
+}
+
+struct EventDecoder<S> {
+    inner: S,
+    buf: Vec<u8>,
+    done: bool,
+    /// The output text received so far, reported if the stream is cut short.
+    partial: String,
+}
+
+/// Decode a server-sent event byte stream into response events.
+///
+/// The connection closing before a terminal event is reported as `SdkError::StreamInterrupted`.
+pub(crate) fn decode_response_events<S, B, E>(inner: S) -> ResponseStream
+where
+    S: Stream<Item = Result<B, E>> + Unpin + MaybeSend + 'static,
+    B: AsRef<[u8]>,
+    E: Into<anyhow::Error>,
+{
+    let decoder = EventDecoder {
+        inner,
+        buf: Vec::new(),
+        done: false,
+        partial: String::new(),
+    };
+    let stream = stream::unfold(decoder, |mut decoder| async move {
+        loop {
+            if decoder.done {
+                return None;
+            }
+            match next_sse_event(&mut decoder.buf) {
+                Some(SseEvent::Data(data)) => {
+                    let event = serde_json::from_str::<ResponseStreamEvent>(&data);
+                    if let Ok(event) = &event {
+                        if let ResponseStreamEvent::OutputTextDelta { delta, .. } = event {
+                            decoder.partial.push_str(delta);
+                        }
+                        decoder.done = event.is_terminal();
+                    }
+                    return Some((event.map_err(Into::into), decoder));
+                }
+                Some(SseEvent::Done) => return None,
+                None => {}
+            }
+            let reason = match decoder.inner.next().await {
+                Some(Ok(bytes)) => {
+                    let bytes = bytes.as_ref().iter().filter(|b| **b != b'\r');
+                    decoder.buf.extend(bytes);
+                    continue;
+                }
+                Some(Err(e)) => e.into().to_string(),
+                None => "connection closed before the response completed".to_string(),
+            };
+            decoder.done = true;
+            let err = SdkError::StreamInterrupted {
+                partial: decoder.partial.clone(),
+                reason,
+            };
+            return Some((Err(err.into()), decoder));
+        }
+    });
+    Box::pin(stream)
+}
+
+// https://platform.openai.com/docs/api-reference/responses/create
+impl IntoRequest for CreateResponseRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.post(format!("{}/responses", base_url)).json(&self)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/responses/get
+impl IntoRequest for RetrieveResponseRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.get(format!("{}/responses/{}", base_url, self.response_id))
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/responses/delete
+impl IntoRequest for DeleteResponseRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.delete(format!("{}/responses/{}", base_url, self.response_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    #[test]
+    fn create_response_request_should_serialize() -> Result<()> {
+        let req = CreateResponseRequestBuilder::default()
+            .model(ChatCompleteModel::Gpt4Turbo)
+            .input(vec![
+                InputItem::user("What's new in Rust?"),
+                InputItem::function_call_output("call_1", "{\"ok\":true}"),
+            ])
+            .previous_response_id("resp_1")
+            .tools(vec![
+                ResponseTool::web_search(),
+                ResponseTool::file_search(vec!["vs_1".to_string()]),
+                ResponseTool::code_interpreter(vec![]),
+            ])
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(req)?,
+            json!({
+                "model": "gpt-4-1106-preview",
+                "input": [
+                    { "type": "message", "role": "user", "content": "What's new in Rust?" },
+                    { "type": "function_call_output", "call_id": "call_1", "output": "{\"ok\":true}" }
+                ],
+                "previous_response_id": "resp_1",
+                "tools": [
+                    { "type": "web_search" },
+                    { "type": "file_search", "vector_store_ids": ["vs_1"] },
+                    { "type": "code_interpreter", "container": { "type": "auto" } }
+                ]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn model_response_should_deserialize() -> Result<()> {
+        let res: ModelResponse = ObjectType::Response.parse(json!({
+            "id": "resp_2",
+            "object": "response",
+            "created_at": 1741476542,
+            "status": "completed",
+            "model": "gpt-4o-mini-2024-07-18",
+            "output": [
+                { "type": "web_search_call", "id": "ws_1", "status": "completed" },
+                {
+                    "type": "message",
+                    "id": "msg_1",
+                    "status": "completed",
+                    "role": "assistant",
+                    "content": [{
+                        "type": "output_text",
+                        "text": "Rust 1.85 shipped the 2024 edition.",
+                        "annotations": [{ "type": "url_citation", "url": "https://blog.rust-lang.org" }]
+                    }]
+                },
+                { "type": "function_call", "id": "fc_1", "call_id": "call_2", "name": "lookup", "arguments": "{}" },
+                { "type": "image_generation_call", "id": "ig_1" }
+            ],
+            "previous_response_id": "resp_1",
+            "usage": {
+                "input_tokens": 36,
+                "input_tokens_details": { "cached_tokens": 0 },
+                "output_tokens": 87,
+                "output_tokens_details": { "reasoning_tokens": 0 },
+                "total_tokens": 123
+            }
+        }))?;
+        assert_eq!(res.status, ResponseStatus::Completed);
+        assert_eq!(res.output_text(), "Rust 1.85 shipped the 2024 edition.");
+        assert_eq!(
+            res.function_calls().collect::<Vec<_>>(),
+            [("call_2", "lookup", "{}")]
+        );
+        assert!(matches!(res.output[3], OutputItem::Other));
+        assert_eq!(
+            ChatCompleteUsage::from(res.usage.unwrap()).total_tokens,
+            123
+        );
+
+        let next = serde_json::to_value(res.follow_up("And before that?"))?;
+        assert_eq!(next["previous_response_id"], "resp_2");
+        assert_eq!(next["model"], "gpt-4o-mini-2024-07-18");
+        Ok(())
+    }
+
+    const EVENTS: &str = r#"event: response.created
+data: {"type":"response.created","sequence_number":0,"response":{"id":"resp_3","object":"response","created_at":1741476542,"status":"in_progress","model":"gpt-4o-mini","output":[]}}
+
+event: response.output_text.delta
+data: {"type":"response.output_text.delta","sequence_number":1,"item_id":"msg_1","output_index":0,"content_index":0,"delta":"Hel"}
+
+event: response.web_search_call.searching
+data: {"type":"response.web_search_call.searching","sequence_number":2,"item_id":"ws_1","output_index":1}
+
+event: response.output_text.delta
+data: {"type":"response.output_text.delta","sequence_number":3,"item_id":"msg_1","output_index":0,"content_index":0,"delta":"lo"}
+
+"#;
+
+    #[tokio::test]
+    async fn decode_response_events_should_parse_events() -> Result<()> {
+        let completed = r#"event: response.completed
+data: {"type":"response.completed","sequence_number":4,"response":{"id":"resp_3","object":"response","created_at":1741476542,"status":"completed","model":"gpt-4o-mini","output":[]}}
+
+"#;
+        let parts = vec![Ok::<_, anyhow::Error>(format!("{}{}", EVENTS, completed))];
+        let events: Vec<_> = decode_response_events(stream::iter(parts))
+            .try_collect()
+            .await?;
+        assert_eq!(events.len(), 5);
+        assert!(matches!(events[0], ResponseStreamEvent::Created { .. }));
+        assert!(matches!(events[2], ResponseStreamEvent::Other));
+        assert!(events[4].is_terminal());
+
+        // the server hung up before response.completed
+        let parts = vec![Ok::<_, anyhow::Error>(EVENTS)];
+        let results: Vec<_> = decode_response_events(stream::iter(parts)).collect().await;
+        match results[4].as_ref().unwrap_err().downcast_ref::<SdkError>() {
+            Some(SdkError::StreamInterrupted { partial, .. }) => assert_eq!(partial, "Hello"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use bytes::Bytes;
+use derive_builder::Builder;
+use futures::{Stream, StreamExt};
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{BoxStream, IntoRequest};
+
+/// Audio as it is generated, chunk by chunk.
+pub type SpeechStream = BoxStream<Bytes>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable")]
+pub struct CreateSpeechRequest {
+    /// One of the available TTS models: tts-1 or tts-1-hd
+    #[builder(default)]
+    #[serde(default)]
+    model: SpeechModel,
+    /// The text to generate audio for. The maximum length is 4096 characters.
+    #[builder(setter(into))]
+    input: String,
+    /// The voice to use when generating the audio.
+    #[builder(default)]
+    #[serde(default)]
+    voice: SpeechVoice,
+    /// The format to audio in. Supported formats are mp3, opus, aac, flac, wav, and pcm.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<SpeechResponseFormat>,
+    /// The speed of the generated audio. Select a value from 0.25 to 4.0. 1.0 is the default.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speed: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+pub enum SpeechModel {
+    #[serde(rename = "tts-1")]
+    #[default]
+    Tts1,
+    #[serde(rename = "tts-1-hd")]
+    Tts1Hd,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeechVoice {
+    #[default]
+    Alloy,
+    Ash,
+    Ballad,
+    Coral,
+    Echo,
+    Fable,
+    Onyx,
+    Nova,
+    Sage,
+    Shimmer,
+    Verse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeechResponseFormat {
+    #[default]
+    Mp3,
+    Opus,
+    Aac,
+    Flac,
+    Wav,
+    Pcm,
+}
+
+// https://platform.openai.com/docs/api-reference/audio/createSpeech
+impl IntoRequest for CreateSpeechRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .post(format!("{}/audio/speech", base_url))
+            .json(&self)
+    }
+}
+
+impl CreateSpeechRequest {
+    pub fn new(input: impl Into<String>, voice: SpeechVoice) -> Self {
+        CreateSpeechRequestBuilder::default()
+            .input(input)
+            .voice(voice)
+            .build()
+            .unwrap()
+    }
+}
+
+/// Write audio chunks to `writer` as they arrive and return the number of bytes written.
+pub async fn stream_to_writer<S, W>(mut stream: S, writer: &mut W) -> Result<u64>
+where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let mut written = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        writer.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+    }
+    writer.flush().await?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn create_speech_request_should_serialize() -> Result<()> {
+        let req = CreateSpeechRequestBuilder::default()
+            .model(SpeechModel::Tts1Hd)
+            .input("The quick brown fox jumped over the lazy dog.")
+            .voice(SpeechVoice::Nova)
+            .response_format(SpeechResponseFormat::Opus)
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "model": "tts-1-hd",
+                "input": "The quick brown fox jumped over the lazy dog.",
+                "voice": "nova",
+                "response_format": "opus"
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_to_writer_should_copy_every_chunk() -> Result<()> {
+        let chunks: Vec<Result<Bytes>> = vec![Ok(Bytes::from("ID3")), Ok(Bytes::from("audio"))];
+        let mut out = Vec::new();
+        let written = stream_to_writer(futures::stream::iter(chunks), &mut out).await?;
+        assert_eq!(written, 8);
+        assert_eq!(out, b"ID3audio");
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+
+use derive_builder::Builder;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChatCompletionMessage, ChatCompletionResponse, IntoRequest, ListOrder, PageRequest};
+
+/// Lists the chat completions created with `store` set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable", default)]
+pub struct ListStoredCompletionsRequest {
+    /// Only the completions generated by this model.
+    #[builder(setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// Only the completions with all of these metadata pairs.
+    #[builder(setter(into))]
+    #[serde(skip)]
+    metadata: BTreeMap<String, String>,
+    /// A limit on the number of objects to be returned, 20 by default.
+    #[builder(setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    /// Sort order by the created timestamp of the completions.
+    #[builder(setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<ListOrder>,
+    /// The id of the last completion of the previous page.
+    #[builder(setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetrieveStoredCompletionRequest {
+    completion_id: String,
+}
+
+/// Lists the messages of a stored completion: the prompt, without the reply.
+#[derive(Debug, Clone, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable")]
+pub struct ListStoredMessagesRequest {
+    #[builder(setter(into))]
+    #[serde(skip)]
+    completion_id: String,
+    /// A limit on the number of objects to be returned, 20 by default.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    /// Sort order by the position of the messages in the prompt.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<ListOrder>,
+    /// The id of the last message of the previous page.
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+}
+
+/// Replaces the metadata of a stored completion, the only part that can be modified.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateStoredCompletionRequest {
+    #[serde(skip)]
+    completion_id: String,
+    metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteStoredCompletionRequest {
+    completion_id: String,
+}
+
+/// A message of a stored completion.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StoredMessage {
+    /// The identifier of the message, e.g. `chatcmpl-abc123-0`.
+    pub id: String,
+    #[serde(flatten)]
+    pub message: ChatCompletionMessage,
+}
+
+impl RetrieveStoredCompletionRequest {
+    pub fn new(completion_id: impl Into<String>) -> Self {
+        Self {
+            completion_id: completion_id.into(),
+        }
+    }
+}
+
+impl ListStoredMessagesRequest {
+    pub fn new(completion_id: impl Into<String>) -> Self {
+        ListStoredMessagesRequestBuilder::default()
+            .completion_id(completion_id)
+            .build()
+            .unwrap()
+    }
+}
+
+impl UpdateStoredCompletionRequest {
+    pub fn new(completion_id: impl Into<String>, metadata: BTreeMap<String, String>) -> Self {
+        Self {
+            completion_id: completion_id.into(),
+            metadata,
+        }
+    }
+}
+
+impl DeleteStoredCompletionRequest {
+    pub fn new(completion_id: impl Into<String>) -> Self {
+        Self {
+            completion_id: completion_id.into(),
+        }
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/chat/list
+impl IntoRequest for ListStoredCompletionsRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        let metadata: Vec<_> = self
+            .metadata
+            .iter()
+            .map(|(key, value)| (format!("metadata[{}]", key), value))
+            .collect();
+        client
+            .get(format!("{}/chat/completions", base_url))
+            .query(&self)
+            .query(&metadata)
+    }
+}
+
+impl PageRequest for ListStoredCompletionsRequest {
+    type Item = ChatCompletionResponse;
+
+    fn set_after(&mut self, after: String) {
+        self.after = Some(after);
+    }
+
+    fn item_id(item: &ChatCompletionResponse) -> &str {
+        &item.id
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/chat/get
+impl IntoRequest for RetrieveStoredCompletionRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.get(format!(
+            "{}/chat/completions/{}",
+            base_url, self.completion_id
+        ))
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/chat/getMessages
+impl IntoRequest for ListStoredMessagesRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .get(format!(
+                "{}/chat/completions/{}/messages",
+                base_url, self.completion_id
+            ))
+            .query(&self)
+    }
+}
+
+impl PageRequest for ListStoredMessagesRequest {
+    type Item = StoredMessage;
+
+    fn set_after(&mut self, after: String) {
+        self.after = Some(after);
+    }
+
+    fn item_id(item: &StoredMessage) -> &str {
+        &item.id
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/chat/update
+impl IntoRequest for UpdateStoredCompletionRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .post(format!(
+                "{}/chat/completions/{}",
+                base_url, self.completion_id
+            ))
+            .json(&self)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/chat/delete
+impl IntoRequest for DeleteStoredCompletionRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.delete(format!(
+            "{}/chat/completions/{}",
+            base_url, self.completion_id
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::completion_json, ChatCompletionResponse, List, ObjectType, OPENAI_BASE_URL,
+    };
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn list_stored_completions_request_should_use_query() -> Result<()> {
+        let req = ListStoredCompletionsRequestBuilder::default()
+            .model("gpt-4o")
+            .metadata([("team".to_string(), "search".to_string())])
+            .limit(10)
+            .build()?;
+        let req = req.into_request(OPENAI_BASE_URL, Client::new()).build()?;
+        assert_eq!(req.url().path(), "/v1/chat/completions");
+        assert_eq!(
+            req.url().query(),
+            Some("model=gpt-4o&limit=10&metadata%5Bteam%5D=search")
+        );
+
+        let req = UpdateStoredCompletionRequest::new("chatcmpl-1", BTreeMap::new());
+        let req = req.into_request(OPENAI_BASE_URL, Client::new()).build()?;
+        assert_eq!(req.method(), "POST");
+        assert_eq!(req.url().path(), "/v1/chat/completions/chatcmpl-1");
+        Ok(())
+    }
+
+    #[test]
+    fn stored_completions_should_deserialize() -> Result<()> {
+        let mut completion = completion_json("Hi!", "stop", (8, 2));
+        completion["metadata"] = json!({ "team": "search" });
+        let list: List<ChatCompletionResponse> = ObjectType::List.parse(json!({
+            "object": "list",
+            "data": [completion],
+            "first_id": "chatcmpl-1",
+            "last_id": "chatcmpl-1",
+            "has_more": false
+        }))?;
+        assert_eq!(list.data[0].metadata.as_ref().unwrap()["team"], "search");
+
+        let messages: List<StoredMessage> = ObjectType::List.parse(json!({
+            "object": "list",
+            "data": [{
+                "id": "chatcmpl-1-0",
+                "role": "user",
+                "content": "Say hi",
+                "name": null,
+                "content_parts": null
+            }],
+            "has_more": false
+        }))?;
+        assert_eq!(messages.data[0].id, "chatcmpl-1-0");
+        assert_eq!(messages.data[0].message.content(), Some("Say hi"));
+        Ok(())
+    }
+}
@@ -0,0 +1,376 @@
+use std::time::Duration;
+
+use reqwest::{
+    multipart::{Form, Part},
+    Client, RequestBuilder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{IntoRequest, ObjectType};
+
+/// The maximum size of a single upload part.
+pub const MAX_PART_SIZE: usize = 64 * 1024 * 1024;
+
+/// Time for a part of `MAX_PART_SIZE` at about 1 Mbit/s.
+const DEFAULT_PART_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateUploadRequest {
+    /// The name of the file to upload.
+    filename: String,
+    /// The intended purpose of the uploaded file.
+    purpose: FilePurpose,
+    /// The number of bytes in the file you are uploading.
+    bytes: u64,
+    /// The MIME type of the file. This must fall within the supported MIME types for your file purpose.
+    mime_type: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AddUploadPartRequest {
+    upload_id: String,
+    data: Vec<u8>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteUploadRequest {
+    #[serde(skip)]
+    upload_id: String,
+    /// The ordered list of Part IDs.
+    part_ids: Vec<String>,
+    /// The optional md5 checksum for the file contents to verify if the bytes uploaded matches what you expect.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    md5: Option<String>,
+}
+
+/// Upload a file of up to 512 MB in a single request, see `LlmSdk::upload_file` for larger ones.
+#[derive(Debug, Clone)]
+pub struct CreateFileRequest {
+    filename: String,
+    purpose: FilePurpose,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CancelUploadRequest {
+    upload_id: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FilePurpose {
+    Assistants,
+    Batch,
+    FineTune,
+    Vision,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Upload {
+    /// The Upload unique identifier, which can be referenced in API endpoints.
+    pub id: String,
+    /// The object type, which is always "upload".
+    pub object: ObjectType,
+    /// The intended number of bytes to be uploaded.
+    pub bytes: u64,
+    /// The Unix timestamp (in seconds) for when the Upload was created.
+    pub created_at: u64,
+    /// The name of the file to be uploaded.
+    pub filename: String,
+    /// The intended purpose of the file.
+    pub purpose: FilePurpose,
+    /// The status of the Upload.
+    pub status: UploadStatus,
+    /// The Unix timestamp (in seconds) for when the Upload will expire.
+    pub expires_at: u64,
+    /// The ready File object after the Upload is completed.
+    #[serde(default)]
+    pub file: Option<FileObject>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadStatus {
+    Pending,
+    Completed,
+    Cancelled,
+    Expired,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadPart {
+    /// The upload Part unique identifier, which can be referenced in API endpoints.
+    pub id: String,
+    /// The object type, which is always upload.part.
+    pub object: ObjectType,
+    /// The Unix timestamp (in seconds) for when the Part was created.
+    pub created_at: u64,
+    /// The ID of the Upload object that this Part was added to.
+    pub upload_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileObject {
+    /// The file identifier, which can be referenced in the API endpoints.
+    pub id: String,
+    /// The object type, which is always file.
+    pub object: ObjectType,
+    /// The size of the file, in bytes.
+    pub bytes: u64,
+    /// The Unix timestamp (in seconds) for when the file was created.
+    pub created_at: u64,
+    /// The name of the file.
+    pub filename: String,
+    /// The intended purpose of the file.
+    pub purpose: FilePurpose,
+}
+
+/// How `LlmSdk::upload_file` splits and sends a file.
+#[derive(Debug, Clone, Copy)]
+pub struct UploadOptions {
+    /// Size of each part in bytes, at most `MAX_PART_SIZE`.
+    pub part_size: usize,
+    /// How many parts are uploaded at the same time.
+    pub concurrency: usize,
+    /// How many times a failed part is retried.
+    pub max_retries: usize,
+    /// How long sending one part may take.
+    pub part_timeout: Duration,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            part_size: MAX_PART_SIZE,
+            concurrency: 4,
+            max_retries: 3,
+            part_timeout: DEFAULT_PART_TIMEOUT,
+        }
+    }
+}
+
+impl CreateUploadRequest {
+    pub fn new(
+        filename: impl Into<String>,
+        purpose: FilePurpose,
+        bytes: u64,
+        mime_type: impl Into<String>,
+    ) -> Self {
+        Self {
+            filename: filename.into(),
+            purpose,
+            bytes,
+            mime_type: mime_type.into(),
+        }
+    }
+}
+
+impl CreateFileRequest {
+    pub fn new(filename: impl Into<String>, purpose: FilePurpose, data: Vec<u8>) -> Self {
+        Self {
+            filename: filename.into(),
+            purpose,
+            data,
+        }
+    }
+}
+
+impl FilePurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilePurpose::Assistants => "assistants",
+            FilePurpose::Batch => "batch",
+            FilePurpose::FineTune => "fine-tune",
+            FilePurpose::Vision => "vision",
+        }
+    }
+}
+
+impl AddUploadPartRequest {
+    pub fn new(upload_id: impl Into<String>, data: Vec<u8>) -> Self {
+        Self {
+            upload_id: upload_id.into(),
+            data,
+            timeout: None,
+        }
+    }
+
+    /// How long sending the part may take, instead of the timeout of other requests.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl CompleteUploadRequest {
+    pub fn new(upload_id: impl Into<String>, part_ids: Vec<String>) -> Self {
+        Self {
+            upload_id: upload_id.into(),
+            part_ids,
+            md5: None,
+        }
+    }
+
+    pub fn with_md5(mut self, md5: impl Into<String>) -> Self {
+        self.md5 = Some(md5.into());
+        self
+    }
+}
+
+impl CancelUploadRequest {
+    pub fn new(upload_id: impl Into<String>) -> Self {
+        Self {
+            upload_id: upload_id.into(),
+        }
+    }
+}
+
+impl UploadOptions {
+    /// Split `bytes` into (offset, len) parts.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn parts(&self, bytes: u64) -> Vec<(u64, usize)> {
+        let part_size = self.part_size.clamp(1, MAX_PART_SIZE) as u64;
+        (0..bytes)
+            .step_by(part_size as usize)
+            .map(|offset| (offset, part_size.min(bytes - offset) as usize))
+            .collect()
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/files/create
+impl IntoRequest for CreateFileRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        let form = Form::new()
+            .text("purpose", self.purpose.as_str())
+            .part("file", Part::bytes(self.data).file_name(self.filename));
+        client.post(format!("{}/files", base_url)).multipart(form)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/uploads/create
+impl IntoRequest for CreateUploadRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.post(format!("{}/uploads", base_url)).json(&self)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/uploads/add-part
+impl IntoRequest for AddUploadPartRequest {
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        let part = Part::bytes(self.data).file_name("part");
+        client
+            .post(format!("{}/uploads/{}/parts", base_url, self.upload_id))
+            .multipart(Form::new().part("data", part))
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/uploads/complete
+impl IntoRequest for CompleteUploadRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .post(format!("{}/uploads/{}/complete", base_url, self.upload_id))
+            .json(&self)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/uploads/cancel
+impl IntoRequest for CancelUploadRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client.post(format!("{}/uploads/{}/cancel", base_url, self.upload_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OPENAI_BASE_URL;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn create_upload_request_should_serialize() -> Result<()> {
+        let req = CreateUploadRequest::new(
+            "training_examples.jsonl",
+            FilePurpose::FineTune,
+            2147483648,
+            "text/jsonl",
+        );
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "filename": "training_examples.jsonl",
+                "purpose": "fine-tune",
+                "bytes": 2147483648u64,
+                "mime_type": "text/jsonl"
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn create_file_request_should_be_multipart() -> Result<()> {
+        for purpose in [FilePurpose::FineTune, FilePurpose::Vision] {
+            assert_eq!(serde_json::to_value(purpose)?, json!(purpose.as_str()));
+        }
+        let req = CreateFileRequest::new("train.jsonl", FilePurpose::FineTune, b"{}".to_vec())
+            .into_request(OPENAI_BASE_URL, Client::new())
+            .build()?;
+        assert_eq!(req.url().path(), "/v1/files");
+        let content_type = req.headers()["content-type"].to_str()?;
+        assert!(content_type.starts_with("multipart/form-data"));
+        Ok(())
+    }
+
+    #[test]
+    fn complete_upload_request_should_serialize() -> Result<()> {
+        let req = CompleteUploadRequest::new("upload_abc", vec!["part_a".into(), "part_b".into()]);
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({ "part_ids": ["part_a", "part_b"] })
+        );
+        let req = req.into_request(OPENAI_BASE_URL, Client::new()).build()?;
+        assert_eq!(req.url().path(), "/v1/uploads/upload_abc/complete");
+        Ok(())
+    }
+
+    #[test]
+    fn upload_options_should_split_parts() {
+        let options = UploadOptions {
+            part_size: 10,
+            ..Default::default()
+        };
+        assert_eq!(options.parts(25), vec![(0, 10), (10, 10), (20, 5)]);
+        assert_eq!(options.parts(20), vec![(0, 10), (10, 10)]);
+        assert!(options.parts(0).is_empty());
+    }
+
+    #[test]
+    fn upload_should_deserialize() -> Result<()> {
+        let upload: Upload = serde_json::from_value(json!({
+            "id": "upload_abc123",
+            "object": "upload",
+            "bytes": 2147483648u64,
+            "created_at": 1719184911,
+            "filename": "training_examples.jsonl",
+            "purpose": "fine-tune",
+            "status": "completed",
+            "expires_at": 1719127296,
+            "file": {
+                "id": "file-xyz321",
+                "object": "file",
+                "bytes": 2147483648u64,
+                "created_at": 1719186911,
+                "filename": "training_examples.jsonl",
+                "purpose": "fine-tune"
+            }
+        }))?;
+        assert_eq!(upload.status, UploadStatus::Completed);
+        assert_eq!(upload.file.unwrap().id, "file-xyz321");
+        Ok(())
+    }
+}
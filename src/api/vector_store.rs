@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+
+use derive_builder::Builder;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{IntoRequest, ListOrder, ObjectType, PageRequest};
+
+/// Vector stores are part of the Assistants API beta.
+const BETA_HEADER: (&str, &str) = ("OpenAI-Beta", "assistants=v2");
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable", default)]
+pub struct CreateVectorStoreRequest {
+    /// A list of File IDs that the vector store should use.
+    #[builder(setter(into))]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    file_ids: Vec<String>,
+    /// The name of the vector store.
+    #[builder(setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// The expiration policy for a vector store.
+    #[builder(setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_after: Option<ExpiresAfter>,
+    /// Set of 16 key-value pairs that can be attached to an object.
+    #[builder(setter(into))]
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Builder)]
+#[builder(pattern = "mutable", default)]
+pub struct ListVectorStoresRequest {
+    /// A limit on the number of objects to be returned. Limit can range between 1 and 100, and the default is 20.
+    #[builder(setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    /// Sort order by the created_at timestamp of the objects.
+    #[builder(setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<ListOrder>,
+    /// A cursor for use in pagination. after is an object ID that defines your place in the list.
+    #[builder(setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+    /// A cursor for use in pagination. before is an object ID that defines your place in the list.
+    #[builder(setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetrieveVectorStoreRequest {
+    vector_store_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteVectorStoreRequest {
+    vector_store_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateVectorStoreFileRequest {
+    #[serde(skip)]
+    vector_store_id: String,
+    /// A File ID that the vector store should use.
+    file_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetrieveVectorStoreFileRequest {
+    vector_store_id: String,
+    file_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateVectorStoreFileBatchRequest {
+    #[serde(skip)]
+    vector_store_id: String,
+    /// A list of File IDs that the vector store should use.
+    file_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetrieveVectorStoreFileBatchRequest {
+    vector_store_id: String,
+    batch_id: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpiresAfter {
+    /// Anchor timestamp after which the expiration policy applies.
+    pub anchor: ExpiresAfterAnchor,
+    /// The number of days after the anchor time that the vector store will expire.
+    pub days: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiresAfterAnchor {
+    #[default]
+    LastActiveAt,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VectorStore {
+    /// The identifier, which can be referenced in API endpoints.
+    pub id: String,
+    /// The object type, which is always vector_store.
+    pub object: ObjectType,
+    /// The Unix timestamp (in seconds) for when the vector store was created.
+    pub created_at: u64,
+    /// The name of the vector store.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The total number of bytes used by the files in the vector store.
+    #[serde(default)]
+    pub usage_bytes: u64,
+    pub file_counts: FileCounts,
+    /// The status of the vector store. A status of completed indicates that the vector store is ready for use.
+    pub status: VectorStoreStatus,
+    /// The expiration policy for a vector store.
+    #[serde(default)]
+    pub expires_after: Option<ExpiresAfter>,
+    /// The Unix timestamp (in seconds) for when the vector store will expire.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// The Unix timestamp (in seconds) for when the vector store was last active.
+    #[serde(default)]
+    pub last_active_at: Option<u64>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub struct FileCounts {
+    pub in_progress: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorStoreStatus {
+    Expired,
+    InProgress,
+    Completed,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VectorStoreFile {
+    /// The identifier, which can be referenced in API endpoints.
+    pub id: String,
+    /// The object type, which is always vector_store.file.
+    pub object: ObjectType,
+    /// The total vector store usage in bytes.
+    #[serde(default)]
+    pub usage_bytes: u64,
+    /// The Unix timestamp (in seconds) for when the vector store file was created.
+    pub created_at: u64,
+    /// The ID of the vector store that the File is attached to.
+    pub vector_store_id: String,
+    /// The status of the vector store file. The status completed indicates that the file is ready for use.
+    pub status: VectorStoreFileStatus,
+    /// The last error associated with this vector store file.
+    #[serde(default)]
+    pub last_error: Option<VectorStoreFileError>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VectorStoreFileError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorStoreFileStatus {
+    InProgress,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VectorStoreFileBatch {
+    /// The identifier, which can be referenced in API endpoints.
+    pub id: String,
+    /// The object type, which is always vector_store.files_batch.
+    pub object: ObjectType,
+    /// The Unix timestamp (in seconds) for when the vector store files batch was created.
+    pub created_at: u64,
+    /// The ID of the vector store that the File is attached to.
+    pub vector_store_id: String,
+    /// The status of the vector store files batch.
+    pub status: VectorStoreFileStatus,
+    pub file_counts: FileCounts,
+}
+
+impl RetrieveVectorStoreRequest {
+    pub fn new(vector_store_id: impl Into<String>) -> Self {
+        Self {
+            vector_store_id: vector_store_id.into(),
+        }
+    }
+}
+
+impl DeleteVectorStoreRequest {
+    pub fn new(vector_store_id: impl Into<String>) -> Self {
+        Self {
+            vector_store_id: vector_store_id.into(),
+        }
+    }
+}
+
+impl CreateVectorStoreFileRequest {
+    pub fn new(vector_store_id: impl Into<String>, file_id: impl Into<String>) -> Self {
+        Self {
+            vector_store_id: vector_store_id.into(),
+            file_id: file_id.into(),
+        }
+    }
+}
+
+impl RetrieveVectorStoreFileRequest {
+    pub fn new(vector_store_id: impl Into<String>, file_id: impl Into<String>) -> Self {
+        Self {
+            vector_store_id: vector_store_id.into(),
+            file_id: file_id.into(),
+        }
+    }
+}
+
+impl CreateVectorStoreFileBatchRequest {
+    pub fn new(vector_store_id: impl Into<String>, file_ids: Vec<String>) -> Self {
+        Self {
+            vector_store_id: vector_store_id.into(),
+            file_ids,
+        }
+    }
+}
+
+impl RetrieveVectorStoreFileBatchRequest {
+    pub fn new(vector_store_id: impl Into<String>, batch_id: impl Into<String>) -> Self {
+        Self {
+            vector_store_id: vector_store_id.into(),
+            batch_id: batch_id.into(),
+        }
+    }
+}
+
+impl ExpiresAfter {
+    /// Expire the vector store `days` after it was last active.
+    pub fn days(days: u32) -> Self {
+        Self {
+            anchor: ExpiresAfterAnchor::LastActiveAt,
+            days,
+        }
+    }
+}
+
+impl VectorStoreFileStatus {
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, VectorStoreFileStatus::InProgress)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/vector-stores/create
+impl IntoRequest for CreateVectorStoreRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .post(format!("{}/vector_stores", base_url))
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+            .json(&self)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/vector-stores/list
+impl IntoRequest for ListVectorStoresRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .get(format!("{}/vector_stores", base_url))
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+            .query(&self)
+    }
+}
+
+impl PageRequest for ListVectorStoresRequest {
+    type Item = VectorStore;
+
+    fn set_after(&mut self, after: String) {
+        self.after = Some(after);
+    }
+
+    fn item_id(item: &VectorStore) -> &str {
+        &item.id
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/vector-stores/retrieve
+impl IntoRequest for RetrieveVectorStoreRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .get(format!(
+                "{}/vector_stores/{}",
+                base_url, self.vector_store_id
+            ))
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/vector-stores/delete
+impl IntoRequest for DeleteVectorStoreRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .delete(format!(
+                "{}/vector_stores/{}",
+                base_url, self.vector_store_id
+            ))
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/vector-stores-files/createFile
+impl IntoRequest for CreateVectorStoreFileRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .post(format!(
+                "{}/vector_stores/{}/files",
+                base_url, self.vector_store_id
+            ))
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+            .json(&self)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/vector-stores-files/getFile
+impl IntoRequest for RetrieveVectorStoreFileRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .get(format!(
+                "{}/vector_stores/{}/files/{}",
+                base_url, self.vector_store_id, self.file_id
+            ))
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/vector-stores-file-batches/createBatch
+impl IntoRequest for CreateVectorStoreFileBatchRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .post(format!(
+                "{}/vector_stores/{}/file_batches",
+                base_url, self.vector_store_id
+            ))
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+            .json(&self)
+    }
+}
+
+// https://platform.openai.com/docs/api-reference/vector-stores-file-batches/getBatch
+impl IntoRequest for RetrieveVectorStoreFileBatchRequest {
+    fn into_request(self, base_url: &str, client: Client) -> RequestBuilder {
+        client
+            .get(format!(
+                "{}/vector_stores/{}/file_batches/{}",
+                base_url, self.vector_store_id, self.batch_id
+            ))
+            .header(BETA_HEADER.0, BETA_HEADER.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::List;
+    use crate::OPENAI_BASE_URL;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn create_vector_store_request_should_serialize() -> Result<()> {
+        let req = CreateVectorStoreRequestBuilder::default()
+            .name("Support FAQ")
+            .file_ids(vec!["file-abc123".to_string()])
+            .expires_after(ExpiresAfter::days(7))
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(&req)?,
+            json!({
+                "name": "Support FAQ",
+                "file_ids": ["file-abc123"],
+                "expires_after": { "anchor": "last_active_at", "days": 7 }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn list_vector_stores_request_should_use_query() -> Result<()> {
+        let req = ListVectorStoresRequestBuilder::default()
+            .limit(10)
+            .order(ListOrder::Asc)
+            .after("vs_abc")
+            .build()?;
+        let req = req.into_request(OPENAI_BASE_URL, Client::new()).build()?;
+        assert_eq!(req.url().query(), Some("limit=10&order=asc&after=vs_abc"));
+        assert_eq!(req.headers()["OpenAI-Beta"], "assistants=v2");
+        Ok(())
+    }
+
+    #[test]
+    fn vector_store_list_should_deserialize() -> Result<()> {
+        let list: List<VectorStore> = ObjectType::List.parse(json!({
+            "object": "list",
+            "data": [{
+                "id": "vs_abc123",
+                "object": "vector_store",
+                "created_at": 1699061776,
+                "name": "Support FAQ",
+                "usage_bytes": 139920,
+                "status": "completed",
+                "file_counts": {
+                    "in_progress": 0,
+                    "completed": 3,
+                    "failed": 0,
+                    "cancelled": 0,
+                    "total": 3
+                }
+            }],
+            "first_id": "vs_abc123",
+            "last_id": "vs_abc123",
+            "has_more": false
+        }))?;
+        assert_eq!(list.data[0].status, VectorStoreStatus::Completed);
+        assert_eq!(list.data[0].file_counts.total, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn vector_store_file_batch_should_deserialize() -> Result<()> {
+        let batch: VectorStoreFileBatch = serde_json::from_value(json!({
+            "id": "vsfb_abc123",
+            "object": "vector_store.files_batch",
+            "created_at": 1699061776,
+            "vector_store_id": "vs_abc123",
+            "status": "in_progress",
+            "file_counts": {
+                "in_progress": 1,
+                "completed": 1,
+                "failed": 0,
+                "cancelled": 0,
+                "total": 2
+            }
+        }))?;
+        assert!(!batch.status.is_terminal());
+        assert_eq!(batch.object, ObjectType::VectorStoreFileBatch);
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+use std::{fmt::Debug, sync::Arc};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::File, io::Write, path::Path, sync::Mutex};
+
+use anyhow::Result;
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    ChatCompleteUsage, ChatCompletionMessage, ChatCompletionRequest, ChatCompletionResponse,
+    ChatCompletionStream,
+};
+
+/// Receives a record of every chat completion made by `LlmSdk`, for an audit trail.
+///
+/// Sinks run in the order they were added with `LlmSdk::with_audit_sink`, once the call is
+/// over: when the response arrived, the request failed or, for a stream, when it is dropped.
+/// A sink error fails the call, so nothing is answered that wasn't audited; a streamed call
+/// ends with it instead.
+pub trait AuditSink: Debug + Send + Sync {
+    fn record(&self, record: &AuditRecord) -> Result<()>;
+}
+
+/// One chat completion, as sent to and received from the model.
+///
+/// The messages are those sent, after the scrubbers of the SDK ran, and the reply is the one
+/// received, before output filters ran: values redacted by a `PiiRedactor` stay redacted.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    /// When the call was made, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// The model that answered, or the requested one if none did.
+    pub model: String,
+    pub messages: Vec<ChatCompletionMessage>,
+    /// The text of the first choice.
+    pub reply: Option<String>,
+    pub usage: Option<ChatCompleteUsage>,
+    /// The duration of the call, fallbacks included; for a stream, until it ended.
+    pub latency_ms: u64,
+    pub streamed: bool,
+    pub outcome: AuditOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Error {
+        message: String,
+    },
+    /// The caller dropped the call, or the stream, before it was over.
+    Cancelled,
+}
+
+/// Appends every record as a line of JSON to a file.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct JsonlAuditSink {
+    file: Mutex<File>,
+}
+
+/// Emits every record as an `INFO` event with the target `llm_sdk::audit`.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAuditSink;
+
+/// The record of a call in progress, passed to the sinks once when the call is over.
+pub(crate) struct PendingAudit {
+    sinks: Vec<Arc<dyn AuditSink>>,
+    record: AuditRecord,
+    start: Instant,
+    /// Whether a streamed choice reached its finish reason.
+    finished: bool,
+    done: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl JsonlAuditSink {
+    /// Append to the file at `path`, creating it if needed.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AuditSink for JsonlAuditSink {
+    fn record(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&line)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl AuditSink for TracingAuditSink {
+    fn record(&self, record: &AuditRecord) -> Result<()> {
+        let usage = record.usage.clone().unwrap_or_default();
+        tracing::info!(
+            target: "llm_sdk::audit",
+            timestamp_ms = record.timestamp_ms,
+            model = %record.model,
+            messages = %serde_json::to_string(&record.messages)?,
+            reply = record.reply.as_deref(),
+            prompt_tokens = usage.prompt_tokens,
+            completion_tokens = usage.completion_tokens,
+            latency_ms = record.latency_ms,
+            streamed = record.streamed,
+            outcome = %serde_json::to_string(&record.outcome)?,
+            "chat completion"
+        );
+        Ok(())
+    }
+}
+
+impl PendingAudit {
+    /// Start recording the call of `req`, `None` without sinks.
+    pub(crate) fn start(
+        sinks: &[Arc<dyn AuditSink>],
+        req: &ChatCompletionRequest,
+        streamed: bool,
+    ) -> Option<Self> {
+        if sinks.is_empty() {
+            return None;
+        }
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let record = AuditRecord {
+            timestamp_ms,
+            model: req.model().as_str().to_string(),
+            messages: req.messages().to_vec(),
+            reply: None,
+            usage: None,
+            latency_ms: 0,
+            streamed,
+            outcome: AuditOutcome::Cancelled,
+        };
+        Some(Self {
+            sinks: sinks.to_vec(),
+            record,
+            start: Instant::now(),
+            finished: false,
+            done: false,
+        })
+    }
+
+    /// Record the result of a call that isn't streamed.
+    pub(crate) fn finish(
+        mut self,
+        res: Result<&ChatCompletionResponse, &anyhow::Error>,
+    ) -> Result<()> {
+        let outcome = match res {
+            Ok(res) => {
+                self.record.model = res.model.clone();
+                self.record.reply = res.text().map(str::to_string);
+                self.record.usage = Some(res.usage.clone());
+                AuditOutcome::Success
+            }
+            Err(e) => AuditOutcome::Error {
+                message: e.to_string(),
+            },
+        };
+        self.emit(outcome)
+    }
+
+    /// Record the stream as it is consumed; it ends with the error of a failing sink.
+    pub(crate) fn watch(self, stream: ChatCompletionStream) -> ChatCompletionStream {
+        Box::pin(stream::unfold(Some((stream, self)), |state| async move {
+            let (mut stream, mut audit) = state?;
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    audit.record.model.clone_from(&chunk.model);
+                    for choice in chunk.choices.iter().filter(|choice| choice.index == 0) {
+                        if let Some(content) = &choice.delta.content {
+                            audit
+                                .record
+                                .reply
+                                .get_or_insert_with(String::new)
+                                .push_str(content);
+                        }
+                        audit.finished |= choice.finish_reason.is_some();
+                    }
+                    if chunk.usage.is_some() {
+                        audit.record.usage.clone_from(&chunk.usage);
+                    }
+                    Some((Ok(chunk), Some((stream, audit))))
+                }
+                Some(Err(e)) => {
+                    // the error of the stream matters more than the one of the sink
+                    let _ = audit.emit(AuditOutcome::Error {
+                        message: e.to_string(),
+                    });
+                    Some((Err(e), None))
+                }
+                None => {
+                    let outcome = if audit.finished {
+                        AuditOutcome::Success
+                    } else {
+                        AuditOutcome::Error {
+                            message: "stream ended before the model finished".to_string(),
+                        }
+                    };
+                    match audit.emit(outcome) {
+                        Ok(()) => None,
+                        Err(e) => Some((Err(e), None)),
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Pass the record to every sink, returning the first error once all have run.
+    fn emit(&mut self, outcome: AuditOutcome) -> Result<()> {
+        self.done = true;
+        self.record.outcome = outcome;
+        self.record.latency_ms = self.start.elapsed().as_millis() as u64;
+        let mut result = Ok(());
+        for sink in &self.sinks {
+            let recorded = sink.record(&self.record);
+            if result.is_ok() {
+                result = recorded;
+            }
+        }
+        result
+    }
+}
+
+impl Drop for PendingAudit {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.emit(AuditOutcome::Cancelled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChatCompletionChunk, ChatCompletionRequestBuilder};
+
+    fn chunk(value: serde_json::Value) -> Result<ChatCompletionChunk> {
+        Ok(serde_json::from_value(value)?)
+    }
+
+    #[tokio::test]
+    async fn jsonl_sink_should_append_streamed_calls() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("llm-sdk-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sinks: Vec<Arc<dyn AuditSink>> = vec![Arc::new(JsonlAuditSink::new(&path)?)];
+        let req = ChatCompletionRequestBuilder::default()
+            .user_message("hi")
+            .build()?;
+        let chunks = ["Hel", "lo!"].map(|content| {
+            chunk(serde_json::json!({
+                "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 1700000000,
+                "model": "gpt-4o-mini",
+                "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": null }]
+            }))
+        });
+        let last = chunk(serde_json::json!({
+            "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }]
+        }));
+        let complete = stream::iter(chunks.into_iter().chain([last]));
+        let audit = PendingAudit::start(&sinks, &req, true).unwrap();
+        let received: Vec<_> = audit.watch(Box::pin(complete)).collect().await;
+        assert_eq!(received.len(), 3);
+
+        // dropped after the first chunk
+        let chunks = ["Hel", "lo!"].map(|content| {
+            chunk(serde_json::json!({
+                "id": "chatcmpl-2", "object": "chat.completion.chunk", "created": 1700000000,
+                "model": "gpt-4o-mini",
+                "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": null }]
+            }))
+        });
+        let audit = PendingAudit::start(&sinks, &req, true).unwrap();
+        let mut stream = audit.watch(Box::pin(stream::iter(chunks)));
+        stream.next().await.unwrap()?;
+        drop(stream);
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["messages"][0]["content"], "hi");
+        assert_eq!(lines[0]["reply"], "Hello!");
+        assert_eq!(lines[0]["streamed"], true);
+        assert_eq!(
+            lines[0]["outcome"],
+            serde_json::json!({ "status": "success" })
+        );
+        assert_eq!(lines[1]["reply"], "Hel");
+        assert_eq!(
+            lines[1]["outcome"],
+            serde_json::json!({ "status": "cancelled" })
+        );
+        Ok(())
+    }
+}
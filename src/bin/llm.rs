@@ -0,0 +1,210 @@
+//! `llm`: a small command line client for quick checks against the API.
+//!
+//! Settings are read from the config file at `$LLM_CONFIG`, `$XDG_CONFIG_HOME/llm/config.toml`
+//! or `~/.config/llm/config.toml`, see `SdkSettings` for its format, and the `OPENAI_*`
+//! environment variables override it.
+
+use std::{
+    io::{IsTerminal, Read, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::{Parser, Subcommand};
+use futures::StreamExt;
+use llm_sdk::{
+    ChatCompleteModel, ChatCompletionRequestBuilder, CreateEmbeddingRequestBuilder,
+    CreateImageRequestBuilder, EmbeddingModel, ImageModel, ImageSize, SdkSettings,
+};
+use serde::de::DeserializeOwned;
+
+#[derive(Debug, Parser)]
+#[command(name = "llm", version, about = "Talk to an OpenAI-compatible API")]
+struct Cli {
+    /// Use another API base URL, e.g. a local OpenAI-compatible server.
+    #[arg(long, global = true)]
+    base_url: Option<String>,
+    /// The profile of the config file to use.
+    #[arg(short, long, global = true, env = "LLM_SDK_PROFILE")]
+    profile: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Send a prompt, joined with stdin when it is piped, and print the reply.
+    Chat {
+        prompt: Option<String>,
+        #[arg(short, long)]
+        model: Option<String>,
+        #[arg(short, long)]
+        system: Option<String>,
+        /// Print the reply as it is generated.
+        #[arg(long)]
+        stream: bool,
+    },
+    /// Generate an image and print its URL, or save it with `--output`.
+    Image {
+        prompt: Option<String>,
+        /// e.g. dall-e-3 or gpt-image-1.
+        #[arg(short, long)]
+        model: Option<String>,
+        /// e.g. 1024x1024.
+        #[arg(long)]
+        size: Option<String>,
+        /// Write the image to this file instead of printing its URL.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print the embedding of a text as a JSON array.
+    Embed {
+        text: Option<String>,
+        /// e.g. text-embedding-3-small.
+        #[arg(short, long)]
+        model: Option<String>,
+    },
+    /// List the models available to the API key.
+    Models,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut settings = match config_path().filter(|path| path.exists()) {
+        Some(path) => SdkSettings::load(path, cli.profile.as_deref())?,
+        None => SdkSettings::default(),
+    }
+    .with_env();
+    if let Some(base_url) = cli.base_url {
+        settings.base_url = Some(base_url);
+    }
+    let sdk = settings.into_sdk()?;
+
+    match cli.command {
+        Command::Chat {
+            prompt,
+            model,
+            system,
+            stream,
+        } => {
+            let mut req = ChatCompletionRequestBuilder::default();
+            if let Some(model) = model
+                .map(ChatCompleteModel::from)
+                .or_else(|| sdk.default_model().cloned())
+            {
+                req.model(model);
+            }
+            if let Some(system) = system {
+                req.system(system);
+            }
+            let req = req.user_message(input(prompt)?).build()?;
+            if stream {
+                let mut chunks = sdk.chat_completion_stream(req).await?;
+                let mut stdout = std::io::stdout();
+                while let Some(chunk) = chunks.next().await {
+                    let chunk = chunk?;
+                    if let Some(content) = chunk
+                        .choices
+                        .first()
+                        .and_then(|choice| choice.delta.content.as_deref())
+                    {
+                        write!(stdout, "{}", content)?;
+                        stdout.flush()?;
+                    }
+                }
+                writeln!(stdout)?;
+            } else {
+                let res = sdk.chat_completion(req).await?;
+                println!("{}", res.text().unwrap_or_default());
+            }
+        }
+        Command::Image {
+            prompt,
+            model,
+            size,
+            output,
+        } => {
+            let mut req = CreateImageRequestBuilder::default();
+            req.prompt(input(prompt)?);
+            if let Some(model) = model {
+                req.model(parse_name::<ImageModel>("image model", &model)?);
+            }
+            if let Some(size) = size {
+                req.size(parse_name::<ImageSize>("image size", &size)?);
+            }
+            let res = sdk.create_image(req.build()?).await?;
+            let image = res.data.into_iter().next().context("no image returned")?;
+            match (output, image.b64_json, image.url) {
+                (Some(path), Some(data), _) => std::fs::write(path, STANDARD.decode(data)?)?,
+                (Some(path), None, Some(url)) => {
+                    let bytes = reqwest::get(&url)
+                        .await?
+                        .error_for_status()?
+                        .bytes()
+                        .await?;
+                    std::fs::write(path, bytes)?;
+                }
+                (None, _, Some(url)) => println!("{}", url),
+                _ => bail!("the image has no URL, use --output to save it"),
+            }
+        }
+        Command::Embed { text, model } => {
+            let mut req = CreateEmbeddingRequestBuilder::default();
+            req.input(input(text)?);
+            if let Some(model) = model {
+                req.model(parse_name::<EmbeddingModel>("embedding model", &model)?);
+            }
+            let res = sdk.create_embedding(req.build()?).await?;
+            let vector = res
+                .data
+                .into_iter()
+                .next()
+                .context("no embedding returned")?;
+            println!("{}", serde_json::to_string(&vector.embedding)?);
+        }
+        Command::Models => {
+            let mut models = sdk.list_models().await?.data;
+            models.sort_by(|a, b| a.id.cmp(&b.id));
+            for model in models {
+                println!("{}", model.id);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("LLM_CONFIG") {
+        return Some(path.into());
+    }
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("llm").join("config.toml"))
+}
+
+/// The argument and piped stdin, joined by a blank line, e.g. `git diff | llm chat "review this"`.
+fn input(arg: Option<String>) -> Result<String> {
+    let mut stdin = std::io::stdin();
+    let piped = if stdin.is_terminal() {
+        None
+    } else {
+        let mut text = String::new();
+        stdin.read_to_string(&mut text)?;
+        Some(text).filter(|text| !text.trim().is_empty())
+    };
+    match (arg, piped) {
+        (Some(arg), Some(piped)) => Ok(format!("{}\n\n{}", arg, piped)),
+        (Some(text), None) | (None, Some(text)) => Ok(text),
+        (None, None) => bail!("no input: pass it as an argument or pipe it to stdin"),
+    }
+}
+
+/// Parse the API name of a serde enum, e.g. `dall-e-3` for `ImageModel::DallE3`.
+fn parse_name<T: DeserializeOwned>(what: &str, name: &str) -> Result<T> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .with_context(|| format!("unknown {} `{}`", what, name))
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+
+use crate::{ChatCompleteUsage, ModelPrice, ModelUsage, SdkError, UsageTracker};
+
+/// The completion tokens assumed for a request without `max_tokens`, the output limit of
+/// most chat models.
+pub const DEFAULT_COMPLETION_TOKENS: usize = 4096;
+
+/// Token and cost limits checked before a chat completion is sent.
+///
+/// Set one on `LlmSdk::with_budget` to cap the whole client, or on `Conversation::with_budget`
+/// to cap a single session. Prompt tokens are estimated with the model's tokenizer and the
+/// completion is assumed to use all of `max_tokens`, or `DEFAULT_COMPLETION_TOKENS` without
+/// it, so a request is only sent if it fits even in the worst case. Clones share what has
+/// been spent.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    max_request_tokens: Option<usize>,
+    max_total_tokens: Option<usize>,
+    max_cost: Option<f64>,
+    spent: UsageTracker,
+}
+
+/// The limit a request would break, see `SdkError::BudgetExceeded`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetLimit {
+    /// Prompt and completion tokens of a single request.
+    RequestTokens,
+    /// Tokens across every request.
+    TotalTokens,
+    /// USD across every request.
+    TotalCost,
+}
+
+impl Budget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_request_tokens(mut self, tokens: usize) -> Self {
+        self.max_request_tokens = Some(tokens);
+        self
+    }
+
+    pub fn with_max_total_tokens(mut self, tokens: usize) -> Self {
+        self.max_total_tokens = Some(tokens);
+        self
+    }
+
+    /// Limit the cumulative cost in USD. Needs a price for the models used, see `with_price`;
+    /// requests to a model without one fail with `SdkError::UnknownPrice`.
+    pub fn with_max_cost(mut self, usd: f64) -> Self {
+        self.max_cost = Some(usd);
+        self
+    }
+
+    /// Set the price of a model, with the same prefix matching as `UsageTracker::with_price`.
+    pub fn with_price(self, model: impl Into<String>, price: ModelPrice) -> Self {
+        self.spent.set_price(model, price);
+        self
+    }
+
+    /// Fail if a request with this many prompt tokens and `max_tokens` could break a limit.
+    pub fn check(
+        &self,
+        model: &str,
+        prompt_tokens: usize,
+        max_tokens: Option<usize>,
+    ) -> Result<(), SdkError> {
+        let completion_tokens = max_tokens.unwrap_or(DEFAULT_COMPLETION_TOKENS);
+        let tokens = prompt_tokens + completion_tokens;
+        if let Some(max) = self.max_request_tokens {
+            exceeds(BudgetLimit::RequestTokens, tokens as f64, max as f64)?;
+        }
+        let spent = self.spent();
+        if let Some(max) = self.max_total_tokens {
+            let needed = spent.total_tokens + tokens;
+            exceeds(BudgetLimit::TotalTokens, needed as f64, max as f64)?;
+        }
+        if let Some(max) = self.max_cost {
+            let cost = self
+                .spent
+                .estimate_cost(model, prompt_tokens, completion_tokens)
+                .ok_or_else(|| SdkError::UnknownPrice {
+                    model: model.to_string(),
+                })?;
+            exceeds(BudgetLimit::TotalCost, spent.cost + cost, max)?;
+        }
+        Ok(())
+    }
+
+    /// Count the usage of a completed request against the budget.
+    pub fn record(&self, model: &str, usage: &ChatCompleteUsage) {
+        self.spent.record(model, usage);
+    }
+
+    /// What has been spent so far.
+    pub fn spent(&self) -> ModelUsage {
+        self.spent.total()
+    }
+
+    /// Tokens left before `max_total_tokens` is reached, if set.
+    pub fn remaining_tokens(&self) -> Option<usize> {
+        let max = self.max_total_tokens?;
+        Some(max.saturating_sub(self.spent().total_tokens))
+    }
+
+    /// Forget what has been spent, e.g. at the start of a billing period.
+    pub fn reset(&self) {
+        self.spent.reset();
+    }
+}
+
+fn exceeds(limit: BudgetLimit, needed: f64, max: f64) -> Result<(), SdkError> {
+    if needed > max {
+        return Err(SdkError::BudgetExceeded { limit, needed, max });
+    }
+    Ok(())
+}
+
+impl fmt::Display for BudgetLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BudgetLimit::RequestTokens => "per-request token",
+            BudgetLimit::TotalTokens => "total token",
+            BudgetLimit::TotalCost => "total cost (USD)",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: usize, completion_tokens: usize) -> ChatCompleteUsage {
+        ChatCompleteUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            ..Default::default()
+        }
+    }
+
+    fn limit(result: Result<(), SdkError>) -> Option<BudgetLimit> {
+        match result {
+            Err(SdkError::BudgetExceeded { limit, .. }) => Some(limit),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn budget_should_check_request_tokens() {
+        let budget = Budget::new().with_max_request_tokens(1000);
+        assert_eq!(limit(budget.check("gpt-4", 800, Some(200))), None);
+        assert_eq!(
+            limit(budget.check("gpt-4", 800, Some(201))),
+            Some(BudgetLimit::RequestTokens)
+        );
+    }
+
+    #[test]
+    fn budget_should_check_cumulative_spending() {
+        let budget = Budget::new()
+            .with_max_total_tokens(2000)
+            .with_max_cost(0.05)
+            .with_price(
+                "gpt-4",
+                ModelPrice {
+                    prompt: 0.03,
+                    completion: 0.06,
+                    ..Default::default()
+                },
+            );
+        let session = budget.clone();
+        session.record("gpt-4-0613", &usage(500, 100));
+        assert_eq!(budget.remaining_tokens(), Some(1400));
+        // 0.018 spent, 0.009 + 0.006 more fits
+        assert_eq!(limit(budget.check("gpt-4", 300, Some(100))), None);
+        // 0.018 spent, 0.036 more doesn't
+        assert_eq!(
+            limit(budget.check("gpt-4", 0, Some(600))),
+            Some(BudgetLimit::TotalCost)
+        );
+        assert_eq!(
+            limit(budget.check("gpt-4", 1, None)),
+            Some(BudgetLimit::TotalTokens)
+        );
+        assert!(matches!(
+            budget.check("claude-3-haiku", 10, Some(10)),
+            Err(SdkError::UnknownPrice { model }) if model == "claude-3-haiku"
+        ));
+        budget.reset();
+        assert_eq!(budget.remaining_tokens(), Some(2000));
+    }
+}
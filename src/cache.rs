@@ -0,0 +1,309 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use web_time::Instant;
+
+use crate::{cosine_similarity, ChatCompletionMessage, ChatCompletionRequest, Embedder};
+
+const DEFAULT_CAPACITY: usize = 1024;
+const DEFAULT_THRESHOLD: f32 = 0.95;
+
+/// A store for raw JSON responses, keyed by a hash of the request that produced them.
+///
+/// Implement this to back the response cache with Redis, disk or anything else.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Cache: Debug + Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>>;
+    async fn set(&self, key: &str, value: String) -> Result<()>;
+}
+
+/// In-memory cache evicting the least recently used response once full.
+#[derive(Debug)]
+pub struct LruCache {
+    capacity: usize,
+    inner: Mutex<LruState>,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    entries: HashMap<String, String>,
+    // least recently used key first
+    order: VecDeque<String>,
+}
+
+impl LruCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(LruState::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for LruCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl LruState {
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(pos).unwrap();
+            self.order.push_back(key);
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Cache for LruCache {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        let mut state = self.inner.lock().unwrap();
+        let value = state.entries.get(key).cloned();
+        if value.is_some() {
+            state.touch(key);
+        }
+        Ok(value)
+    }
+
+    async fn set(&self, key: &str, value: String) -> Result<()> {
+        let mut state = self.inner.lock().unwrap();
+        if state.entries.insert(key.to_string(), value).is_some() {
+            state.touch(key);
+            return Ok(());
+        }
+        state.order.push_back(key.to_string());
+        while state.order.len() > self.capacity {
+            if let Some(oldest) = state.order.pop_front() {
+                state.entries.remove(&oldest);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serves the answer of a previous question similar enough to the new one.
+///
+/// The last message of a request, if from the user, is embedded and compared with the questions
+/// answered before with the same model, parameters and earlier messages. Set it with
+/// `LlmSdk::with_semantic_cache`; unlike the exact cache it applies to every non-streaming
+/// request unless it is disabled with `ChatCompletionRequestBuilder::cache(false)`.
+#[derive(Debug)]
+pub struct SemanticCache {
+    embedder: Arc<dyn Embedder>,
+    threshold: f32,
+    ttl: Option<Duration>,
+    capacity: usize,
+    // oldest first
+    entries: Mutex<VecDeque<SemanticEntry>>,
+}
+
+#[derive(Debug)]
+struct SemanticEntry {
+    query: SemanticQuery,
+    value: String,
+    inserted: Instant,
+}
+
+/// The lookup key of a request: everything but the question, hashed, and the question embedded.
+#[derive(Debug, Clone)]
+pub(crate) struct SemanticQuery {
+    scope: String,
+    embedding: Vec<f32>,
+}
+
+impl SemanticCache {
+    pub fn new(embedder: impl Embedder + 'static) -> Self {
+        Self {
+            embedder: Arc::new(embedder),
+            threshold: DEFAULT_THRESHOLD,
+            ttl: None,
+            capacity: DEFAULT_CAPACITY,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// The cosine similarity from which two questions get the same answer. Defaults to 0.95.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Stop serving answers older than `ttl`.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Evict the oldest answers beyond `capacity`. Defaults to 1024.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Embed the question of `req`; `None` if the last message isn't a user question.
+    pub(crate) async fn query(&self, req: &ChatCompletionRequest) -> Result<Option<SemanticQuery>> {
+        let mut req = req.clone();
+        let question = match req.messages_mut().pop() {
+            Some(msg @ ChatCompletionMessage::User(_)) => match msg.content() {
+                Some(text) => text.to_string(),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let embedding = self
+            .embedder
+            .embed(vec![question])
+            .await?
+            .pop()
+            .unwrap_or_default();
+        Ok(Some(SemanticQuery {
+            scope: cache_key(&req)?,
+            embedding,
+        }))
+    }
+
+    /// The answer of the most similar question above the threshold.
+    pub(crate) fn get(&self, query: &SemanticQuery) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(ttl) = self.ttl {
+            entries.retain(|entry| entry.inserted.elapsed() < ttl);
+        }
+        entries
+            .iter()
+            .filter(|entry| {
+                entry.query.scope == query.scope
+                    && entry.query.embedding.len() == query.embedding.len()
+            })
+            .map(|entry| {
+                let score = cosine_similarity(&entry.query.embedding, &query.embedding);
+                (score, entry)
+            })
+            .filter(|(score, _)| *score >= self.threshold)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, entry)| entry.value.clone())
+    }
+
+    pub(crate) fn insert(&self, query: SemanticQuery, value: String) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(SemanticEntry {
+            query,
+            value,
+            inserted: Instant::now(),
+        });
+        while entries.len() > self.capacity {
+            entries.pop_front();
+        }
+    }
+}
+
+/// Hash a serialized request into a stable cache key.
+pub(crate) fn cache_key(req: &impl Serialize) -> Result<String> {
+    let body = serde_json::to_vec(req)?;
+    Ok(format!("{:x}", Sha256::digest(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn lru_cache_should_evict_least_recently_used() -> Result<()> {
+        let cache = LruCache::new(2);
+        cache.set("a", "1".into()).await?;
+        cache.set("b", "2".into()).await?;
+        assert_eq!(cache.get("a").await?, Some("1".into()));
+        cache.set("c", "3".into()).await?;
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b").await?, None);
+        assert_eq!(cache.get("a").await?, Some("1".into()));
+        assert_eq!(cache.get("c").await?, Some("3".into()));
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct NoEmbedder;
+
+    #[async_trait]
+    impl Embedder for NoEmbedder {
+        async fn embed(&self, _texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            unreachable!()
+        }
+    }
+
+    fn query(scope: &str, embedding: [f32; 2]) -> SemanticQuery {
+        SemanticQuery {
+            scope: scope.to_string(),
+            embedding: embedding.to_vec(),
+        }
+    }
+
+    #[test]
+    fn semantic_cache_should_serve_similar_questions_in_scope() {
+        let cache = SemanticCache::new(NoEmbedder).with_threshold(0.9);
+        cache.insert(query("a", [1.0, 0.0]), "east".into());
+        cache.insert(query("a", [0.0, 1.0]), "north".into());
+
+        assert_eq!(cache.get(&query("a", [0.99, 0.1])), Some("east".into()));
+        assert_eq!(cache.get(&query("a", [0.1, 0.99])), Some("north".into()));
+        assert_eq!(cache.get(&query("a", [1.0, 1.0])), None);
+        assert_eq!(cache.get(&query("b", [1.0, 0.0])), None);
+    }
+
+    #[test]
+    fn semantic_cache_should_expire_and_evict() {
+        let cache = SemanticCache::new(NoEmbedder).with_capacity(1);
+        cache.insert(query("a", [1.0, 0.0]), "east".into());
+        cache.insert(query("a", [0.0, 1.0]), "north".into());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&query("a", [1.0, 0.0])), None);
+
+        let cache = SemanticCache::new(NoEmbedder).with_ttl(Duration::ZERO);
+        cache.insert(query("a", [1.0, 0.0]), "east".into());
+        assert_eq!(cache.get(&query("a", [1.0, 0.0])), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_key_should_be_stable() -> Result<()> {
+        let a = cache_key(&json!({"model": "gpt-4", "messages": []}))?;
+        let b = cache_key(&json!({"model": "gpt-4", "messages": []}))?;
+        let c = cache_key(&json!({"model": "gpt-3.5", "messages": []}))?;
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.len(), 64);
+        Ok(())
+    }
+}
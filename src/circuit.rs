@@ -0,0 +1,353 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{Request, StatusCode};
+use web_time::Instant;
+
+use crate::SdkError;
+
+/// Fails requests fast while an endpoint is down, instead of waiting for each one to time out.
+///
+/// Every endpoint, or every host with `CircuitScope::Host`, has its own circuit. After
+/// `failure_threshold` consecutive failures (transport errors, 408, 429 and 5xx statuses) the
+/// circuit opens and requests fail with `SdkError::CircuitOpen` for the cooldown. Then a single
+/// probe request is let through: its success closes the circuit, its failure opens it again.
+/// Set one with `LlmSdk::with_circuit_breaker`; clones share their circuits.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: usize,
+    cooldown: Duration,
+    scope: CircuitScope,
+    circuits: Arc<Mutex<HashMap<String, Circuit>>>,
+    on_event: Option<EventCallback>,
+}
+
+type EventCallback = Arc<dyn Fn(&CircuitEvent) + Send + Sync>;
+
+/// What a circuit covers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CircuitScope {
+    /// One circuit per host and route, e.g. `api.openai.com/v1/files/{id}/content`: the ids in
+    /// a path don't get circuits of their own.
+    #[default]
+    Endpoint,
+    /// One circuit per host, i.e. per provider.
+    Host,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent.
+    Closed,
+    /// Requests fail fast until the cooldown is over.
+    Open,
+    /// The cooldown is over and a probe request decides whether to close the circuit.
+    HalfOpen,
+}
+
+/// A state change of a circuit, passed to `CircuitBreaker::on_event`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitEvent {
+    pub circuit: String,
+    pub state: CircuitState,
+    /// The consecutive failures that opened the circuit, 0 otherwise.
+    pub failures: usize,
+}
+
+/// The counters of one circuit, see `CircuitBreaker::stats`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircuitStats {
+    pub state: CircuitState,
+    /// Requests sent, not counting those failed fast.
+    pub requests: usize,
+    pub failures: usize,
+    /// Requests failed fast while the circuit was open.
+    pub rejected: usize,
+    /// How many times the circuit opened.
+    pub opened: usize,
+}
+
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: usize,
+    /// When the circuit opened or the probe was let through.
+    since: Instant,
+    stats: CircuitStats,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: usize, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            scope: CircuitScope::default(),
+            circuits: Default::default(),
+            on_event: None,
+        }
+    }
+
+    pub fn with_scope(mut self, scope: CircuitScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Call `f` when a circuit opens, half-opens or closes, e.g. to update a gauge or log.
+    pub fn on_event(mut self, f: impl Fn(&CircuitEvent) + Send + Sync + 'static) -> Self {
+        self.on_event = Some(Arc::new(f));
+        self
+    }
+
+    /// The counters of every circuit that has seen a request, by circuit name.
+    pub fn stats(&self) -> HashMap<String, CircuitStats> {
+        let circuits = self.circuits.lock().unwrap();
+        circuits
+            .iter()
+            .map(|(name, circuit)| (name.clone(), circuit.stats))
+            .collect()
+    }
+
+    /// The name of the circuit `req` goes through.
+    pub fn circuit(&self, req: &Request) -> String {
+        let url = req.url();
+        let host = url.host_str().unwrap_or_default();
+        match self.scope {
+            CircuitScope::Endpoint => format!("{}{}", host, route(url.path())),
+            CircuitScope::Host => host.to_string(),
+        }
+    }
+
+    /// Let a request through `circuit`, or fail with `SdkError::CircuitOpen`.
+    pub(crate) fn acquire(&self, circuit: &str) -> Result<(), SdkError> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let entry = circuits
+            .entry(circuit.to_string())
+            .or_insert_with(Circuit::new);
+        let elapsed = entry.since.elapsed();
+        let event = match entry.state {
+            CircuitState::Closed => None,
+            // a probe that never reported back, e.g. because it was cancelled, is replaced
+            CircuitState::Open | CircuitState::HalfOpen if elapsed >= self.cooldown => {
+                entry.since = Instant::now();
+                (entry.state == CircuitState::Open)
+                    .then(|| entry.transition(circuit, CircuitState::HalfOpen))
+            }
+            CircuitState::Open | CircuitState::HalfOpen => {
+                entry.stats.rejected += 1;
+                return Err(SdkError::CircuitOpen {
+                    circuit: circuit.to_string(),
+                    retry_after: self.cooldown.saturating_sub(elapsed),
+                });
+            }
+        };
+        entry.stats.requests += 1;
+        drop(circuits);
+        self.emit(event);
+        Ok(())
+    }
+
+    /// Record the outcome of a request let through by `acquire`.
+    pub(crate) fn record(&self, circuit: &str, status: Option<StatusCode>) {
+        let failed = status.is_none_or(is_failure);
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(entry) = circuits.get_mut(circuit) else {
+            return;
+        };
+        let event = if failed {
+            entry.stats.failures += 1;
+            entry.consecutive_failures += 1;
+            let trips = entry.state == CircuitState::HalfOpen
+                || (entry.state == CircuitState::Closed
+                    && entry.consecutive_failures >= self.failure_threshold);
+            trips.then(|| {
+                entry.since = Instant::now();
+                entry.stats.opened += 1;
+                entry.transition(circuit, CircuitState::Open)
+            })
+        } else {
+            entry.consecutive_failures = 0;
+            (entry.state != CircuitState::Closed)
+                .then(|| entry.transition(circuit, CircuitState::Closed))
+        };
+        drop(circuits);
+        self.emit(event);
+    }
+
+    fn emit(&self, event: Option<CircuitEvent>) {
+        if let (Some(f), Some(event)) = (&self.on_event, event) {
+            f(&event);
+        }
+    }
+}
+
+impl Circuit {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            since: Instant::now(),
+            stats: CircuitStats {
+                state: CircuitState::Closed,
+                requests: 0,
+                failures: 0,
+                rejected: 0,
+                opened: 0,
+            },
+        }
+    }
+
+    fn transition(&mut self, name: &str, state: CircuitState) -> CircuitEvent {
+        self.state = state;
+        self.stats.state = state;
+        CircuitEvent {
+            circuit: name.to_string(),
+            state,
+            failures: if state == CircuitState::Open {
+                self.consecutive_failures
+            } else {
+                0
+            },
+        }
+    }
+}
+
+impl CircuitStats {
+    /// The share of sent requests that failed.
+    pub fn failure_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.requests as f64
+    }
+}
+
+/// Whether a status says the endpoint is unhealthy, rather than that the request was wrong.
+fn is_failure(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("failure_threshold", &self.failure_threshold)
+            .field("cooldown", &self.cooldown)
+            .field("scope", &self.scope)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half-open",
+        })
+    }
+}
+
+/// `path` with each segment that names a resource, i.e. has a digit but isn't an API version
+/// such as `v1`, replaced by `{id}`.
+fn route(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            let is_version = segment
+                .strip_prefix('v')
+                .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()));
+            if segment.chars().any(|c| c.is_ascii_digit()) && !is_version {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(breaker: &CircuitBreaker, circuit: &str) -> CircuitState {
+        breaker.stats()[circuit].state
+    }
+
+    #[test]
+    fn circuit_should_open_after_consecutive_failures() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let breaker = CircuitBreaker::new(2, Duration::ZERO)
+            .on_event(move |event| seen.lock().unwrap().push(event.state));
+        let chat = "api.openai.com/v1/chat/completions";
+
+        breaker.acquire(chat).unwrap();
+        breaker.record(chat, Some(StatusCode::BAD_GATEWAY));
+        breaker.acquire(chat).unwrap();
+        // a client error isn't the endpoint's fault
+        breaker.record(chat, Some(StatusCode::BAD_REQUEST));
+        breaker.acquire(chat).unwrap();
+        breaker.record(chat, None);
+        assert_eq!(state(&breaker, chat), CircuitState::Closed);
+        breaker.acquire(chat).unwrap();
+        breaker.record(chat, Some(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(state(&breaker, chat), CircuitState::Open);
+
+        // the cooldown is over: one probe, whose failure opens the circuit again
+        breaker.acquire(chat).unwrap();
+        assert_eq!(state(&breaker, chat), CircuitState::HalfOpen);
+        breaker.record(chat, Some(StatusCode::SERVICE_UNAVAILABLE));
+        breaker.acquire(chat).unwrap();
+        breaker.record(chat, Some(StatusCode::OK));
+        assert_eq!(state(&breaker, chat), CircuitState::Closed);
+
+        let stats = breaker.stats()[chat];
+        assert_eq!((stats.requests, stats.failures, stats.opened), (6, 4, 2));
+        assert!((stats.failure_rate() - 4.0 / 6.0).abs() < 1e-9);
+        use CircuitState::*;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [Open, HalfOpen, Open, HalfOpen, Closed]
+        );
+    }
+
+    #[test]
+    fn open_circuit_should_fail_fast() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        breaker.acquire("a").unwrap();
+        breaker.record("a", None);
+        let err = breaker.acquire("a").unwrap_err();
+        assert!(matches!(
+            err,
+            SdkError::CircuitOpen { ref circuit, retry_after }
+                if circuit == "a" && retry_after > Duration::from_secs(59)
+        ));
+        // other circuits are unaffected
+        breaker.acquire("b").unwrap();
+        assert_eq!(breaker.stats()["a"].rejected, 1);
+    }
+
+    #[test]
+    fn endpoint_circuits_should_be_per_route() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        let circuit =
+            |url: &str| breaker.circuit(&Request::new(reqwest::Method::GET, url.parse().unwrap()));
+        assert_eq!(
+            circuit("https://api.openai.com/v1/files/file-abc123/content"),
+            "api.openai.com/v1/files/{id}/content"
+        );
+        assert_eq!(
+            circuit("https://api.openai.com/v1/threads/thread_1/runs/run_2?limit=5"),
+            "api.openai.com/v1/threads/{id}/runs/{id}"
+        );
+        assert_eq!(
+            circuit("https://api.openai.com/v1/chat/completions"),
+            "api.openai.com/v1/chat/completions"
+        );
+    }
+}
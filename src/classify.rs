@@ -0,0 +1,158 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::{
+    repair, ChatCompletionMessage, ChatCompletionRequest, ChatCompletionRequestBuilder,
+    ChatResponseFormatObject, LlmSdk, SdkError, DEFAULT_EXTRACT_ATTEMPTS,
+};
+
+/// A closed set of answers for `LlmSdk::classify`, usually a fieldless enum. `Display` gives
+/// the label the model answers with and `FromStr` parses it back.
+///
+/// With the `strum` feature, every enum deriving strum's `Display`, `EnumString` and
+/// `EnumIter` is a `Label`:
+///
+/// ```ignore
+/// #[derive(strum::Display, strum::EnumString, strum::EnumIter)]
+/// #[strum(serialize_all = "lowercase")]
+/// enum Sentiment {
+///     Positive,
+///     Negative,
+/// }
+/// ```
+pub trait Label: Display + FromStr + Sized {
+    /// Every answer, in the order they are offered to the model.
+    fn variants() -> Vec<Self>;
+}
+
+#[cfg(feature = "strum")]
+impl<E: strum::IntoEnumIterator + Display + FromStr> Label for E {
+    fn variants() -> Vec<Self> {
+        E::iter().collect()
+    }
+}
+
+impl LlmSdk {
+    /// Classify `input` as one of the variants of `E`, following `instructions`.
+    ///
+    /// Uses the default model and retries twice on invalid answers, see `classify_with`.
+    ///
+    /// ```no_run
+    /// # use std::{fmt, str::FromStr};
+    /// # use llm_sdk::{Label, LlmSdk};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Sentiment { Positive, Negative, Neutral }
+    /// # impl fmt::Display for Sentiment {
+    /// #     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Debug::fmt(self, f) }
+    /// # }
+    /// # impl FromStr for Sentiment {
+    /// #     type Err = ();
+    /// #     fn from_str(s: &str) -> Result<Self, ()> { Self::variants().into_iter().find(|v| v.to_string() == s).ok_or(()) }
+    /// # }
+    /// impl Label for Sentiment {
+    ///     fn variants() -> Vec<Self> {
+    ///         vec![Self::Positive, Self::Negative, Self::Neutral]
+    ///     }
+    /// }
+    ///
+    /// # async fn run(sdk: LlmSdk) -> anyhow::Result<()> {
+    /// let sentiment: Sentiment = sdk
+    ///     .classify("The battery died after an hour.", "Classify the sentiment of the review.")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn classify<E: Label>(
+        &self,
+        input: impl Into<String>,
+        instructions: impl Into<String>,
+    ) -> Result<E> {
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![
+                ChatCompletionMessage::new_system(instructions, ""),
+                ChatCompletionMessage::new_user(input.into(), ""),
+            ])
+            .build()?;
+        self.classify_with(req, DEFAULT_EXTRACT_ATTEMPTS).await
+    }
+
+    /// Send `req` constrained to answer with a label of `E` and parse the answer.
+    ///
+    /// The labels are listed in a system message, for models without structured outputs, and
+    /// set as the enum of the response schema, for those with. Plain-text answers are accepted
+    /// too, ignoring case. An invalid answer is sent back with the labels and the model is
+    /// asked again, up to `max_attempts` requests in total; then `SdkError::Extraction` is
+    /// returned.
+    pub async fn classify_with<E: Label>(
+        &self,
+        mut req: ChatCompletionRequest,
+        max_attempts: usize,
+    ) -> Result<E> {
+        let labels: Vec<String> = E::variants().iter().map(ToString::to_string).collect();
+        let list = labels.join(", ");
+        req.push_message(ChatCompletionMessage::new_system(
+            format!(
+                "Answer with exactly one of these labels: {}. Reply with JSON: {{\"label\": \"<label>\"}}",
+                list
+            ),
+            "",
+        ));
+        let schema = json!({
+            "type": "object",
+            "properties": { "label": { "type": "string", "enum": labels } },
+            "required": ["label"],
+            "additionalProperties": false
+        });
+        req.set_response_format(ChatResponseFormatObject::json_schema("label", schema, true));
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let res = self.chat_completion(req.clone()).await?;
+            let content = res.text().unwrap_or_default().to_string();
+            let answer = parse_answer(&content);
+            if let Some(label) = find_label::<E>(&answer) {
+                return Ok(label);
+            }
+            let reason = format!("`{}` is not one of: {}", answer, list);
+            if attempts >= max_attempts {
+                return Err(SdkError::Extraction {
+                    attempts,
+                    reason,
+                    content,
+                }
+                .into());
+            }
+            req.push_message(ChatCompletionMessage::new_assistant(content, "", vec![]));
+            req.push_message(ChatCompletionMessage::new_user(
+                format!("{}. Answer again with one of the labels.", reason),
+                "",
+            ));
+        }
+    }
+}
+
+/// The label in a `{"label": ...}` reply, or the reply itself without quotes or a full stop.
+fn parse_answer(content: &str) -> String {
+    let content = repair::strip_code_fence(content).trim();
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|value| value["label"].as_str().map(str::to_string))
+        .unwrap_or_else(|| {
+            content
+                .trim_end_matches('.')
+                .trim_matches(|c| c == '"' || c == '\'' || c == '`')
+                .to_string()
+        })
+}
+
+/// The variant `answer` names, exactly or else ignoring case.
+fn find_label<E: Label>(answer: &str) -> Option<E> {
+    if let Ok(label) = answer.parse() {
+        return Some(label);
+    }
+    E::variants()
+        .into_iter()
+        .find(|label| label.to_string().eq_ignore_ascii_case(answer))
+}
@@ -0,0 +1,291 @@
+#[cfg(feature = "config")]
+use std::{collections::BTreeMap, path::Path};
+
+use reqwest::header::HeaderMap;
+use secrecy::SecretString;
+use serde::Deserialize;
+
+use crate::{ChatCompleteModel, LlmSdk, SdkError};
+
+/// Environment variables read by `SdkSettings::with_env`.
+pub const API_KEY_ENV: &str = "OPENAI_API_KEY";
+pub const BASE_URL_ENV: &str = "OPENAI_BASE_URL";
+pub const ORGANIZATION_ENV: &str = "OPENAI_ORG_ID";
+pub const PROJECT_ENV: &str = "OPENAI_PROJECT_ID";
+/// Selects the profile of a config file when none is given, see `LlmSdk::from_config`.
+pub const PROFILE_ENV: &str = "LLM_SDK_PROFILE";
+
+/// What `LlmSdk::from_env` and `LlmSdk::from_config` build the SDK from.
+///
+/// A config file is TOML. Top-level settings apply to every profile and a profile overrides
+/// them; `default_profile` picks the profile used when none is asked for:
+///
+/// ```toml
+/// default_profile = "openai"
+///
+/// [profiles.openai]
+/// api_key = "sk-..."
+/// model = "gpt-4o-mini"
+///
+/// [profiles.local]
+/// api_key = ""
+/// base_url = "http://localhost:11434/v1"
+/// model = "llama3.1"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SdkSettings {
+    /// Required; set it to an empty string for a server that needs no key.
+    /// Zeroized on drop and redacted from `Debug`.
+    pub api_key: Option<SecretString>,
+    pub base_url: Option<String>,
+    /// Sent as the `OpenAI-Organization` header.
+    pub organization: Option<String>,
+    /// Sent as the `OpenAI-Project` header.
+    pub project: Option<String>,
+    /// The chat model to use when the caller doesn't pick one, see `LlmSdk::with_default_model`.
+    pub model: Option<ChatCompleteModel>,
+}
+
+/// Values filled into the requests `LlmSdk` sends when they leave them unset, see
+/// `LlmSdk::with_request_defaults`.
+///
+/// ```no_run
+/// # use llm_sdk::{ChatCompleteModel, LlmSdk, RequestDefaults};
+/// # use reqwest::header::{HeaderMap, HeaderValue};
+/// let mut headers = HeaderMap::new();
+/// headers.insert("x-team", HeaderValue::from_static("search"));
+/// let sdk = LlmSdk::new("sk-...").with_request_defaults(RequestDefaults {
+///     model: Some(ChatCompleteModel::Gpt4Turbo),
+///     temperature: Some(0.2),
+///     max_tokens: Some(1024),
+///     user: Some("search-service".to_string()),
+///     headers,
+/// });
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestDefaults {
+    /// The model of chat requests. Image requests always name theirs.
+    pub model: Option<ChatCompleteModel>,
+    /// The temperature of chat requests, except to reasoning models, which only take theirs.
+    pub temperature: Option<f32>,
+    /// The completion token limit of chat requests.
+    pub max_tokens: Option<usize>,
+    /// The end-user id of chat and image requests.
+    pub user: Option<String>,
+    /// Sent with every request, unless it has the header already.
+    pub headers: HeaderMap,
+}
+
+#[cfg(feature = "config")]
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    default_profile: Option<String>,
+    #[serde(flatten)]
+    settings: SdkSettings,
+    #[serde(default)]
+    profiles: BTreeMap<String, SdkSettings>,
+}
+
+impl SdkSettings {
+    /// Settings from the environment variables only.
+    pub fn from_env() -> Self {
+        let mut settings = Self::default();
+        settings.apply_env(|name| std::env::var(name).ok());
+        settings
+    }
+
+    /// Parse a TOML config and pick `profile`, or its `default_profile` if `None`.
+    #[cfg(feature = "config")]
+    pub fn parse(toml: &str, profile: Option<&str>) -> Result<Self, SdkError> {
+        let file: ConfigFile =
+            toml::from_str(toml).map_err(|e| SdkError::Config(format!("invalid TOML: {}", e)))?;
+        file.select(profile)
+    }
+
+    /// Read a TOML config file, see `parse`.
+    #[cfg(feature = "config")]
+    pub fn load(path: impl AsRef<Path>, profile: Option<&str>) -> Result<Self, SdkError> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path)
+            .map_err(|e| SdkError::Config(format!("cannot read {}: {}", path.display(), e)))?;
+        Self::parse(&toml, profile).map_err(|e| match e {
+            SdkError::Config(reason) => SdkError::Config(format!("{}: {}", path.display(), reason)),
+            e => e,
+        })
+    }
+
+    /// Let the `OPENAI_*` environment variables override these settings.
+    pub fn with_env(mut self) -> Self {
+        self.apply_env(|name| std::env::var(name).ok());
+        self
+    }
+
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        if let Some(api_key) = var(API_KEY_ENV) {
+            self.api_key = Some(api_key.into());
+        }
+        let fields = [
+            (BASE_URL_ENV, &mut self.base_url),
+            (ORGANIZATION_ENV, &mut self.organization),
+            (PROJECT_ENV, &mut self.project),
+        ];
+        for (name, field) in fields {
+            if let Some(value) = var(name) {
+                *field = Some(value);
+            }
+        }
+    }
+
+    /// `other`'s settings where set, these otherwise.
+    #[cfg(feature = "config")]
+    fn overridden_by(self, other: SdkSettings) -> Self {
+        Self {
+            api_key: other.api_key.or(self.api_key),
+            base_url: other.base_url.or(self.base_url),
+            organization: other.organization.or(self.organization),
+            project: other.project.or(self.project),
+            model: other.model.or(self.model),
+        }
+    }
+
+    /// Build the SDK, failing if no API key is set.
+    pub fn into_sdk(self) -> Result<LlmSdk, SdkError> {
+        let Some(api_key) = self.api_key else {
+            return Err(SdkError::Config(format!(
+                "no API key: set {} or `api_key` in the config file",
+                API_KEY_ENV
+            )));
+        };
+        let mut sdk = LlmSdk::new(api_key);
+        if let Some(base_url) = self.base_url {
+            sdk = sdk.with_base_url(base_url);
+        }
+        if let Some(organization) = self.organization {
+            sdk = sdk.with_organization(organization);
+        }
+        if let Some(project) = self.project {
+            sdk = sdk.with_project(project);
+        }
+        if let Some(model) = self.model {
+            sdk = sdk.with_default_model(model);
+        }
+        Ok(sdk)
+    }
+}
+
+#[cfg(feature = "config")]
+impl ConfigFile {
+    fn select(mut self, profile: Option<&str>) -> Result<SdkSettings, SdkError> {
+        let Some(name) = profile.map(str::to_string).or(self.default_profile.take()) else {
+            return Ok(self.settings);
+        };
+        match self.profiles.remove(&name) {
+            Some(profile) => Ok(self.settings.overridden_by(profile)),
+            None => Err(SdkError::Config(format!(
+                "no profile `{}`, expected one of: {}",
+                name,
+                self.profiles.into_keys().collect::<Vec<_>>().join(", ")
+            ))),
+        }
+    }
+}
+
+impl LlmSdk {
+    /// Create the SDK from `OPENAI_API_KEY`, and `OPENAI_BASE_URL`, `OPENAI_ORG_ID` and
+    /// `OPENAI_PROJECT_ID` if set.
+    pub fn from_env() -> Result<Self, SdkError> {
+        SdkSettings::from_env().into_sdk()
+    }
+
+    /// Create the SDK from a TOML config file, see `SdkSettings`. The profile is the one named
+    /// by `LLM_SDK_PROFILE`, or the file's `default_profile`. Environment variables override
+    /// the file, so a key can be kept out of it.
+    #[cfg(feature = "config")]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        let profile = std::env::var(PROFILE_ENV).ok();
+        Self::from_config_profile(path, profile.as_deref())
+    }
+
+    /// Like `from_config` with the given profile.
+    #[cfg(feature = "config")]
+    pub fn from_config_profile(
+        path: impl AsRef<Path>,
+        profile: Option<&str>,
+    ) -> Result<Self, SdkError> {
+        SdkSettings::load(path, profile)?.with_env().into_sdk()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    #[test]
+    fn env_should_override_settings() {
+        let mut settings = SdkSettings {
+            api_key: Some("sk-file".into()),
+            base_url: Some("http://localhost:8080/v1".to_string()),
+            ..Default::default()
+        };
+        settings.apply_env(|name| (name == API_KEY_ENV).then(|| "sk-env".to_string()));
+        assert_eq!(settings.api_key.unwrap().expose_secret(), "sk-env");
+        assert_eq!(
+            settings.base_url.as_deref(),
+            Some("http://localhost:8080/v1")
+        );
+    }
+
+    #[test]
+    fn into_sdk_should_require_an_api_key() {
+        let err = SdkSettings::default().into_sdk().unwrap_err();
+        assert!(err.to_string().contains("OPENAI_API_KEY"));
+        let sdk = SdkSettings {
+            api_key: Some("".into()),
+            model: Some(ChatCompleteModel::Other("llama3.1".to_string())),
+            ..Default::default()
+        }
+        .into_sdk()
+        .unwrap();
+        assert_eq!(
+            sdk.default_model(),
+            Some(&ChatCompleteModel::Other("llama3.1".to_string()))
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn config_should_select_profiles() -> Result<(), SdkError> {
+        let toml = r#"
+            default_profile = "openai"
+            organization = "org-shared"
+
+            [profiles.openai]
+            api_key = "sk-openai"
+            model = "gpt-4o-mini"
+
+            [profiles.local]
+            api_key = ""
+            base_url = "http://localhost:11434/v1"
+        "#;
+        let openai = SdkSettings::parse(toml, None)?;
+        assert_eq!(openai.api_key.unwrap().expose_secret(), "sk-openai");
+        assert_eq!(openai.organization.as_deref(), Some("org-shared"));
+        assert_eq!(
+            openai.model,
+            Some(ChatCompleteModel::Other("gpt-4o-mini".to_string()))
+        );
+
+        let local = SdkSettings::parse(toml, Some("local"))?;
+        assert_eq!(local.base_url.as_deref(), Some("http://localhost:11434/v1"));
+        assert_eq!(local.model, None);
+
+        let err = SdkSettings::parse(toml, Some("azure")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid configuration: no profile `azure`, expected one of: local, openai"
+        );
+        Ok(())
+    }
+}
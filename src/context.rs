@@ -0,0 +1,171 @@
+use crate::{ChatCompleteModel, ChatCompletionMessage};
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation below in a few sentences. \
+Keep the facts, decisions and open questions needed to continue it.";
+
+/// What to do when a chat completion's prompt doesn't fit the model's context window.
+///
+/// System messages and the last message are always kept. A tool result is dropped
+/// together with the assistant message that called the tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextPolicy {
+    /// Drop the oldest messages until the prompt fits.
+    TruncateOldest,
+    /// Keep only the last `n` non-system messages, then drop the oldest of those if still too long.
+    KeepSystemAndLastN(usize),
+    /// Replace the messages that have to go with a summary written by `model`, a cheap one ideally.
+    SummarizeOverflow { model: ChatCompleteModel },
+}
+
+impl ContextPolicy {
+    /// Remove messages until `fits` accepts them, returning the removed ones in order.
+    pub(crate) fn trim(
+        &self,
+        messages: &mut Vec<ChatCompletionMessage>,
+        fits: impl Fn(&[ChatCompletionMessage]) -> bool,
+    ) -> Vec<ChatCompletionMessage> {
+        let mut dropped = Vec::new();
+        if let ContextPolicy::KeepSystemAndLastN(n) = self {
+            while count_non_system(messages) > (*n).max(1) {
+                if !drop_oldest(messages, &mut dropped) {
+                    break;
+                }
+            }
+        }
+        while !fits(messages) && drop_oldest(messages, &mut dropped) {}
+        dropped
+    }
+}
+
+/// The request asking `model` to summarize the dropped messages.
+pub(crate) fn summary_messages(dropped: &[ChatCompletionMessage]) -> Vec<ChatCompletionMessage> {
+    let transcript = dropped
+        .iter()
+        .filter_map(|msg| Some(format!("{}: {}", role(msg), msg.content()?)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    vec![
+        ChatCompletionMessage::new_system(SUMMARY_PROMPT, ""),
+        ChatCompletionMessage::new_user(transcript, ""),
+    ]
+}
+
+/// Insert the summary of the dropped messages after the leading system messages.
+pub(crate) fn insert_summary(messages: &mut Vec<ChatCompletionMessage>, summary: &str) {
+    let pos = messages
+        .iter()
+        .position(|msg| !matches!(msg, ChatCompletionMessage::System(_)))
+        .unwrap_or(messages.len());
+    let content = format!("Summary of the earlier conversation: {}", summary);
+    messages.insert(pos, ChatCompletionMessage::new_system(content, ""));
+}
+
+fn role(msg: &ChatCompletionMessage) -> &'static str {
+    match msg {
+        ChatCompletionMessage::System(_) => "system",
+        ChatCompletionMessage::User(_) => "user",
+        ChatCompletionMessage::Assistant(_) => "assistant",
+        ChatCompletionMessage::Tool(_) => "tool",
+    }
+}
+
+fn count_non_system(messages: &[ChatCompletionMessage]) -> usize {
+    messages
+        .iter()
+        .filter(|msg| !matches!(msg, ChatCompletionMessage::System(_)))
+        .count()
+}
+
+/// Drop the oldest non-system message and the tool results answering it, never the last message.
+fn drop_oldest(
+    messages: &mut Vec<ChatCompletionMessage>,
+    dropped: &mut Vec<ChatCompletionMessage>,
+) -> bool {
+    let last = messages.len().saturating_sub(1);
+    let Some(pos) = messages[..last]
+        .iter()
+        .position(|msg| !matches!(msg, ChatCompletionMessage::System(_)))
+    else {
+        return false;
+    };
+    dropped.push(messages.remove(pos));
+    while pos < messages.len() - 1 && matches!(messages[pos], ChatCompletionMessage::Tool(_)) {
+        dropped.push(messages.remove(pos));
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FunctionCall, ToolCall, ToolType};
+
+    fn messages() -> Vec<ChatCompletionMessage> {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            r#type: ToolType::Function,
+            function: FunctionCall {
+                name: "get_weather".to_string(),
+                arguments: "{}".to_string(),
+            },
+        };
+        vec![
+            ChatCompletionMessage::new_system("You are helpful.", ""),
+            ChatCompletionMessage::new_user("What's the weather?", ""),
+            ChatCompletionMessage::new_assistant("", "", vec![call]),
+            ChatCompletionMessage::new_tool("sunny", "call_1"),
+            ChatCompletionMessage::new_assistant("It's sunny.", "", vec![]),
+            ChatCompletionMessage::new_user("Thanks!", ""),
+        ]
+    }
+
+    fn contents(messages: &[ChatCompletionMessage]) -> Vec<&str> {
+        messages.iter().filter_map(|msg| msg.content()).collect()
+    }
+
+    #[test]
+    fn truncate_oldest_should_drop_tool_results_with_their_call() {
+        let mut msgs = messages();
+        let dropped = ContextPolicy::TruncateOldest.trim(&mut msgs, |msgs| msgs.len() <= 4);
+        assert_eq!(
+            contents(&msgs),
+            ["You are helpful.", "It's sunny.", "Thanks!"]
+        );
+        assert_eq!(dropped.len(), 3);
+
+        let mut msgs = messages();
+        ContextPolicy::TruncateOldest.trim(&mut msgs, |_| false);
+        assert_eq!(contents(&msgs), ["You are helpful.", "Thanks!"]);
+    }
+
+    #[test]
+    fn keep_system_and_last_n_should_keep_recent_messages() {
+        let mut msgs = messages();
+        let dropped = ContextPolicy::KeepSystemAndLastN(2).trim(&mut msgs, |_| true);
+        assert_eq!(
+            contents(&msgs),
+            ["You are helpful.", "It's sunny.", "Thanks!"]
+        );
+        assert_eq!(contents(&dropped), ["What's the weather?", "sunny"]);
+    }
+
+    #[test]
+    fn summary_should_follow_system_messages() {
+        let mut msgs = messages();
+        let dropped = ContextPolicy::TruncateOldest.trim(&mut msgs, |_| false);
+        let request = summary_messages(&dropped);
+        assert_eq!(
+            request[1].content(),
+            Some("user: What's the weather?\ntool: sunny\nassistant: It's sunny.")
+        );
+        insert_summary(&mut msgs, "The user asked about the weather.");
+        assert_eq!(
+            contents(&msgs),
+            [
+                "You are helpful.",
+                "Summary of the earlier conversation: The user asked about the weather.",
+                "Thanks!"
+            ]
+        );
+    }
+}
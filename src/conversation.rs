@@ -0,0 +1,598 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{broadcast, Mutex};
+
+use crate::{
+    Budget, ChatCompletionMessage, ChatCompletionRequestBuilder, ChatCompletionResponse,
+    FunctionCall, LlmSdk, ToolCall, ToolType,
+};
+
+/// The message history of one chat session, identified by an id so it can be persisted
+/// with a `ChatStore` and resumed later.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Conversation {
+    id: String,
+    #[serde(default)]
+    messages: Vec<ChatCompletionMessage>,
+    /// Not persisted: a resumed conversation starts without a budget.
+    #[serde(skip)]
+    budget: Option<Budget>,
+}
+
+impl Conversation {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            messages: Vec::new(),
+            budget: None,
+        }
+    }
+
+    /// Start the conversation with a system message.
+    pub fn with_system(mut self, content: impl Into<String>) -> Self {
+        self.messages
+            .insert(0, ChatCompletionMessage::new_system(content, ""));
+        self
+    }
+
+    /// Cap the tokens or cost of this conversation, see `request_builder`.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn budget(&self) -> Option<&Budget> {
+        self.budget.as_ref()
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn messages(&self) -> &[ChatCompletionMessage] {
+        &self.messages
+    }
+
+    pub fn into_messages(self) -> Vec<ChatCompletionMessage> {
+        self.messages
+    }
+
+    pub fn push(&mut self, message: ChatCompletionMessage) {
+        self.messages.push(message);
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// A request builder with the history so far as its messages and the budget of the conversation.
+    pub fn request_builder(&self) -> ChatCompletionRequestBuilder {
+        let mut builder = ChatCompletionRequestBuilder::default();
+        builder.messages(self.messages.clone());
+        if let Some(budget) = &self.budget {
+            builder.budget(budget.clone());
+        }
+        builder
+    }
+}
+
+/// A format `Conversation::export` writes and `Conversation::import` reads.
+///
+/// Only `OpenAiJsonl` keeps everything; the others keep the text of each message, so images
+/// are dropped, and ShareGPT has no tool call ids, so new ones are made up on import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversationFormat {
+    /// A transcript with a `## Role` heading per message, for docs and reviews. Tool calls are
+    /// `tool_call` code blocks with the tool name and call id in the info string.
+    Markdown,
+    /// One `{"messages": [...]}` line, the chat format of the fine-tuning API.
+    OpenAiJsonl,
+    /// A `{"id": ..., "conversations": [{"from": ..., "value": ...}]}` object, as used by
+    /// open-source fine-tuning tools, with `function_call` and `observation` turns for tools.
+    ShareGpt,
+}
+
+/// A line of the fine-tuning JSONL.
+#[derive(Serialize, Deserialize)]
+struct JsonlExample {
+    messages: Vec<ChatCompletionMessage>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ShareGpt {
+    #[serde(default)]
+    id: String,
+    conversations: Vec<ShareGptTurn>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ShareGptTurn {
+    from: ShareGptRole,
+    value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ShareGptRole {
+    System,
+    #[serde(alias = "user")]
+    Human,
+    #[serde(alias = "assistant")]
+    Gpt,
+    /// `{"name": ..., "arguments": {...}}`
+    FunctionCall,
+    /// A tool result, answering the oldest unanswered function call.
+    #[serde(alias = "tool")]
+    Observation,
+}
+
+const MARKDOWN_ROLES: [&str; 4] = ["System", "User", "Assistant", "Tool"];
+const TOOL_CALL_FENCE: &str = "```tool_call ";
+
+impl Conversation {
+    /// Write the history in `format`, e.g. to reuse a chat log as a fine-tuning example.
+    pub fn export(&self, format: ConversationFormat) -> Result<String> {
+        match format {
+            ConversationFormat::Markdown => Ok(self.to_markdown()),
+            ConversationFormat::OpenAiJsonl => {
+                let example = JsonlExample {
+                    messages: self.messages.clone(),
+                };
+                Ok(serde_json::to_string(&example)? + "\n")
+            }
+            ConversationFormat::ShareGpt => {
+                let sharegpt = ShareGpt {
+                    id: self.id.clone(),
+                    conversations: self.to_sharegpt_turns(),
+                };
+                Ok(serde_json::to_string_pretty(&sharegpt)?)
+            }
+        }
+    }
+
+    /// Read a conversation written in `format`, the inverse of `export`.
+    pub fn import(id: impl Into<String>, input: &str, format: ConversationFormat) -> Result<Self> {
+        let messages = match format {
+            ConversationFormat::Markdown => parse_markdown(input)?,
+            ConversationFormat::OpenAiJsonl => {
+                let lines: Vec<_> = input.lines().filter(|l| !l.trim().is_empty()).collect();
+                let [line] = lines[..] else {
+                    bail!("expected one JSONL line, got {}", lines.len());
+                };
+                serde_json::from_str::<JsonlExample>(line)?.messages
+            }
+            ConversationFormat::ShareGpt => {
+                parse_sharegpt_turns(serde_json::from_str::<ShareGpt>(input)?.conversations)?
+            }
+        };
+        let mut conversation = Self::new(id);
+        conversation.messages = messages;
+        Ok(conversation)
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut sections = Vec::with_capacity(self.messages.len());
+        for message in &self.messages {
+            let (role, label, tool_calls) = match message {
+                ChatCompletionMessage::System(_) => ("System", message.name(), &[][..]),
+                ChatCompletionMessage::User(_) => ("User", message.name(), &[][..]),
+                ChatCompletionMessage::Assistant(msg) => {
+                    ("Assistant", message.name(), msg.tool_calls())
+                }
+                ChatCompletionMessage::Tool(msg) => ("Tool", Some(msg.tool_call_id()), &[][..]),
+            };
+            let mut section = match label {
+                Some(label) => format!("## {} ({})\n", role, label),
+                None => format!("## {}\n", role),
+            };
+            if let Some(content) = message.content() {
+                section.push_str(&format!("\n{}\n", content));
+            }
+            for call in tool_calls {
+                section.push_str(&format!(
+                    "\n{}{} {}\n{}\n```\n",
+                    TOOL_CALL_FENCE, call.function.name, call.id, call.function.arguments
+                ));
+            }
+            sections.push(section);
+        }
+        sections.join("\n")
+    }
+
+    fn to_sharegpt_turns(&self) -> Vec<ShareGptTurn> {
+        let mut turns = Vec::new();
+        let mut push = |from, value: Option<&str>| {
+            turns.push(ShareGptTurn {
+                from,
+                value: value.unwrap_or_default().to_string(),
+            })
+        };
+        for message in &self.messages {
+            match message {
+                ChatCompletionMessage::System(_) => push(ShareGptRole::System, message.content()),
+                ChatCompletionMessage::User(_) => push(ShareGptRole::Human, message.content()),
+                ChatCompletionMessage::Tool(_) => {
+                    push(ShareGptRole::Observation, message.content())
+                }
+                ChatCompletionMessage::Assistant(msg) => {
+                    if msg.content().is_some() || msg.tool_calls().is_empty() {
+                        push(ShareGptRole::Gpt, msg.content());
+                    }
+                    for call in msg.tool_calls() {
+                        // arguments are sent as an object when they are valid JSON
+                        let arguments = serde_json::from_str(&call.function.arguments)
+                            .unwrap_or_else(|_| Value::String(call.function.arguments.clone()));
+                        let value = serde_json::json!({
+                            "name": call.function.name,
+                            "arguments": arguments,
+                        });
+                        push(ShareGptRole::FunctionCall, Some(&value.to_string()));
+                    }
+                }
+            }
+        }
+        turns
+    }
+}
+
+fn parse_markdown(input: &str) -> Result<Vec<ChatCompletionMessage>> {
+    let mut sections: Vec<(&str, Option<&str>, Vec<&str>)> = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in input.lines().enumerate() {
+        let heading = (!in_fence).then(|| parse_heading(line)).flatten();
+        if line.starts_with("```") {
+            in_fence = !in_fence;
+        }
+        match (heading, sections.last_mut()) {
+            (Some((role, label)), _) => sections.push((role, label, Vec::new())),
+            (None, Some((_, _, body))) => body.push(line),
+            (None, None) if line.trim().is_empty() => {}
+            (None, None) => bail!("line {}: expected a `## Role` heading", i + 1),
+        }
+    }
+
+    let mut messages = Vec::with_capacity(sections.len());
+    for (role, label, body) in sections {
+        let name = label.unwrap_or_default();
+        let message = match role {
+            "System" => ChatCompletionMessage::new_system(join_body(&body), name),
+            "User" => ChatCompletionMessage::new_user(join_body(&body), name),
+            "Tool" => ChatCompletionMessage::new_tool(join_body(&body), name),
+            _ => {
+                let (content, tool_calls) = parse_tool_calls(&body)?;
+                ChatCompletionMessage::new_assistant(content, name, tool_calls)
+            }
+        };
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+/// `## User` or `## User (alice)`.
+fn parse_heading(line: &str) -> Option<(&str, Option<&str>)> {
+    let heading = line.strip_prefix("## ")?.trim_end();
+    let (role, label) = match heading.split_once(' ') {
+        Some((role, label)) => {
+            let label = label.strip_prefix('(')?.strip_suffix(')')?;
+            (role, Some(label))
+        }
+        None => (heading, None),
+    };
+    MARKDOWN_ROLES.contains(&role).then_some((role, label))
+}
+
+/// Split an assistant section into its text and its `tool_call` code blocks.
+fn parse_tool_calls(body: &[&str]) -> Result<(String, Vec<ToolCall>)> {
+    let mut text = Vec::new();
+    let mut tool_calls = Vec::new();
+    let mut lines = body.iter();
+    while let Some(line) = lines.next() {
+        let Some(info) = line.strip_prefix(TOOL_CALL_FENCE) else {
+            text.push(*line);
+            continue;
+        };
+        let (name, id) = info
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| anyhow!("`{}` must have a tool name and a call id", line))?;
+        let arguments: Vec<_> = lines
+            .by_ref()
+            .take_while(|line| **line != "```")
+            .copied()
+            .collect();
+        tool_calls.push(ToolCall {
+            id: id.to_string(),
+            r#type: ToolType::Function,
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.join("\n"),
+            },
+        });
+    }
+    Ok((join_body(&text), tool_calls))
+}
+
+/// The lines of a section, without the blank lines around them.
+fn join_body(lines: &[&str]) -> String {
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+fn parse_sharegpt_turns(turns: Vec<ShareGptTurn>) -> Result<Vec<ChatCompletionMessage>> {
+    #[derive(Deserialize)]
+    struct Call {
+        name: String,
+        #[serde(default)]
+        arguments: Value,
+    }
+
+    let mut messages = Vec::with_capacity(turns.len());
+    // the assistant message being built from a `gpt` turn and the function calls after it
+    let mut assistant: Option<(String, Vec<ToolCall>)> = None;
+    let mut unanswered = VecDeque::new();
+    let flush = |assistant: &mut Option<(String, Vec<ToolCall>)>,
+                 messages: &mut Vec<ChatCompletionMessage>| {
+        if let Some((content, tool_calls)) = assistant.take() {
+            messages.push(ChatCompletionMessage::new_assistant(
+                content, "", tool_calls,
+            ));
+        }
+    };
+    for (i, turn) in turns.into_iter().enumerate() {
+        if turn.from != ShareGptRole::FunctionCall {
+            flush(&mut assistant, &mut messages);
+        }
+        match turn.from {
+            ShareGptRole::System => {
+                messages.push(ChatCompletionMessage::new_system(turn.value, ""))
+            }
+            ShareGptRole::Human => messages.push(ChatCompletionMessage::new_user(turn.value, "")),
+            ShareGptRole::Gpt => assistant = Some((turn.value, Vec::new())),
+            ShareGptRole::FunctionCall => {
+                let call: Call = serde_json::from_str(&turn.value)
+                    .map_err(|e| anyhow!("turn {}: invalid function call: {}", i, e))?;
+                let id = format!("call_{}", i);
+                unanswered.push_back(id.clone());
+                let arguments = match call.arguments {
+                    Value::String(arguments) => arguments,
+                    arguments => arguments.to_string(),
+                };
+                let (_, tool_calls) = assistant.get_or_insert_with(Default::default);
+                tool_calls.push(ToolCall {
+                    id,
+                    r#type: ToolType::Function,
+                    function: FunctionCall {
+                        name: call.name,
+                        arguments,
+                    },
+                });
+            }
+            ShareGptRole::Observation => {
+                let id = unanswered
+                    .pop_front()
+                    .ok_or_else(|| anyhow!("turn {}: observation without a function call", i))?;
+                messages.push(ChatCompletionMessage::new_tool(turn.value, id));
+            }
+        }
+    }
+    flush(&mut assistant, &mut messages);
+    Ok(messages)
+}
+
+/// A message added to a `SharedConversation`, sent to every subscriber.
+#[derive(Debug, Clone)]
+pub struct ConversationEvent {
+    /// Position of the message in the history.
+    pub index: usize,
+    pub message: ChatCompletionMessage,
+}
+
+/// A `Conversation` that can be used from several tasks at once.
+///
+/// Clones share the same history. Completions are serialized: while one turn waits for the
+/// model, other turns wait for it to finish, so every reply sees the history it answers.
+/// Observers such as a UI layer can `subscribe` to every message as it is added.
+#[derive(Debug, Clone)]
+pub struct SharedConversation {
+    inner: Arc<SharedState>,
+}
+
+#[derive(Debug)]
+struct SharedState {
+    conversation: Mutex<Conversation>,
+    events: broadcast::Sender<ConversationEvent>,
+}
+
+/// Events kept for a subscriber that falls behind, older ones are reported as lagged.
+const EVENT_CAPACITY: usize = 64;
+
+impl SharedConversation {
+    pub fn new(conversation: Conversation) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            inner: Arc::new(SharedState {
+                conversation: Mutex::new(conversation),
+                events,
+            }),
+        }
+    }
+
+    /// Receive every message added from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<ConversationEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// A copy of the conversation, e.g. to save it with a `ChatStore`. Waits for an in-flight turn.
+    pub async fn snapshot(&self) -> Conversation {
+        self.inner.conversation.lock().await.clone()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.inner.conversation.lock().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.inner.conversation.lock().await.is_empty()
+    }
+
+    /// Append a message without asking for a reply, e.g. a tool result.
+    pub async fn push(&self, message: ChatCompletionMessage) {
+        let mut conversation = self.inner.conversation.lock().await;
+        self.append(&mut conversation, message);
+    }
+
+    /// Append a user message and ask the model to reply to it, see `complete`.
+    ///
+    /// If the completion fails the user message stays in the history, so the turn can be
+    /// retried with `complete`.
+    pub async fn send(
+        &self,
+        sdk: &LlmSdk,
+        message: ChatCompletionMessage,
+        configure: impl FnOnce(&mut ChatCompletionRequestBuilder),
+    ) -> Result<ChatCompletionResponse> {
+        let mut conversation = self.inner.conversation.lock().await;
+        self.append(&mut conversation, message);
+        self.complete_locked(&mut conversation, sdk, configure)
+            .await
+    }
+
+    /// Ask the model to reply to the history so far and append its reply.
+    ///
+    /// `configure` sets the model and other options of the request; its messages are the history.
+    /// The conversation stays locked until the reply has been appended.
+    pub async fn complete(
+        &self,
+        sdk: &LlmSdk,
+        configure: impl FnOnce(&mut ChatCompletionRequestBuilder),
+    ) -> Result<ChatCompletionResponse> {
+        let mut conversation = self.inner.conversation.lock().await;
+        self.complete_locked(&mut conversation, sdk, configure)
+            .await
+    }
+
+    async fn complete_locked(
+        &self,
+        conversation: &mut Conversation,
+        sdk: &LlmSdk,
+        configure: impl FnOnce(&mut ChatCompletionRequestBuilder),
+    ) -> Result<ChatCompletionResponse> {
+        let mut builder = conversation.request_builder();
+        configure(&mut builder);
+        let res = sdk.chat_completion(builder.build()?).await?;
+        if let Some(message) = res.clone().into_assistant_message() {
+            self.append(conversation, message);
+        }
+        Ok(res)
+    }
+
+    fn append(&self, conversation: &mut Conversation, message: ChatCompletionMessage) {
+        let index = conversation.len();
+        conversation.push(message.clone());
+        // no subscribers is not an error
+        let _ = self.inner.events.send(ConversationEvent { index, message });
+    }
+}
+
+impl From<Conversation> for SharedConversation {
+    fn from(conversation: Conversation) -> Self {
+        Self::new(conversation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Conversation {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            r#type: ToolType::Function,
+            function: FunctionCall {
+                name: "get_weather".to_string(),
+                arguments: r#"{"city":"Paris"}"#.to_string(),
+            },
+        };
+        let mut conversation = Conversation::new("c1").with_system("You are terse.");
+        conversation.push(ChatCompletionMessage::new_user(
+            "Weather in Paris?",
+            "alice",
+        ));
+        conversation.push(ChatCompletionMessage::new_assistant("", "", vec![call]));
+        conversation.push(ChatCompletionMessage::new_tool("Sunny, 21°C", "call_1"));
+        conversation.push(ChatCompletionMessage::new_assistant(
+            "Sunny.\n\n## Not a heading, as it has no role",
+            "",
+            vec![],
+        ));
+        conversation
+    }
+
+    fn roundtrip(format: ConversationFormat) -> Result<Vec<Value>> {
+        let exported = conversation().export(format)?;
+        let imported = Conversation::import("c1", &exported, format)?;
+        Ok(imported
+            .messages()
+            .iter()
+            .map(|msg| serde_json::to_value(msg).unwrap())
+            .collect())
+    }
+
+    #[test]
+    fn conversation_should_export_markdown() -> Result<()> {
+        let markdown = conversation().export(ConversationFormat::Markdown)?;
+        assert_eq!(
+            markdown,
+            "## System\n\nYou are terse.\n\n## User (alice)\n\nWeather in Paris?\n\n\
+             ## Assistant\n\n```tool_call get_weather call_1\n{\"city\":\"Paris\"}\n```\n\n\
+             ## Tool (call_1)\n\nSunny, 21°C\n\n\
+             ## Assistant\n\nSunny.\n\n## Not a heading, as it has no role\n"
+        );
+        let err = Conversation::import("c1", "Hi", ConversationFormat::Markdown).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: expected a `## Role` heading");
+        Ok(())
+    }
+
+    #[test]
+    fn conversation_should_roundtrip_every_format() -> Result<()> {
+        let original: Vec<_> = conversation()
+            .messages()
+            .iter()
+            .map(|msg| serde_json::to_value(msg).unwrap())
+            .collect();
+        assert_eq!(roundtrip(ConversationFormat::Markdown)?, original);
+        assert_eq!(roundtrip(ConversationFormat::OpenAiJsonl)?, original);
+
+        // ShareGPT has no names or call ids
+        let sharegpt = roundtrip(ConversationFormat::ShareGpt)?;
+        assert_eq!(sharegpt.len(), original.len());
+        assert_eq!(sharegpt[1]["content"], "Weather in Paris?");
+        assert_eq!(
+            sharegpt[2]["tool_calls"][0]["id"],
+            sharegpt[3]["tool_call_id"]
+        );
+        assert_eq!(
+            sharegpt[2]["tool_calls"][0]["function"],
+            original[2]["tool_calls"][0]["function"]
+        );
+        let exported: Value =
+            serde_json::from_str(&conversation().export(ConversationFormat::ShareGpt)?)?;
+        let from: Vec<_> = exported["conversations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|turn| turn["from"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            from,
+            ["system", "human", "function_call", "observation", "gpt"]
+        );
+        Ok(())
+    }
+}
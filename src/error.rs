@@ -0,0 +1,238 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+use crate::{BudgetLimit, ValidationError};
+
+/// Errors raised by the SDK itself, as opposed to transport or serde errors.
+///
+/// They are returned inside `anyhow::Error`; use `downcast_ref::<SdkError>()` to branch on them.
+#[derive(Debug, Error)]
+pub enum SdkError {
+    /// The API answered with an error status.
+    #[error("API error ({status}): {}", error.message)]
+    Api {
+        status: StatusCode,
+        /// The `x-request-id` header, to quote when contacting support.
+        request_id: Option<String>,
+        error: ApiErrorBody,
+    },
+    /// The server answered with something other than the expected payload,
+    /// e.g. an HTML error page from a proxy or captive portal.
+    #[error("expected `{expected}` response but got `{content_type}` ({status}): {snippet}")]
+    UnexpectedContentType {
+        status: StatusCode,
+        expected: &'static str,
+        content_type: String,
+        /// The beginning of the response body.
+        snippet: String,
+    },
+    /// A streamed response stopped before the model finished, e.g. because the connection dropped.
+    #[error("stream interrupted after {} bytes of content: {reason}", partial.len())]
+    StreamInterrupted {
+        /// The content received so far, usable as an assistant prefix to resume the generation.
+        partial: String,
+        reason: String,
+    },
+    /// The model never replied with JSON matching the expected type, see `LlmSdk::extract`.
+    #[error("no valid reply after {attempts} attempts: {reason}")]
+    Extraction {
+        attempts: usize,
+        /// Why the last reply was rejected.
+        reason: String,
+        /// The last reply.
+        content: String,
+    },
+    /// The moderations endpoint flagged the prompt, see `CreateImageRequestBuilder::moderate_prompt`.
+    #[error("prompt blocked by moderation: {}", categories.join(", "))]
+    BlockedByModeration {
+        /// The flagged categories, e.g. `violence`.
+        categories: Vec<String>,
+        /// The score of every category, flagged or not.
+        scores: BTreeMap<String, f64>,
+    },
+    /// Sending the request could break a `Budget` limit, so it wasn't sent.
+    #[error("{limit} budget exceeded: {needed} needed, {max} allowed")]
+    BudgetExceeded {
+        limit: BudgetLimit,
+        /// What the limit would reach with this request, in tokens or USD.
+        needed: f64,
+        max: f64,
+    },
+    /// A `Budget` limits the cost, but has no price for the model of the request, so it
+    /// wasn't sent.
+    #[error("no price for model {model} to check the cost budget")]
+    UnknownPrice { model: String },
+    /// A `VcrClient` replaying a cassette got a request that wasn't recorded.
+    #[error("no recorded response for {method} {url}")]
+    CassetteMiss { method: String, url: String },
+    /// The request body is over the limit set with `LlmSdk::with_max_request_body`, so it
+    /// wasn't sent. Trim the message history, e.g. with a `ContextPolicy`.
+    #[error("request body of {size} bytes is over the {max} byte limit")]
+    RequestTooLarge { size: usize, max: usize },
+    /// The endpoint failed too often recently, so the request wasn't sent, see `CircuitBreaker`.
+    #[error("circuit for {circuit} is open, retry in {retry_after:?}")]
+    CircuitOpen {
+        circuit: String,
+        retry_after: Duration,
+    },
+    /// The request couldn't be answered before the deadline set with `LlmSdk::with_deadline`,
+    /// so it was abandoned, or never sent if the deadline had passed.
+    #[error("deadline exceeded after {elapsed:?}")]
+    DeadlineExceeded {
+        /// The time spent on the request, waiting in a queue and retrying included.
+        elapsed: Duration,
+    },
+    /// The SDK couldn't be created from the environment or a config file.
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    /// The request was rejected locally before being sent.
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+}
+
+/// The `error` object of an API error response.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiErrorBody {
+    pub message: String,
+    /// The error category, e.g. `invalid_request_error`.
+    #[serde(default)]
+    pub r#type: Option<String>,
+    /// The request parameter the error is about.
+    #[serde(default)]
+    pub param: Option<String>,
+    /// A machine-readable code, see `known_code`.
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub code: Option<String>,
+}
+
+/// Error codes worth branching on. Anything else is left as the raw `ApiErrorBody::code`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KnownErrorCode {
+    /// The prompt and max_tokens don't fit in the model's context window.
+    ContextLengthExceeded,
+    RateLimitExceeded,
+    /// The account is out of credits.
+    InsufficientQuota,
+    InvalidApiKey,
+    /// The model doesn't exist or the key has no access to it.
+    ModelNotFound,
+    /// The prompt or the generated content was rejected by the safety system.
+    ContentPolicyViolation,
+    UnsupportedParameter,
+    UnsupportedValue,
+    ServerError,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorResponse {
+    error: ApiErrorBody,
+}
+
+impl SdkError {
+    /// The known code of an API error.
+    pub fn code(&self) -> Option<KnownErrorCode> {
+        match self {
+            SdkError::Api { error, .. } => error.known_code(),
+            _ => None,
+        }
+    }
+}
+
+impl ApiErrorBody {
+    /// Parse an error response body; the beginning of a body not in the OpenAI format, such
+    /// as a proxy's HTML page, becomes the message.
+    pub(crate) fn parse(body: &str) -> Self {
+        match serde_json::from_str::<ApiErrorResponse>(body) {
+            Ok(res) => res.error,
+            Err(_) => Self {
+                message: body.trim().chars().take(crate::SNIPPET_LEN).collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The code, if it is one of the known ones. Falls back to the type, which some
+    /// errors such as `insufficient_quota` use instead of the code.
+    pub fn known_code(&self) -> Option<KnownErrorCode> {
+        self.code
+            .as_deref()
+            .and_then(KnownErrorCode::from_code)
+            .or_else(|| self.r#type.as_deref().and_then(KnownErrorCode::from_code))
+    }
+}
+
+impl KnownErrorCode {
+    pub fn from_code(code: &str) -> Option<Self> {
+        Some(match code {
+            "context_length_exceeded" => KnownErrorCode::ContextLengthExceeded,
+            "rate_limit_exceeded" => KnownErrorCode::RateLimitExceeded,
+            "insufficient_quota" => KnownErrorCode::InsufficientQuota,
+            "invalid_api_key" => KnownErrorCode::InvalidApiKey,
+            "model_not_found" => KnownErrorCode::ModelNotFound,
+            "content_policy_violation" => KnownErrorCode::ContentPolicyViolation,
+            "unsupported_parameter" => KnownErrorCode::UnsupportedParameter,
+            "unsupported_value" => KnownErrorCode::UnsupportedValue,
+            "server_error" => KnownErrorCode::ServerError,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KnownErrorCode::ContextLengthExceeded => "context_length_exceeded",
+            KnownErrorCode::RateLimitExceeded => "rate_limit_exceeded",
+            KnownErrorCode::InsufficientQuota => "insufficient_quota",
+            KnownErrorCode::InvalidApiKey => "invalid_api_key",
+            KnownErrorCode::ModelNotFound => "model_not_found",
+            KnownErrorCode::ContentPolicyViolation => "content_policy_violation",
+            KnownErrorCode::UnsupportedParameter => "unsupported_parameter",
+            KnownErrorCode::UnsupportedValue => "unsupported_value",
+            KnownErrorCode::ServerError => "server_error",
+        }
+    }
+}
+
+/// Some compatible servers send numeric codes.
+fn string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::String(code)) => Some(code),
+            Some(serde_json::Value::Number(code)) => Some(code.to_string()),
+            _ => None,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_error_body_should_parse_known_codes() {
+        let error = ApiErrorBody::parse(
+            r#"{"error":{"message":"This model's maximum context length is 8192 tokens.","type":"invalid_request_error","param":"messages","code":"context_length_exceeded"}}"#,
+        );
+        assert_eq!(error.param.as_deref(), Some("messages"));
+        assert_eq!(
+            error.known_code(),
+            Some(KnownErrorCode::ContextLengthExceeded)
+        );
+
+        let error = ApiErrorBody::parse(
+            r#"{"error":{"message":"You exceeded your current quota.","type":"insufficient_quota","param":null,"code":null}}"#,
+        );
+        assert_eq!(error.known_code(), Some(KnownErrorCode::InsufficientQuota));
+
+        let error = ApiErrorBody::parse(r#"{"error":{"message":"Bad request","code":400}}"#);
+        assert_eq!(error.code.as_deref(), Some("400"));
+        assert_eq!(error.known_code(), None);
+
+        let error = ApiErrorBody::parse("upstream connect error");
+        assert_eq!(error.message, "upstream connect error");
+    }
+}
@@ -0,0 +1,410 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use anyhow::Result;
+use serde::Serialize;
+use web_time::Instant;
+
+use crate::{
+    ChatCompleteModel, ChatCompletionMessage, ChatCompletionRequestBuilder, ChatCompletionResponse,
+    LlmSdk,
+};
+
+const DEFAULT_SEED: i64 = 42;
+const GRADER_PROMPT: &str = "You grade answers against criteria. \
+Reply with PASS if the answer meets every criterion, otherwise reply with FAIL.";
+const CSV_HEADER: &str =
+    "case,model,passed,latency_ms,prompt_tokens,completion_tokens,cost,error,output";
+
+/// How the reply to an `EvalCase` is judged.
+#[derive(Clone)]
+pub enum Expected {
+    /// The reply, with surrounding whitespace trimmed, equals the text.
+    Exact(String),
+    /// The reply contains the text, ignoring case.
+    Contains(String),
+    /// The matcher returns true for the reply.
+    Matches(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+    /// The grader model of the suite judges the reply against these criteria.
+    Graded(String),
+}
+
+/// A prompt and what its reply is expected to be.
+#[derive(Debug, Clone)]
+pub struct EvalCase {
+    pub name: String,
+    pub messages: Vec<ChatCompletionMessage>,
+    pub expected: Expected,
+}
+
+/// Runs eval cases against one or more models and reports how they did.
+///
+/// Every request is sent with temperature 0 and the same seed, so reruns of a suite are as
+/// reproducible as the provider allows.
+#[derive(Debug, Clone)]
+pub struct EvalSuite {
+    sdk: LlmSdk,
+    cases: Vec<EvalCase>,
+    models: Vec<ChatCompleteModel>,
+    seed: i64,
+    grader: ChatCompleteModel,
+}
+
+/// The outcome of one case on one model.
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalResult {
+    pub case: String,
+    pub model: String,
+    pub passed: bool,
+    /// The text of the reply, empty if the request failed.
+    pub output: String,
+    /// Why the case couldn't be run, e.g. an API error.
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Cost in USD, from the provider or the price table of the SDK's usage tracker.
+    pub cost: Option<f64>,
+}
+
+/// Aggregated results of one model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EvalSummary {
+    pub model: String,
+    pub cases: usize,
+    pub passed: usize,
+    pub pass_rate: f64,
+    pub avg_latency_ms: f64,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EvalReport {
+    pub results: Vec<EvalResult>,
+}
+
+impl Expected {
+    /// Whether `output` passes, `None` for graded expectations.
+    pub fn check(&self, output: &str) -> Option<bool> {
+        match self {
+            Expected::Exact(text) => Some(output.trim() == text.as_str()),
+            Expected::Contains(text) => Some(output.to_lowercase().contains(&text.to_lowercase())),
+            Expected::Matches(matcher) => Some(matcher(output)),
+            Expected::Graded(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Exact(text) => f.debug_tuple("Exact").field(text).finish(),
+            Expected::Contains(text) => f.debug_tuple("Contains").field(text).finish(),
+            Expected::Matches(_) => f.write_str("Matches(..)"),
+            Expected::Graded(criteria) => f.debug_tuple("Graded").field(criteria).finish(),
+        }
+    }
+}
+
+impl EvalCase {
+    pub fn new(
+        name: impl Into<String>,
+        messages: Vec<ChatCompletionMessage>,
+        expected: Expected,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            messages,
+            expected,
+        }
+    }
+
+    /// A case with `prompt` as the only message.
+    pub fn prompt(name: impl Into<String>, prompt: impl Into<String>, expected: Expected) -> Self {
+        Self::new(
+            name,
+            vec![ChatCompletionMessage::new_user(prompt.into(), "")],
+            expected,
+        )
+    }
+}
+
+impl EvalSuite {
+    /// A suite running `cases` on the default model, graded by the default model.
+    pub fn new(sdk: LlmSdk, cases: Vec<EvalCase>) -> Self {
+        Self {
+            sdk,
+            cases,
+            models: vec![ChatCompleteModel::default()],
+            seed: DEFAULT_SEED,
+            grader: ChatCompleteModel::default(),
+        }
+    }
+
+    pub fn with_models(mut self, models: impl IntoIterator<Item = ChatCompleteModel>) -> Self {
+        self.models = models.into_iter().collect();
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The model judging `Expected::Graded` cases.
+    pub fn with_grader(mut self, grader: ChatCompleteModel) -> Self {
+        self.grader = grader;
+        self
+    }
+
+    /// Run every case on every model, one request at a time.
+    ///
+    /// A failing request fails its case instead of aborting the run.
+    pub async fn run(&self) -> Result<EvalReport> {
+        let mut results = Vec::with_capacity(self.models.len() * self.cases.len());
+        for model in &self.models {
+            for case in &self.cases {
+                results.push(self.run_case(model, case).await);
+            }
+        }
+        Ok(EvalReport { results })
+    }
+
+    async fn run_case(&self, model: &ChatCompleteModel, case: &EvalCase) -> EvalResult {
+        let mut result = EvalResult {
+            case: case.name.clone(),
+            model: String::from(model.clone()),
+            passed: false,
+            output: String::new(),
+            error: None,
+            latency_ms: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            cost: None,
+        };
+        let start = Instant::now();
+        let res = match self.complete(model.clone(), case.messages.clone()).await {
+            Ok(res) => res,
+            Err(e) => {
+                result.error = Some(e.to_string());
+                return result;
+            }
+        };
+        result.latency_ms = start.elapsed().as_millis() as u64;
+        result.output = res.text().unwrap_or_default().to_string();
+        result.prompt_tokens = res.usage.prompt_tokens;
+        result.completion_tokens = res.usage.completion_tokens;
+        result.cost = res.usage.cost.or_else(|| {
+            self.sdk.usage_tracker().and_then(|tracker| {
+                tracker.estimate_cost(
+                    &res.model,
+                    res.usage.prompt_tokens,
+                    res.usage.completion_tokens,
+                )
+            })
+        });
+        match case.expected.check(&result.output) {
+            Some(passed) => result.passed = passed,
+            None => match self.grade(&case.expected, &result.output).await {
+                Ok(passed) => result.passed = passed,
+                Err(e) => result.error = Some(format!("grader failed: {}", e)),
+            },
+        }
+        result
+    }
+
+    async fn grade(&self, expected: &Expected, output: &str) -> Result<bool> {
+        let Expected::Graded(criteria) = expected else {
+            unreachable!("only graded expectations need a grader")
+        };
+        let prompt = format!("Criteria:\n{}\n\nAnswer:\n{}", criteria, output);
+        let messages = vec![
+            ChatCompletionMessage::new_system(GRADER_PROMPT, ""),
+            ChatCompletionMessage::new_user(prompt, ""),
+        ];
+        let res = self.complete(self.grader.clone(), messages).await?;
+        let verdict = res.text().unwrap_or_default().trim().to_uppercase();
+        Ok(verdict.starts_with("PASS"))
+    }
+
+    async fn complete(
+        &self,
+        model: ChatCompleteModel,
+        messages: Vec<ChatCompletionMessage>,
+    ) -> Result<ChatCompletionResponse> {
+        let req = ChatCompletionRequestBuilder::default()
+            .model(model)
+            .messages(messages)
+            .temperature(0.0)
+            .seed(self.seed)
+            .build()?;
+        self.sdk.chat_completion(req).await
+    }
+}
+
+impl EvalReport {
+    /// One summary per model, in model name order.
+    pub fn summary(&self) -> Vec<EvalSummary> {
+        let mut models: BTreeMap<&str, EvalSummary> = BTreeMap::new();
+        for result in &self.results {
+            let summary = models.entry(&result.model).or_insert_with(|| EvalSummary {
+                model: result.model.clone(),
+                ..Default::default()
+            });
+            summary.cases += 1;
+            summary.passed += result.passed as usize;
+            summary.avg_latency_ms += result.latency_ms as f64;
+            summary.prompt_tokens += result.prompt_tokens;
+            summary.completion_tokens += result.completion_tokens;
+            summary.cost += result.cost.unwrap_or_default();
+        }
+        models
+            .into_values()
+            .map(|mut summary| {
+                summary.pass_rate = summary.passed as f64 / summary.cases as f64;
+                summary.avg_latency_ms /= summary.cases as f64;
+                summary
+            })
+            .collect()
+    }
+
+    /// The pass rate across all models and cases.
+    pub fn pass_rate(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        let passed = self.results.iter().filter(|r| r.passed).count();
+        passed as f64 / self.results.len() as f64
+    }
+
+    /// The summary and every result as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        let report = serde_json::json!({
+            "summary": self.summary(),
+            "results": self.results,
+        });
+        Ok(serde_json::to_string_pretty(&report)?)
+    }
+
+    /// One CSV row per result, with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for r in &self.results {
+            let row = [
+                csv_field(&r.case),
+                csv_field(&r.model),
+                r.passed.to_string(),
+                r.latency_ms.to_string(),
+                r.prompt_tokens.to_string(),
+                r.completion_tokens.to_string(),
+                r.cost.map(|cost| cost.to_string()).unwrap_or_default(),
+                csv_field(r.error.as_deref().unwrap_or_default()),
+                csv_field(&r.output),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quote a field if it contains a separator, quote or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{completion_json, ScriptedClient};
+
+    #[test]
+    fn expected_should_check_output() {
+        assert_eq!(Expected::Exact("4".into()).check(" 4\n"), Some(true));
+        assert_eq!(Expected::Exact("4".into()).check("four"), Some(false));
+        assert_eq!(
+            Expected::Contains("paris".into()).check("It's Paris."),
+            Some(true)
+        );
+        let is_number = Expected::Matches(Arc::new(|s| s.trim().parse::<f64>().is_ok()));
+        assert_eq!(is_number.check("3.14"), Some(true));
+        assert_eq!(Expected::Graded("polite".into()).check("hi"), None);
+    }
+
+    #[tokio::test]
+    async fn eval_suite_should_run_cases_with_fixed_seed() -> Result<()> {
+        let client = ScriptedClient::replying(
+            ["4", "Lyon", "Thank you kindly!", "PASS"]
+                .map(|reply| completion_json(reply, "stop", (10, 2))),
+        );
+        let bodies = client.bodies.clone();
+        let sdk = LlmSdk::new("sk-test".to_string()).with_http_client(client);
+        let cases = vec![
+            EvalCase::prompt("math", "What is 2 + 2?", Expected::Exact("4".into())),
+            EvalCase::prompt(
+                "capital",
+                "What is the capital of France?",
+                Expected::Contains("paris".into()),
+            ),
+            EvalCase::prompt(
+                "polite",
+                "Say thanks.",
+                Expected::Graded("The answer is polite.".into()),
+            ),
+        ];
+        let report = EvalSuite::new(sdk, cases).with_seed(7).run().await?;
+
+        let passed: Vec<_> = report.results.iter().map(|r| r.passed).collect();
+        assert_eq!(passed, [true, false, true]);
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 4);
+        assert!(bodies
+            .iter()
+            .all(|body| body["seed"] == 7 && body["temperature"] == 0.0));
+        assert!(bodies[3]["messages"][1]["content"]
+            .as_str()
+            .unwrap()
+            .contains("Thank you kindly!"));
+
+        let summary = report.summary();
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].model, "gpt-3.5-turbo-1106");
+        assert_eq!(summary[0].passed, 2);
+        assert_eq!(summary[0].prompt_tokens, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn eval_report_should_write_csv_and_json() -> Result<()> {
+        let report = EvalReport {
+            results: vec![EvalResult {
+                case: "quote".to_string(),
+                model: "gpt-4".to_string(),
+                passed: false,
+                output: "He said \"hi\", then left".to_string(),
+                error: None,
+                latency_ms: 120,
+                prompt_tokens: 5,
+                completion_tokens: 7,
+                cost: Some(0.5),
+            }],
+        };
+        assert_eq!(
+            report.to_csv(),
+            format!(
+                "{}\nquote,gpt-4,false,120,5,7,0.5,,\"He said \"\"hi\"\", then left\"\n",
+                CSV_HEADER
+            )
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()?)?;
+        assert_eq!(json["summary"][0]["pass_rate"], 0.0);
+        assert_eq!(json["results"][0]["latency_ms"], 120);
+        Ok(())
+    }
+}
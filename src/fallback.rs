@@ -0,0 +1,86 @@
+use crate::{ChatCompleteModel, KnownErrorCode, SdkError};
+
+/// Models to try, in order, when a chat completion fails in a way another model may not.
+///
+/// The next model is tried when the API reports that the model doesn't exist, that the prompt
+/// doesn't fit in its context window, or answers with a 429 or a 5xx status once any key pool
+/// retries are exhausted. Other errors are returned right away. Set one on the SDK with
+/// `LlmSdk::with_fallback` or on a request with `ChatCompletionRequestBuilder::fallback`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FallbackPolicy {
+    models: Vec<ChatCompleteModel>,
+}
+
+impl FallbackPolicy {
+    pub fn new(models: impl IntoIterator<Item = ChatCompleteModel>) -> Self {
+        Self {
+            models: models.into_iter().collect(),
+        }
+    }
+
+    /// The fallback models, tried after the model of the request.
+    pub fn models(&self) -> &[ChatCompleteModel] {
+        &self.models
+    }
+
+    /// Whether `err` is worth retrying with the next model.
+    pub fn should_fall_back(err: &anyhow::Error) -> bool {
+        let Some(SdkError::Api { status, error, .. }) = err.downcast_ref::<SdkError>() else {
+            return false;
+        };
+        match error.known_code() {
+            Some(KnownErrorCode::ModelNotFound | KnownErrorCode::ContextLengthExceeded) => true,
+            // every model shares the account's quota
+            Some(KnownErrorCode::InsufficientQuota) => false,
+            _ => status.as_u16() == 429 || status.is_server_error(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use super::*;
+    use crate::ApiErrorBody;
+
+    fn api_error(status: u16, code: &str) -> anyhow::Error {
+        SdkError::Api {
+            status: StatusCode::from_u16(status).unwrap(),
+            request_id: None,
+            error: ApiErrorBody {
+                code: (!code.is_empty()).then(|| code.to_string()),
+                ..Default::default()
+            },
+        }
+        .into()
+    }
+
+    #[test]
+    fn should_fall_back_only_on_model_specific_errors() {
+        assert!(FallbackPolicy::should_fall_back(&api_error(
+            404,
+            "model_not_found"
+        )));
+        assert!(FallbackPolicy::should_fall_back(&api_error(
+            400,
+            "context_length_exceeded"
+        )));
+        assert!(FallbackPolicy::should_fall_back(&api_error(503, "")));
+        assert!(FallbackPolicy::should_fall_back(&api_error(
+            429,
+            "rate_limit_exceeded"
+        )));
+        assert!(!FallbackPolicy::should_fall_back(&api_error(
+            429,
+            "insufficient_quota"
+        )));
+        assert!(!FallbackPolicy::should_fall_back(&api_error(
+            401,
+            "invalid_api_key"
+        )));
+        assert!(!FallbackPolicy::should_fall_back(&anyhow::anyhow!(
+            "connection reset"
+        )));
+    }
+}
@@ -0,0 +1,336 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::OnceCell;
+
+use crate::{
+    cosine_similarity, tokenizer::TOKENS_PER_REPLY, ChatCompletionMessage, ChatCompletionRequest,
+    Embedder, HeuristicTokenizer, Tokenizer,
+};
+
+const DEFAULT_HEADER: &str = "Examples of inputs and the expected outputs:";
+
+/// Few-shot examples for a prompt, selected to fit a token budget and merged into requests.
+///
+/// Without an embedder the examples are taken in the order they were added while they fit
+/// the budget; with one, the examples most similar to the last user message are taken first.
+/// Either way the selected examples keep the order they were added in, so the prompt prefix
+/// changes as little as possible between requests. The examples are embedded once, on the
+/// first selection.
+///
+/// ```no_run
+/// # use llm_sdk::{ChatCompletionRequestBuilder, EmbeddingModel, FewShot, LlmSdk, SdkEmbedder};
+/// # async fn run(sdk: LlmSdk) -> anyhow::Result<()> {
+/// let examples = FewShot::new()
+///     .example("The battery died after an hour.", "negative")
+///     .example("Arrived early and works great.", "positive")
+///     .with_embedder(SdkEmbedder::new(sdk.clone(), EmbeddingModel::default()))
+///     .with_max_tokens(500);
+/// let mut req = ChatCompletionRequestBuilder::default()
+///     .system("Classify the sentiment of the review.")
+///     .user_message("The screen cracked on day two.")
+///     .build()?;
+/// examples.apply(&mut req).await?;
+/// let res = sdk.chat_completion(req).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FewShot {
+    examples: Vec<Example>,
+    format: ExampleFormat,
+    header: String,
+    max_tokens: Option<usize>,
+    tokenizer: Arc<dyn Tokenizer>,
+    embedder: Option<Arc<dyn Embedder>>,
+    /// The embeddings of `examples`, in order.
+    embeddings: OnceCell<Vec<Vec<f32>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example {
+    pub input: String,
+    pub output: String,
+}
+
+/// How a `FewShot` renders its examples.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExampleFormat {
+    /// A user message with the input followed by an assistant message with the output, per
+    /// example, before the last user message.
+    #[default]
+    Messages,
+    /// A single system message listing every example as `Input:` and `Output:` lines, after
+    /// the leading system messages.
+    SystemBlock,
+}
+
+impl Default for FewShot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FewShot {
+    pub fn new() -> Self {
+        Self {
+            examples: Vec::new(),
+            format: ExampleFormat::default(),
+            header: DEFAULT_HEADER.to_string(),
+            max_tokens: None,
+            tokenizer: Arc::new(HeuristicTokenizer),
+            embedder: None,
+            embeddings: OnceCell::new(),
+        }
+    }
+
+    pub fn example(mut self, input: impl Into<String>, output: impl Into<String>) -> Self {
+        self.examples.push(Example {
+            input: input.into(),
+            output: output.into(),
+        });
+        self.embeddings = OnceCell::new();
+        self
+    }
+
+    pub fn with_format(mut self, format: ExampleFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// The first line of an `ExampleFormat::SystemBlock`.
+    pub fn with_header(mut self, header: impl Into<String>) -> Self {
+        self.header = header.into();
+        self
+    }
+
+    /// Take only the examples that fit in `max_tokens` prompt tokens, as counted by the
+    /// tokenizer. An example too long for what is left is skipped for shorter ones.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// The tokenizer counting the cost of examples, `HeuristicTokenizer` by default.
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    /// Take the examples most similar to the query first.
+    pub fn with_embedder(mut self, embedder: impl Embedder + 'static) -> Self {
+        self.embedder = Some(Arc::new(embedder));
+        self.embeddings = OnceCell::new();
+        self
+    }
+
+    pub fn examples(&self) -> &[Example] {
+        &self.examples
+    }
+
+    /// The examples to show for `query`, in the order they were added.
+    pub async fn select(&self, query: &str) -> Result<Vec<&Example>> {
+        let mut order: Vec<usize> = (0..self.examples.len()).collect();
+        if let (Some(embedder), false) = (&self.embedder, query.is_empty()) {
+            let embeddings = self
+                .embeddings
+                .get_or_try_init(|| {
+                    let inputs = self.examples.iter().map(|e| e.input.clone()).collect();
+                    embedder.embed(inputs)
+                })
+                .await?;
+            let query = embedder
+                .embed(vec![query.to_string()])
+                .await?
+                .pop()
+                .unwrap_or_default();
+            let scores: Vec<f32> = embeddings
+                .iter()
+                .map(|embedding| cosine_similarity(&query, embedding))
+                .collect();
+            // stable, so equally similar examples keep their order
+            order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+        }
+
+        let mut left = self.max_tokens.unwrap_or(usize::MAX);
+        if self.format == ExampleFormat::SystemBlock && !order.is_empty() {
+            left = left.saturating_sub(self.tokenizer.count_tokens(&self.header));
+        }
+        let mut selected = Vec::new();
+        for i in order {
+            let cost = self.cost(&self.examples[i]);
+            if cost <= left {
+                left -= cost;
+                selected.push(i);
+            }
+        }
+        selected.sort_unstable();
+        Ok(selected.into_iter().map(|i| &self.examples[i]).collect())
+    }
+
+    /// The messages showing the examples selected for `query`.
+    pub async fn render(&self, query: &str) -> Result<Vec<ChatCompletionMessage>> {
+        let examples = self.select(query).await?;
+        if examples.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(match self.format {
+            ExampleFormat::Messages => examples.into_iter().flat_map(example_messages).collect(),
+            ExampleFormat::SystemBlock => {
+                let mut block = self.header.clone();
+                for example in examples {
+                    block.push_str("\n\n");
+                    block.push_str(&block_entry(example));
+                }
+                vec![ChatCompletionMessage::new_system(block, "")]
+            }
+        })
+    }
+
+    /// Add the examples selected for the last user message of `req` to it: before that
+    /// message, or after the leading system messages for `ExampleFormat::SystemBlock`.
+    pub async fn apply(&self, req: &mut ChatCompletionRequest) -> Result<()> {
+        let messages = req.messages();
+        let last_user = messages
+            .iter()
+            .rposition(|msg| matches!(msg, ChatCompletionMessage::User(_)));
+        let query = last_user
+            .and_then(|i| messages[i].content())
+            .unwrap_or_default();
+        let rendered = self.render(query).await?;
+        let at = match self.format {
+            ExampleFormat::Messages => last_user.unwrap_or(messages.len()),
+            ExampleFormat::SystemBlock => messages
+                .iter()
+                .position(|msg| !matches!(msg, ChatCompletionMessage::System(_)))
+                .unwrap_or(messages.len()),
+        };
+        req.messages_mut().splice(at..at, rendered);
+        Ok(())
+    }
+
+    /// The prompt tokens `example` adds in the format of the examples.
+    fn cost(&self, example: &Example) -> usize {
+        match self.format {
+            ExampleFormat::Messages => {
+                let messages: Vec<_> = example_messages(example).collect();
+                self.tokenizer
+                    .count_message_tokens(&messages)
+                    .saturating_sub(TOKENS_PER_REPLY)
+            }
+            ExampleFormat::SystemBlock => self.tokenizer.count_tokens(&block_entry(example)),
+        }
+    }
+}
+
+fn example_messages(example: &Example) -> impl Iterator<Item = ChatCompletionMessage> {
+    [
+        ChatCompletionMessage::new_user(example.input.as_str(), ""),
+        ChatCompletionMessage::new_assistant(example.output.as_str(), "", vec![]),
+    ]
+    .into_iter()
+}
+
+fn block_entry(example: &Example) -> String {
+    format!("Input: {}\nOutput: {}", example.input, example.output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    use super::*;
+    use crate::ChatCompletionRequestBuilder;
+
+    /// Embeds text as the counts of a few keywords, counting the texts embedded.
+    #[derive(Debug, Default)]
+    struct KeywordEmbedder {
+        embedded: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    ["battery", "screen", "delivery"]
+                        .iter()
+                        .map(|word| text.matches(word).count() as f32)
+                        .collect()
+                })
+                .collect())
+        }
+    }
+
+    fn examples() -> FewShot {
+        FewShot::new()
+            .example("the battery died", "negative")
+            .example("fast delivery", "positive")
+            .example("bright screen", "positive")
+    }
+
+    #[tokio::test]
+    async fn few_shot_should_select_similar_examples_within_budget() -> Result<()> {
+        let embedder = KeywordEmbedder::default();
+        let embedded = embedder.embedded.clone();
+        // every example costs 12 tokens
+        let few_shot = examples().with_embedder(embedder).with_max_tokens(24);
+        let selected = few_shot.select("the screen broke, and the battery").await?;
+        let inputs: Vec<_> = selected.iter().map(|e| e.input.as_str()).collect();
+        assert_eq!(inputs, ["the battery died", "bright screen"]);
+        few_shot.select("late delivery").await?;
+        // the examples are embedded once
+        assert_eq!(embedded.load(Ordering::SeqCst), 5);
+
+        // in the order added without an embedder
+        let few_shot = examples().with_max_tokens(24);
+        assert_eq!(few_shot.select("bright screen").await?.len(), 2);
+        assert_eq!(few_shot.select("").await?[1].input, "fast delivery");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn few_shot_should_merge_into_requests() -> Result<()> {
+        let req = ChatCompletionRequestBuilder::default()
+            .system("Classify the review.")
+            .user_message("it broke")
+            .build()?;
+        let few_shot = examples().with_max_tokens(24);
+        let mut with_messages = req.clone();
+        few_shot.apply(&mut with_messages).await?;
+        assert_eq!(
+            serde_json::to_value(with_messages.messages())?,
+            json!([
+                { "role": "system", "content": "Classify the review." },
+                { "role": "user", "content": "the battery died" },
+                { "role": "assistant", "content": "negative" },
+                { "role": "user", "content": "fast delivery" },
+                { "role": "assistant", "content": "positive" },
+                { "role": "user", "content": "it broke" }
+            ])
+        );
+
+        let mut with_block = req;
+        examples()
+            .with_format(ExampleFormat::SystemBlock)
+            .with_header("Examples:")
+            .apply(&mut with_block)
+            .await?;
+        let messages = with_block.messages();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[1].content(),
+            Some(
+                "Examples:\n\nInput: the battery died\nOutput: negative\n\n\
+                 Input: fast delivery\nOutput: positive\n\nInput: bright screen\nOutput: positive"
+            )
+        );
+        assert_eq!(messages[2].content(), Some("it broke"));
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    validation::Validator, ChatCompletionMessage, CreateFileRequest, FileObject, FilePurpose,
+    HeuristicTokenizer, LlmSdk, SdkError, Tokenizer, ValidationError,
+};
+
+/// The fewest examples a fine-tuning job accepts.
+pub const MIN_FINE_TUNE_EXAMPLES: usize = 10;
+/// The context length of an example for current fine-tunable models; longer ones are truncated.
+pub const DEFAULT_MAX_EXAMPLE_TOKENS: usize = 65_536;
+
+// how the API picks `n_epochs` when it isn't set, see
+// https://cookbook.openai.com/examples/chat_finetuning_data_prep
+const TARGET_EPOCHS: usize = 3;
+const MIN_TARGET_EXAMPLES: usize = 100;
+const MAX_TARGET_EXAMPLES: usize = 25_000;
+const MAX_DEFAULT_EPOCHS: usize = 25;
+
+/// Chat examples for a fine-tuning job, checked against the rules of the chat JSONL format
+/// before they are written or uploaded.
+///
+/// ```no_run
+/// # use llm_sdk::{ChatCompletionMessage, FineTuneDataset, LlmSdk};
+/// # async fn run(sdk: LlmSdk, pairs: Vec<(String, String)>) -> anyhow::Result<()> {
+/// let dataset: FineTuneDataset = pairs
+///     .into_iter()
+///     .map(|(question, answer)| {
+///         vec![
+///             ChatCompletionMessage::new_user(question, ""),
+///             ChatCompletionMessage::new_assistant(answer, "", vec![]),
+///         ]
+///     })
+///     .collect();
+/// println!("about ${:.2}", dataset.estimate_cost(0.008, None));
+/// let file = sdk.upload_fine_tune_dataset(&dataset, "train.jsonl").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FineTuneDataset {
+    examples: Vec<Vec<ChatCompletionMessage>>,
+    max_example_tokens: usize,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+/// One line of the JSONL file.
+#[derive(Serialize)]
+struct Example<'a> {
+    messages: &'a [ChatCompletionMessage],
+}
+
+impl Default for FineTuneDataset {
+    fn default() -> Self {
+        Self {
+            examples: Vec::new(),
+            max_example_tokens: DEFAULT_MAX_EXAMPLE_TOKENS,
+            tokenizer: Arc::new(HeuristicTokenizer),
+        }
+    }
+}
+
+impl FineTuneDataset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_example(mut self, messages: Vec<ChatCompletionMessage>) -> Self {
+        self.push(messages);
+        self
+    }
+
+    /// Lower the token limit of an example, e.g. for a base model with a smaller context.
+    pub fn with_max_example_tokens(mut self, tokens: usize) -> Self {
+        self.max_example_tokens = tokens;
+        self
+    }
+
+    /// Count tokens with the base model's tokenizer rather than the heuristic.
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    pub fn push(&mut self, messages: Vec<ChatCompletionMessage>) {
+        self.examples.push(messages);
+    }
+
+    pub fn examples(&self) -> &[Vec<ChatCompletionMessage>] {
+        &self.examples
+    }
+
+    pub fn len(&self) -> usize {
+        self.examples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.examples.is_empty()
+    }
+
+    /// The tokens of every example.
+    pub fn example_tokens(&self) -> Vec<usize> {
+        self.examples
+            .iter()
+            .map(|messages| self.tokenizer.count_message_tokens(messages))
+            .collect()
+    }
+
+    /// Check every example, reporting all problems at once: there must be at least
+    /// `MIN_FINE_TUNE_EXAMPLES`, each with an assistant message to learn from, and none over
+    /// the token limit.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut v = Validator::default();
+        v.check(
+            self.examples.len() >= MIN_FINE_TUNE_EXAMPLES,
+            "examples",
+            format!(
+                "must have at least {} examples, got {}",
+                MIN_FINE_TUNE_EXAMPLES,
+                self.examples.len()
+            ),
+        );
+        for (i, (messages, tokens)) in self.examples.iter().zip(self.example_tokens()).enumerate() {
+            let field = format!("examples[{}]", i);
+            v.check(
+                messages
+                    .iter()
+                    .any(|msg| matches!(msg, ChatCompletionMessage::Assistant(_))),
+                &field,
+                "must have an assistant message",
+            );
+            v.check(
+                tokens <= self.max_example_tokens,
+                &field,
+                format!(
+                    "has {} tokens, over the {} token limit",
+                    tokens, self.max_example_tokens
+                ),
+            );
+            for (j, msg) in messages.iter().enumerate() {
+                if let ChatCompletionMessage::Assistant(msg) = msg {
+                    v.check(
+                        msg.content().is_some() || !msg.tool_calls().is_empty(),
+                        format!("{}.messages[{}]", field, j),
+                        "must have content or tool calls",
+                    );
+                }
+            }
+        }
+        v.finish()
+    }
+
+    /// The epochs the API trains for when `n_epochs` isn't set: 3, raised or lowered so that
+    /// small and large datasets are seen between 100 and 25,000 times in total.
+    pub fn default_epochs(&self) -> usize {
+        let n = self.examples.len().max(1);
+        if n * TARGET_EPOCHS < MIN_TARGET_EXAMPLES {
+            (MIN_TARGET_EXAMPLES / n).min(MAX_DEFAULT_EPOCHS)
+        } else if n * TARGET_EPOCHS > MAX_TARGET_EXAMPLES {
+            (MAX_TARGET_EXAMPLES / n).max(1)
+        } else {
+            TARGET_EPOCHS
+        }
+    }
+
+    /// The tokens billed for training over `epochs`, or `default_epochs` if `None`.
+    /// Examples over the token limit count as truncated.
+    pub fn billed_tokens(&self, epochs: Option<usize>) -> usize {
+        let tokens: usize = self
+            .example_tokens()
+            .into_iter()
+            .map(|tokens| tokens.min(self.max_example_tokens))
+            .sum();
+        tokens * epochs.unwrap_or_else(|| self.default_epochs())
+    }
+
+    /// Estimate the training cost in USD, given the model's training price per 1K tokens.
+    pub fn estimate_cost(&self, usd_per_1k_tokens: f64, epochs: Option<usize>) -> f64 {
+        self.billed_tokens(epochs) as f64 * usd_per_1k_tokens / 1000.0
+    }
+
+    /// The examples in the JSONL format of the fine-tuning API, one `{"messages": [...]}` per line.
+    pub fn to_jsonl(&self) -> Result<String> {
+        let mut jsonl = String::new();
+        for messages in &self.examples {
+            jsonl.push_str(&serde_json::to_string(&Example { messages })?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+
+    /// Validate the examples and write them to a JSONL file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.validate().map_err(SdkError::from)?;
+        std::fs::write(path, self.to_jsonl()?)?;
+        Ok(())
+    }
+}
+
+impl FromIterator<Vec<ChatCompletionMessage>> for FineTuneDataset {
+    fn from_iter<I: IntoIterator<Item = Vec<ChatCompletionMessage>>>(iter: I) -> Self {
+        let mut dataset = Self::new();
+        dataset.extend(iter);
+        dataset
+    }
+}
+
+impl Extend<Vec<ChatCompletionMessage>> for FineTuneDataset {
+    fn extend<I: IntoIterator<Item = Vec<ChatCompletionMessage>>>(&mut self, iter: I) {
+        self.examples.extend(iter);
+    }
+}
+
+impl LlmSdk {
+    /// Validate a dataset and upload it as a `fine-tune` file, whose id can be passed to
+    /// `CreateFineTuningJobRequest`. Invalid datasets fail with `SdkError::Validation`
+    /// before anything is sent.
+    pub async fn upload_fine_tune_dataset(
+        &self,
+        dataset: &FineTuneDataset,
+        filename: impl Into<String>,
+    ) -> Result<FileObject> {
+        dataset.validate().map_err(SdkError::from)?;
+        let data = dataset.to_jsonl()?.into_bytes();
+        self.create_file(CreateFileRequest::new(
+            filename,
+            FilePurpose::FineTune,
+            data,
+        ))
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(answer: &str) -> Vec<ChatCompletionMessage> {
+        vec![
+            ChatCompletionMessage::new_system("You are terse.", ""),
+            ChatCompletionMessage::new_user("What is the capital of France?", ""),
+            ChatCompletionMessage::new_assistant(answer, "", vec![]),
+        ]
+    }
+
+    #[test]
+    fn dataset_should_report_every_violation() {
+        let mut dataset: FineTuneDataset = (0..9).map(|_| example("Paris.")).collect();
+        dataset.push(vec![ChatCompletionMessage::new_user("Hi", "")]);
+        dataset.push(example(&"word ".repeat(100)));
+        let err = dataset.with_max_example_tokens(100).validate().unwrap_err();
+        let fields: Vec<_> = err.violations.iter().map(|v| v.field.as_str()).collect();
+        assert_eq!(fields, ["examples[9]", "examples[10]"]);
+
+        let err = FineTuneDataset::new()
+            .with_example(example("Paris."))
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid request: `examples` must have at least 10 examples, got 1"
+        );
+    }
+
+    #[test]
+    fn dataset_should_estimate_cost_and_write_jsonl() -> Result<()> {
+        let dataset: FineTuneDataset = (0..10).map(|_| example("Paris.")).collect();
+        dataset.validate()?;
+        assert_eq!(dataset.default_epochs(), 10);
+        let tokens: usize = dataset.example_tokens().iter().sum();
+        assert_eq!(dataset.billed_tokens(Some(2)), tokens * 2);
+        let cost = dataset.estimate_cost(0.008, None);
+        assert!((cost - (tokens * 10) as f64 * 0.008 / 1000.0).abs() < 1e-12);
+
+        let jsonl = dataset.to_jsonl()?;
+        assert_eq!(jsonl.lines().count(), 10);
+        let line: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap())?;
+        assert_eq!(line["messages"][2]["role"], "assistant");
+        assert_eq!(line["messages"][2]["content"], "Paris.");
+        Ok(())
+    }
+}
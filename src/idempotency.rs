@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use sha2::{Digest, Sha256};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// The header gateways use to recognize a retried request and return the first response.
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A new key for a logical request, formatted like a UUID.
+///
+/// Keys are unique within the process thanks to a counter, and across processes thanks to
+/// the time and the process id (a random number in the browser).
+pub fn new_idempotency_key() -> String {
+    let mut hasher = Sha256::new();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.update(now.as_nanos().to_le_bytes());
+    hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    #[cfg(not(target_arch = "wasm32"))]
+    hasher.update(std::process::id().to_le_bytes());
+    #[cfg(target_arch = "wasm32")]
+    hasher.update(js_sys::Math::random().to_le_bytes());
+    let hex = format!("{:x}", hasher.finalize());
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_idempotency_key_should_be_unique() {
+        let a = new_idempotency_key();
+        let b = new_idempotency_key();
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 36);
+        assert_eq!(a.matches('-').count(), 4);
+    }
+}
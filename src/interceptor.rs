@@ -0,0 +1,82 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use reqwest::{Request, Response};
+
+/// Hooks into every HTTP call made by `LlmSdk`.
+///
+/// Interceptors run in the order they were added. `on_request` sees the fully built request,
+/// including auth and body, so it can add headers, sign the request or audit it.
+pub trait RequestInterceptor: Debug + Send + Sync {
+    fn on_request(&self, _req: &mut Request) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_response(&self, _res: &Response) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+    use crate::{
+        ChatCompletionMessage, ChatCompletionRequestBuilder, KeyPool, LlmSdk, RotationStrategy,
+    };
+    use reqwest::header::HeaderValue;
+
+    #[derive(Debug, Default)]
+    struct TagInterceptor {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl RequestInterceptor for TagInterceptor {
+        fn on_request(&self, req: &mut Request) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            req.headers_mut()
+                .insert("x-audit-tag", HeaderValue::from_static("llm-sdk"));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn interceptor_should_mutate_outgoing_request() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let sdk = LlmSdk::new("sk-test".to_string()).with_interceptor(TagInterceptor {
+            calls: calls.clone(),
+        });
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![ChatCompletionMessage::new_user("hi", "")])
+            .build()?;
+        let req = sdk.build_request(req, "application/json")?;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(req.headers()["x-audit-tag"], "llm-sdk");
+        assert_eq!(req.headers()["authorization"], "Bearer sk-test");
+        Ok(())
+    }
+
+    #[test]
+    fn interceptor_should_not_run_again_when_switching_keys() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let pool = KeyPool::new(["sk-a", "sk-b"], RotationStrategy::Failover);
+        let sdk = LlmSdk::new(String::new())
+            .with_key_pool(pool.clone())
+            .with_interceptor(TagInterceptor {
+                calls: calls.clone(),
+            });
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![ChatCompletionMessage::new_user("hi", "")])
+            .build()?;
+        let mut req = sdk.build_request_for_key(req, "application/json", Some(0))?;
+        sdk.switch_key(&pool, &mut req, 0, 1)?;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(req.headers()["authorization"], "Bearer sk-b");
+        assert_eq!(req.headers()["x-audit-tag"], "llm-sdk");
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{header::HeaderMap, Response, StatusCode};
+use secrecy::{ExposeSecret, SecretString};
+use web_time::Instant;
+
+/// How long a rate-limited key is skipped when the response doesn't say.
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(1);
+
+/// An API key, optionally for a different OpenAI-compatible endpoint than the SDK's base URL.
+#[derive(Debug, Clone)]
+pub struct Credential {
+    /// Zeroized on drop and redacted from `Debug`.
+    pub token: SecretString,
+    pub base_url: Option<String>,
+}
+
+/// How `KeyPool` picks the key for the next request. Rate-limited and revoked keys are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RotationStrategy {
+    /// Cycle through the keys.
+    #[default]
+    RoundRobin,
+    /// The key that was rate limited the longest ago, preferring the most remaining requests.
+    LeastRecentlyLimited,
+    /// Always the first key, moving on to the next only when it gets a 429 or 401.
+    Failover,
+}
+
+/// The last known rate-limit state of a key, from the `x-ratelimit-*` response headers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyState {
+    pub remaining_requests: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    /// When the key last got a 429.
+    pub limited_at: Option<Instant>,
+    /// The key is skipped until then.
+    pub limited_until: Option<Instant>,
+    /// The key got a 401 and is never used again.
+    pub revoked: bool,
+}
+
+/// Several API keys shared by the clones of an `LlmSdk`, see `LlmSdk::with_key_pool`.
+///
+/// A request that gets a 429 or 401 is retried once per remaining key.
+#[derive(Debug, Clone)]
+pub struct KeyPool {
+    inner: Arc<KeyPoolInner>,
+}
+
+#[derive(Debug)]
+struct KeyPoolInner {
+    credentials: Vec<Credential>,
+    strategy: RotationStrategy,
+    state: Mutex<PoolState>,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    next: usize,
+    keys: Vec<KeyState>,
+}
+
+impl Credential {
+    pub fn new(token: impl Into<SecretString>) -> Self {
+        Self {
+            token: token.into(),
+            base_url: None,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into().trim_end_matches('/').to_string());
+        self
+    }
+}
+
+impl PartialEq for Credential {
+    fn eq(&self, other: &Self) -> bool {
+        self.token.expose_secret() == other.token.expose_secret() && self.base_url == other.base_url
+    }
+}
+
+impl Eq for Credential {}
+
+impl From<String> for Credential {
+    fn from(token: String) -> Self {
+        Self::new(token)
+    }
+}
+
+impl From<&str> for Credential {
+    fn from(token: &str) -> Self {
+        Self::new(token)
+    }
+}
+
+impl KeyPool {
+    /// # Panics
+    ///
+    /// If `credentials` is empty.
+    pub fn new(
+        credentials: impl IntoIterator<Item = impl Into<Credential>>,
+        strategy: RotationStrategy,
+    ) -> Self {
+        let credentials: Vec<Credential> = credentials.into_iter().map(Into::into).collect();
+        assert!(!credentials.is_empty(), "a key pool needs at least one key");
+        let state = PoolState {
+            next: 0,
+            keys: vec![KeyState::default(); credentials.len()],
+        };
+        Self {
+            inner: Arc::new(KeyPoolInner {
+                credentials,
+                strategy,
+                state: Mutex::new(state),
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.credentials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.credentials.is_empty()
+    }
+
+    pub fn credential(&self, index: usize) -> &Credential {
+        &self.inner.credentials[index]
+    }
+
+    /// The rate-limit state of every key, in the order they were given.
+    pub fn states(&self) -> Vec<KeyState> {
+        self.inner.state.lock().unwrap().keys.clone()
+    }
+
+    /// The index of the key to use for the next request.
+    pub fn select(&self) -> usize {
+        let now = Instant::now();
+        let mut state = self.inner.state.lock().unwrap();
+        let available = |key: &KeyState| !key.revoked && key.limited_until.is_none_or(|t| t <= now);
+        let len = state.keys.len();
+        let picked = match self.inner.strategy {
+            RotationStrategy::RoundRobin => (0..len)
+                .map(|offset| (state.next + offset) % len)
+                .find(|&i| available(&state.keys[i])),
+            RotationStrategy::Failover => (0..len).find(|&i| available(&state.keys[i])),
+            RotationStrategy::LeastRecentlyLimited => (0..len)
+                .filter(|&i| available(&state.keys[i]))
+                .min_by_key(|&i| {
+                    let key = &state.keys[i];
+                    (key.limited_at, std::cmp::Reverse(key.remaining_requests))
+                }),
+        };
+        // everything is limited: use the key that frees up first
+        let index = picked.unwrap_or_else(|| {
+            (0..len)
+                .filter(|&i| !state.keys[i].revoked)
+                .min_by_key(|&i| state.keys[i].limited_until)
+                .unwrap_or(0)
+        });
+        state.next = (index + 1) % len;
+        index
+    }
+
+    /// Update the state of the key at `index` from the response to a request made with it.
+    pub fn record(&self, index: usize, res: &Response) {
+        self.record_headers(index, res.status(), res.headers());
+    }
+
+    fn record_headers(&self, index: usize, status: StatusCode, headers: &HeaderMap) {
+        let now = Instant::now();
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let remaining_requests =
+            header("x-ratelimit-remaining-requests").and_then(|v| v.parse().ok());
+        let remaining_tokens = header("x-ratelimit-remaining-tokens").and_then(|v| v.parse().ok());
+        let reset_requests = header("x-ratelimit-reset-requests").and_then(parse_reset);
+        let reset_tokens = header("x-ratelimit-reset-tokens").and_then(parse_reset);
+        let retry_after = header("retry-after")
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs_f64);
+
+        let mut state = self.inner.state.lock().unwrap();
+        let key = &mut state.keys[index];
+        key.remaining_requests = remaining_requests.or(key.remaining_requests);
+        key.remaining_tokens = remaining_tokens.or(key.remaining_tokens);
+        match status {
+            StatusCode::UNAUTHORIZED => key.revoked = true,
+            StatusCode::TOO_MANY_REQUESTS => {
+                let reset = match remaining_tokens {
+                    Some(0) => reset_tokens,
+                    _ => reset_requests,
+                };
+                let cooldown = retry_after.or(reset).unwrap_or(DEFAULT_COOLDOWN);
+                key.limited_at = Some(now);
+                key.limited_until = Some(now + cooldown);
+            }
+            _ if remaining_requests == Some(0) => {
+                key.limited_until = Some(now + reset_requests.unwrap_or(DEFAULT_COOLDOWN));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse a reset duration like `1s`, `6m0s`, `20ms` or `1h2m3.5s`.
+fn parse_reset(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let number: f64 = rest[..split].parse().ok()?;
+        rest = &rest[split..];
+        let unit_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let secs = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        total += number * secs;
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn parse_reset_should_handle_openai_durations() {
+        assert_eq!(parse_reset("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_reset("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_reset("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(
+            parse_reset("1h2m3.5s"),
+            Some(Duration::from_millis(3_723_500))
+        );
+        assert_eq!(parse_reset("soon"), None);
+    }
+
+    #[test]
+    fn round_robin_should_skip_limited_and_revoked_keys() {
+        let pool = KeyPool::new(["a", "b", "c"], RotationStrategy::RoundRobin);
+        assert_eq!(
+            (0..4).map(|_| pool.select()).collect::<Vec<_>>(),
+            [0, 1, 2, 0]
+        );
+        pool.record_headers(
+            1,
+            StatusCode::TOO_MANY_REQUESTS,
+            &headers(&[("retry-after", "60")]),
+        );
+        pool.record_headers(2, StatusCode::UNAUTHORIZED, &HeaderMap::new());
+        assert_eq!((0..3).map(|_| pool.select()).collect::<Vec<_>>(), [0, 0, 0]);
+        let states = pool.states();
+        assert!(states[1].limited_at.is_some());
+        assert!(states[2].revoked);
+    }
+
+    #[test]
+    fn failover_should_stick_to_the_first_available_key() {
+        let pool = KeyPool::new(["a", "b"], RotationStrategy::Failover);
+        assert_eq!(pool.select(), 0);
+        assert_eq!(pool.select(), 0);
+        pool.record_headers(
+            0,
+            StatusCode::OK,
+            &headers(&[
+                ("x-ratelimit-remaining-requests", "0"),
+                ("x-ratelimit-reset-requests", "1m"),
+            ]),
+        );
+        assert_eq!(pool.select(), 1);
+        assert_eq!(pool.states()[0].remaining_requests, Some(0));
+    }
+
+    #[test]
+    fn least_recently_limited_should_prefer_never_limited_keys() {
+        let pool = KeyPool::new(["a", "b", "c"], RotationStrategy::LeastRecentlyLimited);
+        pool.record_headers(
+            0,
+            StatusCode::TOO_MANY_REQUESTS,
+            &headers(&[("retry-after", "0")]),
+        );
+        pool.record_headers(
+            1,
+            StatusCode::OK,
+            &headers(&[("x-ratelimit-remaining-requests", "10")]),
+        );
+        pool.record_headers(
+            2,
+            StatusCode::OK,
+            &headers(&[("x-ratelimit-remaining-requests", "90")]),
+        );
+        assert_eq!(pool.select(), 2);
+    }
+}
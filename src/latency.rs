@@ -0,0 +1,268 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{stream, StreamExt};
+use web_time::Instant;
+
+use crate::{ChatCompleteModel, ChatCompletionStream};
+
+/// Weight of the newest measurement in the moving average of tokens/sec.
+const SMOOTHING: f64 = 0.3;
+/// Never cap max_tokens below this, so a slow measurement can't starve a response.
+const MIN_TOKENS: usize = 16;
+
+/// Caps `max_tokens` so a chat completion can be generated within a deadline.
+///
+/// The SDK measures the tokens/sec of every completion per model; once a model has been measured,
+/// requests to it get `max_tokens` capped to what fits in the deadline at that speed.
+#[derive(Debug, Clone)]
+pub struct LatencyBudget {
+    deadline: Duration,
+    throughput: Arc<Mutex<HashMap<ChatCompleteModel, f64>>>,
+}
+
+/// How long a chat completion took, see `ChatCompletionResponse::timing` and
+/// `LlmSdk::with_timing_callback`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionTiming {
+    /// The model that answered.
+    pub model: String,
+    pub streamed: bool,
+    /// From sending the request to the first chunk with generated content, for a stream.
+    /// Otherwise to the response headers, which only arrive once the reply is complete.
+    pub time_to_first_token: Duration,
+    /// From sending the request to the end of the response.
+    pub total: Duration,
+    /// `None` for a stream that didn't report its usage.
+    pub completion_tokens: Option<usize>,
+}
+
+/// Called with the timing of every chat completion, see `LlmSdk::with_timing_callback`.
+#[derive(Clone)]
+pub(crate) struct TimingCallback(pub(crate) Arc<dyn Fn(&CompletionTiming) + Send + Sync>);
+
+/// The timing of a stream being consumed.
+struct StreamTiming {
+    start: Instant,
+    model: String,
+    first_token: Option<Duration>,
+    completion_tokens: Option<usize>,
+}
+
+impl LatencyBudget {
+    pub fn new(deadline: Duration) -> Self {
+        Self {
+            deadline,
+            throughput: Default::default(),
+        }
+    }
+
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /// The measured tokens/sec of a model, if any completion has been recorded for it.
+    pub fn tokens_per_sec(&self, model: &ChatCompleteModel) -> Option<f64> {
+        self.throughput.lock().unwrap().get(model).copied()
+    }
+
+    /// The max_tokens that fits in the deadline for a model, if it has been measured.
+    pub fn max_tokens(&self, model: &ChatCompleteModel) -> Option<usize> {
+        let tokens_per_sec = self.tokens_per_sec(model)?;
+        let tokens = (self.deadline.as_secs_f64() * tokens_per_sec) as usize;
+        Some(tokens.max(MIN_TOKENS))
+    }
+
+    pub fn record(&self, model: &ChatCompleteModel, completion_tokens: usize, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if completion_tokens == 0 || secs == 0.0 {
+            return;
+        }
+        let measured = completion_tokens as f64 / secs;
+        let mut throughput = self.throughput.lock().unwrap();
+        throughput
+            .entry(model.clone())
+            .and_modify(|avg| *avg = SMOOTHING * measured + (1.0 - SMOOTHING) * *avg)
+            .or_insert(measured);
+    }
+}
+
+impl CompletionTiming {
+    /// The speed of generation: completion tokens per second after the first token for a
+    /// stream, over the whole call otherwise.
+    pub fn tokens_per_sec(&self) -> Option<f64> {
+        let generating = if self.streamed {
+            self.total.saturating_sub(self.time_to_first_token)
+        } else {
+            self.total
+        };
+        let tokens = self.completion_tokens? as f64;
+        let secs = generating.as_secs_f64();
+        (secs > 0.0).then(|| tokens / secs)
+    }
+}
+
+impl fmt::Debug for TimingCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TimingCallback")
+    }
+}
+
+impl TimingCallback {
+    /// Pass the timing of `stream`, started at `start`, to the callback once it completes.
+    pub(crate) fn watch(
+        self,
+        stream: ChatCompletionStream,
+        start: Instant,
+    ) -> ChatCompletionStream {
+        let timing = StreamTiming {
+            start,
+            model: String::new(),
+            first_token: None,
+            completion_tokens: None,
+        };
+        Box::pin(stream::unfold(
+            Some((stream, timing, self)),
+            |state| async move {
+                let (mut stream, mut timing, callback) = state?;
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        timing.model.clone_from(&chunk.model);
+                        let generated = chunk.choices.iter().any(|choice| {
+                            choice.delta.content.as_ref().is_some_and(|c| !c.is_empty())
+                                || choice.delta.refusal.is_some()
+                                || !choice.delta.tool_calls.is_empty()
+                        });
+                        if generated && timing.first_token.is_none() {
+                            timing.first_token = Some(timing.start.elapsed());
+                        }
+                        if let Some(usage) = &chunk.usage {
+                            timing.completion_tokens = Some(usage.completion_tokens);
+                        }
+                        Some((Ok(chunk), Some((stream, timing, callback))))
+                    }
+                    Some(Err(e)) => Some((Err(e), None)),
+                    None => {
+                        let total = timing.start.elapsed();
+                        (callback.0)(&CompletionTiming {
+                            model: timing.model,
+                            streamed: true,
+                            time_to_first_token: timing.first_token.unwrap_or(total),
+                            total,
+                            completion_tokens: timing.completion_tokens,
+                        });
+                        None
+                    }
+                }
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_budget_should_cap_by_measured_throughput() {
+        let budget = LatencyBudget::new(Duration::from_secs(2));
+        assert_eq!(budget.max_tokens(&ChatCompleteModel::Gpt3Turbo), None);
+
+        budget.record(&ChatCompleteModel::Gpt3Turbo, 100, Duration::from_secs(2));
+        assert_eq!(budget.max_tokens(&ChatCompleteModel::Gpt3Turbo), Some(100));
+
+        budget.record(&ChatCompleteModel::Gpt3Turbo, 300, Duration::from_secs(2));
+        // 0.3 * 150 + 0.7 * 50
+        assert_eq!(budget.max_tokens(&ChatCompleteModel::Gpt3Turbo), Some(160));
+        assert_eq!(budget.max_tokens(&ChatCompleteModel::Gpt4Turbo), None);
+    }
+
+    #[test]
+    fn latency_budget_should_not_cap_below_minimum() {
+        let budget = LatencyBudget::new(Duration::from_millis(10));
+        budget.record(&ChatCompleteModel::Gpt4Turbo, 10, Duration::from_secs(1));
+        assert_eq!(
+            budget.max_tokens(&ChatCompleteModel::Gpt4Turbo),
+            Some(MIN_TOKENS)
+        );
+    }
+
+    #[tokio::test]
+    async fn timing_callback_should_measure_streams() -> anyhow::Result<()> {
+        let chunk = |delta: serde_json::Value, usage: serde_json::Value| {
+            Ok(serde_json::from_value(serde_json::json!({
+                "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 1700000000,
+                "model": "gpt-4o-mini",
+                "choices": if delta.is_null() {
+                    serde_json::json!([])
+                } else {
+                    serde_json::json!([{ "index": 0, "delta": delta }])
+                },
+                "usage": usage
+            }))?)
+        };
+        let chunks = vec![
+            chunk(
+                serde_json::json!({ "role": "assistant", "content": "" }),
+                serde_json::Value::Null,
+            ),
+            chunk(
+                serde_json::json!({ "content": "Hi!" }),
+                serde_json::Value::Null,
+            ),
+            chunk(
+                serde_json::Value::Null,
+                serde_json::json!({ "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }),
+            ),
+        ];
+        let timings = Arc::new(Mutex::new(Vec::new()));
+        let callback = TimingCallback(Arc::new({
+            let timings = timings.clone();
+            move |timing: &CompletionTiming| timings.lock().unwrap().push(timing.clone())
+        }));
+        // the first chunk only says who is talking, the second one is the first token
+        let delayed = stream::iter(chunks).then(|chunk| async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            chunk
+        });
+        let start = Instant::now();
+        let received: Vec<_> = callback.watch(Box::pin(delayed), start).collect().await;
+        assert_eq!(received.len(), 3);
+
+        let timings = timings.lock().unwrap();
+        let timing = &timings[0];
+        assert_eq!(timing.model, "gpt-4o-mini");
+        assert!(timing.streamed);
+        assert!(timing.time_to_first_token >= Duration::from_millis(20));
+        assert!(timing.total >= timing.time_to_first_token + Duration::from_millis(10));
+        assert_eq!(timing.completion_tokens, Some(2));
+        assert!(timing.tokens_per_sec().unwrap() > 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn tokens_per_sec_should_skip_the_wait_for_the_first_token_of_streams() {
+        let timing = CompletionTiming {
+            model: "gpt-4o".to_string(),
+            streamed: true,
+            time_to_first_token: Duration::from_secs(1),
+            total: Duration::from_secs(3),
+            completion_tokens: Some(100),
+        };
+        assert_eq!(timing.tokens_per_sec(), Some(50.0));
+        let timing = CompletionTiming {
+            streamed: false,
+            ..timing
+        };
+        assert_eq!(timing.tokens_per_sec(), Some(100.0 / 3.0));
+        let timing = CompletionTiming {
+            completion_tokens: None,
+            ..timing
+        };
+        assert_eq!(timing.tokens_per_sec(), None);
+    }
+}
@@ -0,0 +1,94 @@
+use std::{future::Future, pin::Pin, time::Duration};
+
+use anyhow::{anyhow, Result};
+use futures::{future::Either, Stream, StreamExt};
+
+/// `Send` on native targets. On `wasm32` futures are tied to the JS event loop and are never
+/// `Send`, so there the bound is dropped.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+/// A boxed stream of results, `Send` unless compiled for `wasm32`.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxStream<T> = Pin<Box<dyn Stream<Item = Result<T>> + Send>>;
+#[cfg(target_arch = "wasm32")]
+pub type BoxStream<T> = Pin<Box<dyn Stream<Item = Result<T>>>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Sleep on the JS event loop: there is no tokio timer driver in browsers or edge workers.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: Duration) {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let global = js_sys::global();
+        let set_timeout = js_sys::Reflect::get(&global, &"setTimeout".into())
+            .map(js_sys::Function::from)
+            .expect("setTimeout is available in browsers and workers");
+        let millis = duration.as_millis() as f64;
+        let _ = set_timeout.call2(&global, &resolve, &millis.into());
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// The output of `fut`, or `None` if it takes longer than `duration`.
+pub(crate) async fn timeout<F: Future>(duration: Duration, fut: F) -> Option<F::Output> {
+    let fut = std::pin::pin!(fut);
+    match futures::future::select(fut, Box::pin(sleep(duration))).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+/// End `stream` with an error once it yields nothing for `idle`.
+pub(crate) fn idle_timeout<S, T, E>(stream: S, idle: Duration) -> impl Stream<Item = Result<T>>
+where
+    S: Stream<Item = Result<T, E>> + Unpin,
+    E: Into<anyhow::Error>,
+{
+    futures::stream::unfold(Some(stream), move |stream| async move {
+        let mut stream = stream?;
+        let timeout = Box::pin(sleep(idle));
+        match futures::future::select(stream.next(), timeout).await {
+            Either::Left((Some(item), _)) => Some((item.map_err(Into::into), Some(stream))),
+            Either::Left((None, _)) => None,
+            Either::Right(_) => Some((Err(anyhow!("no data received for {:?}", idle)), None)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn idle_timeout_should_end_a_silent_stream() {
+        let items = futures::stream::iter([Ok::<_, anyhow::Error>(1), Ok(2)]);
+        let items: Vec<_> = idle_timeout(items, Duration::from_millis(10))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(items, [1, 2]);
+
+        let silent =
+            futures::stream::iter([Ok::<_, anyhow::Error>(1)]).chain(futures::stream::pending());
+        let items: Vec<_> = idle_timeout(silent, Duration::from_millis(10))
+            .collect()
+            .await;
+        assert_eq!(items.len(), 2);
+        assert!(items[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("no data received"));
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use reqwest::{Body, Request};
+use serde_json::Value;
+
+use crate::{AuthStyle, RequestInterceptor};
+
+/// OpenAI-compatible vendors that `LlmSdk::with_preset` knows how to talk to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Mistral,
+    Groq,
+    TogetherAI,
+    DeepSeek,
+    XAI,
+}
+
+impl Preset {
+    pub fn base_url(&self) -> &'static str {
+        match self {
+            Preset::Mistral => "https://api.mistral.ai/v1",
+            Preset::Groq => "https://api.groq.com/openai/v1",
+            Preset::TogetherAI => "https://api.together.xyz/v1",
+            Preset::DeepSeek => "https://api.deepseek.com/v1",
+            Preset::XAI => "https://api.x.ai/v1",
+        }
+    }
+
+    pub fn auth_style(&self) -> AuthStyle {
+        AuthStyle::Bearer
+    }
+
+    /// Rewrite a JSON request body into something the vendor accepts.
+    fn fix_body(&self, body: &mut Value) {
+        let Some(body) = body.as_object_mut() else {
+            return;
+        };
+        match self {
+            // https://console.groq.com/docs/openai
+            Preset::Groq => {
+                for field in ["logprobs", "top_logprobs", "logit_bias", "n"] {
+                    body.remove(field);
+                }
+                if let Some(Value::Array(messages)) = body.get_mut("messages") {
+                    for message in messages.iter_mut().filter_map(Value::as_object_mut) {
+                        message.remove("name");
+                    }
+                }
+            }
+            // https://docs.mistral.ai/api/
+            Preset::Mistral => {
+                for field in ["user", "logprobs", "stream_options"] {
+                    body.remove(field);
+                }
+                if let Some(seed) = body.remove("seed") {
+                    body.insert("random_seed".to_string(), seed);
+                }
+            }
+            Preset::TogetherAI | Preset::DeepSeek | Preset::XAI => {}
+        }
+    }
+}
+
+/// Applies a preset's request quirks to every outgoing JSON body.
+#[derive(Debug)]
+pub(crate) struct PresetQuirks(pub Preset);
+
+impl RequestInterceptor for PresetQuirks {
+    fn on_request(&self, req: &mut Request) -> Result<()> {
+        let Some(bytes) = req.body().and_then(Body::as_bytes) else {
+            return Ok(());
+        };
+        let Ok(mut body) = serde_json::from_slice::<Value>(bytes) else {
+            return Ok(());
+        };
+        self.0.fix_body(&mut body);
+        *req.body_mut() = Some(serde_json::to_vec(&body)?.into());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChatCompletionMessage, ChatCompletionRequestBuilder, LlmSdk};
+    use serde_json::json;
+
+    fn body(req: &Request) -> Value {
+        serde_json::from_slice(req.body().unwrap().as_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn groq_preset_should_drop_unsupported_fields() -> Result<()> {
+        let sdk = LlmSdk::with_preset(Preset::Groq, "gsk-test".to_string());
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![ChatCompletionMessage::new_user("hi", "user1")])
+            .n(2)
+            .build()?;
+        let req = sdk.build_request(req, "application/json")?;
+        assert_eq!(
+            req.url().as_str(),
+            "https://api.groq.com/openai/v1/chat/completions"
+        );
+        assert_eq!(req.headers()["authorization"], "Bearer gsk-test");
+        assert_eq!(
+            body(&req),
+            json!({ "messages": [{ "role": "user", "content": "hi" }] })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn mistral_preset_should_rename_seed() -> Result<()> {
+        let sdk = LlmSdk::with_preset(Preset::Mistral, "key".to_string());
+        let req = ChatCompletionRequestBuilder::default()
+            .messages(vec![ChatCompletionMessage::new_user("hi", "")])
+            .seed(42)
+            .user("user1")
+            .build()?;
+        let req = sdk.build_request(req, "application/json")?;
+        assert_eq!(
+            body(&req),
+            json!({
+                "messages": [{ "role": "user", "content": "hi" }],
+                "random_seed": 42
+            })
+        );
+        Ok(())
+    }
+}
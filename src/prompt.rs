@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::ChatCompletionMessage;
+
+/// Partials may include other partials, but not more than this deep.
+const MAX_PARTIAL_DEPTH: usize = 8;
+
+/// A text template with `{name}` variables and `{>name}` partials.
+///
+/// Use `{{` and `}}` for literal braces. The syntax is a subset of `format!`'s, so
+/// the `prompt!` macro can check variable names at compile time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptTemplate {
+    template: String,
+    partials: BTreeMap<String, String>,
+}
+
+/// A chat prompt: an optional system template, few-shot examples and a user template.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChatPrompt {
+    system: Option<PromptTemplate>,
+    examples: Vec<(String, String)>,
+    user: PromptTemplate,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment<'a> {
+    Text(&'a str),
+    Var(&'a str),
+    Partial(&'a str),
+}
+
+impl PromptTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            partials: BTreeMap::new(),
+        }
+    }
+
+    /// Register a template that `{>name}` expands to. Partials see the same variables.
+    pub fn with_partial(mut self, name: impl Into<String>, template: impl Into<String>) -> Self {
+        self.partials.insert(name.into(), template.into());
+        self
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// The variables used by the template and its partials, sorted and deduplicated.
+    pub fn variables(&self) -> Result<Vec<&str>> {
+        let mut vars = Vec::new();
+        self.collect_variables(&self.template, 0, &mut vars)?;
+        vars.sort_unstable();
+        vars.dedup();
+        Ok(vars)
+    }
+
+    /// Substitute every variable; a variable missing from `vars` is an error.
+    pub fn render(&self, vars: &[(&str, &str)]) -> Result<String> {
+        let mut out = String::with_capacity(self.template.len());
+        self.render_into(&self.template, vars, 0, &mut out)?;
+        Ok(out)
+    }
+
+    pub fn render_system(&self, vars: &[(&str, &str)]) -> Result<ChatCompletionMessage> {
+        Ok(ChatCompletionMessage::new_system(self.render(vars)?, ""))
+    }
+
+    pub fn render_user(&self, vars: &[(&str, &str)]) -> Result<ChatCompletionMessage> {
+        Ok(ChatCompletionMessage::new_user(self.render(vars)?, ""))
+    }
+
+    fn partial(&self, name: &str, depth: usize) -> Result<&str> {
+        if depth >= MAX_PARTIAL_DEPTH {
+            bail!("partials nested too deeply at `{}`", name);
+        }
+        self.partials
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("unknown partial `{}`", name))
+    }
+
+    fn collect_variables<'a>(
+        &'a self,
+        template: &'a str,
+        depth: usize,
+        vars: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        for segment in parse(template)? {
+            match segment {
+                Segment::Text(_) => {}
+                Segment::Var(name) => vars.push(name),
+                Segment::Partial(name) => {
+                    self.collect_variables(self.partial(name, depth)?, depth + 1, vars)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn render_into(
+        &self,
+        template: &str,
+        vars: &[(&str, &str)],
+        depth: usize,
+        out: &mut String,
+    ) -> Result<()> {
+        for segment in parse(template)? {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Var(name) => {
+                    let (_, value) = vars
+                        .iter()
+                        .find(|(var, _)| *var == name)
+                        .ok_or_else(|| anyhow!("missing value for prompt variable `{}`", name))?;
+                    out.push_str(value);
+                }
+                Segment::Partial(name) => {
+                    self.render_into(self.partial(name, depth)?, vars, depth + 1, out)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ChatPrompt {
+    pub fn new(user: impl Into<PromptTemplate>) -> Self {
+        Self {
+            system: None,
+            examples: Vec::new(),
+            user: user.into(),
+        }
+    }
+
+    pub fn system(mut self, system: impl Into<PromptTemplate>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    /// Add a few-shot example, sent as a user message followed by the assistant's answer.
+    pub fn example(mut self, input: impl Into<String>, output: impl Into<String>) -> Self {
+        self.examples.push((input.into(), output.into()));
+        self
+    }
+
+    /// Render the system message, the examples in order, then the user message.
+    pub fn render(&self, vars: &[(&str, &str)]) -> Result<Vec<ChatCompletionMessage>> {
+        let mut messages = Vec::with_capacity(self.examples.len() * 2 + 2);
+        if let Some(system) = &self.system {
+            messages.push(system.render_system(vars)?);
+        }
+        for (input, output) in &self.examples {
+            messages.push(ChatCompletionMessage::new_user(input.as_str(), ""));
+            messages.push(ChatCompletionMessage::new_assistant(
+                output.as_str(),
+                "",
+                vec![],
+            ));
+        }
+        messages.push(self.user.render_user(vars)?);
+        Ok(messages)
+    }
+}
+
+impl From<&str> for PromptTemplate {
+    fn from(template: &str) -> Self {
+        Self::new(template)
+    }
+}
+
+impl From<String> for PromptTemplate {
+    fn from(template: String) -> Self {
+        Self::new(template)
+    }
+}
+
+fn parse(template: &str) -> Result<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find(['{', '}']) {
+        if pos > 0 {
+            segments.push(Segment::Text(&rest[..pos]));
+        }
+        let tail = &rest[pos..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            segments.push(Segment::Text(&tail[..1]));
+            rest = &tail[2..];
+            continue;
+        }
+        if tail.starts_with('}') {
+            bail!(
+                "unmatched `}}` in prompt template at byte {}",
+                template.len() - tail.len()
+            );
+        }
+        let end = tail
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed `{{` in prompt template"))?;
+        let name = tail[1..end].trim();
+        let (ident, segment) = match name.strip_prefix('>') {
+            Some(partial) => (partial.trim(), Segment::Partial(partial.trim())),
+            None => (name, Segment::Var(name)),
+        };
+        if !is_identifier(ident) {
+            bail!("invalid prompt variable name `{}`", ident);
+        }
+        segments.push(segment);
+        rest = &tail[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    Ok(segments)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Build a `PromptTemplate`, rejecting at compile time any variable not listed and
+/// any listed variable the template doesn't use.
+///
+/// ```
+/// let template = llm_sdk::prompt!("Translate {text} into {language}.", text, language);
+/// let prompt = template.render(&[("text", "bonjour"), ("language", "English")]).unwrap();
+/// assert_eq!(prompt, "Translate bonjour into English.");
+/// ```
+///
+/// ```compile_fail
+/// // `language` is used but not listed
+/// let template = llm_sdk::prompt!("Translate {text} into {language}.", text);
+/// ```
+#[macro_export]
+macro_rules! prompt {
+    ($template:literal $(, $var:ident)* $(,)?) => {{
+        // a fn item can't capture locals, so format! only sees the listed variables
+        #[allow(dead_code)]
+        fn __check_prompt_variables() {
+            let _ = format!($template $(, $var = "")*);
+        }
+        $crate::PromptTemplate::new($template)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn prompt_template_should_render_variables_and_partials() -> Result<()> {
+        let template = PromptTemplate::new("{>greeting} Reply in {{JSON}} about {topic}.")
+            .with_partial("greeting", "Hello {name}!");
+        assert_eq!(template.variables()?, ["name", "topic"]);
+        assert_eq!(
+            template.render(&[("name", "Tyr"), ("topic", "Rust")])?,
+            "Hello Tyr! Reply in {JSON} about Rust."
+        );
+        assert!(template.render(&[("name", "Tyr")]).is_err());
+        assert!(PromptTemplate::new("{>missing}").render(&[]).is_err());
+        assert!(PromptTemplate::new("Hello {name").render(&[]).is_err());
+        assert!(PromptTemplate::new("a}b").render(&[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_partials_should_fail() {
+        let template = PromptTemplate::new("{>a}").with_partial("a", "{>a}");
+        assert!(template.render(&[]).is_err());
+        assert!(template.variables().is_err());
+    }
+
+    #[test]
+    fn chat_prompt_should_render_messages() -> Result<()> {
+        let prompt = ChatPrompt::new(prompt!("Translate: {text}", text))
+            .system("You translate {from} to {to}.")
+            .example("Translate: bonjour", "hello");
+        let messages =
+            prompt.render(&[("from", "French"), ("to", "English"), ("text", "merci")])?;
+        assert_eq!(
+            serde_json::to_value(&messages)?,
+            json!([
+                { "role": "system", "content": "You translate French to English." },
+                { "role": "user", "content": "Translate: bonjour" },
+                { "role": "assistant", "content": "hello" },
+                { "role": "user", "content": "Translate: merci" }
+            ])
+        );
+        Ok(())
+    }
+}
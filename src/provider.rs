@@ -0,0 +1,94 @@
+use std::{any::Any, fmt::Debug};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    default_tokenizer, ChatCompleteModel, ChatCompletionMessage, ChatCompletionRequest,
+    ChatCompletionResponse, ChatCompletionStream, LlmSdk,
+};
+
+/// A chat completion backend, so an app can take a `Box<dyn ChatProvider>` and stay vendor
+/// neutral. `LlmSdk` is the OpenAI implementation, and serves any OpenAI-compatible server.
+///
+/// Provider-specific extras stay reachable through `downcast_ref`:
+///
+/// ```no_run
+/// # use llm_sdk::{ChatProvider, LlmSdk};
+/// # fn run(provider: &dyn ChatProvider) {
+/// if let Some(sdk) = provider.downcast_ref::<LlmSdk>() {
+///     println!("{:?}", sdk.usage_tracker().map(|tracker| tracker.total()));
+/// }
+/// # }
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ChatProvider: AsAny + Debug + Send + Sync {
+    async fn complete(&self, req: ChatCompletionRequest) -> Result<ChatCompletionResponse>;
+
+    async fn complete_stream(&self, req: ChatCompletionRequest) -> Result<ChatCompletionStream>;
+
+    /// Estimate the prompt tokens of `messages` for `model`.
+    fn count_tokens(&self, model: &ChatCompleteModel, messages: &[ChatCompletionMessage]) -> usize {
+        default_tokenizer(model).count_message_tokens(messages)
+    }
+
+    /// The context window of `model` in tokens.
+    fn max_context(&self, model: &ChatCompleteModel) -> usize {
+        model.context_window()
+    }
+}
+
+/// Gives a `dyn ChatProvider` its concrete type back, implemented for every type.
+pub trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl dyn ChatProvider {
+    /// The concrete provider, for what the trait doesn't cover.
+    pub fn downcast_ref<T: ChatProvider>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ChatProvider for LlmSdk {
+    async fn complete(&self, req: ChatCompletionRequest) -> Result<ChatCompletionResponse> {
+        self.chat_completion(req).await
+    }
+
+    async fn complete_stream(&self, req: ChatCompletionRequest) -> Result<ChatCompletionStream> {
+        self.chat_completion_stream(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{completion_json, ScriptedClient},
+        ChatCompletionRequestBuilder,
+    };
+
+    #[tokio::test]
+    async fn llm_sdk_should_be_a_chat_provider() -> Result<()> {
+        let client = ScriptedClient::replying([completion_json("Hello!", "stop", (9, 2))]);
+        let provider: Box<dyn ChatProvider> =
+            Box::new(LlmSdk::new("sk-test".to_string()).with_http_client(client));
+        let req = ChatCompletionRequestBuilder::default()
+            .user_message("hi")
+            .build()?;
+        assert_eq!(provider.complete(req.clone()).await?.text(), Some("Hello!"));
+        assert!(provider.count_tokens(&req.model(), req.messages()) > 0);
+        assert_eq!(provider.max_context(&ChatCompleteModel::Gpt4Turbo), 128_000);
+        assert!(provider.downcast_ref::<LlmSdk>().is_some());
+        Ok(())
+    }
+}
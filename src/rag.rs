@@ -0,0 +1,366 @@
+use std::{fmt::Debug, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    ChatCompleteModel, ChatCompletionMessage, ChatCompletionRequest, ChatCompletionRequestBuilder,
+    ChatCompletionResponse, CreateEmbeddingRequestBuilder, EmbeddingIndex, EmbeddingModel, LlmSdk,
+    PromptTemplate,
+};
+
+const DEFAULT_TOP_K: usize = 4;
+const DEFAULT_CHUNK_CHARS: usize = 1000;
+/// How many chunks are embedded per request.
+const EMBED_BATCH: usize = 256;
+const DEFAULT_PROMPT: &str = "Answer the question using only the context below. \
+If the answer isn't in the context, say that you don't know.\n\nContext:\n{context}";
+const CONTEXT_SEPARATOR: &str = "\n\n---\n\n";
+
+/// Splits a document into the pieces that get embedded and retrieved.
+pub trait Chunker: Debug + Send + Sync {
+    fn chunk(&self, text: &str) -> Vec<String>;
+}
+
+/// Turns text into embedding vectors, one per input, in order.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Embedder: Debug + Send + Sync {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Windows of `size` characters, each starting `size - overlap` characters after the previous one.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedSizeChunker {
+    size: usize,
+    overlap: usize,
+}
+
+/// Paragraphs (separated by blank lines), merged up to `max_chars`.
+/// A paragraph longer than that is split with a `FixedSizeChunker`.
+#[derive(Debug, Clone, Copy)]
+pub struct ParagraphChunker {
+    max_chars: usize,
+}
+
+/// Embeds through the SDK's embeddings endpoint.
+#[derive(Debug, Clone)]
+pub struct SdkEmbedder {
+    sdk: LlmSdk,
+    model: EmbeddingModel,
+}
+
+/// A chunk of a document added to a `RagPipeline`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// The id the document was added with.
+    pub document: String,
+    pub text: String,
+}
+
+/// A chunk retrieved for a query, with its similarity score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrievedChunk {
+    pub chunk: Chunk,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RagAnswer {
+    /// The model's answer, empty if it returned no content.
+    pub answer: String,
+    /// The chunks given to the model as context, most similar first.
+    pub sources: Vec<RetrievedChunk>,
+    pub response: ChatCompletionResponse,
+}
+
+/// Retrieval-augmented generation: chunk and embed documents, then answer questions
+/// with the most similar chunks injected into the system prompt.
+///
+/// Every stage can be replaced: the chunker, the embedder, the number of chunks retrieved,
+/// the system prompt template (which must use `{context}`) and the chat model.
+#[derive(Debug)]
+pub struct RagPipeline {
+    sdk: LlmSdk,
+    chunker: Box<dyn Chunker>,
+    embedder: Arc<dyn Embedder>,
+    index: EmbeddingIndex<Chunk>,
+    top_k: usize,
+    prompt: PromptTemplate,
+    model: ChatCompleteModel,
+}
+
+impl FixedSizeChunker {
+    /// `overlap` is capped below `size` so every chunk makes progress.
+    pub fn new(size: usize, overlap: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            size,
+            overlap: overlap.min(size - 1),
+        }
+    }
+}
+
+impl Chunker for FixedSizeChunker {
+    fn chunk(&self, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let step = self.size - self.overlap;
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let end = (start + self.size).min(chars.len());
+            chunks.push(chars[start..end].iter().collect());
+            if end == chars.len() {
+                break;
+            }
+            start += step;
+        }
+        chunks
+    }
+}
+
+impl ParagraphChunker {
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            max_chars: max_chars.max(1),
+        }
+    }
+}
+
+impl Default for ParagraphChunker {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_CHARS)
+    }
+}
+
+impl Chunker for ParagraphChunker {
+    fn chunk(&self, text: &str) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        let paragraphs = text.split("\n\n").map(str::trim).filter(|p| !p.is_empty());
+        for paragraph in paragraphs {
+            let len = paragraph.chars().count();
+            if len > self.max_chars {
+                if !current.is_empty() {
+                    chunks.push(std::mem::take(&mut current));
+                }
+                chunks.extend(FixedSizeChunker::new(self.max_chars, 0).chunk(paragraph));
+                continue;
+            }
+            if !current.is_empty() && current.chars().count() + 2 + len > self.max_chars {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(paragraph);
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        chunks
+    }
+}
+
+impl SdkEmbedder {
+    pub fn new(sdk: LlmSdk, model: EmbeddingModel) -> Self {
+        Self { sdk, model }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Embedder for SdkEmbedder {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let req = CreateEmbeddingRequestBuilder::default()
+            .input(texts)
+            .model(self.model)
+            .build()?;
+        Ok(self.sdk.create_embedding(req).await?.into_vectors())
+    }
+}
+
+impl RagPipeline {
+    /// A pipeline embedding with the default embedding model and answering with the default chat model.
+    pub fn new(sdk: LlmSdk) -> Self {
+        Self {
+            embedder: Arc::new(SdkEmbedder::new(sdk.clone(), EmbeddingModel::default())),
+            sdk,
+            chunker: Box::new(ParagraphChunker::default()),
+            index: EmbeddingIndex::new(),
+            top_k: DEFAULT_TOP_K,
+            prompt: PromptTemplate::new(DEFAULT_PROMPT),
+            model: ChatCompleteModel::default(),
+        }
+    }
+
+    pub fn with_chunker(mut self, chunker: impl Chunker + 'static) -> Self {
+        self.chunker = Box::new(chunker);
+        self
+    }
+
+    /// Documents already added stay embedded with the previous embedder.
+    pub fn with_embedder(mut self, embedder: impl Embedder + 'static) -> Self {
+        self.embedder = Arc::new(embedder);
+        self
+    }
+
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// The system prompt; `{context}` is replaced with the retrieved chunks.
+    pub fn with_prompt(mut self, prompt: PromptTemplate) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    pub fn with_model(mut self, model: ChatCompleteModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// The number of chunks in the index.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Chunk, embed and index a document, returning the number of chunks added.
+    pub async fn add_document(&mut self, id: impl Into<String>, text: &str) -> Result<usize> {
+        let id = id.into();
+        let chunks = self.chunker.chunk(text);
+        for batch in chunks.chunks(EMBED_BATCH) {
+            let embeddings = self.embedder.embed(batch.to_vec()).await?;
+            let batch = batch.iter().map(|text| Chunk {
+                document: id.clone(),
+                text: text.clone(),
+            });
+            self.index.extend(batch.zip(embeddings))?;
+        }
+        Ok(chunks.len())
+    }
+
+    /// The `top_k` chunks most similar to `query`.
+    pub async fn retrieve(&self, query: &str) -> Result<Vec<RetrievedChunk>> {
+        if self.index.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query = self
+            .embedder
+            .embed(vec![query.to_string()])
+            .await?
+            .pop()
+            .unwrap_or_default();
+        let hits = self.index.search(&query, self.top_k)?;
+        Ok(hits
+            .into_iter()
+            .map(|hit| RetrievedChunk {
+                chunk: hit.item.clone(),
+                score: hit.score,
+            })
+            .collect())
+    }
+
+    /// The chat completion request answering `question` from the retrieved chunks.
+    pub async fn prepare(
+        &self,
+        question: &str,
+    ) -> Result<(ChatCompletionRequest, Vec<RetrievedChunk>)> {
+        let sources = self.retrieve(question).await?;
+        let context = sources
+            .iter()
+            .map(|source| source.chunk.text.as_str())
+            .collect::<Vec<_>>()
+            .join(CONTEXT_SEPARATOR);
+        let req = ChatCompletionRequestBuilder::default()
+            .model(self.model.clone())
+            .messages(vec![
+                self.prompt.render_system(&[("context", &context)])?,
+                ChatCompletionMessage::new_user(question, ""),
+            ])
+            .build()?;
+        Ok((req, sources))
+    }
+
+    pub async fn ask(&self, question: &str) -> Result<RagAnswer> {
+        let (req, sources) = self.prepare(question).await?;
+        let response = self.sdk.chat_completion(req).await?;
+        let answer = response.text().unwrap_or_default().to_string();
+        Ok(RagAnswer {
+            answer,
+            sources,
+            response,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Embeds text as the counts of a few keywords.
+    #[derive(Debug)]
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    ["rust", "python", "coffee"]
+                        .iter()
+                        .map(|word| text.matches(word).count() as f32)
+                        .collect()
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn fixed_size_chunker_should_overlap() {
+        let chunks = FixedSizeChunker::new(4, 1).chunk("abcdefghij");
+        assert_eq!(chunks, ["abcd", "defg", "ghij"]);
+        assert!(FixedSizeChunker::new(4, 1).chunk("").is_empty());
+    }
+
+    #[test]
+    fn paragraph_chunker_should_merge_small_paragraphs() {
+        let chunks = ParagraphChunker::new(12).chunk("one\n\ntwo\n\n\nthree four five");
+        assert_eq!(chunks, ["one\n\ntwo", "three four f", "ive"]);
+    }
+
+    #[tokio::test]
+    async fn rag_pipeline_should_inject_retrieved_chunks() -> Result<()> {
+        let mut rag = RagPipeline::new(LlmSdk::new("sk-test".to_string()))
+            .with_embedder(KeywordEmbedder)
+            .with_top_k(1)
+            .with_prompt(PromptTemplate::new("Context:\n{context}"));
+        let added = rag
+            .add_document(
+                "notes",
+                "Rust is a systems language.\n\nPython is a scripting language.",
+            )
+            .await?;
+        assert_eq!(added, 1);
+        rag.add_document("cafe", "Coffee is brewed from beans.")
+            .await?;
+        assert_eq!(rag.len(), 2);
+
+        let (req, sources) = rag.prepare("How is coffee made?").await?;
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].chunk.document, "cafe");
+        assert_eq!(
+            req.messages()[0].content(),
+            Some("Context:\nCoffee is brewed from beans.")
+        );
+        assert_eq!(req.messages()[1].content(), Some("How is coffee made?"));
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+/// Rewrite almost-JSON as models sometimes produce it into JSON.
+///
+/// Fixes single-quoted strings, unquoted object keys, trailing commas, Python's
+/// `True`/`False`/`None` and a surrounding markdown code fence. Anything else is left as is,
+/// so the result of invalid input is still invalid.
+pub fn repair_json(input: &str) -> String {
+    let input = strip_code_fence(input);
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::with_capacity(input.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => i = copy_string(&chars, i, &mut out),
+            ',' if matches!(next_non_space(&chars, i + 1), Some('}' | ']')) => i += 1,
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$'))
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if next_non_space(&chars, i) == Some(':') {
+                    out.push('"');
+                    out.push_str(&word);
+                    out.push('"');
+                } else {
+                    out.push_str(match word.as_str() {
+                        "True" => "true",
+                        "False" => "false",
+                        "None" => "null",
+                        word => word,
+                    });
+                }
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Close JSON that was cut off, e.g. by the token limit, into the longest valid JSON it starts.
+///
+/// Open strings, arrays and objects are closed; a value, key or number cut in the middle of
+/// its syntax is dropped along with its key, back to the last complete element. So is a
+/// number or literal the text ends with, since `2` may have been cut from `25`. Text before
+/// the first `{` or `[`, such as an opening code fence, is skipped. Returns `None` without
+/// either.
+///
+/// ```
+/// # use llm_sdk::close_json;
+/// assert_eq!(close_json(r#"{"tags": ["a", "b"], "note": "cut her"#).unwrap(), r#"{"tags": ["a", "b"], "note": "cut her"}"#);
+/// assert_eq!(close_json(r#"{"a": [1, 2], "b": tr"#).unwrap(), r#"{"a": [1, 2]}"#);
+/// ```
+pub fn close_json(input: &str) -> Option<String> {
+    let input = &input[input.find(['{', '['])?..];
+    let input = input.trim_end();
+    if serde_json::from_str::<serde::de::IgnoredAny>(input).is_ok() {
+        return Some(input.to_string());
+    }
+    // where the text may be cut: before a comma, or right after an opening bracket
+    let mut cuts = vec![];
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            ',' => cuts.push(i),
+            '{' | '[' => cuts.push(i + 1),
+            _ => {}
+        }
+    }
+    // a bare number or literal at the end may be cut short however valid it looks
+    let whole = in_string || input.ends_with(['"', ',', ':', '{', '[', '}', ']']);
+    whole
+        .then_some(input.len())
+        .into_iter()
+        .chain(cuts.into_iter().rev())
+        .find_map(|cut| {
+            let mut closed = input[..cut].trim_end().to_string();
+            closed.push_str(&closers(&closed));
+            serde_json::from_str::<serde::de::IgnoredAny>(&closed)
+                .is_ok()
+                .then_some(closed)
+        })
+}
+
+/// What closes the strings, arrays and objects left open at the end of `prefix`.
+fn closers(prefix: &str) -> String {
+    let mut open = vec![];
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in prefix.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '{' => open.push('}'),
+            '[' => open.push(']'),
+            '}' | ']' => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    // a dangling escape can't be closed, but cutting further back can
+    let mut closers = String::new();
+    if in_string && !escaped {
+        closers.push('"');
+    }
+    closers.extend(open.into_iter().rev());
+    closers
+}
+
+/// Copy the string starting at `start` as a double-quoted JSON string, returning the index after it.
+fn copy_string(chars: &[char], start: usize, out: &mut String) -> usize {
+    let quote = chars[start];
+    out.push('"');
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                // `\'` isn't a JSON escape
+                if chars[i + 1] != '\'' {
+                    out.push('\\');
+                }
+                out.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            c if c == quote => {
+                out.push('"');
+                return i + 1;
+            }
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+        i += 1;
+    }
+    out.push('"');
+    i
+}
+
+fn next_non_space(chars: &[char], from: usize) -> Option<char> {
+    chars[from.min(chars.len())..]
+        .iter()
+        .copied()
+        .find(|c| !c.is_whitespace())
+}
+
+/// The JSON inside a markdown code fence, which some models wrap replies in despite JSON mode.
+pub(crate) fn strip_code_fence(content: &str) -> &str {
+    let content = content.trim();
+    content
+        .strip_prefix("```json")
+        .or_else(|| content.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn parse(input: &str) -> Value {
+        serde_json::from_str(&repair_json(input)).unwrap()
+    }
+
+    #[test]
+    fn repair_json_should_fix_common_mistakes() {
+        assert_eq!(
+            parse("{location: 'Paris, \"FR\"', days: [1, 2,], }"),
+            json!({ "location": "Paris, \"FR\"", "days": [1, 2] })
+        );
+        assert_eq!(
+            parse("```json\n{'ok': True, 'error': None, 'it\\'s': 'a\nb'}\n```"),
+            json!({ "ok": true, "error": null, "it's": "a\nb" })
+        );
+        assert_eq!(strip_code_fence(" {\"a\": 1} "), "{\"a\": 1}");
+        // valid JSON is untouched
+        let valid = r#"{"a": "x, }", "b": [true, null]}"#;
+        assert_eq!(repair_json(valid), valid);
+    }
+
+    #[test]
+    fn close_json_should_close_cut_off_json() {
+        let close =
+            |input| close_json(input).map(|closed| serde_json::from_str::<Value>(&closed).unwrap());
+        assert_eq!(
+            close("```json\n{\"items\": [{\"id\": 1, \"name\": \"a \\\"b"),
+            Some(json!({ "items": [{ "id": 1, "name": "a \"b" }] }))
+        );
+        // an unfinished key, literal, number or escape is dropped
+        assert_eq!(close(r#"{"a": 1, "b"#), Some(json!({ "a": 1 })));
+        assert_eq!(close(r#"{"a": 1, "b": nu"#), Some(json!({ "a": 1 })));
+        assert_eq!(close(r#"[1, 2.5e"#), Some(json!([1])));
+        assert_eq!(close(r#"[1, 2"#), Some(json!([1])));
+        assert_eq!(close(r#"{"a": "x", "b": true"#), Some(json!({ "a": "x" })));
+        assert_eq!(close(r#"{"a": [1, 2] "#), Some(json!({ "a": [1, 2] })));
+        assert_eq!(close(r#"{"a": [], "b": "x\u00"#), Some(json!({ "a": [] })));
+        assert_eq!(close(r#"{"a": {"b": "#), Some(json!({ "a": {} })));
+        assert_eq!(close(r#"[{"a": 1}, "#), Some(json!([{ "a": 1 }])));
+        // complete JSON is untouched
+        assert_eq!(close_json("{\"a\": [1]}\n").unwrap(), "{\"a\": [1]}");
+        assert_eq!(close("Sure! {\"a"), Some(json!({})));
+        assert_eq!(close("no JSON"), None);
+    }
+}
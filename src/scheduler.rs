@@ -0,0 +1,304 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::oneshot;
+use web_time::Instant;
+
+/// Which requests go first when a `Scheduler` is busy, see `LlmSdk::with_priority`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Someone is waiting for the reply, e.g. a chat UI.
+    #[default]
+    Interactive,
+    /// Nobody is waiting, e.g. embedding a corpus or an eval run. Sent only when no
+    /// interactive request is queued.
+    Batch,
+}
+
+/// Shares a budget of in-flight requests between interactive and batch work.
+///
+/// At most `max_in_flight` requests are sent at once by the SDK and its clones; the others
+/// wait in a queue per `Priority`. When a request finishes, the interactive request that
+/// waited longest is sent next, and a batch request only when no interactive one is queued:
+/// a batch job can use the whole budget, yet a user waits for one slot at most. Set one with
+/// `LlmSdk::with_scheduler`; clones share the budget.
+///
+/// A slot is freed once the response headers arrive, so a streamed response doesn't hold it
+/// while it streams. `stats` reports the queue depth and wait times, e.g. to shed batch work
+/// while the API throttles.
+///
+/// ```no_run
+/// # use llm_sdk::{LlmSdk, Priority, Scheduler};
+/// # fn run(sdk: LlmSdk) {
+/// let sdk = sdk.with_scheduler(Scheduler::new(8));
+/// let batch = sdk.clone().with_priority(Priority::Batch);
+/// // hand `sdk` to request handlers and `batch` to background jobs
+/// if batch.scheduler().unwrap().stats().interactive.queued > 0 {
+///     // users are waiting: postpone the next background job
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+/// The counters of a `Scheduler`, see `Scheduler::stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchedulerStats {
+    pub max_in_flight: usize,
+    /// Requests sent and not answered yet.
+    pub in_flight: usize,
+    pub interactive: QueueStats,
+    pub batch: QueueStats,
+}
+
+/// The counters of the queue of one priority.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Requests waiting for a slot now.
+    pub queued: usize,
+    /// Requests that got a slot, right away or after waiting.
+    pub started: usize,
+    /// The time requests that got a slot spent waiting for it.
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+}
+
+#[derive(Debug)]
+struct SchedulerState {
+    max_in_flight: usize,
+    in_flight: usize,
+    interactive: VecDeque<Waiter>,
+    batch: VecDeque<Waiter>,
+    next_id: u64,
+    interactive_stats: QueueStats,
+    batch_stats: QueueStats,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    grant: oneshot::Sender<()>,
+}
+
+/// A queued request; dropping it gives up its place in the queue, or its slot if it was
+/// granted one in the meantime.
+struct Waiting {
+    state: Arc<Mutex<SchedulerState>>,
+    priority: Priority,
+    id: u64,
+    grant: Option<oneshot::Receiver<()>>,
+}
+
+/// A slot of a `Scheduler`, freed on drop.
+pub(crate) struct Permit {
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+impl Scheduler {
+    pub fn new(max_in_flight: usize) -> Self {
+        let state = SchedulerState {
+            max_in_flight: max_in_flight.max(1),
+            in_flight: 0,
+            interactive: VecDeque::new(),
+            batch: VecDeque::new(),
+            next_id: 0,
+            interactive_stats: QueueStats::default(),
+            batch_stats: QueueStats::default(),
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        let state = self.state.lock().unwrap();
+        SchedulerStats {
+            max_in_flight: state.max_in_flight,
+            in_flight: state.in_flight,
+            interactive: QueueStats {
+                queued: state.interactive.len(),
+                ..state.interactive_stats
+            },
+            batch: QueueStats {
+                queued: state.batch.len(),
+                ..state.batch_stats
+            },
+        }
+    }
+
+    /// Wait for a slot. Requests of the same priority get one in the order they asked.
+    pub(crate) async fn acquire(&self, priority: Priority) -> Permit {
+        let start = Instant::now();
+        let mut waiting = {
+            let mut state = self.state.lock().unwrap();
+            if state.in_flight < state.max_in_flight
+                && state.interactive.is_empty()
+                && state.batch.is_empty()
+            {
+                state.in_flight += 1;
+                state.stats_mut(priority).record(Duration::ZERO);
+                return self.permit();
+            }
+            let (grant, granted) = oneshot::channel();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.queue_mut(priority).push_back(Waiter { id, grant });
+            Waiting {
+                state: self.state.clone(),
+                priority,
+                id,
+                grant: Some(granted),
+            }
+        };
+        // the sender is only dropped after sending, the state being kept alive by `waiting`
+        let _ = waiting.grant.as_mut().unwrap().await;
+        waiting.grant = None;
+        let mut state = self.state.lock().unwrap();
+        state.stats_mut(priority).record(start.elapsed());
+        self.permit()
+    }
+
+    fn permit(&self) -> Permit {
+        Permit {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl SchedulerStats {
+    /// Requests waiting for a slot now, of any priority.
+    pub fn queued(&self) -> usize {
+        self.interactive.queued + self.batch.queued
+    }
+}
+
+impl QueueStats {
+    /// The average time a request waited for a slot, zero if none got one.
+    pub fn mean_wait(&self) -> Duration {
+        match self.started {
+            0 => Duration::ZERO,
+            started => self.total_wait / started as u32,
+        }
+    }
+
+    fn record(&mut self, wait: Duration) {
+        self.started += 1;
+        self.total_wait += wait;
+        self.max_wait = self.max_wait.max(wait);
+    }
+}
+
+impl SchedulerState {
+    fn queue_mut(&mut self, priority: Priority) -> &mut VecDeque<Waiter> {
+        match priority {
+            Priority::Interactive => &mut self.interactive,
+            Priority::Batch => &mut self.batch,
+        }
+    }
+
+    fn stats_mut(&mut self, priority: Priority) -> &mut QueueStats {
+        match priority {
+            Priority::Interactive => &mut self.interactive_stats,
+            Priority::Batch => &mut self.batch_stats,
+        }
+    }
+
+    /// Free a slot and hand it to the next queued request, interactive ones first.
+    fn release(&mut self) {
+        self.in_flight -= 1;
+        while self.in_flight < self.max_in_flight {
+            let Some(waiter) = self
+                .interactive
+                .pop_front()
+                .or_else(|| self.batch.pop_front())
+            else {
+                break;
+            };
+            if waiter.grant.send(()).is_ok() {
+                self.in_flight += 1;
+            }
+        }
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        let Some(mut granted) = self.grant.take() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        let id = self.id;
+        let queue = state.queue_mut(self.priority);
+        if let Some(i) = queue.iter().position(|waiter| waiter.id == id) {
+            queue.remove(i);
+        } else if granted.try_recv().is_ok() {
+            state.release();
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{poll, FutureExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn scheduler_should_send_interactive_requests_first() {
+        let scheduler = Scheduler::new(1);
+        let first = scheduler.acquire(Priority::Batch).await;
+        let mut batch = scheduler.acquire(Priority::Batch).boxed();
+        let mut interactive = scheduler.acquire(Priority::Interactive).boxed();
+        assert!(poll!(&mut batch).is_pending());
+        assert!(poll!(&mut interactive).is_pending());
+        let stats = scheduler.stats();
+        assert_eq!((stats.in_flight, stats.queued()), (1, 2));
+
+        // the interactive request asked last but goes first
+        drop(first);
+        let second = poll!(&mut interactive);
+        assert!(second.is_ready());
+        assert!(poll!(&mut batch).is_pending());
+        drop(second);
+        let third = poll!(&mut batch);
+        assert!(third.is_ready());
+        drop(third);
+
+        let stats = scheduler.stats();
+        assert_eq!((stats.in_flight, stats.queued()), (0, 0));
+        assert_eq!((stats.interactive.started, stats.batch.started), (1, 2));
+        assert!(stats.batch.max_wait >= stats.batch.mean_wait());
+    }
+
+    #[tokio::test]
+    async fn scheduler_should_reclaim_slots_of_cancelled_requests() {
+        let scheduler = Scheduler::new(1);
+        let first = scheduler.acquire(Priority::Interactive).await;
+        let mut queued = scheduler.acquire(Priority::Batch).boxed();
+        assert!(poll!(&mut queued).is_pending());
+        drop(queued);
+        assert_eq!(scheduler.stats().queued(), 0);
+
+        // granted a slot, but dropped before it could use it
+        let mut granted = scheduler.acquire(Priority::Batch).boxed();
+        assert!(poll!(&mut granted).is_pending());
+        drop(first);
+        drop(granted);
+        assert_eq!(scheduler.stats().in_flight, 0);
+        assert!(scheduler
+            .acquire(Priority::Interactive)
+            .now_or_never()
+            .is_some());
+    }
+}
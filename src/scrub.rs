@@ -0,0 +1,182 @@
+use std::fmt::Debug;
+
+use regex::Regex;
+
+/// Rewrites the text of chat completion messages before they are sent.
+///
+/// Scrubbers run in the order they were added with `LlmSdk::with_scrubber`, on every text of
+/// every message, before the request reaches the cache or the API. Record what is replaced in
+/// the `RedactionMap` so it can be put back into the reply, see `RestoreRedactions`.
+pub trait Scrubber: Debug + Send + Sync {
+    fn scrub(&self, text: &str, redactions: &mut RedactionMap) -> String;
+}
+
+/// Rewrites the text of the choices of a chat completion before it is returned.
+///
+/// Filters run in the order they were added with `LlmSdk::with_output_filter`. Streamed
+/// completions are not filtered.
+pub trait OutputFilter: Debug + Send + Sync {
+    fn filter(&self, text: &str, redactions: &RedactionMap) -> String;
+}
+
+/// The values replaced by scrubbers, by the placeholder sent in their place.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactionMap {
+    // (placeholder, original), in order of first appearance
+    entries: Vec<(String, String)>,
+}
+
+/// Replaces emails, phone numbers and API keys with placeholders such as `[EMAIL_1]`.
+#[derive(Debug, Clone)]
+pub struct PiiRedactor {
+    patterns: Vec<(String, Regex)>,
+}
+
+/// Puts the values replaced by scrubbers back into the reply, so they never leave the process
+/// but the caller still sees them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestoreRedactions;
+
+impl RedactionMap {
+    /// The placeholder for `original`, e.g. `[EMAIL_2]` for the second email.
+    /// The same value always gets the same placeholder.
+    pub fn redact(&mut self, kind: &str, original: &str) -> String {
+        let prefix = format!("[{}_", kind);
+        if let Some((placeholder, _)) = self
+            .entries
+            .iter()
+            .find(|(p, o)| o == original && p.starts_with(&prefix))
+        {
+            return placeholder.clone();
+        }
+        let n = self
+            .entries
+            .iter()
+            .filter(|(p, _)| p.starts_with(&prefix))
+            .count();
+        let placeholder = format!("{}{}]", prefix, n + 1);
+        self.entries
+            .push((placeholder.clone(), original.to_string()));
+        placeholder
+    }
+
+    /// The value replaced by `placeholder`.
+    pub fn original(&self, placeholder: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(p, _)| p == placeholder)
+            .map(|(_, o)| o.as_str())
+    }
+
+    /// `text` with every placeholder replaced by its original value.
+    pub fn restore(&self, text: &str) -> String {
+        self.entries
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, original)| {
+                text.replace(placeholder, original)
+            })
+    }
+
+    /// The placeholders and original values, in order of first appearance.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(p, o)| (p.as_str(), o.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl PiiRedactor {
+    pub fn new() -> Self {
+        Self::empty()
+            // before phone numbers, which may match the digits of a key
+            .with_pattern(
+                "API_KEY",
+                Regex::new(r"\b(?:sk|pk|rk)-[A-Za-z0-9_-]{16,}|\bAKIA[0-9A-Z]{16}\b|\bgh[pousr]_[A-Za-z0-9]{36,}")
+                    .unwrap(),
+            )
+            .with_pattern(
+                "EMAIL",
+                Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b").unwrap(),
+            )
+            .with_pattern(
+                "PHONE",
+                Regex::new(r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{3}\)|\b\d{3})[\s.-]?\d{3}[\s.-]?\d{4}\b")
+                    .unwrap(),
+            )
+    }
+
+    /// A redactor without the built-in patterns.
+    pub fn empty() -> Self {
+        Self {
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Also redact the matches of `pattern` as `[{kind}_n]`, after the patterns already added.
+    pub fn with_pattern(mut self, kind: impl Into<String>, pattern: Regex) -> Self {
+        self.patterns.push((kind.into(), pattern));
+        self
+    }
+}
+
+impl Default for PiiRedactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scrubber for PiiRedactor {
+    fn scrub(&self, text: &str, redactions: &mut RedactionMap) -> String {
+        self.patterns
+            .iter()
+            .fold(text.to_string(), |text, (kind, pattern)| {
+                pattern
+                    .replace_all(&text, |caps: &regex::Captures| {
+                        redactions.redact(kind, &caps[0])
+                    })
+                    .into_owned()
+            })
+    }
+}
+
+impl OutputFilter for RestoreRedactions {
+    fn filter(&self, text: &str, redactions: &RedactionMap) -> String {
+        redactions.restore(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pii_redactor_should_redact_reversibly() {
+        let mut redactions = RedactionMap::default();
+        let text =
+            "Mail jane.doe@example.com or +1 415-555-0132, key sk-proj-abcdefghijklmnop1234. \
+                    Again: jane.doe@example.com";
+        let scrubbed = PiiRedactor::new().scrub(text, &mut redactions);
+        assert_eq!(
+            scrubbed,
+            "Mail [EMAIL_1] or [PHONE_1], key [API_KEY_1]. Again: [EMAIL_1]"
+        );
+        assert_eq!(redactions.len(), 3);
+        assert_eq!(redactions.original("[PHONE_1]"), Some("+1 415-555-0132"));
+        assert_eq!(redactions.restore(&scrubbed), text);
+    }
+
+    #[test]
+    fn pii_redactor_should_accept_custom_patterns() {
+        let mut redactions = RedactionMap::default();
+        let redactor =
+            PiiRedactor::empty().with_pattern("ACCOUNT", Regex::new(r"ACC-\d+").unwrap());
+        let scrubbed = redactor.scrub("ACC-1 and ACC-22 to a@b.io", &mut redactions);
+        assert_eq!(scrubbed, "[ACCOUNT_1] and [ACCOUNT_2] to a@b.io");
+    }
+}
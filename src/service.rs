@@ -0,0 +1,59 @@
+use std::task::{Context, Poll};
+
+use anyhow::Result;
+use bytes::Bytes;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::future::BoxFuture;
+#[cfg(target_arch = "wasm32")]
+use futures::future::LocalBoxFuture as BoxFuture;
+use tower_service::Service;
+
+use crate::{
+    ChatCompletionRequest, ChatCompletionResponse, CreateCompletionRequest,
+    CreateCompletionResponse, CreateEmbeddingRequest, CreateEmbeddingResponse, CreateImageRequest,
+    CreateImageResponse, CreateModerationRequest, CreateModerationResponse, CreateResponseRequest,
+    CreateSpeechRequest, LlmSdk, ModelResponse,
+};
+
+/// `LlmSdk` as a `tower::Service` of each request type, so tower middleware such as rate
+/// limiting, load shedding, timeouts or metrics can be layered on it:
+///
+/// ```ignore
+/// let mut chat = ServiceBuilder::new()
+///     .concurrency_limit(8)
+///     .timeout(Duration::from_secs(30))
+///     .service(sdk.clone());
+/// let res: ChatCompletionResponse = chat.ready().await?.call(req).await?;
+/// ```
+///
+/// The SDK is always ready; each call runs on a clone of it.
+macro_rules! impl_service {
+    ($($request:ty => $method:ident -> $response:ty),* $(,)?) => {
+        $(
+            impl Service<$request> for LlmSdk {
+                type Response = $response;
+                type Error = anyhow::Error;
+                type Future = BoxFuture<'static, Result<$response>>;
+
+                fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+                    Poll::Ready(Ok(()))
+                }
+
+                fn call(&mut self, req: $request) -> Self::Future {
+                    let sdk = self.clone();
+                    Box::pin(async move { sdk.$method(req).await })
+                }
+            }
+        )*
+    };
+}
+
+impl_service! {
+    ChatCompletionRequest => chat_completion -> ChatCompletionResponse,
+    CreateCompletionRequest => create_completion -> CreateCompletionResponse,
+    CreateResponseRequest => create_response -> ModelResponse,
+    CreateEmbeddingRequest => create_embedding -> CreateEmbeddingResponse,
+    CreateImageRequest => create_image -> CreateImageResponse,
+    CreateModerationRequest => create_moderation -> CreateModerationResponse,
+    CreateSpeechRequest => create_speech -> Bytes,
+}
@@ -0,0 +1,137 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Request,
+};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Signs every request sent by `LlmSdk`, for gateways that authenticate callers by signature.
+///
+/// The signer set with `LlmSdk::with_signer` runs on every endpoint, after the interceptors and
+/// body compression, so it signs the bytes that are sent. A request moved to another key of a
+/// `KeyPool` is signed again.
+pub trait RequestSigner: Debug + Send + Sync {
+    fn sign(&self, req: &mut Request) -> Result<()>;
+}
+
+/// Sent instead of the body hash for bodies that are streamed, e.g. file uploads.
+pub const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Signs requests with HMAC-SHA256 of the method, path, timestamp and body.
+///
+/// The string signed is
+///
+/// ```text
+/// {timestamp}\n{METHOD}\n{path and query}\n{hex SHA-256 of the body}
+/// ```
+///
+/// with the timestamp in Unix seconds, and the headers sent are `X-Key-Id`, `X-Timestamp`
+/// and `X-Signature` (the hex HMAC), unless renamed with `with_headers`.
+#[derive(Debug, Clone)]
+pub struct HmacSigner {
+    key_id: String,
+    /// Zeroized on drop and redacted from `Debug`.
+    secret: SecretString,
+    key_id_header: HeaderName,
+    timestamp_header: HeaderName,
+    signature_header: HeaderName,
+}
+
+impl HmacSigner {
+    pub fn new(key_id: impl Into<String>, secret: impl Into<SecretString>) -> Self {
+        Self {
+            key_id: key_id.into(),
+            secret: secret.into(),
+            key_id_header: HeaderName::from_static("x-key-id"),
+            timestamp_header: HeaderName::from_static("x-timestamp"),
+            signature_header: HeaderName::from_static("x-signature"),
+        }
+    }
+
+    /// Send the key id, timestamp and signature in the headers the gateway expects.
+    pub fn with_headers(mut self, key_id: &str, timestamp: &str, signature: &str) -> Result<Self> {
+        self.key_id_header = HeaderName::try_from(key_id)?;
+        self.timestamp_header = HeaderName::try_from(timestamp)?;
+        self.signature_header = HeaderName::try_from(signature)?;
+        Ok(self)
+    }
+
+    /// The hex signature of `req` at `timestamp`.
+    pub fn signature(&self, req: &Request, timestamp: u64) -> Result<String> {
+        let body_hash = match req.body() {
+            None => format!("{:x}", Sha256::digest(b"")),
+            Some(body) => match body.as_bytes() {
+                Some(bytes) => format!("{:x}", Sha256::digest(bytes)),
+                None => UNSIGNED_PAYLOAD.to_string(),
+            },
+        };
+        let url = req.url();
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.expose_secret().as_bytes())?;
+        mac.update(format!("{}\n{}\n{}\n{}", timestamp, req.method(), path, body_hash).as_bytes());
+        Ok(format!("{:x}", mac.finalize().into_bytes()))
+    }
+}
+
+impl RequestSigner for HmacSigner {
+    fn sign(&self, req: &mut Request) -> Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let signature = self.signature(req, timestamp)?;
+        let headers = req.headers_mut();
+        headers.insert(
+            self.key_id_header.clone(),
+            HeaderValue::try_from(&self.key_id)?,
+        );
+        headers.insert(self.timestamp_header.clone(), HeaderValue::from(timestamp));
+        let mut signature = HeaderValue::try_from(signature)?;
+        signature.set_sensitive(true);
+        headers.insert(self.signature_header.clone(), signature);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChatCompletionRequestBuilder, LlmSdk};
+
+    #[test]
+    fn hmac_signer_should_sign_the_sent_body() -> Result<()> {
+        let sdk = LlmSdk::new("sk-test")
+            .with_request_compression(16)
+            .with_signer(HmacSigner::new("team-a", "s3cret"));
+        let req = ChatCompletionRequestBuilder::default()
+            .user_message("a message long enough to be compressed")
+            .build()?;
+        let req = sdk.build_request(req, "application/json")?;
+        assert_eq!(req.headers()["x-key-id"], "team-a");
+        let timestamp: u64 = req.headers()["x-timestamp"].to_str()?.parse()?;
+
+        // the gzipped body is what gets signed
+        let body = req.body().and_then(|body| body.as_bytes()).unwrap();
+        assert_eq!(&body[..2], [0x1f, 0x8b]);
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret")?;
+        mac.update(
+            format!(
+                "{}\nPOST\n/v1/chat/completions\n{:x}",
+                timestamp,
+                Sha256::digest(body)
+            )
+            .as_bytes(),
+        );
+        let expected = format!("{:x}", mac.finalize().into_bytes());
+        assert_eq!(req.headers()["x-signature"], expected.as_str());
+        Ok(())
+    }
+}
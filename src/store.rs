@@ -0,0 +1,225 @@
+use std::fmt::Debug;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io::ErrorKind, path::PathBuf};
+
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::Conversation;
+
+/// Persists conversations so a chatbot can resume them across sessions.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ChatStore: Debug + Send + Sync {
+    /// Save the conversation, replacing any previous version with the same id.
+    async fn save_conversation(&self, conversation: &Conversation) -> Result<()>;
+    /// Load a conversation, or `None` if none was saved under `id`.
+    async fn load_conversation(&self, id: &str) -> Result<Option<Conversation>>;
+    async fn delete_conversation(&self, id: &str) -> Result<()>;
+}
+
+/// Stores each conversation as `<id>.json` in a directory.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct JsonFileStore {
+    dir: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl JsonFileStore {
+    /// The directory is created on the first save.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        // ids become file names, so keep them from escaping the directory
+        let valid = !id.is_empty()
+            && !id.starts_with('.')
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            bail!("invalid conversation id for a file store: {:?}", id);
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl ChatStore for JsonFileStore {
+    async fn save_conversation(&self, conversation: &Conversation) -> Result<()> {
+        let path = self.path(conversation.id())?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // write then rename, so a crash never leaves a half-written conversation
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(conversation)?).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn load_conversation(&self, id: &str) -> Result<Option<Conversation>> {
+        match tokio::fs::read(self.path(id)?).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete_conversation(&self, id: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(id)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::{
+        path::Path,
+        sync::{Arc, Mutex},
+    };
+
+    use rusqlite::{Connection, OptionalExtension};
+
+    use super::*;
+
+    /// Stores conversations as JSON in a `conversations` table of a SQLite database.
+    #[derive(Debug, Clone)]
+    pub struct SqliteStore {
+        conn: Arc<Mutex<Connection>>,
+    }
+
+    impl SqliteStore {
+        pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+            Self::init(Connection::open(path)?)
+        }
+
+        pub fn open_in_memory() -> Result<Self> {
+            Self::init(Connection::open_in_memory()?)
+        }
+
+        fn init(conn: Connection) -> Result<Self> {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS conversations (
+                    id TEXT PRIMARY KEY,
+                    data TEXT NOT NULL,
+                    updated_at INTEGER NOT NULL DEFAULT (unixepoch())
+                )",
+            )?;
+            Ok(Self {
+                conn: Arc::new(Mutex::new(conn)),
+            })
+        }
+
+        /// Run a blocking database call off the async runtime.
+        async fn with_conn<T, F>(&self, f: F) -> Result<T>
+        where
+            T: Send + 'static,
+            F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+        {
+            let conn = self.conn.clone();
+            let res = tokio::task::spawn_blocking(move || f(&conn.lock().unwrap())).await??;
+            Ok(res)
+        }
+    }
+
+    #[async_trait]
+    impl ChatStore for SqliteStore {
+        async fn save_conversation(&self, conversation: &Conversation) -> Result<()> {
+            let id = conversation.id().to_string();
+            let data = serde_json::to_string(conversation)?;
+            self.with_conn(move |conn| {
+                conn.execute(
+                    "INSERT INTO conversations (id, data) VALUES (?1, ?2)
+                     ON CONFLICT(id) DO UPDATE SET data = excluded.data, updated_at = unixepoch()",
+                    (id, data),
+                )
+            })
+            .await?;
+            Ok(())
+        }
+
+        async fn load_conversation(&self, id: &str) -> Result<Option<Conversation>> {
+            let id = id.to_string();
+            let data: Option<String> = self
+                .with_conn(move |conn| {
+                    conn.query_row(
+                        "SELECT data FROM conversations WHERE id = ?1",
+                        [id],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                })
+                .await?;
+            Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+        }
+
+        async fn delete_conversation(&self, id: &str) -> Result<()> {
+            let id = id.to_string();
+            self.with_conn(move |conn| {
+                conn.execute("DELETE FROM conversations WHERE id = ?1", [id])
+            })
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChatCompletionMessage;
+
+    fn conversation() -> Conversation {
+        let mut conversation = Conversation::new("session-1").with_system("You are helpful.");
+        conversation.push(ChatCompletionMessage::new_user("hi", ""));
+        conversation.push(ChatCompletionMessage::new_assistant("Hello!", "", vec![]));
+        conversation
+    }
+
+    async fn round_trip(store: &dyn ChatStore) -> Result<()> {
+        assert!(store.load_conversation("session-1").await?.is_none());
+        store.save_conversation(&conversation()).await?;
+        let loaded = store.load_conversation("session-1").await?.unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded)?,
+            serde_json::to_value(conversation())?
+        );
+
+        let mut updated = loaded;
+        updated.push(ChatCompletionMessage::new_user("bye", ""));
+        store.save_conversation(&updated).await?;
+        assert_eq!(
+            store.load_conversation("session-1").await?.unwrap().len(),
+            4
+        );
+
+        store.delete_conversation("session-1").await?;
+        assert!(store.load_conversation("session-1").await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn json_file_store_should_round_trip() -> Result<()> {
+        let dir = std::env::temp_dir().join("llm-sdk-json-file-store");
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        let store = JsonFileStore::new(&dir);
+        round_trip(&store).await?;
+        assert!(store.load_conversation("../etc/passwd").await.is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn sqlite_store_should_round_trip() -> Result<()> {
+        round_trip(&SqliteStore::open_in_memory()?).await
+    }
+}
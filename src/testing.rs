@@ -0,0 +1,129 @@
+//! Stubs shared by the tests of every module.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+    Request, Response,
+};
+use serde_json::{json, Value};
+
+use crate::HttpClient;
+
+/// Answers every request with the next scripted response and records what was sent.
+///
+/// The recordings are shared, so a test clones them before handing the client to
+/// `LlmSdk::with_http_client`.
+#[derive(Debug, Default)]
+pub(crate) struct ScriptedClient {
+    /// The status and JSON body of each response, in order. A string body is sent as is, as
+    /// `text/html`, like the error page of a gateway; `null` is sent as no body at all.
+    responses: Mutex<VecDeque<(u16, Value)>>,
+    /// Added to every response.
+    response_headers: HeaderMap,
+    /// Never answer, e.g. to run into a deadline.
+    hang: bool,
+    pub urls: Arc<Mutex<Vec<String>>>,
+    /// The `model` of every request body, empty if it has none.
+    pub models: Arc<Mutex<Vec<String>>>,
+    /// Every request body, `null` if it isn't JSON.
+    pub bodies: Arc<Mutex<Vec<Value>>>,
+    pub headers: Arc<Mutex<Vec<HeaderMap>>>,
+}
+
+impl ScriptedClient {
+    pub fn new(responses: impl IntoIterator<Item = (u16, Value)>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into_iter().collect()),
+            ..Default::default()
+        }
+    }
+
+    /// Answer with `bodies`, all with a 200 status.
+    pub fn replying(bodies: impl IntoIterator<Item = Value>) -> Self {
+        Self::new(bodies.into_iter().map(|body| (200, body)))
+    }
+
+    /// Record requests but never answer them.
+    pub fn hanging() -> Self {
+        Self {
+            hang: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_response_header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.response_headers
+            .insert(name, HeaderValue::from_static(value));
+        self
+    }
+}
+
+#[async_trait]
+impl HttpClient for ScriptedClient {
+    async fn execute(&self, req: Request) -> Result<Response> {
+        let body: Value = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|bytes| serde_json::from_slice(bytes).ok())
+            .unwrap_or_default();
+        let model = body["model"].as_str().unwrap_or_default().to_string();
+        self.urls.lock().unwrap().push(req.url().to_string());
+        self.models.lock().unwrap().push(model);
+        self.bodies.lock().unwrap().push(body);
+        self.headers.lock().unwrap().push(req.headers().clone());
+        if self.hang {
+            return futures::future::pending().await;
+        }
+        let (status, body) = self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("no scripted response left");
+        let mut res = http::Response::builder().status(status);
+        let body = match body {
+            Value::Null => String::new(),
+            Value::String(page) => {
+                res = res.header(CONTENT_TYPE, "text/html");
+                page
+            }
+            body => {
+                res = res.header(CONTENT_TYPE, "application/json");
+                body.to_string()
+            }
+        };
+        let mut res = res.body(body)?;
+        res.headers_mut().extend(self.response_headers.clone());
+        Ok(res.into())
+    }
+}
+
+/// A `chat.completion` with one choice replying `content`, and `(prompt, completion)` tokens.
+pub(crate) fn completion_json(
+    content: &str,
+    finish_reason: &str,
+    (prompt_tokens, completion_tokens): (usize, usize),
+) -> Value {
+    json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o-mini",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": content },
+            "finish_reason": finish_reason
+        }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens
+        }
+    })
+}
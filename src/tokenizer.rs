@@ -0,0 +1,161 @@
+use std::fmt::Debug;
+
+use crate::{ChatCompleteModel, ChatCompletionMessage, ChatCompletionRequest};
+
+/// Tokens every message costs on top of its content, see
+/// https://github.com/openai/openai-cookbook/blob/main/examples/How_to_count_tokens_with_tiktoken.ipynb
+const TOKENS_PER_MESSAGE: usize = 3;
+const TOKENS_PER_NAME: usize = 1;
+/// Every reply is primed with <|start|>assistant<|message|>.
+pub(crate) const TOKENS_PER_REPLY: usize = 3;
+
+/// Counts the tokens of a piece of text for a model family.
+pub trait Tokenizer: Debug + Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Estimate the prompt tokens of a list of chat messages.
+    fn count_message_tokens(&self, messages: &[ChatCompletionMessage]) -> usize {
+        messages
+            .iter()
+            .map(|msg| {
+                let name = msg
+                    .name()
+                    .map_or(0, |name| self.count_tokens(name) + TOKENS_PER_NAME);
+                let content = msg
+                    .content()
+                    .map_or(0, |content| self.count_tokens(content));
+                TOKENS_PER_MESSAGE + content + name
+            })
+            .sum::<usize>()
+            + TOKENS_PER_REPLY
+    }
+}
+
+/// Roughly one token per four characters, for models without a known tokenizer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenizer;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
+
+/// OpenAI's BPE tokenizers.
+#[cfg(feature = "tiktoken")]
+pub struct TiktokenTokenizer {
+    bpe: tiktoken_rs::CoreBPE,
+}
+
+#[cfg(feature = "tiktoken")]
+impl TiktokenTokenizer {
+    /// The cl100k_base encoding used by gpt-3.5-turbo and gpt-4.
+    pub fn cl100k_base() -> anyhow::Result<Self> {
+        Ok(Self {
+            bpe: tiktoken_rs::cl100k_base()?,
+        })
+    }
+
+    pub fn for_model(model: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            bpe: tiktoken_rs::get_bpe_from_model(model)?,
+        })
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+impl Debug for TiktokenTokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TiktokenTokenizer").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+impl Tokenizer for TiktokenTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
+/// Tokenizers from the HuggingFace hub, e.g. for Llama or Qwen models served through compatible endpoints.
+#[cfg(feature = "hf-tokenizers")]
+#[derive(Debug)]
+pub struct HfTokenizer {
+    inner: tokenizers::Tokenizer,
+}
+
+#[cfg(feature = "hf-tokenizers")]
+impl HfTokenizer {
+    /// Load a tokenizer.json file.
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let inner = tokenizers::Tokenizer::from_file(path).map_err(|e| anyhow::anyhow!(e))?;
+        Ok(Self { inner })
+    }
+}
+
+#[cfg(feature = "hf-tokenizers")]
+impl Tokenizer for HfTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.inner
+            .encode(text, false)
+            .map(|encoding| encoding.len())
+            .unwrap_or_else(|_| HeuristicTokenizer.count_tokens(text))
+    }
+}
+
+/// The best tokenizer available for a model: tiktoken when enabled, the heuristic otherwise.
+#[cfg_attr(not(feature = "tiktoken"), allow(unused_variables))]
+pub fn default_tokenizer(model: &ChatCompleteModel) -> Box<dyn Tokenizer> {
+    #[cfg(feature = "tiktoken")]
+    if let Ok(tokenizer) = TiktokenTokenizer::for_model(model.base_model()) {
+        return Box::new(tokenizer);
+    }
+    Box::new(HeuristicTokenizer)
+}
+
+impl ChatCompletionRequest {
+    /// Estimate the prompt tokens of this request.
+    pub fn prompt_tokens(&self, tokenizer: &dyn Tokenizer) -> usize {
+        tokenizer.count_message_tokens(self.messages())
+    }
+
+    /// Whether the prompt leaves room for the completion in the model's context window.
+    pub fn fits_context_window(&self, tokenizer: &dyn Tokenizer) -> bool {
+        let completion = self.max_completion_tokens().unwrap_or_default();
+        self.prompt_tokens(tokenizer) + completion <= self.model().context_window()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<ChatCompletionMessage> {
+        vec![
+            ChatCompletionMessage::new_system("You are a helpful assistant.", ""),
+            ChatCompletionMessage::new_user("Hello!", "user1"),
+        ]
+    }
+
+    #[test]
+    fn heuristic_tokenizer_should_round_up() {
+        assert_eq!(HeuristicTokenizer.count_tokens(""), 0);
+        assert_eq!(HeuristicTokenizer.count_tokens("abcde"), 2);
+        assert_eq!(HeuristicTokenizer.count_tokens("你好世界"), 1);
+    }
+
+    #[test]
+    fn heuristic_tokenizer_should_count_messages() {
+        // (3 + 7) + (3 + 2 + 2 + 1) + 3
+        assert_eq!(HeuristicTokenizer.count_message_tokens(&messages()), 21);
+    }
+
+    #[cfg(feature = "tiktoken")]
+    #[test]
+    fn tiktoken_tokenizer_should_count_messages() -> anyhow::Result<()> {
+        let tokenizer = TiktokenTokenizer::cl100k_base()?;
+        assert_eq!(tokenizer.count_tokens("hello world"), 2);
+        assert_eq!(tokenizer.count_message_tokens(&messages()), 20);
+        Ok(())
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    panic::AssertUnwindSafe,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    default_tokenizer, platform, ChatCompleteModel, ChatCompletionMessage, Tokenizer, Tool,
+    ToolCall, ToolMessage,
+};
+
+/// A tool the model can call, e.g. generated by `#[llm_tool]`.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ToolFunction: Send + Sync {
+    /// The definition sent in `ChatCompletionRequest::tools`.
+    fn tool(&self) -> Tool;
+
+    /// Run the tool with the JSON arguments produced by the model.
+    async fn call(&self, arguments: &str) -> Result<serde_json::Value>;
+}
+
+/// Tools by name, ready to answer the tool calls of an assistant message.
+///
+/// A misbehaving tool can't take the turn down with it: calls can be limited in time and
+/// output size, a panic is caught, and tools can be allowed or denied by name. Every failure
+/// is reported to the model as a `ToolError`.
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, (Tool, Arc<dyn ToolFunction>)>,
+    result_limit: Option<(usize, ResultOverflow)>,
+    timeout: Option<Duration>,
+    tool_timeouts: BTreeMap<String, Duration>,
+    max_output_bytes: Option<usize>,
+    /// Only these tools may run, if set.
+    allowed: Option<BTreeSet<String>>,
+    denied: BTreeSet<String>,
+}
+
+/// Why a tool call failed. The model gets it as the tool message, as
+/// `{"error": {"kind": "timeout", "message": "..."}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Error)]
+#[error("tool `{tool}` failed ({kind}): {message}")]
+pub struct ToolError {
+    #[serde(skip)]
+    pub tool: String,
+    pub kind: ToolErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolErrorKind {
+    /// No tool of that name is registered.
+    UnknownTool,
+    /// The tool is denied, or not in the allowlist.
+    NotAllowed,
+    /// The tool returned an error, e.g. for invalid arguments.
+    Failed,
+    /// The tool didn't finish within its timeout.
+    Timeout,
+    /// The tool panicked.
+    Panicked,
+    /// The output is over `ToolRegistry::with_max_output_bytes`.
+    OutputTooLarge,
+}
+
+/// What to do with a tool result over its token budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResultOverflow {
+    /// Keep the start of the result, see `ToolMessage::truncate`.
+    #[default]
+    Truncate,
+    /// Send the whole result over several tool messages, see `ToolMessage::paginate`. Falls
+    /// back to `Truncate` for a limit too small for the page headers.
+    Paginate,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tool(mut self, tool: impl ToolFunction + 'static) -> Self {
+        self.register(tool);
+        self
+    }
+
+    /// Keep each result within `max_tokens`, counted with the tokenizer of the default model.
+    pub fn with_result_limit(mut self, max_tokens: usize, overflow: ResultOverflow) -> Self {
+        self.result_limit = Some((max_tokens, overflow));
+        self
+    }
+
+    /// Give up on a call after `timeout`. Only tools that await can be interrupted: one that
+    /// blocks the thread runs to completion.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Override the timeout of one tool.
+    pub fn with_tool_timeout(mut self, name: impl Into<String>, timeout: Duration) -> Self {
+        self.tool_timeouts.insert(name.into(), timeout);
+        self
+    }
+
+    /// Fail calls whose output is over `max_bytes`, rather than sending it to the model.
+    /// See `with_result_limit` to trim long results instead.
+    pub fn with_max_output_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_output_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Only let these tools run; the others are neither offered to the model nor called.
+    pub fn with_allowed_tools<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Never let these tools run, even if allowed.
+    pub fn with_denied_tools<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.denied.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Whether the allowlist and denylist let `name` run.
+    pub fn is_allowed(&self, name: &str) -> bool {
+        !self.denied.contains(name)
+            && self
+                .allowed
+                .as_ref()
+                .is_none_or(|allowed| allowed.contains(name))
+    }
+
+    pub fn register(&mut self, tool: impl ToolFunction + 'static) {
+        let def = tool.tool();
+        self.tools
+            .insert(def.name().to_string(), (def, Arc::new(tool)));
+    }
+
+    /// The definitions of the registered tools that are allowed to run.
+    pub fn tools(&self) -> Vec<Tool> {
+        self.tools
+            .iter()
+            .filter(|(name, _)| self.is_allowed(name))
+            .map(|(_, (def, _))| def.clone())
+            .collect()
+    }
+
+    /// Run a single tool call and return its output as text.
+    pub async fn call(&self, call: &ToolCall) -> Result<String, ToolError> {
+        let name = &call.function.name;
+        let error = |kind, message: String| ToolError {
+            tool: name.clone(),
+            kind,
+            message,
+        };
+        let Some((_, tool)) = self.tools.get(name) else {
+            return Err(error(ToolErrorKind::UnknownTool, "no such tool".into()));
+        };
+        if !self.is_allowed(name) {
+            let message = "the tool may not be called".into();
+            return Err(error(ToolErrorKind::NotAllowed, message));
+        }
+        let run = AssertUnwindSafe(tool.call(&call.function.arguments)).catch_unwind();
+        let timeout = self.tool_timeouts.get(name).copied().or(self.timeout);
+        let output = match timeout {
+            Some(timeout) => platform::timeout(timeout, run).await.ok_or_else(|| {
+                let message = format!("no result after {:?}", timeout);
+                error(ToolErrorKind::Timeout, message)
+            })?,
+            None => run.await,
+        };
+        let output = match output {
+            Ok(Ok(serde_json::Value::String(s))) => s,
+            Ok(Ok(value)) => value.to_string(),
+            Ok(Err(e)) => return Err(error(ToolErrorKind::Failed, format!("{:#}", e))),
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "the tool panicked".into());
+                return Err(error(ToolErrorKind::Panicked, message));
+            }
+        };
+        match self.max_output_bytes {
+            Some(max) if output.len() > max => {
+                let message = format!(
+                    "{} bytes of output, over the {} byte limit",
+                    output.len(),
+                    max
+                );
+                Err(error(ToolErrorKind::OutputTooLarge, message))
+            }
+            _ => Ok(output),
+        }
+    }
+
+    /// Run every tool call and turn the outputs into tool messages, in order.
+    ///
+    /// Errors are reported back to the model as the message content rather than aborting,
+    /// see `ToolError`.
+    /// With `ResultOverflow::Paginate`, a call may be answered by several messages.
+    pub async fn run(&self, calls: &[ToolCall]) -> Vec<ChatCompletionMessage> {
+        let outputs = self.call_all(calls).await;
+        self.to_messages(calls, outputs)
+    }
+
+    /// Run every tool call concurrently, returning the outputs in order.
+    pub async fn call_all(&self, calls: &[ToolCall]) -> Vec<Result<String, ToolError>> {
+        futures::future::join_all(calls.iter().map(|call| self.call(call))).await
+    }
+
+    /// The tool messages answering `calls` with the outputs of `call_all`, see `run`.
+    pub fn to_messages(
+        &self,
+        calls: &[ToolCall],
+        outputs: Vec<Result<String, ToolError>>,
+    ) -> Vec<ChatCompletionMessage> {
+        let tokenizer = self
+            .result_limit
+            .map(|_| default_tokenizer(&ChatCompleteModel::default()));
+        calls
+            .iter()
+            .zip(outputs)
+            .flat_map(|(call, output)| {
+                let content = output.unwrap_or_else(|e| e.to_content());
+                let msg = ToolMessage::new(content, &call.id);
+                match (self.result_limit, &tokenizer) {
+                    (Some((max, ResultOverflow::Truncate)), Some(tokenizer)) => {
+                        vec![msg.truncate(tokenizer.as_ref(), max)]
+                    }
+                    (Some((max, ResultOverflow::Paginate)), Some(tokenizer)) => {
+                        match msg.clone().paginate(tokenizer.as_ref(), max) {
+                            Ok(pages) => pages,
+                            Err(_) => vec![msg.truncate(tokenizer.as_ref(), max)],
+                        }
+                    }
+                    _ => vec![msg],
+                }
+            })
+            .map(ChatCompletionMessage::from)
+            .collect()
+    }
+}
+
+impl ToolError {
+    /// The tool message content reporting this error to the model.
+    pub fn to_content(&self) -> String {
+        serde_json::json!({ "error": self }).to_string()
+    }
+}
+
+impl fmt::Display for ToolErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ToolErrorKind::UnknownTool => "unknown tool",
+            ToolErrorKind::NotAllowed => "not allowed",
+            ToolErrorKind::Failed => "failed",
+            ToolErrorKind::Timeout => "timeout",
+            ToolErrorKind::Panicked => "panicked",
+            ToolErrorKind::OutputTooLarge => "output too large",
+        })
+    }
+}
+
+impl ToolMessage {
+    /// Keep the start of the content within `max_tokens`, marker included.
+    pub fn truncate(self, tokenizer: &dyn Tokenizer, max_tokens: usize) -> Self {
+        let total = tokenizer.count_tokens(self.content());
+        if total <= max_tokens {
+            return self;
+        }
+        // sized for the largest count it can show
+        let marker = format!("\n[truncated: {} tokens omitted]", total);
+        let budget = max_tokens.saturating_sub(tokenizer.count_tokens(&marker));
+        let kept = &self.content()[..prefix_len(self.content(), tokenizer, budget)];
+        let omitted = total - tokenizer.count_tokens(kept);
+        let content = format!("{}\n[truncated: {} tokens omitted]", kept, omitted);
+        ToolMessage::new(content, self.tool_call_id())
+    }
+
+    /// Split the content into messages of at most `max_tokens` each, headed `[part i/n]`.
+    ///
+    /// Fails if `max_tokens` leaves no room for content after the header.
+    pub fn paginate(self, tokenizer: &dyn Tokenizer, max_tokens: usize) -> Result<Vec<Self>> {
+        if tokenizer.count_tokens(self.content()) <= max_tokens {
+            return Ok(vec![self]);
+        }
+        let header = tokenizer.count_tokens("[part 999/999]\n");
+        if header >= max_tokens {
+            bail!(
+                "the page header takes {header} tokens, leaving none of {max_tokens} for content"
+            );
+        }
+        let budget = max_tokens - header;
+        let mut pages = Vec::new();
+        let mut rest = self.content();
+        while !rest.is_empty() {
+            let end = prefix_len(rest, tokenizer, budget);
+            pages.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        let n = pages.len();
+        Ok(pages
+            .into_iter()
+            .enumerate()
+            .map(|(i, page)| {
+                ToolMessage::new(
+                    format!("[part {}/{}]\n{}", i + 1, n, page),
+                    self.tool_call_id(),
+                )
+            })
+            .collect())
+    }
+}
+
+/// The byte length of the longest prefix of `text` within `max_tokens`, at least one character.
+fn prefix_len(text: &str, tokenizer: &dyn Tokenizer, max_tokens: usize) -> usize {
+    let mut bounds: Vec<usize> = text.char_indices().map(|(i, _)| i).skip(1).collect();
+    bounds.push(text.len());
+    let fits = bounds.partition_point(|&end| tokenizer.count_tokens(&text[..end]) <= max_tokens);
+    bounds[fits.saturating_sub(1)]
+}
+
+impl fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("tools", &self.tools.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeuristicTokenizer;
+    use serde_json::json;
+
+    #[test]
+    fn tool_message_from_serialize_should_encode_json() -> Result<()> {
+        let msg = ToolMessage::from_serialize("call_1", &json!({ "temp": 22 }))?;
+        assert_eq!(msg.content(), r#"{"temp":22}"#);
+        assert_eq!(msg.tool_call_id(), "call_1");
+        let msg = ToolMessage::from_serialize("call_2", &"sunny")?;
+        assert_eq!(msg.content(), "sunny");
+        Ok(())
+    }
+
+    #[test]
+    fn tool_message_should_fit_token_budget() -> Result<()> {
+        let tokenizer = HeuristicTokenizer;
+        let msg = ToolMessage::new("a".repeat(400), "call_1").truncate(&tokenizer, 20);
+        assert!(tokenizer.count_tokens(msg.content()) <= 20);
+        assert!(msg.content().ends_with("\n[truncated: 88 tokens omitted]"));
+
+        let content = "b".repeat(100);
+        let pages = ToolMessage::new(content.clone(), "call_2").paginate(&tokenizer, 10)?;
+        assert_eq!(pages.len(), 5);
+        assert!(pages[0].content().starts_with("[part 1/5]\n"));
+        assert!(pages
+            .iter()
+            .all(|page| tokenizer.count_tokens(page.content()) <= 10
+                && page.tool_call_id() == "call_2"));
+        let joined: String = pages
+            .iter()
+            .map(|page| page.content().split_once('\n').unwrap().1)
+            .collect();
+        assert_eq!(joined, content);
+
+        // no room for content after the header
+        let msg = ToolMessage::new(content, "call_3");
+        assert!(msg.clone().paginate(&tokenizer, 4).is_err());
+        assert_eq!(msg.paginate(&tokenizer, 100)?.len(), 1);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "macros"))]
+mod registry_tests {
+    use super::*;
+    use crate::{llm_tool, FunctionCall, ToolType};
+    use serde_json::json;
+
+    /// Get the current weather in a given location.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - The city and state, e.g. San Francisco, CA
+    /// * `unit` - Either celsius or fahrenheit
+    #[llm_tool]
+    async fn get_current_weather(location: String, unit: Option<String>) -> Result<String> {
+        Ok(format!(
+            "22 degrees {} in {}",
+            unit.as_deref().unwrap_or("celsius"),
+            location
+        ))
+    }
+
+    /// Add numbers.
+    #[llm_tool]
+    fn add(numbers: Vec<i64>) -> Result<i64> {
+        Ok(numbers.iter().sum())
+    }
+
+    fn tool_call(id: &str, name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            r#type: ToolType::Function,
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn llm_tool_should_derive_schema_from_signature() {
+        let tool = GetCurrentWeatherTool.tool();
+        assert_eq!(
+            serde_json::to_value(&tool).unwrap(),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "get_current_weather",
+                    "description": "Get the current weather in a given location.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "location": {
+                                "type": "string",
+                                "description": "The city and state, e.g. San Francisco, CA"
+                            },
+                            "unit": {
+                                "type": "string",
+                                "description": "Either celsius or fahrenheit"
+                            }
+                        },
+                        "required": ["location"]
+                    }
+                }
+            })
+        );
+        assert_eq!(
+            AddTool.tool().parameters()["properties"]["numbers"],
+            json!({ "type": "array", "items": { "type": "integer" } })
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_registry_should_run_calls() {
+        let registry = ToolRegistry::new()
+            .with_tool(GetCurrentWeatherTool)
+            .with_tool(AddTool);
+        assert_eq!(registry.tools().len(), 2);
+
+        let calls = [
+            tool_call("call_1", "get_current_weather", r#"{"location":"Paris"}"#),
+            tool_call("call_2", "add", r#"{"numbers":[1,2,3]}"#),
+            tool_call("call_3", "add", r#"{"numbers":"oops"}"#),
+            tool_call("call_4", "search", "{}"),
+        ];
+        let messages = registry.run(&calls).await;
+        let contents: Vec<_> = messages.iter().map(|m| m.content().unwrap()).collect();
+        assert_eq!(contents[0], "22 degrees celsius in Paris");
+        assert_eq!(contents[1], "6");
+        let error: serde_json::Value = serde_json::from_str(contents[2]).unwrap();
+        assert_eq!(error["error"]["kind"], "failed");
+        assert_eq!(
+            contents[3],
+            r#"{"error":{"kind":"unknown_tool","message":"no such tool"}}"#
+        );
+    }
+
+    /// Misbehaves according to its arguments.
+    struct FlakyTool;
+
+    #[async_trait]
+    impl ToolFunction for FlakyTool {
+        fn tool(&self) -> Tool {
+            Tool::new_function("flaky", None, json!({ "type": "object" }))
+        }
+
+        async fn call(&self, arguments: &str) -> Result<serde_json::Value> {
+            match arguments {
+                "hang" => std::future::pending().await,
+                "panic" => panic!("flaky tool exploded"),
+                "big" => Ok(json!("x".repeat(1000))),
+                _ => Ok(json!("ok")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_registry_should_contain_misbehaving_tools() {
+        let registry = ToolRegistry::new()
+            .with_tool(FlakyTool)
+            .with_tool(AddTool)
+            .with_tool_timeout("flaky", Duration::from_millis(10))
+            .with_max_output_bytes(100)
+            .with_denied_tools(["add"]);
+        assert_eq!(registry.tools().len(), 1);
+
+        let kind = |arguments: &str| {
+            let call = tool_call("call_1", "flaky", arguments);
+            let registry = registry.clone();
+            async move { registry.call(&call).await.map_err(|e| e.kind) }
+        };
+        assert_eq!(kind("hang").await, Err(ToolErrorKind::Timeout));
+        assert_eq!(kind("panic").await, Err(ToolErrorKind::Panicked));
+        assert_eq!(kind("big").await, Err(ToolErrorKind::OutputTooLarge));
+        assert_eq!(kind("{}").await, Ok("ok".to_string()));
+
+        let err = registry
+            .call(&tool_call("call_2", "add", r#"{"numbers":[1]}"#))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, ToolErrorKind::NotAllowed);
+        let registry = registry.with_allowed_tools(["add"]);
+        assert!(!registry.is_allowed("flaky") && !registry.is_allowed("add"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Helpers feeding the spans opened by `#[tracing::instrument]` on `LlmSdk` methods.
+//! They compile to nothing without the `tracing` feature.
+
+use std::time::Duration;
+
+use reqwest::{Request, Response};
+
+use crate::ChatCompleteUsage;
+
+#[cfg(feature = "tracing")]
+const REDACTED_HEADERS: [&str; 3] = ["authorization", "api-key", "x-api-key"];
+
+#[cfg(feature = "tracing")]
+pub(crate) fn record_request(req: &Request) {
+    let span = tracing::Span::current();
+    span.record("endpoint", req.url().path());
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return;
+    }
+    let headers: Vec<_> = req
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = if value.is_sensitive() || REDACTED_HEADERS.contains(&name.as_str()) {
+                "[REDACTED]"
+            } else {
+                value.to_str().unwrap_or("[binary]")
+            };
+            format!("{name}: {value}")
+        })
+        .collect();
+    let body = req
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(String::from_utf8_lossy)
+        .unwrap_or_default();
+    tracing::debug!(method = %req.method(), url = %req.url(), ?headers, %body, "sending request");
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn record_response(res: &Response, latency: Duration) {
+    let span = tracing::Span::current();
+    span.record("status", res.status().as_u16());
+    span.record("latency_ms", latency.as_millis() as u64);
+    if let Some(id) = res
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+    {
+        span.record("request_id", id);
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn record_body(body: &serde_json::Value) {
+    tracing::debug!(%body, "received response");
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn record_usage(usage: &ChatCompleteUsage) {
+    let span = tracing::Span::current();
+    span.record("prompt_tokens", usage.prompt_tokens);
+    span.record("completion_tokens", usage.completion_tokens);
+    span.record("total_tokens", usage.total_tokens);
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn record_cache_error(err: &anyhow::Error) {
+    tracing::warn!(error = %err, "semantic cache lookup failed, sending the request");
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_request(_req: &Request) {}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_response(_res: &Response, _latency: Duration) {}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_body(_body: &serde_json::Value) {}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_usage(_usage: &ChatCompleteUsage) {}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_cache_error(_err: &anyhow::Error) {}
@@ -0,0 +1,54 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, Request, Response};
+
+/// Sends the requests built by `LlmSdk`, see `LlmSdk::with_http_client`.
+///
+/// Requests are still built with `reqwest`, but anything can send them: a hyper or ureq
+/// client, a proxy stack, or a stub returning canned responses in tests. A backend converts
+/// its result with `reqwest::Response::from(http::Response<_>)`.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait HttpClient: Debug + Send + Sync {
+    async fn execute(&self, req: Request) -> Result<Response>;
+}
+
+/// The default backend.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl HttpClient for Client {
+    async fn execute(&self, req: Request) -> Result<Response> {
+        Ok(Client::execute(self, req).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{testing::ScriptedClient, CreateEmbeddingRequest, LlmSdk};
+
+    #[tokio::test]
+    async fn custom_http_client_should_send_requests() -> Result<()> {
+        let client = ScriptedClient::replying([json!({
+            "object": "list",
+            "data": [{ "object": "embedding", "index": 0, "embedding": [0.5, -0.5] }],
+            "model": "text-embedding-3-small",
+            "usage": { "prompt_tokens": 1, "total_tokens": 1 }
+        })]);
+        let urls = client.urls.clone();
+        let sdk = LlmSdk::new("sk-test".to_string()).with_http_client(client);
+        let res = sdk
+            .create_embedding(CreateEmbeddingRequest::new("hi"))
+            .await?;
+        assert_eq!(res.into_vectors(), [vec![0.5, -0.5]]);
+        assert_eq!(
+            *urls.lock().unwrap(),
+            ["https://api.openai.com/v1/embeddings"]
+        );
+        Ok(())
+    }
+}
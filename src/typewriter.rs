@@ -0,0 +1,277 @@
+use std::{collections::VecDeque, time::Duration};
+
+use anyhow::Result;
+use futures::{future::Either, stream, Stream, StreamExt};
+use web_time::Instant;
+
+use crate::{
+    chunk_events, platform::sleep, BoxStream, ChatCompletionStream, ChunkEvent, MaybeSend,
+};
+
+/// The pace of a `Typewriter` by default, about as fast as people read.
+pub const DEFAULT_CHARS_PER_SEC: f64 = 60.0;
+
+/// The slowest pace of a `Typewriter`, a character every ten seconds.
+pub const MIN_CHARS_PER_SEC: f64 = 0.1;
+
+/// Turns the bursty text of a stream into a steady flow of characters, for a UI that types
+/// the reply out.
+///
+/// Models send text in uneven chunks: nothing for a while, then a whole sentence. A typewriter
+/// buffers it and releases `chars_per_sec` characters a second instead. Pieces always hold
+/// whole characters, so CJK text or emoji are never cut mid-codepoint, and combining marks
+/// and zero-width-joined emoji stay with the character they belong to.
+///
+/// ```no_run
+/// # use futures::StreamExt;
+/// # use llm_sdk::{ChatCompletionRequest, FlushPolicy, LlmSdk, Typewriter};
+/// # async fn run(sdk: LlmSdk, req: ChatCompletionRequest) -> anyhow::Result<()> {
+/// let stream = sdk.chat_completion_stream(req).await?;
+/// let typewriter = Typewriter::new(40.0)
+///     .with_flush(FlushPolicy::AtEnd)
+///     .with_max_backlog(200);
+/// let mut pieces = typewriter.type_out(stream);
+/// while let Some(piece) = pieces.next().await {
+///     print!("{}", piece?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Typewriter {
+    chars_per_sec: f64,
+    flush: FlushPolicy,
+    max_backlog: Option<usize>,
+}
+
+/// What a `Typewriter` does with the buffered text once the stream ends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Keep typing at the same pace until everything is shown.
+    #[default]
+    Paced,
+    /// Show the rest at once, so the reply is complete as soon as the model is done.
+    AtEnd,
+}
+
+struct TypewriterState<S> {
+    source: Option<S>,
+    buffer: VecDeque<char>,
+    /// Characters that may be released now; fractions carry over to the next piece.
+    budget: f64,
+    last: Instant,
+    /// The error that ended the source, returned once the text before it is shown.
+    error: Option<anyhow::Error>,
+}
+
+impl Default for Typewriter {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHARS_PER_SEC)
+    }
+}
+
+impl Typewriter {
+    /// Type `chars_per_sec` characters a second, at least `MIN_CHARS_PER_SEC`; zero, negative
+    /// and NaN paces get the minimum.
+    pub fn new(chars_per_sec: f64) -> Self {
+        Self {
+            // `max` returns the other operand for NaN
+            chars_per_sec: chars_per_sec.max(MIN_CHARS_PER_SEC),
+            flush: FlushPolicy::default(),
+            max_backlog: None,
+        }
+    }
+
+    pub fn with_flush(mut self, flush: FlushPolicy) -> Self {
+        self.flush = flush;
+        self
+    }
+
+    /// Never fall more than `chars` characters behind the model: a longer backlog is shown at
+    /// once, down to `chars`.
+    pub fn with_max_backlog(mut self, chars: usize) -> Self {
+        self.max_backlog = Some(chars);
+        self
+    }
+
+    /// The text of the first choice of a chat completion stream, typed out.
+    pub fn type_out(&self, stream: ChatCompletionStream) -> BoxStream<String> {
+        let texts = chunk_events(stream).filter_map(|event| async move {
+            match event {
+                Ok(ChunkEvent::TextDelta { choice: 0, text }) => Some(Ok(text)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            }
+        });
+        self.type_text(texts)
+    }
+
+    /// Any stream of text, typed out. An error of `texts` is returned after the text
+    /// received before it.
+    pub fn type_text<S>(&self, texts: S) -> BoxStream<String>
+    where
+        S: Stream<Item = Result<String>> + MaybeSend + 'static,
+    {
+        let typewriter = *self;
+        let state = TypewriterState {
+            source: Some(Box::pin(texts)),
+            buffer: VecDeque::new(),
+            budget: 1.0,
+            last: Instant::now(),
+            error: None,
+        };
+        Box::pin(stream::unfold(state, move |mut state| async move {
+            let item = typewriter.next_piece(&mut state).await?;
+            Some((item, state))
+        }))
+    }
+
+    async fn next_piece<S>(&self, state: &mut TypewriterState<S>) -> Option<Result<String>>
+    where
+        S: Stream<Item = Result<String>> + Unpin,
+    {
+        loop {
+            let now = Instant::now();
+            if state.buffer.is_empty() {
+                // time spent waiting for the model doesn't add up to a burst later
+                state.budget = 1.0;
+            } else {
+                let elapsed = now.duration_since(state.last).as_secs_f64();
+                state.budget += elapsed * self.chars_per_sec;
+            }
+            state.last = now;
+
+            let ended = state.source.is_none();
+            if ended && state.buffer.is_empty() {
+                return state.error.take().map(Err);
+            }
+            let mut n = if ended && self.flush == FlushPolicy::AtEnd {
+                state.buffer.len()
+            } else {
+                (state.budget as usize).min(state.buffer.len())
+            };
+            if let Some(max) = self.max_backlog {
+                n = n.max(state.buffer.len().saturating_sub(max));
+            }
+            if n > 0 {
+                state.budget = (state.budget - n as f64).max(0.0);
+                return Some(Ok(take_piece(&mut state.buffer, n)));
+            }
+
+            let due = Duration::from_secs_f64((1.0 - state.budget) / self.chars_per_sec);
+            let Some(source) = state.source.as_mut() else {
+                sleep(due).await;
+                continue;
+            };
+            let received = if state.buffer.is_empty() {
+                source.next().await
+            } else {
+                match futures::future::select(source.next(), Box::pin(sleep(due))).await {
+                    Either::Left((received, _)) => received,
+                    Either::Right(_) => continue,
+                }
+            };
+            match received {
+                Some(Ok(text)) => state.buffer.extend(text.chars()),
+                Some(Err(e)) => {
+                    state.error = Some(e);
+                    state.source = None;
+                }
+                None => state.source = None,
+            }
+        }
+    }
+}
+
+/// The first `n` characters of `buffer`, and the characters that must not be separated from
+/// them: combining marks, variation selectors and the rest of a zero-width-joined sequence.
+fn take_piece(buffer: &mut VecDeque<char>, n: usize) -> String {
+    let mut piece: String = buffer.drain(..n).collect();
+    while let Some(&next) = buffer.front() {
+        if !(is_combining(next) || piece.ends_with(ZERO_WIDTH_JOINER)) {
+            break;
+        }
+        piece.push(next);
+        buffer.pop_front();
+    }
+    piece
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Whether `c` modifies the character before it, e.g. an accent or a skin tone.
+fn is_combining(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | ZERO_WIDTH_JOINER)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn texts(texts: &[&str]) -> impl Stream<Item = Result<String>> + Send + 'static {
+        let texts: Vec<_> = texts.iter().map(|text| Ok(text.to_string())).collect();
+        stream::iter(texts)
+    }
+
+    #[tokio::test]
+    async fn typewriter_should_release_whole_characters_at_a_steady_pace() {
+        let start = Instant::now();
+        let pieces: Vec<String> = Typewriter::new(1000.0)
+            .type_text(texts(&["你好，", "世界! e\u{301}", "👩\u{200D}💻"]))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(pieces.concat(), "你好，世界! e\u{301}👩\u{200D}💻");
+        // a busy runtime may batch characters, but never splits one from its marks or joined emoji
+        assert!(!pieces.iter().any(|piece| piece.starts_with(is_combining)));
+        assert!(!pieces
+            .iter()
+            .any(|piece| piece.ends_with(ZERO_WIDTH_JOINER)));
+        // 10 characters at 1000 a second
+        assert!(start.elapsed() >= Duration::from_millis(8));
+    }
+
+    #[test]
+    fn typewriter_should_clamp_its_pace() {
+        for pace in [0.0, -5.0, f64::NAN, 1e-300] {
+            assert_eq!(Typewriter::new(pace).chars_per_sec, MIN_CHARS_PER_SEC);
+        }
+        assert_eq!(Typewriter::new(40.0).chars_per_sec, 40.0);
+    }
+
+    #[tokio::test]
+    async fn typewriter_should_flush_and_catch_up() -> Result<()> {
+        let pieces: Vec<String> = Typewriter::new(1.0)
+            .with_flush(FlushPolicy::AtEnd)
+            .type_text(texts(&["你好世界"]))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(pieces, ["你", "好世界"]);
+
+        let mut pieces = Typewriter::new(1000.0)
+            .with_max_backlog(2)
+            .type_text(texts(&["abcdef"]).chain(stream::iter([Err(anyhow!("reset"))])));
+        assert_eq!(pieces.next().await.unwrap()?, "abcd");
+        let mut rest = String::new();
+        let err = loop {
+            match pieces.next().await.unwrap() {
+                Ok(piece) => rest.push_str(&piece),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(rest, "ef");
+        assert_eq!(err.to_string(), "reset");
+        assert!(pieces.next().await.is_none());
+        Ok(())
+    }
+}
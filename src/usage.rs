@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::ChatCompleteUsage;
+
+/// Price of a model in USD per 1K tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+    /// The price of prompt tokens served from the prompt cache, `prompt` if unset.
+    pub cached_prompt: Option<f64>,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, prompt_tokens: usize, completion_tokens: usize) -> f64 {
+        (prompt_tokens as f64 * self.prompt + completion_tokens as f64 * self.completion) / 1000.0
+    }
+
+    /// The cost of a completed request, with cached prompt tokens at their discounted price.
+    pub fn usage_cost(&self, usage: &ChatCompleteUsage) -> f64 {
+        let cached = usage.cached_tokens().min(usage.prompt_tokens);
+        let cached_price = self.cached_prompt.unwrap_or(self.prompt);
+        self.cost(usage.prompt_tokens - cached, usage.completion_tokens)
+            + cached as f64 * cached_price / 1000.0
+    }
+}
+
+/// Aggregated usage for one model (or for all models in `UsageSnapshot::total`).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelUsage {
+    /// Number of completions recorded.
+    pub requests: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub total_tokens: usize,
+    /// Prompt tokens served from the prompt cache.
+    pub cached_tokens: usize,
+    /// Estimated cost in USD, based on the tracker's price table.
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageSnapshot {
+    /// Usage per model, keyed by the model name returned by the API.
+    pub models: HashMap<String, ModelUsage>,
+    /// Usage across all models.
+    pub total: ModelUsage,
+}
+
+/// Aggregates token usage per model across calls.
+///
+/// The tracker is cheap to clone and all clones share the same totals, so keep
+/// a clone around after handing one to `LlmSdk::with_usage_tracker`.
+#[derive(Debug, Clone, Default)]
+pub struct UsageTracker {
+    inner: Arc<Mutex<UsageState>>,
+}
+
+#[derive(Debug, Default)]
+struct UsageState {
+    prices: HashMap<String, ModelPrice>,
+    usage: HashMap<String, ModelUsage>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the price of a model. A price applies to every model name it is a prefix of,
+    /// e.g. a price for `gpt-4` also covers `gpt-4-1106-preview` unless that has its own price.
+    pub fn with_price(self, model: impl Into<String>, price: ModelPrice) -> Self {
+        self.set_price(model, price);
+        self
+    }
+
+    pub fn set_price(&self, model: impl Into<String>, price: ModelPrice) {
+        let mut state = self.inner.lock().unwrap();
+        state.prices.insert(model.into(), price);
+    }
+
+    /// Estimate the cost in USD of a call before making it, e.g. with tokens counted by a `Tokenizer`.
+    pub fn estimate_cost(
+        &self,
+        model: &str,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+    ) -> Option<f64> {
+        let state = self.inner.lock().unwrap();
+        let price = state.price(model)?;
+        Some(price.cost(prompt_tokens, completion_tokens))
+    }
+
+    pub fn record(&self, model: &str, usage: &ChatCompleteUsage) {
+        let mut state = self.inner.lock().unwrap();
+        let cost = state
+            .price(model)
+            .map(|price| price.usage_cost(usage))
+            .unwrap_or_default();
+        let entry = state.usage.entry(model.to_string()).or_default();
+        entry.requests += 1;
+        entry.prompt_tokens += usage.prompt_tokens;
+        entry.completion_tokens += usage.completion_tokens;
+        entry.total_tokens += usage.total_tokens;
+        entry.cached_tokens += usage.cached_tokens();
+        entry.cost += cost;
+    }
+
+    pub fn snapshot(&self) -> UsageSnapshot {
+        let state = self.inner.lock().unwrap();
+        let models = state.usage.clone();
+        let total = models
+            .values()
+            .fold(ModelUsage::default(), |mut total, usage| {
+                total.requests += usage.requests;
+                total.prompt_tokens += usage.prompt_tokens;
+                total.completion_tokens += usage.completion_tokens;
+                total.total_tokens += usage.total_tokens;
+                total.cached_tokens += usage.cached_tokens;
+                total.cost += usage.cost;
+                total
+            });
+        UsageSnapshot { models, total }
+    }
+
+    pub fn total(&self) -> ModelUsage {
+        self.snapshot().total
+    }
+
+    pub fn reset(&self) {
+        self.inner.lock().unwrap().usage.clear();
+    }
+}
+
+impl ModelUsage {
+    /// The share of prompt tokens served from the prompt cache, `None` without prompt tokens.
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        (self.prompt_tokens > 0).then(|| self.cached_tokens as f64 / self.prompt_tokens as f64)
+    }
+}
+
+impl UsageState {
+    fn price(&self, model: &str) -> Option<ModelPrice> {
+        self.prices
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| *price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PromptTokensDetails;
+
+    fn usage(prompt_tokens: usize, completion_tokens: usize) -> ChatCompleteUsage {
+        ChatCompleteUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn usage_tracker_should_aggregate_per_model() {
+        let tracker = UsageTracker::new()
+            .with_price(
+                "gpt-3.5-turbo",
+                ModelPrice {
+                    prompt: 0.001,
+                    completion: 0.002,
+                    ..Default::default()
+                },
+            )
+            .with_price(
+                "gpt-3.5-turbo-instruct",
+                ModelPrice {
+                    prompt: 0.0015,
+                    completion: 0.002,
+                    ..Default::default()
+                },
+            );
+        tracker.record("gpt-3.5-turbo-1106", &usage(1000, 500));
+        tracker.record("gpt-3.5-turbo-1106", &usage(1000, 500));
+        tracker.record("gpt-3.5-turbo-instruct", &usage(2000, 0));
+        tracker.record("unknown-model", &usage(10, 10));
+
+        let snapshot = tracker.snapshot();
+        let turbo = snapshot.models["gpt-3.5-turbo-1106"];
+        assert_eq!(turbo.requests, 2);
+        assert_eq!(turbo.total_tokens, 3000);
+        assert!((turbo.cost - 0.004).abs() < 1e-9);
+        assert!((snapshot.models["gpt-3.5-turbo-instruct"].cost - 0.003).abs() < 1e-9);
+        assert_eq!(snapshot.models["unknown-model"].cost, 0.0);
+        assert_eq!(snapshot.total.requests, 4);
+        assert_eq!(snapshot.total.total_tokens, 5020);
+
+        let estimate = tracker.estimate_cost("gpt-3.5-turbo-1106", 2000, 1000);
+        assert!((estimate.unwrap() - 0.004).abs() < 1e-9);
+        assert_eq!(tracker.estimate_cost("unknown-model", 10, 10), None);
+
+        tracker.reset();
+        assert_eq!(tracker.total(), ModelUsage::default());
+    }
+
+    #[test]
+    fn usage_tracker_should_price_cached_prompt_tokens() {
+        let tracker = UsageTracker::new().with_price(
+            "gpt-4o",
+            ModelPrice {
+                prompt: 0.0025,
+                completion: 0.01,
+                cached_prompt: Some(0.00125),
+            },
+        );
+        let mut cached = usage(2000, 100);
+        cached.prompt_tokens_details = Some(PromptTokensDetails {
+            cached_tokens: 1000,
+            ..Default::default()
+        });
+        tracker.record("gpt-4o-2024-08-06", &cached);
+
+        let total = tracker.total();
+        assert_eq!(total.cached_tokens, 1000);
+        assert_eq!(total.cache_hit_ratio(), Some(0.5));
+        // 1000 * 0.0025 + 1000 * 0.00125 + 100 * 0.01, per 1K
+        assert!((total.cost - 0.00475).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use thiserror::Error;
+
+/// Check a request against the documented API constraints before it is sent.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationError>;
+}
+
+/// Every constraint a request violates.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub struct ValidationError {
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The request field at fault, e.g. `temperature` or `tools[0].function.name`.
+    pub field: String,
+    pub message: String,
+}
+
+/// Collects violations so that all of them are reported at once.
+#[derive(Debug, Default)]
+pub(crate) struct Validator {
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    pub fn check(&mut self, ok: bool, field: impl Into<String>, message: impl Into<String>) {
+        if !ok {
+            self.violations.push(Violation {
+                field: field.into(),
+                message: message.into(),
+            });
+        }
+    }
+
+    pub fn range(&mut self, field: &str, value: Option<f32>, min: f32, max: f32) {
+        if let Some(value) = value {
+            self.check(
+                (min..=max).contains(&value),
+                field,
+                format!("must be between {} and {}, got {}", min, max, value),
+            );
+        }
+    }
+
+    pub fn max_chars(&mut self, field: &str, value: &str, max: usize) {
+        let len = value.chars().count();
+        self.check(
+            len <= max,
+            field,
+            format!("must be at most {} characters, got {}", max, len),
+        );
+    }
+
+    pub fn finish(self) -> Result<(), ValidationError> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError {
+                violations: self.violations,
+            })
+        }
+    }
+}
+
+/// Function names must be a-z, A-Z, 0-9, underscores or dashes, with a maximum length of 64.
+pub(crate) fn is_valid_function_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid request: ")?;
+        for (i, v) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "`{}` {}", v.field, v.message)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validator_should_collect_all_violations() {
+        let mut v = Validator::default();
+        v.range("temperature", Some(3.0), 0.0, 2.0);
+        v.range("top_p", Some(0.5), 0.0, 1.0);
+        v.range("presence_penalty", None, -2.0, 2.0);
+        v.check(false, "n", "must be at least 1");
+        let err = v.finish().unwrap_err();
+        assert_eq!(err.violations.len(), 2);
+        assert_eq!(
+            err.to_string(),
+            "invalid request: `temperature` must be between 0 and 2, got 3; `n` must be at least 1"
+        );
+    }
+
+    #[test]
+    fn function_name_should_be_checked() {
+        assert!(is_valid_function_name("get_current_weather"));
+        assert!(is_valid_function_name("get-weather-2"));
+        assert!(!is_valid_function_name(""));
+        assert!(!is_valid_function_name("get weather"));
+        assert!(!is_valid_function_name(&"a".repeat(65)));
+    }
+}
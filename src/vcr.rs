@@ -0,0 +1,351 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Request, Response,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{HttpClient, SdkError};
+
+const REDACTED: &str = "[REDACTED]";
+/// Headers whose values never reach a cassette.
+const SECRET_HEADERS: [&str; 7] = [
+    "authorization",
+    "api-key",
+    "x-api-key",
+    "openai-organization",
+    "openai-project",
+    "cookie",
+    "set-cookie",
+];
+
+/// Records the requests sent through it to a cassette file, or replays the responses of one.
+///
+/// Requests are matched by a hash of their method, URL and body, so headers such as the API key
+/// may differ between recording and replay. Identical requests are replayed in recorded order.
+/// Secret headers are redacted before anything is written.
+///
+/// ```no_run
+/// # use llm_sdk::{LlmSdk, VcrClient};
+/// # fn main() -> anyhow::Result<()> {
+/// let vcr = VcrClient::auto("tests/cassettes/chat.json", reqwest::Client::new())?;
+/// let sdk = LlmSdk::new("sk-...".to_string()).with_http_client(vcr);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct VcrClient {
+    path: PathBuf,
+    mode: VcrMode,
+    state: Mutex<VcrState>,
+    redacted: Vec<HeaderName>,
+}
+
+#[derive(Debug, Clone)]
+pub enum VcrMode {
+    /// Send requests with this client and append every exchange to the cassette.
+    Record(Arc<dyn HttpClient>),
+    /// Serve responses from the cassette; a request that wasn't recorded is an error.
+    Replay,
+}
+
+/// The recorded exchanges, stored as JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Hash of the method, URL and body of the request.
+    pub key: String,
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// `None` for streamed bodies such as file uploads.
+    pub body: Option<RecordedBody>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: RecordedBody,
+}
+
+/// UTF-8 bodies are kept as text so cassettes can be read and edited, others as base64.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedBody {
+    Text(String),
+    Base64(String),
+}
+
+#[derive(Debug, Default)]
+struct VcrState {
+    cassette: Cassette,
+    /// How many interactions of each key have been replayed.
+    replayed: HashMap<String, usize>,
+}
+
+impl VcrClient {
+    /// Record to `path` through `inner`, replacing any cassette already there.
+    pub fn record(path: impl Into<PathBuf>, inner: impl HttpClient + 'static) -> Self {
+        Self::new(
+            path.into(),
+            VcrMode::Record(Arc::new(inner)),
+            Cassette::default(),
+        )
+    }
+
+    /// Replay the cassette at `path` without touching the network.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let cassette = serde_json::from_slice(&std::fs::read(&path)?)?;
+        Ok(Self::new(path, VcrMode::Replay, cassette))
+    }
+
+    /// Replay the cassette at `path` if it exists, otherwise record it through `inner`.
+    pub fn auto(path: impl Into<PathBuf>, inner: impl HttpClient + 'static) -> Result<Self> {
+        let path = path.into();
+        match std::fs::read(&path) {
+            Ok(bytes) => Ok(Self::new(
+                path,
+                VcrMode::Replay,
+                serde_json::from_slice(&bytes)?,
+            )),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::record(path, inner)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn new(path: PathBuf, mode: VcrMode, cassette: Cassette) -> Self {
+        Self {
+            path,
+            mode,
+            state: Mutex::new(VcrState {
+                cassette,
+                replayed: HashMap::new(),
+            }),
+            redacted: SECRET_HEADERS
+                .iter()
+                .map(|name| HeaderName::from_static(name))
+                .collect(),
+        }
+    }
+
+    /// Also redact this header, e.g. a custom auth header of a gateway.
+    pub fn with_redacted_header(mut self, name: HeaderName) -> Self {
+        self.redacted.push(name);
+        self
+    }
+
+    pub fn mode(&self) -> &VcrMode {
+        &self.mode
+    }
+
+    /// A copy of the interactions recorded or loaded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.state.lock().unwrap().cassette.clone()
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if value.is_sensitive() || self.redacted.contains(name) {
+                    REDACTED.to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    async fn record_exchange(&self, inner: &dyn HttpClient, req: Request) -> Result<Response> {
+        let key = request_key(&req);
+        let request = RecordedRequest {
+            method: req.method().to_string(),
+            url: req.url().to_string(),
+            headers: self.headers(req.headers()),
+            body: req
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(RecordedBody::from_bytes),
+        };
+        let res = inner.execute(req).await?;
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = res.bytes().await?;
+        let response = RecordedResponse {
+            status: status.as_u16(),
+            headers: self.headers(&headers),
+            body: RecordedBody::from_bytes(&body),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.cassette.interactions.push(Interaction {
+            key,
+            request,
+            response,
+        });
+        // written on every exchange so a test that panics still leaves a usable cassette
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&state.cassette)?)?;
+        drop(state);
+
+        let mut res = http::Response::builder().status(status);
+        if let Some(map) = res.headers_mut() {
+            *map = headers;
+        }
+        Ok(res.body(body)?.into())
+    }
+
+    fn replay_exchange(&self, req: &Request) -> Result<Response> {
+        let key = request_key(req);
+        let mut state = self.state.lock().unwrap();
+        let VcrState { cassette, replayed } = &mut *state;
+        let seen = replayed.entry(key.clone()).or_default();
+        let interaction = cassette
+            .interactions
+            .iter()
+            .filter(|interaction| interaction.key == key)
+            .nth(*seen)
+            .ok_or_else(|| SdkError::CassetteMiss {
+                method: req.method().to_string(),
+                url: req.url().to_string(),
+            })?;
+        *seen += 1;
+        interaction.response.to_response()
+    }
+}
+
+#[async_trait]
+impl HttpClient for VcrClient {
+    async fn execute(&self, req: Request) -> Result<Response> {
+        match &self.mode {
+            VcrMode::Record(inner) => self.record_exchange(inner.as_ref(), req).await,
+            VcrMode::Replay => self.replay_exchange(&req),
+        }
+    }
+}
+
+impl RecordedBody {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => RecordedBody::Text(text.to_string()),
+            Err(_) => RecordedBody::Base64(STANDARD.encode(bytes)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            RecordedBody::Text(text) => Ok(text.clone().into_bytes()),
+            RecordedBody::Base64(data) => Ok(STANDARD.decode(data)?),
+        }
+    }
+}
+
+impl RecordedResponse {
+    fn to_response(&self) -> Result<Response> {
+        let mut res = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            // redacted values are dropped rather than replayed as placeholders
+            if value != REDACTED {
+                res = res.header(name, HeaderValue::from_str(value)?);
+            }
+        }
+        Ok(res.body(self.body.to_bytes()?)?.into())
+    }
+}
+
+fn request_key(req: &Request) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(b" ");
+    hasher.update(req.url().as_str());
+    hasher.update(b"\n");
+    if let Some(body) = req.body().and_then(|body| body.as_bytes()) {
+        hasher.update(body);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{testing::ScriptedClient, CreateEmbeddingRequest, LlmSdk};
+
+    #[tokio::test]
+    async fn vcr_client_should_replay_recorded_responses() -> Result<()> {
+        let path = std::env::temp_dir().join("llm-sdk-vcr/embedding.json");
+        let _ = std::fs::remove_file(&path);
+        let client = ScriptedClient::replying([json!({
+            "object": "list",
+            "data": [{ "object": "embedding", "index": 0, "embedding": [0.5, -0.5] }],
+            "model": "text-embedding-3-small",
+            "usage": { "prompt_tokens": 1, "total_tokens": 1 }
+        })])
+        .with_response_header("openai-organization", "org-secret");
+        let urls = client.urls.clone();
+
+        let sdk =
+            LlmSdk::new("sk-secret".to_string()).with_http_client(VcrClient::record(&path, client));
+        let recorded = sdk
+            .create_embedding(CreateEmbeddingRequest::new("hi"))
+            .await?;
+        assert_eq!(urls.lock().unwrap().len(), 1);
+        let cassette = std::fs::read_to_string(&path)?;
+        assert!(!cassette.contains("sk-secret"));
+        assert!(!cassette.contains("org-secret"));
+
+        let sdk = LlmSdk::new("sk-other".to_string()).with_http_client(VcrClient::replay(&path)?);
+        let replayed = sdk
+            .create_embedding(CreateEmbeddingRequest::new("hi"))
+            .await?;
+        assert_eq!(replayed.into_vectors(), recorded.into_vectors());
+        assert_eq!(urls.lock().unwrap().len(), 1);
+
+        let err = sdk
+            .create_embedding(CreateEmbeddingRequest::new("hi"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SdkError>(),
+            Some(SdkError::CassetteMiss { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn recorded_body_should_fall_back_to_base64() -> Result<()> {
+        let text = RecordedBody::from_bytes(b"{}");
+        assert_eq!(text, RecordedBody::Text("{}".to_string()));
+        let binary = RecordedBody::from_bytes(&[0xff, 0xfe]);
+        assert_eq!(binary, RecordedBody::Base64("//4=".to_string()));
+        assert_eq!(binary.to_bytes()?, [0xff, 0xfe]);
+        Ok(())
+    }
+}
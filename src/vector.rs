@@ -0,0 +1,192 @@
+use anyhow::{bail, Result};
+
+/// Dot product of two vectors of the same length.
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Cosine similarity in `[-1, 1]`; 0 if either vector is all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        return 0.0;
+    }
+    dot(a, b) / norms
+}
+
+pub fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f32>()
+        .sqrt()
+}
+
+pub fn norm(v: &[f32]) -> f32 {
+    dot(v, v).sqrt()
+}
+
+/// Scale a vector to unit length, leaving an all-zero vector as is.
+pub fn normalize(v: &mut [f32]) {
+    let norm = norm(v);
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+pub fn normalize_all(vectors: &mut [Vec<f32>]) {
+    vectors.iter_mut().for_each(|v| normalize(v));
+}
+
+/// How `EmbeddingIndex` compares a query with the stored embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Similarity {
+    #[default]
+    Cosine,
+    Dot,
+    /// Scored as the negated distance, so higher is still more similar.
+    Euclidean,
+}
+
+impl Similarity {
+    pub fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Similarity::Cosine => cosine_similarity(a, b),
+            Similarity::Dot => dot(a, b),
+            Similarity::Euclidean => -euclidean_distance(a, b),
+        }
+    }
+}
+
+/// An in-memory embedding index with brute-force top-k search, for small corpora.
+#[derive(Debug, Clone)]
+pub struct EmbeddingIndex<T> {
+    similarity: Similarity,
+    entries: Vec<(T, Vec<f32>)>,
+}
+
+/// A search result, most similar first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchHit<'a, T> {
+    pub item: &'a T,
+    pub score: f32,
+}
+
+impl<T> EmbeddingIndex<T> {
+    pub fn new() -> Self {
+        Self::with_similarity(Similarity::default())
+    }
+
+    pub fn with_similarity(similarity: Similarity) -> Self {
+        Self {
+            similarity,
+            entries: Vec::new(),
+        }
+    }
+
+    /// The dimension of the stored embeddings, if any were inserted.
+    pub fn dimensions(&self) -> Option<usize> {
+        self.entries.first().map(|(_, v)| v.len())
+    }
+
+    pub fn insert(&mut self, item: T, embedding: Vec<f32>) -> Result<()> {
+        self.check_dimensions(&embedding)?;
+        self.entries.push((item, embedding));
+        Ok(())
+    }
+
+    /// Insert items with their embeddings, e.g. the inputs and `CreateEmbeddingResponse::into_vectors`.
+    pub fn extend(&mut self, items: impl IntoIterator<Item = (T, Vec<f32>)>) -> Result<()> {
+        for (item, embedding) in items {
+            self.insert(item, embedding)?;
+        }
+        Ok(())
+    }
+
+    /// The `k` items most similar to `query`, most similar first.
+    pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchHit<'_, T>>> {
+        self.check_dimensions(query)?;
+        let mut hits: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(item, embedding)| SearchHit {
+                item,
+                score: self.similarity.score(query, embedding),
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(k);
+        Ok(hits)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&T, &[f32])> {
+        self.entries.iter().map(|(item, v)| (item, v.as_slice()))
+    }
+
+    fn check_dimensions(&self, embedding: &[f32]) -> Result<()> {
+        match self.dimensions() {
+            Some(dims) if dims != embedding.len() => bail!(
+                "embedding has {} dimensions but the index has {}",
+                embedding.len(),
+                dims
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T> Default for EmbeddingIndex<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similarity_functions_should_work() {
+        assert_eq!(dot(&[1.0, 2.0], &[3.0, 4.0]), 11.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 2.0]), 0.0);
+        assert!((cosine_similarity(&[1.0, 1.0], &[2.0, 2.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(euclidean_distance(&[0.0, 0.0], &[3.0, 4.0]), 5.0);
+
+        let mut vectors = vec![vec![3.0, 4.0], vec![0.0, 0.0]];
+        normalize_all(&mut vectors);
+        assert_eq!(vectors, vec![vec![0.6, 0.8], vec![0.0, 0.0]]);
+    }
+
+    #[test]
+    fn embedding_index_should_return_top_k() -> Result<()> {
+        let mut index = EmbeddingIndex::new();
+        index.extend([
+            ("rust", vec![1.0, 0.0]),
+            ("python", vec![0.0, 1.0]),
+            ("go", vec![0.7, 0.7]),
+        ])?;
+        let hits = index.search(&[1.0, 0.1], 2)?;
+        let items: Vec<_> = hits.iter().map(|hit| *hit.item).collect();
+        assert_eq!(items, ["rust", "go"]);
+        assert!(hits[0].score > hits[1].score);
+
+        assert!(index.insert("c", vec![1.0, 0.0, 0.0]).is_err());
+        assert!(index.search(&[1.0], 1).is_err());
+
+        let mut index = EmbeddingIndex::with_similarity(Similarity::Euclidean);
+        index.extend([("near", vec![1.0, 1.0]), ("far", vec![5.0, 5.0])])?;
+        assert_eq!(*index.search(&[0.0, 0.0], 1)?[0].item, "near");
+        Ok(())
+    }
+}